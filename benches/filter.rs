@@ -0,0 +1,161 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use match_wire::{
+    commands::{
+        filter::{retain_matching, Server, Sourced},
+        query::FilterQuery,
+    },
+    lowercase_vec,
+    utils::json_data::GetInfo,
+};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 50_000];
+const NAMES: [&str; 6] = [
+    "^1Best ^7TDM ^2| NA East",
+    "^3Hardpoint 24/7 ^7EU",
+    "^5Sniper Only ^7| APAC",
+    "Domination ^1[Bots]",
+    "^2Search ^7and ^2Destroy ^7NA",
+    "^4Free ^7For All ^4EU West",
+];
+
+fn servers(count: usize) -> Vec<Server> {
+    (0..count)
+        .map(|i| Server {
+            source: Sourced::Hmw(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(0x0A00_0000 + (i as u32 / 4))),
+                27016 + (i % 4) as u16,
+            )),
+            info: (i % 17 != 0).then(|| GetInfo {
+                clients: (i % 19) as u8,
+                max_clients: [12, 18, 24][i % 3],
+                private_clients: 0,
+                bots: (i % 5) as u8,
+                game_name: String::from("H2M"),
+                game_type: String::from("war"),
+                host_name: format!("{} #{i}", NAMES[i % NAMES.len()]).into(),
+                map_name: String::from("mp_terminal_cls"),
+            }),
+            community: None,
+        })
+        .collect()
+}
+
+fn query() -> FilterQuery {
+    FilterQuery::builder()
+        .min_players(4)
+        .max_team_size(9)
+        .include("na")
+        .include("eu")
+        .exclude("sniper")
+        .build()
+}
+
+/// `parse_hostname` as it was before it reserved capacity up front
+fn parse_hostname_unsized(name: &str) -> String {
+    let mut host_name = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '^' {
+            if chars.peek().is_some() {
+                chars.next();
+            }
+        } else {
+            host_name.push(c.to_ascii_lowercase());
+        }
+    }
+    host_name
+}
+
+/// The removal loop used before `retain_matching`
+fn swap_remove_matching(host_list: &mut Vec<Server>, args: &FilterQuery) {
+    let include = args.includes.as_ref().map(|s| lowercase_vec(s));
+    let exclude = args.excludes.as_ref().map(|s| lowercase_vec(s));
+
+    for i in (0..host_list.len()).rev() {
+        let server = &host_list[i];
+
+        let Some(ref info) = server.info else {
+            host_list.swap_remove(i);
+            continue;
+        };
+
+        if let Some(team_size_max) = args.team_size_max {
+            if info.max_clients > team_size_max * 2 {
+                host_list.swap_remove(i);
+                continue;
+            }
+        }
+
+        if let Some(player_min) = args.player_min {
+            if info.clients < player_min {
+                host_list.swap_remove(i);
+                continue;
+            }
+        }
+
+        if args.with_bots && info.bots == 0 {
+            host_list.swap_remove(i);
+            continue;
+        }
+
+        if args.without_bots && info.bots != 0 {
+            host_list.swap_remove(i);
+            continue;
+        }
+
+        let mut hostname_l = None;
+        if let Some(ref strings) = include {
+            hostname_l = Some(parse_hostname_unsized(&info.host_name));
+            if !strings
+                .iter()
+                .any(|string| hostname_l.as_ref().unwrap().contains(string))
+            {
+                host_list.swap_remove(i);
+                continue;
+            }
+        }
+        if let Some(ref strings) = exclude {
+            if hostname_l.is_none() {
+                hostname_l = Some(parse_hostname_unsized(&info.host_name));
+            }
+            if strings
+                .iter()
+                .any(|string| hostname_l.as_ref().unwrap().contains(string))
+            {
+                host_list.swap_remove(i);
+            }
+        }
+    }
+}
+
+fn filter_servers(c: &mut Criterion) {
+    let query = query();
+    let mut group = c.benchmark_group("filter");
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("swap_remove", size), &size, |b, &size| {
+            b.iter_batched(
+                || servers(size),
+                |mut list| {
+                    swap_remove_matching(&mut list, black_box(&query));
+                    list
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("retain", size), &size, |b, &size| {
+            b.iter_batched(
+                || servers(size),
+                |mut list| {
+                    retain_matching(&mut list, black_box(&query));
+                    list
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, filter_servers);
+criterion_main!(benches);
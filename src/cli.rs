@@ -1,5 +1,125 @@
 use crate::{commands::reconnect::HISTORY_MAX, H2M_MAX_CLIENT_NUM, H2M_MAX_TEAM_SIZE};
 use clap::{value_parser, ArgAction, Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+
+/// Options accepted by match_wire.exe itself, e.g. from a desktop shortcut
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct AppArgs {
+    /// Disable colored output, same as `--set display.color=false`
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Release channel checked for new versions, same as `--set update.channel=<CHANNEL>`
+    #[arg(long, value_enum)]
+    pub channel: Option<Channel>,
+
+    /// Override a setting for this run only, takes priority over config.toml and H2M_FAVORITES_* env vars
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_setting_override)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Run the commands in FILE once startup is complete
+    #[arg(long, value_name = "FILE")]
+    pub run_script: Option<PathBuf>,
+
+    /// Stop running the script after the first command that fails
+    #[arg(long, requires = "run_script")]
+    pub stop_on_error: bool,
+
+    /// Quit once the script finishes, exiting with code 1 if anything warned or failed. Each problem
+    /// is written to stderr as a line of json
+    #[arg(long, requires = "run_script")]
+    pub strict: bool,
+
+    /// Servers whose location lookup may fail in a `filter` before `--strict` counts it as a problem
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "strict")]
+    pub max_geo_failures: usize,
+
+    /// Answer web requests (master lists, server info, geolocation and version checks) with the
+    /// json files in DIR instead of the network, e.g. ones saved by `--record`
+    #[arg(long, value_name = "DIR", visible_alias = "replay")]
+    pub offline_fixtures: Option<PathBuf>,
+
+    /// Start without network requests: no version check, master server lists or location lookups.
+    /// Commands work from the saved cache and favourites, a cache of any age is used as it is
+    #[arg(long, conflicts_with_all = ["offline_fixtures", "record"])]
+    pub offline: bool,
+
+    /// Save every successful web response to DIR, so the run can be repeated with `--replay <DIR>`
+    #[arg(long, value_name = "DIR", conflicts_with = "offline_fixtures")]
+    pub record: Option<PathBuf>,
+
+    /// Save master server list entries that could not be read to debug_parse.jsonl in the local data
+    /// directory, along with why, to attach to a bug report
+    #[arg(long)]
+    pub debug_parse: bool,
+
+    /// Seconds before a web request is abandoned, same as `--set network.timeout=<SECS>`
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    /// Extra attempts for web requests that fail to connect, same as `--set network.retries=<N>`
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u8>,
+
+    /// Send web requests through an HTTP or SOCKS5 proxy, same as `--set network.proxy=<URL>`
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// favourites.json file to write instead of players2/favourites.json, repeat it to write several,
+    /// same as `--set filter.favorites-path=[<PATH>, ..]`
+    #[arg(long, value_name = "PATH")]
+    pub favorites_path: Vec<PathBuf>,
+
+    /// Modern Warfare Remastered directory to use, saved to config.toml as `launch.game-dir` once the
+    /// game is found there
+    #[arg(long, value_name = "PATH")]
+    pub game_dir: Option<PathBuf>,
+}
+
+impl AppArgs {
+    /// Settings given on the command line, in the order they are applied
+    pub fn setting_overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = self.overrides.clone();
+        if self.no_color {
+            overrides.push((String::from("display.color"), String::from("false")));
+        }
+        if let Some(channel) = self.channel {
+            overrides.push((String::from("update.channel"), channel.to_string()));
+        }
+        if let Some(timeout) = self.timeout {
+            overrides.push((String::from("network.timeout"), timeout.to_string()));
+        }
+        if let Some(retries) = self.retries {
+            overrides.push((String::from("network.retries"), retries.to_string()));
+        }
+        if let Some(ref proxy) = self.proxy {
+            overrides.push((String::from("network.proxy"), proxy.clone()));
+        }
+        if !self.favorites_path.is_empty() {
+            let paths = self
+                .favorites_path
+                .iter()
+                .map(|path| toml::Value::String(path.display().to_string()))
+                .collect();
+            overrides.push((
+                String::from("filter.favorites-path"),
+                toml::Value::Array(paths).to_string(),
+            ));
+        }
+        overrides
+    }
+}
+
+fn parse_setting_override(input: &str) -> Result<(String, String), String> {
+    match input.split_once('=') {
+        Some((key, value)) if key.contains('.') => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("Expected 'section.name=value', found: '{input}'")),
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(about, long_about = None)]
@@ -26,7 +146,23 @@ pub enum Command {
 
     /// Launch HMW/H2M
     #[command(alias = "Launch")]
-    Launch,
+    Launch {
+        /// Game client to launch, e.g. h2m-revived.exe [default: first client found in the game directory]
+        #[arg(long)]
+        exe: Option<String>,
+
+        /// Arguments passed through to the game, e.g. `launch -- +set fs_game mods/example +connect <IP:PORT>`
+        #[arg(last = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+
+    /// Follow the console log of an H2M/HMW instance that was not launched by this app
+    #[command(alias = "Attach")]
+    Attach {
+        /// Path to the console log [default: newest console_mp.log in the game directory]
+        #[arg(long)]
+        log: Option<PathBuf>,
+    },
 
     /// Commands to reset and update the cache file
     #[command(alias = "Cache")]
@@ -36,26 +172,366 @@ pub enum Command {
     },
 
     /// Opens H2M/HMW game console
-    #[command(aliases(["Logs", "logs", "Console"]))]
-    Console,
+    #[command(alias = "Console")]
+    Console {
+        #[clap(flatten)]
+        args: ConsoleArgs,
+    },
 
     /// Open MWR(2017) directory
     #[command(aliases(["Gamedir", "gamedir", "GameDir"]))]
     GameDir,
 
+    /// Open a folder in the file manager, with the newest log or favourites.json selected
+    #[command(alias = "Open")]
+    Open {
+        #[arg(value_enum)]
+        target: OpenTarget,
+    },
+
+    /// Hide the console to the tray icon, or forget the window size and position saved on exit
+    #[command(alias = "Window")]
+    Window {
+        #[arg(value_enum)]
+        option: WindowCmd,
+    },
+
     /// Quit the program
     #[command(alias = "Quit")]
     Quit,
 
+    /// Close H2M/HMW, the process is terminated if it does not exit in time
+    #[command(alias = "QuitGame")]
+    QuitGame,
+
     /// Print version
     #[command(alias = "Version")]
     Version,
 
+    /// Manage words that highlight chat messages mentioning them
+    #[command(alias = "Alerts")]
+    Alerts {
+        #[command(subcommand)]
+        command: AlertsCmd,
+    },
+
+    /// Display player name and key settings read from players2
+    #[command(alias = "Profile")]
+    Profile,
+
+    /// Summarize match events of the current play session
+    #[command(alias = "Session")]
+    Session {
+        #[arg(value_enum)]
+        option: SessionCmd,
+    },
+
+    /// Display game, connection and cache status
+    #[command(alias = "Status")]
+    Status,
+
+    /// Full screen view of player counts and maps on the servers in favourites.json, refreshed until
+    /// a key is pressed
+    #[command(alias = "Dashboard")]
+    Dashboard {
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 15, value_parser = value_parser!(u64).range(3..=600))]
+        interval: u64,
+
+        /// Measure every ping again instead of using pings cached in the last 30 minutes
+        #[arg(long)]
+        ping_refresh: bool,
+    },
+
+    /// Share favourites.json as a code or import a list someone else shared
+    #[command(alias = "Favorites")]
+    Favorites {
+        #[command(subcommand)]
+        command: FavoritesCmd,
+    },
+
+    /// Add the favourites and joined servers of another client to favourites.json and the cache
+    #[command(alias = "Import")]
+    Import {
+        /// iw4x, plutonium or the path of a server list
+        #[arg(long, value_name = "iw4x|plutonium|PATH", value_parser = parse_import_source)]
+        from: ImportSource,
+    },
+
+    /// Track players by name and get notified when they are online
+    #[command(alias = "Friend")]
+    Friend {
+        #[command(subcommand)]
+        command: FriendCmd,
+    },
+
+    /// Use your own findip.net key for location lookups, leave empty to display which key is used and
+    /// how many lookups it made today
+    #[command(alias = "GeoKey")]
+    GeoKey {
+        #[command(subcommand)]
+        command: Option<GeoKeyCmd>,
+    },
+
+    /// Display which server each friend is playing on, online friends can be joined with a key press
+    #[command(alias = "Friends")]
+    Friends,
+
+    /// Query a server directly for its map, mode, players with their scores and pings, and settings
+    #[command(alias = "Info")]
+    Info {
+        /// Numbered entry in history, an IP:PORT or a cached server name
+        #[arg(value_parser = parse_connect_target)]
+        server: ConnectTarget,
+
+        /// List every dvar the server reports
+        #[arg(long)]
+        dvars: bool,
+    },
+
+    /// Send a command to a server you run over rcon, e.g. `rcon 1 map_rotate`
+    #[command(alias = "Rcon")]
+    Rcon {
+        /// Numbered entry in history, an IP:PORT or a cached server name
+        #[arg(value_parser = parse_connect_target)]
+        server: ConnectTarget,
+
+        /// Command to run on the server
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required_unless_present = "forget"
+        )]
+        command: Vec<String>,
+
+        /// Rcon password, the saved password for this server is used when left out
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Save --password for this server, encrypted for your Windows user
+        #[arg(long, requires = "password")]
+        save: bool,
+
+        /// Delete the password saved for this server
+        #[arg(long, conflicts_with_all = ["command", "password"])]
+        forget: bool,
+    },
+
+    /// Display recorded play sessions: when the game was launched, servers joined, time spent and maps
+    /// played on each
+    #[command(aliases(["Journal", "history"]))]
+    Journal {
+        /// Only sessions started today (UTC)
+        #[arg(long)]
+        today: bool,
+
+        /// Only servers with a name containing NAME
+        #[arg(long, value_name = "NAME")]
+        server: Option<String>,
+    },
+
+    /// Display every name a server was seen under, its region, the master servers listing it and when
+    /// it was last seen, without querying the server
+    #[command(alias = "Whois")]
+    Whois {
+        /// Numbered entry in history, an IP, an IP:PORT or part of a server name
+        query: String,
+    },
+
+    /// List cached servers matching QUERY, numbered so they can be appended to favourites.json with
+    /// `add`
+    #[command(alias = "Search")]
+    Search {
+        /// Part of a server name, an IP or an IP:PORT
+        query: String,
+    },
+
+    /// Append servers listed by the last `search` to favourites.json, e.g. `add 1,4 7-9`
+    #[command(alias = "Add")]
+    Add {
+        /// Result numbers or ranges of them
+        #[arg(required = true, num_args = 1..)]
+        picks: Vec<String>,
+    },
+
+    /// Display average command durations, how many location lookups were cached and the slowest
+    /// web requests, recorded locally across runs
+    #[command(alias = "Perf")]
+    Perf {
+        #[arg(value_enum)]
+        option: Option<PerfCmd>,
+    },
+
+    /// List the commands running in the background and how long they have been running, or cancel one
+    #[command(alias = "Tasks")]
+    Tasks {
+        #[command(subcommand)]
+        command: Option<TasksCmd>,
+    },
+
+    /// Compare the master server lists with the snapshot saved the last time this ran, listing new
+    /// servers, servers no longer listed and large changes in player counts
+    #[command(alias = "Diff")]
+    Diff,
+
+    /// Clear the terminal and display a short status line
+    #[command(aliases(["Clear", "cls"]))]
+    Clear,
+
+    /// Enable or disable optional features, saved to config.toml like `settings`
+    #[command(alias = "Set")]
+    Set {
+        #[arg(value_enum)]
+        setting: Setting,
+        #[arg(value_enum)]
+        state: Toggle,
+    },
+
+    /// Customize the prompt, e.g. `prompt "{game_status} {server}"`, leave empty to display placeholders
+    #[command(alias = "Prompt")]
+    Prompt {
+        /// Text displayed before '>', placeholders are filled in as the game state changes
+        #[arg(conflicts_with = "reset")]
+        template: Option<String>,
+
+        /// Restore the default prompt
+        #[arg(long)]
+        reset: bool,
+    },
+
+    /// View or change settings saved to config.toml, e.g. `settings filter.limit 50`
+    #[command(alias = "Settings")]
+    Settings {
+        /// Setting to view or change, in the form 'section.name'. Use 'sources' to see where each value
+        /// came from
+        key: Option<String>,
+
+        /// New value, use 'default' to restore the default
+        #[arg(requires = "key")]
+        value: Option<String>,
+    },
+
+    /// Open config.toml in a text editor or print where it is saved
+    #[command(alias = "Config")]
+    Config {
+        #[arg(value_enum)]
+        option: ConfigCmd,
+    },
+
+    /// Change how much is logged without restarting, leave empty to display the current levels
+    #[command(alias = "LogLevel")]
+    LogLevel {
+        #[arg(value_enum)]
+        level: Option<Verbosity>,
+
+        /// Which output the level applies to
+        #[arg(long, value_enum, default_value_t = LogTarget::All, requires = "level")]
+        target: LogTarget,
+    },
+
+    /// List MatchWire's log files or open the folder they are saved in
+    #[command(alias = "Logs")]
+    Logs {
+        #[command(subcommand)]
+        command: Option<LogsCmd>,
+    },
+
+    /// Bundle recent logs, version info and commands into a zip to attach to a bug report
+    #[command(alias = "Report")]
+    Report,
+
+    /// Check game files, folder permissions and network access for common problems
+    #[command(alias = "Doctor")]
+    Doctor,
+
+    /// Download and install the latest release of MatchWire
+    #[command(alias = "Update")]
+    Update,
+
+    /// Show the release notes of the latest version, or of a given version
+    #[command(alias = "Changelog")]
+    Changelog {
+        /// Version to show release notes for, e.g. 0.8.0
+        version: Option<String>,
+    },
+
+    /// Run newline separated commands from a file
+    #[command(alias = "Run")]
+    Run {
+        #[clap(flatten)]
+        args: ScriptArgs,
+    },
+
     /// Open the current local data directory
     #[command(aliases(["Localenv", "localenv", "LocalEnv"]), hide = true)]
     LocalEnv,
 }
 
+#[derive(Args, Debug)]
+pub struct ScriptArgs {
+    /// File containing one command per line, lines starting with '#' are ignored
+    pub file: PathBuf,
+
+    /// Stop running the script after the first command that fails
+    #[arg(long)]
+    pub stop_on_error: bool,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct ConsoleArgs {
+    /// Only display the last N lines of console history
+    #[arg(long, value_name = "N")]
+    pub tail: Option<usize>,
+
+    /// Only display console history lines matching a (case-insensitive) regex
+    #[arg(long, value_name = "PATTERN", value_parser = parse_grep_pattern)]
+    pub grep: Option<regex::Regex>,
+
+    /// Only display console history read in the last AGE, e.g. `90s`, `10m` or `1h30m`
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub since: Option<Duration>,
+}
+
+/// Parses a duration made of numbers followed by `s`, `m`, `h` or `d`, e.g. `1h30m`
+pub fn parse_age(input: &str) -> Result<Duration, String> {
+    let mut total = 0;
+    let mut digits = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(format!("Unknown unit '{c}', expected s, m, h or d")),
+        };
+        let value = digits
+            .parse::<u64>()
+            .map_err(|_| format!("Expected a number before '{c}'"))?;
+        total = value.saturating_mul(unit).saturating_add(total);
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!(
+            "Missing a unit after '{digits}', e.g. {digits}m for minutes"
+        ));
+    }
+    if total == 0 {
+        return Err(String::from("Expected a duration, e.g. 10m"));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+pub fn parse_grep_pattern(input: &str) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(input)
+        .case_insensitive(true)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
 #[derive(Args, Debug)]
 #[group(multiple = false)]
 pub struct HistoryArgs {
@@ -63,9 +539,52 @@ pub struct HistoryArgs {
     #[arg(short = 'H', long, action = ArgAction::SetTrue)]
     pub history: bool,
 
-    /// Connect to numbered entry in history
-    #[arg(short, long, value_parser = value_parser!(u8).range(1..=HISTORY_MAX as i64))]
-    pub connect: Option<u8>,
+    /// Connect to numbered entry in history, an IP:PORT or a cached server name
+    #[arg(short, long, value_parser = parse_connect_target)]
+    pub connect: Option<ConnectTarget>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectTarget {
+    History(u8),
+    SocketAddr(SocketAddr),
+    HostName(String),
+}
+
+pub fn parse_connect_target(input: &str) -> Result<ConnectTarget, String> {
+    if let Ok(num) = input.parse::<u8>() {
+        if !(1..=HISTORY_MAX as u8).contains(&num) {
+            return Err(format!("history entry must be in range 1..={HISTORY_MAX}"));
+        }
+        return Ok(ConnectTarget::History(num));
+    }
+    if let Ok(socket_addr) = input.parse() {
+        return Ok(ConnectTarget::SocketAddr(socket_addr));
+    }
+    let host_name = input.trim();
+    if host_name.is_empty() {
+        return Err(String::from(
+            "expected a history entry, IP:PORT or server name",
+        ));
+    }
+    Ok(ConnectTarget::HostName(host_name.to_lowercase()))
+}
+
+/// Where `import` reads server lists from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportSource {
+    Iw4x,
+    Plutonium,
+    Path(PathBuf),
+}
+
+pub fn parse_import_source(input: &str) -> Result<ImportSource, String> {
+    match input.trim() {
+        "" => Err(String::from("expected iw4x, plutonium or a path")),
+        client if client.eq_ignore_ascii_case("iw4x") => Ok(ImportSource::Iw4x),
+        client if client.eq_ignore_ascii_case("plutonium") => Ok(ImportSource::Plutonium),
+        path => Ok(ImportSource::Path(PathBuf::from(path))),
+    }
 }
 
 #[derive(Args, Debug, Clone, Default)]
@@ -96,10 +615,46 @@ pub struct Filters {
     #[arg(long)]
     pub include_unresponsive: bool,
 
-    /// Specify region(s) [Default: include all]
-    #[arg(short, long, value_enum, num_args(1..=REGION_LEN))]
+    /// Keep servers that are listed more than once, under another address or by both master servers
+    #[arg(long)]
+    pub keep_duplicates: bool,
+
+    /// Prefer servers that are usually busy at this hour, going by player counts seen before
+    /// {n}  [Note: a server that usually has --player-min players now is kept while it is emptier]
+    #[arg(long)]
+    pub active_now: bool,
+
+    /// Keep servers in the region you play from and write the ones with the lowest ping
+    /// {n}  [Note: your location is estimated once per session from your public IP]
+    #[arg(long, conflicts_with = "active_now")]
+    pub near_me: bool,
+
+    /// Rank --near-me servers by players instead of measuring their ping
+    #[arg(long, requires = "near_me")]
+    pub no_ping: bool,
+
+    /// Leave out servers the master servers listed less than PERCENT of the time
+    /// {n}  [Note: servers are scored once they were looked for 3 times]
+    #[arg(long, value_name = "PERCENT", value_parser = value_parser!(u8).range(0..=100))]
+    pub min_reliability: Option<u8>,
+
+    /// Address type used for servers reachable over both IPv4 and IPv6 [Default: ipv4]
+    #[arg(long, value_enum)]
+    pub prefer: Option<IpFamily>,
+
+    /// Order of the servers in favorites.json, only the order changes, not which servers are written
+    /// {n}  [Note: regions not looked up before are treated as unknown]
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub browser_order: Option<BrowserOrder>,
+
+    /// Specify region(s), na, eu, apac or a group from the region settings [Default: include all]
+    #[arg(short, long, num_args(1..))]
     pub region: Option<Vec<Region>>,
 
+    /// Keep servers whose region could not be looked up, e.g. when the location API is down
+    #[arg(long, requires = "region")]
+    pub keep_unknown_region: bool,
+
     /// Specify source(s) [Default: include all]
     #[arg(short, long, value_enum, num_args(1..=SOURCE_LEN))]
     pub source: Option<Vec<Source>>,
@@ -119,18 +674,183 @@ pub struct Filters {
     /// Specify a maximum number of 'getInfo' retries [Default: 3]
     #[arg(long, value_parser = value_parser!(u8).range(0..=20))]
     pub retry_max: Option<u8>,
+
+    /// Seconds the whole filter may take, servers still being checked then are skipped [Default: 120]
+    /// {n}  [Note: 0 waits for every server]
+    #[arg(long, value_name = "SECONDS")]
+    pub deadline: Option<u64>,
+
+    /// Print the matching servers grouped by region and community instead of writing favorites.json
+    #[arg(long, conflicts_with = "interactive")]
+    pub preview: bool,
+
+    /// When more servers match than --limit, write the ones with the most players instead of asking
+    /// which to leave out
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Asks for region, minimum players, search terms and limit one at a time, other options given
+    /// are kept. The number of matches is shown before favorites.json is written
+    #[arg(long)]
+    pub interactive: bool,
+}
+
+impl Filters {
+    /// Arguments that parse back into these filters, `interactive` is left out
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, values: Vec<String>| {
+            args.push(format!("--{flag}"));
+            args.extend(values);
+        };
+        if let Some(limit) = self.limit {
+            push("limit", vec![limit.to_string()]);
+        }
+        if let Some(players) = self.player_min {
+            push("player-min", vec![players.to_string()]);
+        }
+        if let Some(size) = self.team_size_max {
+            push("team-size-max", vec![size.to_string()]);
+        }
+        for (flag, set) in [
+            ("with-bots", self.with_bots),
+            ("without-bots", self.without_bots),
+            ("include-unresponsive", self.include_unresponsive),
+            ("keep-duplicates", self.keep_duplicates),
+            ("active-now", self.active_now),
+            ("near-me", self.near_me),
+            ("no-ping", self.no_ping),
+            ("keep-unknown-region", self.keep_unknown_region),
+            ("preview", self.preview),
+            ("yes", self.yes),
+        ] {
+            if set {
+                push(flag, Vec::new());
+            }
+        }
+        if let Some(prefer) = self.prefer {
+            push("prefer", vec![value_name(prefer)]);
+        }
+        if let Some(order) = self.browser_order {
+            push("browser-order", vec![value_name(order)]);
+        }
+        if let Some(ref regions) = self.region {
+            push("region", regions.iter().map(Region::to_string).collect());
+        }
+        if let Some(ref sources) = self.source {
+            push("source", sources.iter().copied().map(value_name).collect());
+        }
+        if let Some(ref terms) = self.includes {
+            push("includes", terms.clone());
+        }
+        if let Some(ref terms) = self.excludes {
+            push("excludes", terms.clone());
+        }
+        if let Some(retries) = self.retry_max {
+            push("retry-max", vec![retries.to_string()]);
+        }
+        if let Some(secs) = self.deadline {
+            push("deadline", vec![secs.to_string()]);
+        }
+        if let Some(percent) = self.min_reliability {
+            push("min-reliability", vec![percent.to_string()]);
+        }
+        args
+    }
+}
+
+/// Name clap parses `value` from
+pub fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .expect("no variants are skipped")
+        .get_name()
+        .to_string()
 }
 
 pub const REGION_LEN: usize = 3;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Full releases only
+    #[default]
+    Stable,
+    /// Pre-releases as well as full releases
+    Beta,
+}
+
+/// A built in region or a group named in the `region` settings, see [`crate::utils::regions`]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum Region {
-    #[value(aliases(["Na", "nA", "NorthAmerica", "northAmerica", "northamerica"]))]
     NA,
-    #[value(aliases(["Eu", "eU", "Europe", "europe"]))]
     EU,
-    #[value(aliases(["APAC", "Asia", "AsiaPacific", "asia", "pacific"]))]
     Apac,
+    /// Lowercase name of a user defined group
+    Custom(String),
+}
+
+impl FromStr for Region {
+    type Err = String;
+    /// Names are matched ignoring case, the built in regions also go by `northamerica`, `europe`, `asia`,
+    /// `asiapacific` and `pacific`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        match name.as_str() {
+            "na" | "northamerica" => Ok(Region::NA),
+            "eu" | "europe" => Ok(Region::EU),
+            "apac" | "asia" | "asiapacific" | "pacific" => Ok(Region::Apac),
+            "" => Err(String::from("expected a region name")),
+            _ if name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')) =>
+            {
+                Ok(Region::Custom(name))
+            }
+            _ => Err(format!(
+                "region names only use letters, digits, '_' and '-', found: '{s}'"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Region {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Region> for String {
+    fn from(value: Region) -> Self {
+        value.to_string()
+    }
+}
+
+/// The game client treats IPv4 and IPv6 favourites differently, so the address type written can matter
+#[derive(
+    Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum, Debug, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    #[default]
+    #[value(alias = "v4")]
+    Ipv4,
+    #[value(alias = "v6")]
+    Ipv6,
+}
+
+/// Order servers are written to favorites.json in, the in game server browser lists favorites in file order
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserOrder {
+    /// Alphabetical by server name, ignoring color codes
+    Name,
+    /// Most players first
+    Players,
+    /// NA, EU, APAC, then other and unknown regions, most players first within each region
+    Region,
 }
 
 pub const SOURCE_LEN: usize = 2;
@@ -152,3 +872,175 @@ pub enum CacheCmd {
     /// {n}  Try this if 'reconnect' is returning: "Could not find server in cache"
     Update,
 }
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum Verbosity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum LogTarget {
+    /// The log file in the local environment directory
+    File,
+    /// Messages printed to the terminal
+    Terminal,
+    All,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ConfigCmd {
+    /// Opens config.toml in $EDITOR (notepad if unset), settings are reloaded once the editor closes
+    Edit,
+    /// Prints the location of config.toml
+    Path,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum WindowCmd {
+    /// Hides the console until it is shown again from the tray icon
+    Tray,
+    /// Forgets the saved window size and position, the next run opens with the default ones
+    Forget,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LogsCmd {
+    /// List log files, newest first (default)
+    List,
+    /// Open the folder log files are saved in
+    Open,
+    /// Display the newest log file, one page at a time
+    View {
+        #[clap(flatten)]
+        args: LogViewArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct LogViewArgs {
+    /// Only display entries at this level or more severe
+    #[arg(long, value_enum)]
+    pub level: Option<Verbosity>,
+
+    /// Only display entries matching a (case-insensitive) regex
+    #[arg(long, value_name = "PATTERN", value_parser = parse_grep_pattern)]
+    pub grep: Option<regex::Regex>,
+
+    /// Page to display, page 1 holds the newest entries
+    #[arg(long, default_value_t = 1, value_parser = value_parser!(u32).range(1..))]
+    pub page: u32,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FavoritesCmd {
+    /// Print a code holding the servers in favourites.json
+    ExportCode {
+        /// Print a json list instead, e.g. to post as a gist
+        #[arg(long, conflicts_with = "preset")]
+        json: bool,
+
+        /// Share `filter` arguments instead of the current list, e.g. `--preset "-r na -p 4"`
+        #[arg(long, value_name = "FILTER_ARGS", allow_hyphen_values = true)]
+        preset: Option<String>,
+    },
+    /// Replace favourites.json with the servers in a code, json list or url pointing to either.
+    /// Filter presets are run as `filter`
+    Import {
+        /// Code, json list or http(s) url
+        source: String,
+
+        /// Add the servers to the current list instead of replacing it
+        #[arg(long)]
+        merge: bool,
+    },
+    /// Probe every server in favourites.json and report dead servers, renamed hosts and region
+    /// changes
+    Check {
+        /// Drop dead servers and replace them with matches of the last `filter` command
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Fix a read-only, cloud only or damaged favourites.json. A damaged file is kept as
+    /// favourites.json.bak and rebuilt from the servers that can still be read
+    Repair,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FriendCmd {
+    /// Track NAME, use quotes for names with spaces
+    Add { name: String },
+    /// Stop tracking NAME
+    Remove { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GeoKeyCmd {
+    /// Save KEY encrypted so only your Windows account can read it
+    Set { key: String },
+    /// Remove the saved key, lookups go back to the bundled key
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TasksCmd {
+    /// List running background tasks with their ids (default)
+    List,
+    /// Stop the background task with id ID, leaving the others running
+    Cancel { id: usize },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AlertsCmd {
+    /// Highlight chat messages that mention WORD
+    Add { word: String },
+    /// Stop highlighting chat messages that mention WORD
+    Remove { word: String },
+    /// List all alert words
+    List,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum OpenTarget {
+    /// MWR(2017) directory
+    Gamedir,
+    /// Local environment directory, where settings and the cache are saved
+    Localdata,
+    /// Newest log file of this app
+    Logs,
+    /// favourites.json written by `filter`, the first one when several are set
+    Favorites,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum PerfCmd {
+    /// Forgets every recorded timing
+    Reset,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SessionCmd {
+    /// Kills, round wins and maps recorded since the game was launched
+    Stats,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum Setting {
+    /// Relaunch H2M and rejoin the last server if the game crashes
+    AutoRelaunch,
+    /// Bring the game window to the foreground after connecting to a server
+    FocusOnConnect,
+    /// Show desktop notifications for updates, friends coming online and game crashes
+    Notifications,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum Toggle {
+    #[value(alias = "enable")]
+    On,
+    #[value(alias = "disable")]
+    Off,
+}
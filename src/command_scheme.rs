@@ -21,7 +21,7 @@ impl CommandScheme {
     }
 }
 
-const COMMAND_RECS: [&str; 12] = [
+const COMMAND_RECS: [&str; 46] = [
     "filter",
     "reconnect",
     "launch",
@@ -31,13 +31,47 @@ const COMMAND_RECS: [&str; 12] = [
     "local-env",
     "quit",
     "version",
+    "set",
+    "status",
+    "quit-game",
+    "attach",
+    "session",
+    "alerts",
+    "profile",
+    "run",
+    "clear",
+    "prompt",
+    "settings",
+    "config",
+    "log-level",
     "logs",
+    "report",
+    "doctor",
+    "update",
+    "changelog",
+    "dashboard",
+    "favorites",
+    "friend",
+    "friends",
+    "info",
+    "rcon",
+    "journal",
+    "whois",
+    "perf",
+    "diff",
+    "open",
+    "import",
+    "window",
+    "geo-key",
+    "search",
+    "add",
+    "tasks",
     "gamedir",
     "localenv",
 ];
-const COMMANDS_ALIAS: [(usize, usize); 3] = [(4, 9), (5, 10), (6, 11)];
+const COMMANDS_ALIAS: [(usize, usize); 2] = [(5, 44), (6, 45)];
 
-const FILTER_RECS: [&str; 11] = [
+const FILTER_RECS: [&str; 23] = [
     "limit",
     "player-min",
     "team-size-max",
@@ -49,8 +83,20 @@ const FILTER_RECS: [&str; 11] = [
     "without-bots",
     "include-unresponsive",
     "retry-max",
+    "keep-duplicates",
+    "keep-unknown-region",
+    "prefer",
+    "interactive",
+    "preview",
+    "active-now",
+    "min-reliability",
+    "deadline",
+    "browser-order",
+    "near-me",
+    "no-ping",
+    "yes",
 ];
-const FILTER_SHORT: [(usize, &str); 7] = [
+const FILTER_SHORT: [(usize, &str); 8] = [
     (0, "l"),
     (1, "p"),
     (2, "t"),
@@ -58,6 +104,7 @@ const FILTER_SHORT: [(usize, &str); 7] = [
     (4, "s"),
     (5, "i"),
     (6, "e"),
+    (22, "y"),
 ];
 
 const FILTER_REGIONS: [&str; 8] = [
@@ -72,16 +119,79 @@ const FILTER_REGIONS: [&str; 8] = [
 ];
 const FILTER_REGIONS_ALIAS: [(usize, usize); 5] = [(0, 3), (1, 4), (2, 5), (2, 6), (2, 7)];
 
+const FILTER_PREFER_RECS: [&str; 4] = ["ipv4", "ipv6", "v4", "v6"];
+const FILTER_PREFER_ALIAS: [(usize, usize); 2] = [(0, 2), (1, 3)];
+
+const FILTER_BROWSER_ORDER_RECS: [&str; 3] = ["name", "players", "region"];
+
 const FILTER_SOURCE_RECS: [&str; 4] = ["iw4-master", "hmw-master", "iw4", "hmw"];
 const FILTER_SOURCE_ALIAS: [(usize, usize); 2] = [(0, 2), (1, 3)];
 
 const RECONNECT_RECS: [&str; 2] = ["history", "connect"];
 const RECONNECT_SHORT: [(usize, &str); 2] = [(0, "H"), (1, "c")];
 
+const CONSOLE_RECS: [&str; 3] = ["tail", "grep", "since"];
+
+const LAUNCH_RECS: [&str; 1] = ["exe"];
+
+const LAUNCH_EXE_RECS: [&str; 3] = ["h2m-mod.exe", "h2m-revived.exe", "hmw-mod.exe"];
+
+const ATTACH_RECS: [&str; 1] = ["log"];
+
+const PROMPT_RECS: [&str; 1] = ["reset"];
+
+const CONFIG_RECS: [&str; 2] = ["edit", "path"];
+
+const LOGS_RECS: [&str; 3] = ["list", "open", "view"];
+
+const LOG_LEVEL_RECS: [&str; 1] = ["target"];
+
+const LOG_TARGET_RECS: [&str; 3] = ["file", "terminal", "all"];
+
+const RUN_RECS: [&str; 1] = ["stop-on-error"];
+
+const SESSION_RECS: [&str; 1] = ["stats"];
+
+const DASHBOARD_RECS: [&str; 2] = ["interval", "ping-refresh"];
+
+const FAVORITES_RECS: [&str; 4] = ["export-code", "import", "check", "repair"];
+
+const FRIEND_RECS: [&str; 2] = ["add", "remove"];
+
+const INFO_RECS: [&str; 1] = ["dvars"];
+const RCON_RECS: [&str; 3] = ["password", "save", "forget"];
+
+const JOURNAL_RECS: [&str; 2] = ["today", "server"];
+
 const CACHE_RECS: [&str; 3] = ["reset", "update", "clear"];
 const CACHE_ALIAS: [(usize, usize); 1] = [(0, 2)];
 
-const COMMAND_INNER: [InnerScheme; 9] = [
+const PERF_RECS: [&str; 1] = ["reset"];
+
+const WINDOW_RECS: [&str; 2] = ["tray", "forget"];
+
+const GEO_KEY_RECS: [&str; 2] = ["set", "clear"];
+
+const TASKS_RECS: [&str; 2] = ["list", "cancel"];
+
+const OPEN_RECS: [&str; 4] = ["gamedir", "localdata", "logs", "favorites"];
+
+const IMPORT_RECS: [&str; 1] = ["from"];
+
+const IMPORT_FROM_RECS: [&str; 2] = ["iw4x", "plutonium"];
+
+const SET_RECS: [&str; 7] = [
+    "auto-relaunch",
+    "focus-on-connect",
+    "notifications",
+    "on",
+    "off",
+    "enable",
+    "disable",
+];
+const SET_ALIAS: [(usize, usize); 2] = [(3, 5), (4, 6)];
+
+const COMMAND_INNER: [InnerScheme; 44] = [
     // filter
     InnerScheme::new(
         RecData::new(
@@ -107,7 +217,17 @@ const COMMAND_INNER: [InnerScheme; 9] = [
         Some(&RECONNECT_INNTER),
     ),
     // launch
-    InnerScheme::end(ROOT),
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&LAUNCH_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&LAUNCH_INNER),
+    ),
     // cache
     InnerScheme::new(
         RecData::new(
@@ -121,7 +241,17 @@ const COMMAND_INNER: [InnerScheme; 9] = [
         None,
     ),
     // game-console
-    InnerScheme::end(ROOT),
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&CONSOLE_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&CONSOLE_INNER),
+    ),
     // game-dir
     InnerScheme::end(ROOT),
     // local-env
@@ -130,9 +260,279 @@ const COMMAND_INNER: [InnerScheme; 9] = [
     InnerScheme::end(ROOT),
     // version
     InnerScheme::end(ROOT),
+    // set
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            Some(&SET_ALIAS),
+            None,
+            Some(&SET_RECS),
+            RecKind::value_with_num_args(2),
+            true,
+        ),
+        None,
+    ),
+    // status
+    InnerScheme::end(ROOT),
+    // quit-game
+    InnerScheme::end(ROOT),
+    // attach
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&ATTACH_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&ATTACH_INNER),
+    ),
+    // session
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&SESSION_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // alerts
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(2), true),
+    // profile
+    InnerScheme::end(ROOT),
+    // run
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&RUN_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&RUN_INNER),
+    ),
+    // clear
+    InnerScheme::end(ROOT),
+    // prompt
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&PROMPT_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&PROMPT_INNER),
+    ),
+    // settings
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(2), true),
+    // config
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&CONFIG_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // log-level
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&LOG_LEVEL_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&LOG_LEVEL_INNER),
+    ),
+    // logs
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&LOGS_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // report
+    InnerScheme::end(ROOT),
+    // doctor
+    InnerScheme::end(ROOT),
+    // update
+    InnerScheme::end(ROOT),
+    // changelog
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(1), true),
+    // dashboard
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&DASHBOARD_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&DASHBOARD_INNER),
+    ),
+    // favorites
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&FAVORITES_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // friend
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&FRIEND_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // friends
+    InnerScheme::end(ROOT),
+    // info
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&INFO_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&INFO_INNER),
+    ),
+    // rcon
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&RCON_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&RCON_INNER),
+    ),
+    // journal
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&JOURNAL_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&JOURNAL_INNER),
+    ),
+    // whois
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(1), true),
+    // perf
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&PERF_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // diff
+    InnerScheme::end(ROOT),
+    // open
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&OPEN_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // import
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&IMPORT_RECS),
+            RecKind::Argument,
+            false,
+        ),
+        Some(&IMPORT_INNER),
+    ),
+    // window
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&WINDOW_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // geo-key
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&GEO_KEY_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+    // search
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(1), true),
+    // add
+    InnerScheme::empty_with(ROOT, RecKind::user_defined_with_num_args(usize::MAX), true),
+    // tasks
+    InnerScheme::new(
+        RecData::new(
+            Some(ROOT),
+            None,
+            None,
+            Some(&TASKS_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
 ];
 
-const FILTER_INNER: [InnerScheme; 11] = [
+const FILTER_INNER: [InnerScheme; 23] = [
     // limit
     InnerScheme::empty_with("filter", RecKind::user_defined_with_num_args(1), false),
     // player-min
@@ -183,6 +583,50 @@ const FILTER_INNER: [InnerScheme; 11] = [
     InnerScheme::flag("filter", false),
     // retry-max
     InnerScheme::empty_with("filter", RecKind::user_defined_with_num_args(1), false),
+    // keep-duplicates
+    InnerScheme::flag("filter", false),
+    // keep-unknown-region
+    InnerScheme::flag("filter", false),
+    // prefer
+    InnerScheme::new(
+        RecData::new(
+            Some("filter"),
+            Some(&FILTER_PREFER_ALIAS),
+            None,
+            Some(&FILTER_PREFER_RECS),
+            RecKind::value_with_num_args(1),
+            false,
+        ),
+        None,
+    ),
+    // interactive
+    InnerScheme::flag("filter", false),
+    // preview
+    InnerScheme::flag("filter", false),
+    // active-now
+    InnerScheme::flag("filter", false),
+    // min-reliability
+    InnerScheme::empty_with("filter", RecKind::user_defined_with_num_args(1), false),
+    // deadline
+    InnerScheme::empty_with("filter", RecKind::user_defined_with_num_args(1), false),
+    // browser-order
+    InnerScheme::new(
+        RecData::new(
+            Some("filter"),
+            None,
+            None,
+            Some(&FILTER_BROWSER_ORDER_RECS),
+            RecKind::value_with_num_args(1),
+            false,
+        ),
+        None,
+    ),
+    // near-me
+    InnerScheme::flag("filter", false),
+    // no-ping
+    InnerScheme::flag("filter", false),
+    // yes
+    InnerScheme::flag("filter", false),
 ];
 
 const RECONNECT_INNTER: [InnerScheme; 2] = [
@@ -191,3 +635,100 @@ const RECONNECT_INNTER: [InnerScheme; 2] = [
     // connect
     InnerScheme::empty_with("reconnect", RecKind::user_defined_with_num_args(1), true),
 ];
+
+const CONSOLE_INNER: [InnerScheme; 3] = [
+    // tail
+    InnerScheme::empty_with("console", RecKind::user_defined_with_num_args(1), false),
+    // grep
+    InnerScheme::empty_with("console", RecKind::user_defined_with_num_args(1), false),
+    // since
+    InnerScheme::empty_with("console", RecKind::user_defined_with_num_args(1), false),
+];
+
+const LAUNCH_INNER: [InnerScheme; 1] = [
+    // exe
+    InnerScheme::new(
+        RecData::new(
+            Some("launch"),
+            None,
+            None,
+            Some(&LAUNCH_EXE_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+];
+
+const DASHBOARD_INNER: [InnerScheme; 2] = [
+    // interval
+    InnerScheme::empty_with("dashboard", RecKind::user_defined_with_num_args(1), false),
+    // ping-refresh
+    InnerScheme::flag("dashboard", false),
+];
+
+const INFO_INNER: [InnerScheme; 1] = [
+    // dvars
+    InnerScheme::flag("info", false),
+];
+
+const RCON_INNER: [InnerScheme; 3] = [
+    // password
+    InnerScheme::empty_with("rcon", RecKind::user_defined_with_num_args(1), false),
+    // save
+    InnerScheme::flag("rcon", false),
+    // forget
+    InnerScheme::flag("rcon", false),
+];
+
+const JOURNAL_INNER: [InnerScheme; 2] = [
+    // today
+    InnerScheme::flag("journal", false),
+    // server
+    InnerScheme::empty_with("journal", RecKind::user_defined_with_num_args(1), false),
+];
+
+const ATTACH_INNER: [InnerScheme; 1] = [
+    // log
+    InnerScheme::empty_with("attach", RecKind::user_defined_with_num_args(1), true),
+];
+
+const IMPORT_INNER: [InnerScheme; 1] = [
+    // from
+    InnerScheme::new(
+        RecData::new(
+            Some("import"),
+            None,
+            None,
+            Some(&IMPORT_FROM_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+];
+
+const LOG_LEVEL_INNER: [InnerScheme; 1] = [
+    // target
+    InnerScheme::new(
+        RecData::new(
+            Some("log-level"),
+            None,
+            None,
+            Some(&LOG_TARGET_RECS),
+            RecKind::value_with_num_args(1),
+            true,
+        ),
+        None,
+    ),
+];
+
+const RUN_INNER: [InnerScheme; 1] = [
+    // stop-on-error
+    InnerScheme::flag("run", false),
+];
+
+const PROMPT_INNER: [InnerScheme; 1] = [
+    // reset
+    InnerScheme::flag("prompt", true),
+];
@@ -0,0 +1,210 @@
+use crate::{
+    cli::AlertsCmd,
+    commands::handler::{CommandContext, CommandHandle, Message},
+    strip_color_codes, utc_date_string, utc_date_time,
+    utils::input::style::{MAGENTA, WHITE, YELLOW},
+};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::sync::{mpsc::Sender, Mutex};
+use tracing::{error, info};
+
+const CHAT_DIR: &str = "chat";
+const CHAT_FILE_PREFIX: &str = "chat_";
+const ALERTS_FILE: &str = "alerts.json";
+
+const SAY_PREFIX: &str = "say;";
+const SAY_TEAM_PREFIX: &str = "sayteam;";
+
+pub struct ChatMessage {
+    pub name: String,
+    pub message: String,
+    pub team: bool,
+}
+
+impl ChatMessage {
+    /// Lines use the games_mp.log format: `say;guid;client_num;name;message`
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let line = line
+            .split_once(' ')
+            .filter(|(timestamp, _)| {
+                !timestamp.is_empty() && timestamp.chars().all(|c| c.is_ascii_digit() || c == ':')
+            })
+            .map_or(line, |(_, rest)| rest.trim_start());
+
+        let (chat, team) = if let Some(chat) = line.strip_prefix(SAY_PREFIX) {
+            (chat, false)
+        } else {
+            (line.strip_prefix(SAY_TEAM_PREFIX)?, true)
+        };
+        let mut fields = chat.splitn(4, ';');
+        let (_guid, _num) = (fields.next()?, fields.next()?);
+        let name = strip_color_codes(fields.next()?);
+        let message = strip_color_codes(fields.next()?.trim_start_matches('\u{15}'));
+        Some(ChatMessage {
+            name,
+            message,
+            team,
+        })
+    }
+}
+
+/// Writes chat to a log file per day and watches for alert words
+pub struct ChatLog {
+    local_dir: Option<PathBuf>,
+    alerts: Vec<String>,
+    /// Player name read from the players2 profile, never saved to the alerts file
+    name_alert: Option<String>,
+}
+
+impl ChatLog {
+    pub fn new(local_dir: Option<&Path>) -> Self {
+        let alerts = local_dir
+            .and_then(|dir| std::fs::read(dir.join(ALERTS_FILE)).ok())
+            .and_then(|bytes| serde_json::from_slice::<Vec<String>>(&bytes).ok())
+            .unwrap_or_default();
+        ChatLog {
+            local_dir: local_dir.map(Path::to_path_buf),
+            alerts,
+            name_alert: None,
+        }
+    }
+
+    /// Highlights chat mentioning the player's own name
+    pub fn set_name_alert(&mut self, name: &str) {
+        let name = name.trim().to_lowercase();
+        self.name_alert = (!name.is_empty()).then_some(name);
+    }
+
+    #[inline]
+    pub fn name_alert(&self) -> Option<&str> {
+        self.name_alert.as_deref()
+    }
+
+    #[inline]
+    pub fn alerts(&self) -> &[String] {
+        &self.alerts
+    }
+
+    /// Returns `false` if `word` was already an alert
+    pub fn add_alert(&mut self, word: &str) -> io::Result<bool> {
+        let word = word.trim().to_lowercase();
+        if self.alerts.contains(&word) {
+            return Ok(false);
+        }
+        self.alerts.push(word);
+        self.save_alerts().map(|_| true)
+    }
+
+    /// Returns `false` if `word` was not an alert
+    pub fn remove_alert(&mut self, word: &str) -> io::Result<bool> {
+        let word = word.trim().to_lowercase();
+        let len = self.alerts.len();
+        self.alerts.retain(|alert| *alert != word);
+        if self.alerts.len() == len {
+            return Ok(false);
+        }
+        self.save_alerts().map(|_| true)
+    }
+
+    fn save_alerts(&self) -> io::Result<()> {
+        let Some(ref dir) = self.local_dir else {
+            return Ok(());
+        };
+        let file = std::fs::File::create(dir.join(ALERTS_FILE))?;
+        serde_json::to_writer_pretty(file, &self.alerts)?;
+        Ok(())
+    }
+
+    /// Returns the first alert word `chat` mentions
+    pub fn mentions(&self, chat: &ChatMessage) -> Option<&str> {
+        let message = chat.message.to_lowercase();
+        self.name_alert
+            .iter()
+            .chain(self.alerts.iter())
+            .find(|alert| message.contains(alert.as_str()))
+            .map(String::as_str)
+    }
+
+    pub fn record(&self, chat: &ChatMessage) -> io::Result<()> {
+        let Some(ref dir) = self.local_dir else {
+            return Ok(());
+        };
+        let now = SystemTime::now();
+        let dir = dir.join(CHAT_DIR);
+        std::fs::create_dir_all(&dir)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(format!("{CHAT_FILE_PREFIX}{}.log", utc_date_string(now))))?;
+        let (_, (hour, min, sec)) = utc_date_time(now);
+        writeln!(
+            file,
+            "{hour:02}:{min:02}:{sec:02} {}{}: {}",
+            if chat.team { "(Team) " } else { "" },
+            chat.name,
+            chat.message
+        )
+    }
+}
+
+/// Logs `chat` and prints a highlighted line if it mentions any alert word
+pub async fn process_chat(
+    chat_log_arc: &Arc<Mutex<ChatLog>>,
+    msg_sender: &Arc<Sender<Message>>,
+    chat: ChatMessage,
+) {
+    let chat_log = chat_log_arc.lock().await;
+    if let Err(err) = chat_log.record(&chat) {
+        error!("Failed to write chat log, {err}");
+    }
+    if let Some(word) = chat_log.mentions(&chat) {
+        let _ = msg_sender
+            .send(Message::Str(format!(
+                "{MAGENTA}[{word}] {}: {}{WHITE}",
+                chat.name, chat.message
+            )))
+            .await;
+    }
+}
+
+pub async fn alerts(context: &CommandContext, cmd: AlertsCmd) -> CommandHandle {
+    let chat_log_arc = context.chat_log();
+    let mut chat_log = chat_log_arc.lock().await;
+    let result = match cmd {
+        AlertsCmd::Add { word } => chat_log.add_alert(&word).map(|added| {
+            if added {
+                info!("Chat messages mentioning '{word}' will now be highlighted");
+            } else {
+                info!("'{word}' is already an alert");
+            }
+        }),
+        AlertsCmd::Remove { word } => chat_log.remove_alert(&word).map(|removed| {
+            if removed {
+                info!("Removed alert '{word}'");
+            } else {
+                info!("'{word}' is not an alert");
+            }
+        }),
+        AlertsCmd::List => {
+            if let Some(name) = chat_log.name_alert() {
+                println!("Player name: {name} (from players2 profile)");
+            }
+            if chat_log.alerts().is_empty() {
+                println!("No alerts set, use '{YELLOW}alerts add{WHITE} <WORD>' to add one");
+            } else {
+                println!("Alerts: {}", chat_log.alerts().join(", "));
+            }
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        error!("Failed to save alerts, {err}");
+    }
+    CommandHandle::Processed
+}
@@ -0,0 +1,260 @@
+use crate::{
+    commands::{
+        favorites::read_favorites,
+        filter::{try_get_info, Request, Sourced, FAVORITES},
+        handler::{CommandContext, CommandHandle, Message},
+        services::{CacheService, ConfigService},
+    },
+    utils::{
+        caching::{refresh_pings, Cache},
+        display::DisplayDashboard,
+        http::Http,
+        input::line::{EventLoop, InputEventHook, InputHook},
+        json_data::GetInfo,
+        names::Names,
+    },
+    LOG_ONLY,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc::Sender, Semaphore},
+    task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+const PROBE_CONCURRENCY: usize = 24;
+/// Favourites past this are left out, the view is meant to be read at a glance
+pub const DASHBOARD_MAX: usize = 100;
+
+pub struct DashboardRow {
+    pub socket_addr: SocketAddr,
+    /// `None` when the server did not respond to the last refresh
+    pub info: Option<GetInfo>,
+    /// Change in player count since the previous refresh
+    pub player_change: i16,
+    pub map_changed: bool,
+    /// Cached `getstatus` round trip
+    pub ping: Option<Duration>,
+}
+
+/// Full screen view of the servers in favourites.json, player counts are refreshed on an interval and
+/// changes since the previous refresh are highlighted
+pub struct Dashboard {
+    /// Most players first, unresponsive servers last
+    pub rows: Vec<DashboardRow>,
+    pub interval: Duration,
+    /// Number of favourites left out because of [`DASHBOARD_MAX`]
+    pub skipped: usize,
+    /// Time the last refresh took
+    pub elapsed: Duration,
+    /// (columns, rows)
+    pub term_size: (u16, u16),
+}
+
+impl Dashboard {
+    fn new(interval: Duration, skipped: usize) -> Self {
+        Dashboard {
+            rows: Vec::new(),
+            interval,
+            skipped,
+            elapsed: Duration::ZERO,
+            term_size: (0, 0),
+        }
+    }
+
+    fn update(&mut self, results: Vec<(SocketAddr, Option<GetInfo>)>) {
+        let prev = std::mem::take(&mut self.rows)
+            .into_iter()
+            .filter_map(|row| Some((row.socket_addr, row.info?)))
+            .collect::<HashMap<_, _>>();
+        self.rows = results
+            .into_iter()
+            .map(|(socket_addr, info)| {
+                let (player_change, map_changed) = match (prev.get(&socket_addr), &info) {
+                    (Some(prev), Some(info)) => (
+                        info.clients as i16 - prev.clients as i16,
+                        info.map_name != prev.map_name,
+                    ),
+                    _ => (0, false),
+                };
+                DashboardRow {
+                    socket_addr,
+                    info,
+                    player_change,
+                    map_changed,
+                    ping: None,
+                }
+            })
+            .collect();
+        self.rows.sort_by_key(|row| {
+            std::cmp::Reverse(
+                row.info
+                    .as_ref()
+                    .map(|info| info.clients as i16)
+                    .unwrap_or(-1),
+            )
+        });
+    }
+
+    fn fill_pings(&mut self, cache: &Cache) {
+        for row in self.rows.iter_mut() {
+            row.ping = cache.fresh_ping(row.socket_addr);
+        }
+    }
+
+    pub fn player_total(&self) -> usize {
+        self.rows
+            .iter()
+            .filter_map(|row| row.info.as_ref())
+            .map(|info| info.clients as usize)
+            .sum()
+    }
+}
+
+/// `getInfo` answer of each server in `servers`, `None` for servers that did not answer in time
+pub(crate) async fn probe_all(
+    http: &Http,
+    servers: &[SocketAddr],
+) -> Vec<(SocketAddr, Option<GetInfo>)> {
+    let permits = Arc::new(Semaphore::new(PROBE_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    for &socket_addr in servers {
+        let http = Arc::clone(http);
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let info = try_get_info(
+                Request::New(Sourced::HmwCached(socket_addr)),
+                &*http,
+                Some(PROBE_TIMEOUT),
+            )
+            .await
+            .ok()
+            .and_then(|server| server.info);
+            (socket_addr, info)
+        });
+    }
+    let mut results = Vec::with_capacity(servers.len());
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(err) => error!(name: LOG_ONLY, "{err}"),
+        }
+    }
+    results
+}
+
+/// Pings are measured again once they are older than [`crate::utils::caching::PING_MAX_AGE`], or on the first refresh if
+/// `ping_refresh` is set. Which servers answered the first refresh is added to their reliability
+#[allow(clippy::too_many_arguments)]
+async fn refresh_loop(
+    http: Http,
+    cache: CacheService,
+    config: ConfigService,
+    mut ping_refresh: bool,
+    msg_sender: Arc<Sender<Message>>,
+    servers: Vec<SocketAddr>,
+    mut dashboard: Dashboard,
+    cancel: CancellationToken,
+) {
+    if cancel.is_cancelled() {
+        return;
+    }
+    let loading = format!("Requesting info from {} servers...", servers.len());
+    if msg_sender
+        .send(Message::Screen(Some(loading)))
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let mut first_refresh = true;
+    loop {
+        let start = Instant::now();
+        let probe = async {
+            let (results, ()) = tokio::join!(
+                probe_all(&http, &servers),
+                refresh_pings(&cache, &servers, ping_refresh)
+            );
+            results
+        };
+        let results = tokio::select! {
+            _ = cancel.cancelled() => break,
+            results = probe => results,
+        };
+        ping_refresh = false;
+        if std::mem::take(&mut first_refresh) {
+            let mut cache_guard = cache.lock().await;
+            for (server, info) in results.iter() {
+                cache_guard.reliability.record(*server, info.is_some());
+            }
+            cache.mark_changed();
+        }
+        dashboard.elapsed = start.elapsed();
+        dashboard.update(results);
+        dashboard.fill_pings(&*cache.lock().await);
+        dashboard.term_size = crossterm::terminal::size().unwrap_or((80, 24));
+        let settings = config.current();
+        let screen = DisplayDashboard(&dashboard, Names::from(&settings.display)).to_string();
+        if msg_sender
+            .send(Message::Screen(Some(screen)))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(dashboard.interval) => (),
+        }
+    }
+    let _ = msg_sender.send(Message::Screen(None)).await;
+}
+
+pub async fn dashboard(
+    context: &mut CommandContext,
+    interval: u64,
+    ping_refresh: bool,
+) -> CommandHandle {
+    let path = context.favorites_targets().swap_remove(0);
+    let mut servers = match read_favorites(&path).await {
+        Ok(servers) if servers.is_empty() => {
+            error!("{FAVORITES} has no servers, create a list with `filter`");
+            return CommandHandle::Processed;
+        }
+        Ok(servers) => servers,
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    };
+    let skipped = servers.len().saturating_sub(DASHBOARD_MAX);
+    servers.truncate(DASHBOARD_MAX);
+
+    let cancel = CancellationToken::new();
+    tokio::spawn(refresh_loop(
+        context.http(),
+        context.cache().clone(),
+        context.config_service().clone(),
+        ping_refresh,
+        context.msg_sender(),
+        servers,
+        Dashboard::new(Duration::from_secs(interval), skipped),
+        cancel.clone(),
+    ));
+
+    // the refresh loop runs until the hook, and with it `stop`, is dropped
+    let stop = cancel.drop_guard();
+    let input_hook: Box<InputEventHook> = Box::new(move |_, _| {
+        let _ = &stop;
+        Ok((EventLoop::Continue, true))
+    });
+    CommandHandle::InsertHook(InputHook::with_new_uid(None, input_hook))
+}
@@ -0,0 +1,157 @@
+use crate::{
+    cli::IpFamily,
+    commands::{
+        filter::{hmw_servers, iw4_servers, queue_info_requests, Sourced},
+        handler::{BackgroundTask, CommandContext, CommandHandle, Message},
+    },
+    utils::{
+        display::{DisplayError, DisplayMasterDiff},
+        json_data::{HostMove, MasterSnapshot},
+        lenient::skipped_report,
+        progress::Progress,
+    },
+    LOG_ONLY,
+};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::SystemTime};
+use tracing::error;
+
+/// Smallest change in players that is reported
+pub const POPULATION_SWING: u8 = 6;
+
+pub struct SnapshotDiff {
+    /// When the previous snapshot was taken
+    pub since: SystemTime,
+    pub listed: usize,
+    pub added: Vec<SocketAddr>,
+    pub removed: Vec<SocketAddr>,
+    /// (server, players before, players now), largest change first
+    pub swings: Vec<(SocketAddr, u8, u8)>,
+    /// Filled in from [`crate::utils::caching::Cache::host_moves`], oldest first
+    pub moved: Vec<HostMove>,
+}
+
+/// Servers listed in only one of the snapshots, and servers in both whose player count changed by at
+/// least [`POPULATION_SWING`]
+pub fn compare(prev: &MasterSnapshot, curr: &MasterSnapshot) -> SnapshotDiff {
+    let mut added = curr
+        .servers
+        .keys()
+        .filter(|server| !prev.servers.contains_key(server))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut removed = prev
+        .servers
+        .keys()
+        .filter(|server| !curr.servers.contains_key(server))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut swings = curr
+        .servers
+        .iter()
+        .filter_map(|(&server, &now)| Some((server, (*prev.servers.get(&server)?)?, now?)))
+        .filter(|&(_, before, now)| before.abs_diff(now) >= POPULATION_SWING)
+        .collect::<Vec<_>>();
+    added.sort_unstable();
+    removed.sort_unstable();
+    swings.sort_unstable_by_key(|&(server, before, now)| {
+        (std::cmp::Reverse(before.abs_diff(now)), server)
+    });
+    SnapshotDiff {
+        since: prev.taken,
+        listed: curr.servers.len(),
+        added,
+        removed,
+        swings,
+        moved: Vec::new(),
+    }
+}
+
+/// Compares what the master servers list now with what they listed the last time it ran, a server that
+/// drops off a master list disappears from everyone's browser without an error
+pub fn diff(context: &CommandContext) -> CommandHandle {
+    let cache_arc = context.cache().clone();
+    let msg_sender = context.msg_sender();
+    let http = context.http();
+
+    CommandHandle::Background(BackgroundTask::new(
+        "diff",
+        context.cancel_token(),
+        async move {
+            // both lists are needed, a master that is down would look like all of its servers left
+            let lists = tokio::try_join!(
+                iw4_servers(&*http, None, IpFamily::default()),
+                hmw_servers(&*http, None, IpFamily::default())
+            );
+            let (iw4, hmw) = match lists {
+                Ok(lists) => lists,
+                Err(err) => {
+                    let _ = msg_sender
+                        .send(Message::Err(format!(
+                            "{}\nThe snapshot was not updated",
+                            DisplayError(&err)
+                        )))
+                        .await;
+                    return;
+                }
+            };
+
+            if let Some(skipped) = skipped_report() {
+                let _ = msg_sender.send(Message::Warn(skipped)).await;
+            }
+
+            let mut servers = HashMap::with_capacity(iw4.len() + hmw.len());
+            let mut names = Vec::new();
+            for source in iw4.iter() {
+                if let Sourced::Iw4(meta) = source {
+                    servers.insert(meta.resolved_addr, Some(meta.server.clients));
+                    names.push((meta.resolved_addr, Arc::clone(&meta.server.host_name)));
+                }
+            }
+            // the hmw master does not list players
+            let mut tasks = Vec::with_capacity(hmw.len());
+            queue_info_requests(hmw, &mut tasks, true, &http).await;
+            let mut progress = Progress::new("servers answered", tasks.len(), &msg_sender);
+            for task in tasks {
+                let result = task.await;
+                progress.inc();
+                match result {
+                    Ok(Ok(server)) => {
+                        let addr = server.source.socket_addr();
+                        let info = server.info.as_ref();
+                        servers.insert(addr, info.map(|info| info.clients));
+                        names.extend(info.map(|info| (addr, Arc::clone(&info.host_name))));
+                    }
+                    Ok(Err(err)) => {
+                        servers.entry(err.meta.socket_addr()).or_insert(None);
+                    }
+                    Err(err) => error!(name: LOG_ONLY, "{err}"),
+                }
+            }
+            drop(progress);
+
+            let curr = MasterSnapshot {
+                taken: SystemTime::now(),
+                servers,
+            };
+            let mut cache = cache_arc.lock().await;
+            for (addr, name) in names {
+                cache.book.record(addr, name, curr.taken);
+            }
+            let msg = match cache.snapshot {
+                Some(ref prev) => {
+                    let mut diff = compare(prev, &curr);
+                    diff.moved = std::mem::take(&mut cache.host_moves);
+                    DisplayMasterDiff(&diff, &cache.book).to_string()
+                }
+                None => format!(
+                    "Saved the {} servers listed now, run `diff` again later to see what changed",
+                    curr.servers.len()
+                ),
+            };
+            cache.snapshot = Some(curr);
+            drop(cache);
+            cache_arc.mark_changed();
+            let _ = msg_sender.send(Message::Str(msg)).await;
+        },
+    ))
+}
@@ -0,0 +1,187 @@
+use crate::{
+    cli::IpFamily,
+    commands::{
+        filter::{hmw_servers, iw4_servers, try_location_lookup, FAVORITES_LOC},
+        handler::{CommandContext, CommandHandle},
+        launch_h2m::pty_spawn_check,
+    },
+    error::Error,
+    find_game_executables,
+    utils::{
+        display::DisplayDiagnostics,
+        http::HttpFetch,
+        lenient::take_skipped,
+        players2::{check_writable, inspect},
+    },
+    LOCAL_DATA, REQUIRED_FILES,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+    time::Duration,
+};
+
+/// Any public address works, the lookup only confirms the geolocation API answers
+const LOOKUP_TEST_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Diagnostic {
+    pub name: &'static str,
+    pub result: Result<String, Failure>,
+}
+
+pub struct Failure {
+    pub err: String,
+    /// What the user can do to fix the problem
+    pub hint: &'static str,
+}
+
+impl Diagnostic {
+    fn new(name: &'static str, result: Result<String, Failure>) -> Self {
+        Diagnostic { name, result }
+    }
+}
+
+fn fail<T>(err: impl ToString, hint: &'static str) -> Result<T, Failure> {
+    Err(Failure {
+        err: err.to_string(),
+        hint,
+    })
+}
+
+async fn check_game_files(exe_dir: &Path) -> Result<String, Failure> {
+    const HINT: &str = concat!(
+        "Move ",
+        env!("CARGO_PKG_NAME"),
+        ".exe into the 'Call of Duty Modern Warfare Remastered' directory next to your H2M client, \
+        or set it with `settings launch.game-dir <PATH>`"
+    );
+    if !exe_dir.join(REQUIRED_FILES[0]).is_file() {
+        return fail(format!("{} not found", REQUIRED_FILES[0]), HINT);
+    }
+    match find_game_executables(exe_dir).await {
+        Ok(found) if found.is_empty() => fail("No supported game client found", HINT),
+        Ok(found) => Ok(found.join(", ")),
+        Err(err) => fail(err, HINT),
+    }
+}
+
+fn check_players2(exe_dir: &Path) -> Result<String, Failure> {
+    const HINT: &str =
+        "Make sure the game directory is not read-only, installs under 'Program Files' may \
+        need MatchWire to be run as administrator";
+    let players2 = exe_dir.join(FAVORITES_LOC);
+    if !players2.is_dir() {
+        return fail(
+            format!("{FAVORITES_LOC} folder is missing"),
+            "Use command 'favorites repair' to create it",
+        );
+    }
+    match inspect(exe_dir).into_iter().next() {
+        None => Ok(players2.display().to_string()),
+        Some(issue) if issue.repairable() => {
+            fail(issue, "Use command 'favorites repair' to fix it")
+        }
+        Some(issue) => fail(issue, HINT),
+    }
+}
+
+fn check_local_data(local_dir: Option<&Path>) -> Result<String, Failure> {
+    const HINT: &str =
+        "Settings, cache and logs are saved under %LOCALAPPDATA%, make sure it is set \
+        and that your user can write to it";
+    if std::env::var_os(LOCAL_DATA).is_none() {
+        return fail(format!("%{LOCAL_DATA}% is not set"), HINT);
+    }
+    let Some(local_dir) = local_dir else {
+        return fail("App data folder could not be created at startup", HINT);
+    };
+    match check_writable(local_dir) {
+        Ok(()) => Ok(local_dir.display().to_string()),
+        Err(err) => fail(err, HINT),
+    }
+}
+
+async fn check_master_servers(http: &dyn HttpFetch) -> Result<String, Failure> {
+    const HINT: &str =
+        "Check your internet connection and that a firewall or antivirus is not blocking \
+        MatchWire, the master servers may also be down temporarily";
+    let (iw4, hmw) = tokio::join!(
+        tokio::time::timeout(
+            NETWORK_TIMEOUT,
+            iw4_servers(http, None, IpFamily::default())
+        ),
+        tokio::time::timeout(
+            NETWORK_TIMEOUT,
+            hmw_servers(http, None, IpFamily::default())
+        )
+    );
+    let count = |servers: Result<Result<Vec<_>, Error>, _>, name: &str| match servers {
+        Ok(Ok(servers)) => Ok(format!("{name}: {} servers", servers.len())),
+        Ok(Err(Error::MasterApi { source, .. })) => {
+            Err(format!("{name}: {}", source.without_url()))
+        }
+        Ok(Err(err)) => Err(format!("{name}: {err}")),
+        Err(_) => Err(format!("{name}: timed out")),
+    };
+    let skipped = take_skipped().into_values().sum::<usize>();
+    match (count(iw4, "iw4m"), count(hmw, "hmw")) {
+        (Ok(iw4), Ok(hmw)) if skipped > 0 => Ok(format!(
+            "{iw4}, {hmw}, {skipped} entries in an unexpected format were skipped"
+        )),
+        (Ok(iw4), Ok(hmw)) => Ok(format!("{iw4}, {hmw}")),
+        (Err(err), Ok(_)) | (Ok(_), Err(err)) => fail(err, HINT),
+        (Err(iw4), Err(hmw)) => fail(format!("{iw4}, {hmw}"), HINT),
+    }
+}
+
+async fn check_geolocation(http: &dyn HttpFetch) -> Result<String, Failure> {
+    const HINT: &str = "Region filters will not work, check your internet connection or try again \
+        later in case the daily lookup limit was reached";
+    match tokio::time::timeout(NETWORK_TIMEOUT, try_location_lookup(&LOOKUP_TEST_IP, http)).await {
+        Ok(Ok(_)) => Ok(String::from("lookup succeeded")),
+        Ok(Err(err)) => fail(err, HINT),
+        Err(_) => fail("timed out", HINT),
+    }
+}
+
+async fn check_pty(context: &CommandContext) -> Result<String, Failure> {
+    const HINT: &str =
+        "H2M's console can not be captured, make sure you are on Windows 10 (1809) or \
+        newer and that antivirus software is not blocking conhost.exe";
+    if context.game_control().connected() {
+        return Ok(String::from("H2M is running in a pseudoconsole"));
+    }
+    match tokio::task::spawn_blocking(pty_spawn_check).await {
+        Ok(Ok(())) => Ok(String::from("test console spawned")),
+        Ok(Err(err)) => fail(err, HINT),
+        Err(err) => fail(err, HINT),
+    }
+}
+
+/// Checks the environment problems that most often keep MatchWire from working
+pub async fn doctor(context: &CommandContext) -> CommandHandle {
+    let exe_dir = context
+        .game()
+        .path
+        .parent()
+        .expect("game executable is in a directory");
+    println!("Running diagnostics...");
+    let http = context.http();
+    let (game_files, master, geolocation, pty) = tokio::join!(
+        check_game_files(exe_dir),
+        check_master_servers(&*http),
+        check_geolocation(&*http),
+        check_pty(context)
+    );
+    let diagnostics = [
+        Diagnostic::new("Game files", game_files),
+        Diagnostic::new("players2 writable", check_players2(exe_dir)),
+        Diagnostic::new("Local app data", check_local_data(context.local_dir())),
+        Diagnostic::new("Master servers", master),
+        Diagnostic::new("Geolocation API", geolocation),
+        Diagnostic::new("Pseudoconsole", pty),
+    ];
+    println!("{}", DisplayDiagnostics(&diagnostics));
+    CommandHandle::Processed
+}
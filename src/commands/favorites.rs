@@ -0,0 +1,685 @@
+use crate::{
+    cli::{Command, FavoritesCmd, Filters, UserCommand},
+    commands::{
+        filter::{
+            favorite_entry, try_location_lookup, Location, DEFAULT_H2M_SERVER_CAP, FAVORITES,
+            FAVORITES_LOC,
+        },
+        handler::{new_favorites_with, BackgroundTask, CommandContext, CommandHandle, Message},
+        query::FilterQuery,
+        services::CacheService,
+    },
+    error::{Error, FetchError},
+    parse_hostname, strip_color_codes,
+    utils::{
+        config::Config,
+        display::{DisplayCountOf, DisplayError},
+        getstatus::get_status,
+        http::Http,
+        input::style::{GREEN, WHITE, YELLOW},
+        players2::repair,
+        strict::{self, ProblemKind},
+    },
+    LOG_ONLY,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use clap::Parser;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{mpsc::Sender, Mutex, Semaphore},
+    task::JoinSet,
+};
+use tracing::{error, info, warn};
+
+const SERVERS_PREFIX: &str = "mw1s.";
+const PRESET_PREFIX: &str = "mw1f.";
+const TAG_V4: u8 = 4;
+const TAG_V6: u8 = 6;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+/// `getstatus` is sent over UDP, a server only counts as dead after this many unanswered requests
+const CHECK_ATTEMPTS: usize = 2;
+const CHECK_CONCURRENCY: usize = 64;
+
+/// Held while favourites.json is written, so two commands finishing together can not interleave
+static WRITE_LOCK: Mutex<()> = Mutex::const_new(());
+/// Ticket of the newest write, a write waiting on [`WRITE_LOCK`] is skipped once a later one is queued
+static LATEST_WRITE: AtomicU64 = AtomicU64::new(0);
+
+/// Whether [`write_targets`] wrote its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    /// A newer write was queued before this one started, nothing was written
+    Superseded,
+}
+
+/// Reported in place of the result of a write that was [`WriteOutcome::Superseded`]
+pub const SUPERSEDED: &str =
+    "A newer change to favourites.json replaced this one before it was written";
+
+/// What a shared code or json list holds
+#[derive(Debug, PartialEq, Eq)]
+pub enum SharedList {
+    Servers(Vec<SocketAddr>),
+    /// Arguments for the `filter` command
+    Preset(String),
+}
+
+/// `mw1s.` followed by the url safe base64 of each server packed as an address tag (4 or 6), the
+/// address bytes and the port in big endian
+pub fn encode_servers(servers: &[SocketAddr]) -> String {
+    let mut bytes = Vec::with_capacity(servers.len() * 7);
+    for server in servers {
+        match server.ip() {
+            IpAddr::V4(ip) => {
+                bytes.push(TAG_V4);
+                bytes.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                bytes.push(TAG_V6);
+                bytes.extend_from_slice(&ip.octets());
+            }
+        }
+        bytes.extend_from_slice(&server.port().to_be_bytes());
+    }
+    format!("{SERVERS_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// `mw1f.` followed by the url safe base64 of the `filter` arguments
+pub fn encode_preset(args: &str) -> String {
+    format!("{PRESET_PREFIX}{}", URL_SAFE_NO_PAD.encode(args.trim()))
+}
+
+fn decode_servers(bytes: &[u8]) -> Result<Vec<SocketAddr>, String> {
+    const TRUNCATED: &str = "Code is incomplete, make sure it was copied in full";
+    let mut servers = Vec::new();
+    let mut rest = bytes;
+    while let Some((&tag, tail)) = rest.split_first() {
+        let ip_len = match tag {
+            TAG_V4 => 4,
+            TAG_V6 => 16,
+            _ => return Err(format!("Unknown address tag: {tag}")),
+        };
+        if tail.len() < ip_len + 2 {
+            return Err(String::from(TRUNCATED));
+        }
+        let (ip, tail) = tail.split_at(ip_len);
+        let ip = match tag {
+            TAG_V4 => IpAddr::V4(Ipv4Addr::from(
+                <[u8; 4]>::try_from(ip).expect("length checked"),
+            )),
+            _ => IpAddr::V6(Ipv6Addr::from(
+                <[u8; 16]>::try_from(ip).expect("length checked"),
+            )),
+        };
+        let port = u16::from_be_bytes([tail[0], tail[1]]);
+        servers.push(SocketAddr::new(ip, port));
+        rest = &tail[2..];
+    }
+    Ok(servers)
+}
+
+/// Reads a code or a json list of `IP:PORT` strings, the same format as favourites.json
+pub fn decode(input: &str) -> Result<SharedList, String> {
+    let input = input.trim();
+    if input.starts_with('[') {
+        let entries = serde_json::from_str::<Vec<String>>(input)
+            .map_err(|err| format!("Invalid server list, {err}"))?;
+        return Ok(SharedList::Servers(parse_entries(&entries)));
+    }
+    let invalid = |err: base64::DecodeError| format!("Invalid code, {err}");
+    if let Some(code) = input.strip_prefix(SERVERS_PREFIX) {
+        let bytes = URL_SAFE_NO_PAD.decode(code).map_err(invalid)?;
+        return decode_servers(&bytes).map(SharedList::Servers);
+    }
+    if let Some(code) = input.strip_prefix(PRESET_PREFIX) {
+        let bytes = URL_SAFE_NO_PAD.decode(code).map_err(invalid)?;
+        return String::from_utf8(bytes)
+            .map(SharedList::Preset)
+            .map_err(|_| String::from("Invalid code, preset is not valid text"));
+    }
+    Err(format!(
+        "Expected a code starting with '{SERVERS_PREFIX}' or '{PRESET_PREFIX}', a json list or a url"
+    ))
+}
+
+/// Entries that are not valid `IP:PORT`s are skipped
+fn parse_entries(entries: &[String]) -> Vec<SocketAddr> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.trim().parse().ok())
+        .collect()
+}
+
+#[inline]
+pub fn favorites_path(game_dir: &Path) -> PathBuf {
+    game_dir.join(FAVORITES_LOC).join(FAVORITES)
+}
+
+/// Every favourites.json that is written, the first one is also the one read
+pub fn favorites_targets(game_dir: &Path, targets: &[PathBuf]) -> Vec<PathBuf> {
+    if targets.is_empty() {
+        return vec![favorites_path(game_dir)];
+    }
+    targets.iter().map(|target| game_dir.join(target)).collect()
+}
+
+/// Servers listed in favourites.json, entries that are not valid `IP:PORT`s are skipped
+pub async fn read_favorites(path: &Path) -> Result<Vec<SocketAddr>, String> {
+    let contents = tokio::fs::read(path)
+        .await
+        .map_err(|err| format!("Could not read {FAVORITES}, {err}"))?;
+    let entries = serde_json::from_slice::<Vec<String>>(&contents).map_err(|err| {
+        format!("Could not parse {FAVORITES}, {err}. Use `favorites repair` to rebuild it")
+    })?;
+    Ok(parse_entries(&entries))
+}
+
+pub async fn write_favorites(
+    targets: &[PathBuf],
+    servers: &[SocketAddr],
+) -> Result<WriteOutcome, Error> {
+    let entries = servers
+        .iter()
+        .copied()
+        .map(favorite_entry)
+        .collect::<Vec<_>>();
+    let json = serde_json::to_vec(&entries).expect("strings always serialize");
+    write_targets(targets, &json).await
+}
+
+/// Writes `contents` to every target. One failed write does not stop the rest, the first error is
+/// returned and any others are logged. Writes are made one at a time and the last one queued wins, one
+/// that is still waiting when another is queued is skipped since it would be replaced right after
+pub async fn write_targets(targets: &[PathBuf], contents: &[u8]) -> Result<WriteOutcome, Error> {
+    let ticket = LATEST_WRITE.fetch_add(1, Ordering::SeqCst) + 1;
+    let _guard = WRITE_LOCK.lock().await;
+    if LATEST_WRITE.load(Ordering::SeqCst) > ticket {
+        return Ok(WriteOutcome::Superseded);
+    }
+    let mut result = Ok(WriteOutcome::Written);
+    for target in targets {
+        if let Err(err) = replace_file(target, contents).await {
+            let err = Error::io("write", target, err);
+            if result.is_ok() {
+                result = Err(err);
+            } else {
+                strict::report(ProblemKind::WriteFailed, &err.to_string());
+                error!(name: LOG_ONLY, "{err}");
+            }
+        }
+    }
+    result
+}
+
+/// Writes `contents` to a file next to `target` and renames it over `target`, so a write that is cut
+/// short leaves the previous contents in place
+async fn replace_file(target: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = target.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let written = async {
+        let mut file = tokio::fs::File::create(&temp).await?;
+        file.write_all(contents).await?;
+        file.sync_all().await
+    };
+    if let Err(err) = written.await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(err);
+    }
+    tokio::fs::rename(&temp, target).await.inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
+}
+
+/// Validates `args` the same way the `filter` command would
+pub(crate) fn parse_preset(args: &str) -> Result<Option<Filters>, String> {
+    let words = shellwords::split(args).map_err(|err| err.to_string())?;
+    let tokens = [String::new(), String::from("filter")]
+        .into_iter()
+        .chain(words);
+    match UserCommand::try_parse_from(tokens) {
+        Ok(UserCommand {
+            command: Command::Filter { args },
+        }) => Ok(args),
+        Ok(_) => unreachable!("parsed as `filter`"),
+        Err(err) => Err(err.render().to_string()),
+    }
+}
+
+pub async fn favorites(context: &mut CommandContext, command: FavoritesCmd) -> CommandHandle {
+    let targets = context.favorites_targets();
+    let path = &targets[0];
+    match command {
+        FavoritesCmd::ExportCode {
+            preset: Some(args), ..
+        } => match parse_preset(&args) {
+            Ok(_) => println!("{}", encode_preset(&args)),
+            Err(err) => error!("Invalid filter preset, {err}"),
+        },
+        FavoritesCmd::ExportCode { json, preset: None } => match read_favorites(path).await {
+            Ok(servers) if servers.is_empty() => error!("{FAVORITES} has no servers to export"),
+            Ok(servers) if json => println!(
+                "{}",
+                serde_json::to_string_pretty(
+                    &servers
+                        .iter()
+                        .copied()
+                        .map(favorite_entry)
+                        .collect::<Vec<_>>()
+                )
+                .expect("strings always serialize")
+            ),
+            Ok(servers) => {
+                println!("{}", encode_servers(&servers));
+                info!(
+                    "Share this code, it imports {} with `favorites import <CODE>`",
+                    DisplayCountOf(servers.len(), "server", "servers")
+                );
+            }
+            Err(err) => error!("{err}"),
+        },
+        FavoritesCmd::Import { source, merge } => {
+            return import(context, &targets, source, merge).await
+        }
+        FavoritesCmd::Check { fix } => return check(context, targets, fix),
+        FavoritesCmd::Repair => {
+            match repair(context.game_path().parent().expect("has parent")).await {
+                Ok(repaired) => print!("{repaired}"),
+                Err(err) => error!("{}", DisplayError(&err)),
+            }
+        }
+    }
+    CommandHandle::Processed
+}
+
+async fn import(
+    context: &mut CommandContext,
+    targets: &[PathBuf],
+    source: String,
+    merge: bool,
+) -> CommandHandle {
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        let fetched = async {
+            let response = context
+                .http()
+                .get(&source, None)
+                .await?
+                .error_for_status()?;
+            Ok::<_, FetchError>(String::from_utf8_lossy(&response.body).into_owned())
+        };
+        match fetched.await {
+            Ok(text) => text,
+            Err(err) => {
+                error!("Could not download '{source}', {err}");
+                return CommandHandle::Processed;
+            }
+        }
+    } else {
+        source
+    };
+
+    let mut servers = match decode(&text) {
+        Ok(SharedList::Servers(servers)) => servers,
+        Ok(SharedList::Preset(args)) => {
+            return match parse_preset(&args) {
+                Ok(filters) => {
+                    info!("Running imported filter preset: {YELLOW}filter {args}{WHITE}");
+                    new_favorites_with(filters, context)
+                }
+                Err(err) => {
+                    error!("Imported filter preset is invalid, {err}");
+                    CommandHandle::Processed
+                }
+            };
+        }
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    };
+    if servers.is_empty() {
+        error!("Nothing to import, the list has no valid servers");
+        return CommandHandle::Processed;
+    }
+
+    if merge {
+        match read_favorites(&targets[0]).await {
+            Ok(mut current) => {
+                current.append(&mut servers);
+                servers = current;
+            }
+            Err(err) => warn!("{err}, importing without merging"),
+        }
+    }
+    let mut seen = HashSet::new();
+    servers.retain(|server| seen.insert(*server));
+
+    if context.h2m_version().is_some_and(|version| version < 1.0)
+        && servers.len() > DEFAULT_H2M_SERVER_CAP
+    {
+        warn!(
+            "Only the first {DEFAULT_H2M_SERVER_CAP} of {} servers were imported, the in game \
+            server browser breaks with more",
+            servers.len()
+        );
+        servers.truncate(DEFAULT_H2M_SERVER_CAP);
+    }
+
+    match write_favorites(targets, &servers).await {
+        Ok(WriteOutcome::Written) => (),
+        Ok(WriteOutcome::Superseded) => {
+            warn!("{SUPERSEDED}");
+            return CommandHandle::Processed;
+        }
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    }
+    println!(
+        "{GREEN}{FAVORITES} {} with {}{WHITE}",
+        if merge { "merged" } else { "replaced" },
+        DisplayCountOf(servers.len(), "server", "servers")
+    );
+    CommandHandle::Processed
+}
+
+/// What `favorites check` found, names are without color codes
+#[derive(Default)]
+pub struct FavoritesCheck {
+    pub checked: usize,
+    /// Servers that did not answer, with the name they were last cached under
+    pub dead: Vec<(SocketAddr, Option<String>)>,
+    /// `(server, cached name, current name)`
+    pub renamed: Vec<(SocketAddr, String, String)>,
+    /// `(server, cached continent, current continent)`
+    pub moved: Vec<(SocketAddr, [char; 2], [char; 2])>,
+}
+
+/// `sv_hostname` of each server that answered, `None` for servers that never did
+pub(crate) async fn probe(servers: &[SocketAddr]) -> HashMap<SocketAddr, Option<String>> {
+    let permits = Arc::new(Semaphore::new(CHECK_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    for &server in servers {
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            for _ in 0..CHECK_ATTEMPTS {
+                if let Ok(mut status) = get_status(server, CHECK_TIMEOUT).await {
+                    let host_name = status.dvars.remove("sv_hostname").unwrap_or_default();
+                    return (server, Some(host_name));
+                }
+            }
+            (server, None)
+        });
+    }
+    let mut replies = HashMap::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((server, host_name)) => {
+                replies.insert(server, host_name);
+            }
+            Err(err) => error!(name: LOG_ONLY, "{err}"),
+        }
+    }
+    replies
+}
+
+/// Current location of each ip, ips the location api fails on are left out
+async fn lookup_regions(
+    ips: HashSet<IpAddr>,
+    http: &Http,
+    concurrency: usize,
+) -> HashMap<IpAddr, Location> {
+    let permits = Arc::new(Semaphore::new(if concurrency == 0 {
+        Semaphore::MAX_PERMITS
+    } else {
+        concurrency
+    }));
+    let mut tasks = JoinSet::new();
+    for ip in ips {
+        let (permits, http) = (Arc::clone(&permits), Arc::clone(http));
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            (ip, try_location_lookup(&ip, &*http).await)
+        });
+    }
+    let mut regions = HashMap::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((ip, Ok(location))) => {
+                regions.insert(ip, location);
+            }
+            Ok((_, Err(err))) => error!(name: LOG_ONLY, "{}", DisplayError(&err)),
+            Err(err) => error!(name: LOG_ONLY, "{err}"),
+        }
+    }
+    regions
+}
+
+/// Probes `servers` and compares the replies with the cache, renamed hosts and moved servers are
+/// updated in the cache
+async fn check_servers(
+    servers: &[SocketAddr],
+    cache_service: &CacheService,
+    http: &Http,
+    lookup_concurrency: usize,
+) -> FavoritesCheck {
+    let replies = probe(servers).await;
+    let cached_regions = {
+        let cache = cache_service.lock().await;
+        servers
+            .iter()
+            .filter(|server| replies.get(server).is_some_and(Option::is_some))
+            .filter_map(|server| {
+                let ip = server.ip();
+                cache.ip_to_region.get(&ip).map(|&region| (ip, region))
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let regions = lookup_regions(
+        cached_regions.keys().copied().collect(),
+        http,
+        lookup_concurrency,
+    )
+    .await;
+
+    let mut check = FavoritesCheck {
+        checked: servers.len(),
+        ..Default::default()
+    };
+    let mut cache = cache_service.lock().await;
+    for &server in servers {
+        let last_name = cache
+            .book
+            .host_name(server)
+            .map(|name| strip_color_codes(name));
+        let Some(Some(host_name)) = replies.get(&server) else {
+            check.dead.push((server, last_name));
+            continue;
+        };
+        if let Some(last_name) = last_name {
+            if parse_hostname(&last_name) != parse_hostname(host_name) {
+                check
+                    .renamed
+                    .push((server, last_name, strip_color_codes(host_name)));
+            }
+        }
+        if cache
+            .book
+            .record(server, Arc::from(host_name.as_str()), SystemTime::now())
+        {
+            cache_service.mark_changed();
+        }
+    }
+    for (ip, location) in regions {
+        let Some(&cached) = cached_regions.get(&ip) else {
+            continue;
+        };
+        let country = match location.country {
+            Some(country) => cache.ip_to_country.insert(ip, country),
+            None => cache.ip_to_country.remove(&ip),
+        };
+        if country != location.country {
+            cache_service.mark_changed();
+        }
+        let region = location.continent;
+        if cached == region {
+            continue;
+        }
+        check.moved.extend(
+            servers
+                .iter()
+                .filter(|server| server.ip() == ip)
+                .map(|&server| (server, cached, region)),
+        );
+        cache.ip_to_region.insert(ip, region);
+        cache_service.mark_changed();
+    }
+    check
+}
+
+/// Servers matching the last `filter` command that are not in `listed`, most players first
+async fn backfill(
+    preset: &str,
+    config: &Config,
+    listed: &[SocketAddr],
+    count: usize,
+    http: &Http,
+    cache: CacheService,
+    msg_sender: &Sender<Message>,
+) -> Result<Vec<SocketAddr>, String> {
+    let mut filters = parse_preset(preset)?.unwrap_or_default();
+    config.filter.apply(&mut filters);
+    let query = FilterQuery {
+        limit: None,
+        lookup_concurrency: config.filter.lookup_concurrency,
+        region_groups: config.region.clone(),
+        ..FilterQuery::from(&filters)
+    };
+    let entries = query
+        .execute_with(http, cache, Some(msg_sender))
+        .await
+        .map_err(|err| DisplayError(&err).to_string())?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| entry.socket_addr)
+        .filter(|server| !listed.contains(server))
+        .take(count)
+        .collect())
+}
+
+fn check(context: &CommandContext, targets: Vec<PathBuf>, fix: bool) -> CommandHandle {
+    let cache = context.cache().clone();
+    let msg_sender = context.msg_sender();
+    let cancel = context.cancel_token();
+    let http = context.http();
+    let config = context.config_service().current();
+
+    let task = async move {
+        let servers = match read_favorites(&targets[0]).await {
+            Ok(servers) if servers.is_empty() => {
+                let _ = msg_sender
+                    .send(Message::Err(format!("{FAVORITES} has no servers to check")))
+                    .await;
+                return;
+            }
+            Ok(servers) => servers,
+            Err(err) => {
+                let _ = msg_sender.send(Message::Err(err)).await;
+                return;
+            }
+        };
+        let _ = msg_sender
+            .send(Message::Str(format!(
+                "Checking {} in {FAVORITES}...",
+                DisplayCountOf(servers.len(), "server", "servers")
+            )))
+            .await;
+        let check = check_servers(&servers, &cache, &http, config.filter.lookup_concurrency).await;
+        if cancel.is_cancelled() {
+            return;
+        }
+        let _ = msg_sender.send(Message::Str(check.to_string())).await;
+        if !fix || check.dead.is_empty() {
+            return;
+        }
+
+        let dead = check
+            .dead
+            .iter()
+            .map(|&(server, _)| server)
+            .collect::<HashSet<_>>();
+        let mut kept = servers
+            .iter()
+            .copied()
+            .filter(|server| !dead.contains(server))
+            .collect::<Vec<_>>();
+        let preset = cache.lock().await.last_filter.clone();
+        let added = match preset {
+            Some(preset) => {
+                match backfill(
+                    &preset,
+                    &config,
+                    &servers,
+                    dead.len(),
+                    &http,
+                    cache.clone(),
+                    &msg_sender,
+                )
+                .await
+                {
+                    Ok(added) => added,
+                    Err(err) => {
+                        let _ = msg_sender
+                            .send(Message::Warn(format!(
+                                "Could not backfill with `filter {preset}`, {err}"
+                            )))
+                            .await;
+                        Vec::new()
+                    }
+                }
+            }
+            None => {
+                let _ = msg_sender
+                    .send(Message::Warn(String::from(
+                        "No `filter` has been run yet, dead servers are only removed",
+                    )))
+                    .await;
+                Vec::new()
+            }
+        };
+        if cancel.is_cancelled() {
+            return;
+        }
+        let added_count = added.len();
+        kept.extend(added);
+        let msg = match write_favorites(&targets, &kept).await {
+            Ok(WriteOutcome::Superseded) => Message::Warn(String::from(SUPERSEDED)),
+            Ok(WriteOutcome::Written) => Message::Str(format!(
+                "{GREEN}{FAVORITES} updated, removed {} and added {}{WHITE}",
+                DisplayCountOf(dead.len(), "dead server", "dead servers"),
+                DisplayCountOf(added_count, "server", "servers")
+            )),
+            Err(err) => Message::Err(DisplayError(&err).to_string()),
+        };
+        let _ = msg_sender.send(msg).await;
+    };
+    CommandHandle::Background(BackgroundTask::new(
+        "favorites check",
+        context.cancel_token(),
+        task,
+    ))
+}
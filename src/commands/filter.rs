@@ -1,143 +1,496 @@
 use crate::{
-    cli::{Filters, Region, Source},
-    location_api_key::FIND_IP_NET_PRIVATE_KEY,
-    lowercase_vec, parse_hostname,
+    cli::{BrowserOrder, Filters, IpFamily, Region, Source},
+    commands::{
+        favorites::{write_targets, WriteOutcome, SUPERSEDED},
+        handler::Message,
+        query::FilterQuery,
+        services::CacheService,
+        trim::{trim_window, TrimCandidate, TrimDecision, TrimRequest},
+    },
+    error::{Error, FetchError, GeoCause},
+    lowercase_vec, parse_hostname, parse_hostname_into, strip_color_codes,
     utils::{
-        caching::Cache,
-        display::{DisplayCountOf, DisplayGetInfoCount, DisplayServerCount, SingularPlural},
+        caching::{refresh_pings, Cache, MasterLists, SavedList},
+        config::Config,
+        display::{
+            DisplayCountOf, DisplayError, DisplayGeoFailures, DisplayGetInfoCount, DisplayPreview,
+            DisplayServerCount, SingularPlural,
+        },
+        geo_key::{key_status, record_rate_limited, take_lookup, KeySource},
+        http::{Http, HttpFetch, HttpResponse},
         input::style::{GREEN, RED, WHITE, YELLOW},
         json_data::*,
+        lenient::{parse_entries, skipped_report, ListEntry},
+        perf,
+        progress::Progress,
+        regions::{custom_regions, RegionCode, RegionSettings},
+        server_book::PlayerSample,
+        strict::{self, ProblemKind},
     },
     LOG_ONLY,
 };
 
-use reqwest::Client;
-use tokio::{sync::Mutex, task::JoinHandle};
-use tracing::{error, info, instrument, trace};
+use tokio::{
+    sync::{mpsc::Sender, oneshot, Mutex, Semaphore},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, instrument, trace};
 
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{hash_map::Entry, HashMap, HashSet},
     fmt::Display,
-    fs::File,
-    io::{self, Write},
     net::{AddrParseError, IpAddr, SocketAddr, ToSocketAddrs},
-    path::Path,
-    sync::Arc,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 
 const MASTER_LOCATION_URL: &str = "https://api.findip.net";
+/// Echoes the public address a request came from, used to estimate where `filter --near-me` runs
+const PUBLIC_IP_URL: &str = "https://api.ipify.org?format=json";
 
 const IW4_MASTER_URL: &str = "http://master.iw4.zip";
 const HMW_MASTER_URL: &str = "http://ms.s2mod.to/game-servers";
 const JSON_SERVER_ENDPOINT: &str = "/instance";
 const SERVER_GET_INFO_ENDPOINT: &str = "/getInfo";
-const FAVORITES_LOC: &str = "players2";
-const FAVORITES: &str = "favourites.json";
+pub const FAVORITES_LOC: &str = "players2";
+pub const FAVORITES: &str = "favourites.json";
 
-const DEFAULT_H2M_SERVER_CAP: usize = 100;
+pub const DEFAULT_H2M_SERVER_CAP: usize = 100;
 const DEFUALT_INFO_RETRIES: u8 = 3;
+/// Seconds a `filter` may take when no deadline is set
+pub const DEFAULT_DEADLINE_SECS: u64 = 120;
 const RETRY_TIME_SCALE: u64 = 800; // ms
+const GET_INFO_TIMEOUT: Duration = Duration::from_secs(3);
 const LOCAL_HOST: &str = "localhost";
 
 pub const GAME_ID: &str = "H2M";
-const CODE_NA: [char; 2] = ['N', 'A'];
-const CODE_EU: [char; 2] = ['E', 'U'];
-const APAC_CONT_CODES: [[char; 2]; 3] = [['A', 'F'], ['A', 'S'], ['O', 'C']];
 
-fn serialize_json(into: &mut std::fs::File, from: String) -> io::Result<()> {
+fn serialize_json(from: String) -> String {
     const COMMA: char = ',';
     let ips = if from.ends_with(COMMA) {
         &from[..from.len() - COMMA.len_utf8()]
     } else {
         from.as_str()
     };
-    write!(into, "[{ips}]")
+    format!("[{ips}]")
 }
 
-impl Region {
-    fn matches(&self, country_code: [char; 2]) -> bool {
+impl IpFamily {
+    #[inline]
+    fn matches(self, ip: IpAddr) -> bool {
         match self {
-            Region::NA if country_code != CODE_NA => false,
-            Region::EU if country_code != CODE_EU => false,
-            Region::Apac if !APAC_CONT_CODES.contains(&country_code) => false,
-            _ => true,
+            IpFamily::Ipv4 => ip.is_ipv4(),
+            IpFamily::Ipv6 => ip.is_ipv6(),
         }
     }
 }
 
-async fn get_iw4_master() -> reqwest::Result<Vec<HostData>> {
+/// How a server address is written to favourites.json, IPv6 addresses are bracketed without a scope id
+/// and IPv4 addresses mapped into IPv6 are written as plain IPv4
+pub fn favorite_entry(server: SocketAddr) -> String {
+    match server.ip().to_canonical() {
+        IpAddr::V4(ip) => format!("{ip}:{}", server.port()),
+        IpAddr::V6(ip) => format!("[{ip}]:{}", server.port()),
+    }
+}
+
+async fn get_iw4_master(
+    http: &dyn HttpFetch,
+    cache: Option<&Mutex<Cache>>,
+) -> Result<Vec<HostData>, Error> {
     trace!("retreiving iw4 master server list");
     let instance_url = format!("{IW4_MASTER_URL}{JSON_SERVER_ENDPOINT}");
-    reqwest::get(instance_url.as_str())
-        .await?
-        .json::<Vec<HostData>>()
+    get_master_list(http, &instance_url, cache, |lists| &mut lists.iw4m)
         .await
+        .map_err(|source| Error::MasterApi {
+            master: "iw4m",
+            source,
+        })
 }
 
-async fn get_hmw_master() -> reqwest::Result<Vec<String>> {
+async fn get_hmw_master(
+    http: &dyn HttpFetch,
+    cache: Option<&Mutex<Cache>>,
+) -> Result<Vec<String>, Error> {
     trace!("retreiving hmw master server list");
-    reqwest::get(HMW_MASTER_URL)
-        .await?
-        .json::<Vec<String>>()
+    get_master_list(http, HMW_MASTER_URL, cache, |lists| &mut lists.hmw)
         .await
+        .map_err(|source| Error::MasterApi {
+            master: "hmw",
+            source,
+        })
+}
+
+/// With a `cache` the list is only downloaded when it changed since it was last received, an unchanged
+/// list is taken from [`MasterLists`] as it is
+async fn get_master_list<T: ListEntry + Clone>(
+    http: &dyn HttpFetch,
+    url: &str,
+    cache: Option<&Mutex<Cache>>,
+    saved: fn(&mut MasterLists) -> &mut SavedList<T>,
+) -> Result<Vec<T>, FetchError> {
+    let Some(cache) = cache else {
+        return Ok(parse_entries(&http.get(url, None).await?.body)?);
+    };
+    let validator = saved(&mut cache.lock().await.master_lists)
+        .as_ref()
+        .map(|(validator, _)| validator.clone());
+    let response = match validator {
+        Some(ref validator) => http.get_if_changed(url, validator).await?,
+        None => http.get(url, None).await?,
+    };
+    if response.is_not_modified() {
+        trace!("{url} did not change");
+        // the cache may have been rebuilt while waiting
+        return match saved(&mut cache.lock().await.master_lists) {
+            Some((_, list)) => Ok(list.clone()),
+            None => Err(FetchError::Status(response.status)),
+        };
+    }
+    let list = parse_entries::<T>(&response.body)?;
+    if !response.validator.is_empty() {
+        *saved(&mut cache.lock().await.master_lists) = Some((response.validator, list.clone()));
+    }
+    Ok(list)
 }
 
+#[allow(clippy::too_many_arguments)]
 #[instrument(name = "filter", level = "trace", skip_all)]
 pub async fn build_favorites(
-    curr_dir: &Path,
+    http: &Http,
+    targets: &[PathBuf],
     args: &Filters,
-    cache: Arc<Mutex<Cache>>,
+    cache: CacheService,
     version: f64,
-) -> io::Result<bool> {
-    let mut ip_collected = 0;
-    let mut ips = String::new();
-    let mut favorites_json = File::create(curr_dir.join(format!("{FAVORITES_LOC}/{FAVORITES}")))?;
-    let limit = args.limit.unwrap_or({
+    config: &Config,
+    cancel: CancellationToken,
+    msg_sender: &Sender<Message>,
+) -> Result<bool, Error> {
+    let limit = favorites_limit(args, version);
+    let (servers, update_cache) = matching_servers(
+        http,
+        args,
+        cache.clone(),
+        limit,
+        version,
+        config,
+        &cancel,
+        msg_sender,
+    )
+    .await?;
+    if args.preview {
+        let cache = cache.lock().await;
+        let groups = preview_groups(&servers, &cache.ip_to_region);
+        let _ = msg_sender
+            .send(Message::Str(
+                DisplayPreview(&groups, servers.len(), limit, &cache.reliability).to_string(),
+            ))
+            .await;
+        return Ok(update_cache);
+    }
+    let regions = match args.browser_order {
+        Some(BrowserOrder::Region) => cache.lock().await.ip_to_region.clone(),
+        _ => HashMap::new(),
+    };
+    write_matching(
+        targets,
+        servers,
+        limit,
+        args.browser_order,
+        &regions,
+        &config.region,
+        !args.yes,
+        msg_sender,
+    )
+    .await?;
+    Ok(update_cache)
+}
+
+/// Servers that share a continent, see [`preview_groups`]
+pub struct RegionGroup<'a> {
+    /// `None` when the region was never looked up
+    pub region: Option<[char; 2]>,
+    pub hosts: Vec<HostGroup<'a>>,
+}
+
+impl RegionGroup<'_> {
+    pub fn server_count(&self) -> usize {
+        self.hosts.iter().map(|host| host.servers.len()).sum()
+    }
+}
+
+/// Servers listed by one community, or on one IP address when the community is not known
+pub struct HostGroup<'a> {
+    pub host: String,
+    /// Most players first
+    pub servers: Vec<&'a Server>,
+}
+
+/// Groups `servers` by their region in `regions` and then by community. Regions and communities with
+/// the most servers come first, servers of an unknown region come last
+pub fn preview_groups<'a>(
+    servers: &'a [Server],
+    regions: &HashMap<IpAddr, [char; 2]>,
+) -> Vec<RegionGroup<'a>> {
+    let mut grouped = HashMap::<Option<[char; 2]>, HashMap<String, Vec<&Server>>>::new();
+    for server in servers {
+        let ip = server.source.socket_addr().ip();
+        let host = server
+            .community
+            .as_deref()
+            .map_or_else(|| ip.to_canonical().to_string(), String::from);
+        grouped
+            .entry(regions.get(&ip).copied())
+            .or_default()
+            .entry(host)
+            .or_default()
+            .push(server);
+    }
+    let players = |server: &Server| server.info.as_ref().map_or(0, |info| info.clients);
+    let mut groups = grouped
+        .into_iter()
+        .map(|(region, hosts)| {
+            let mut hosts = hosts
+                .into_iter()
+                .map(|(host, mut servers)| {
+                    servers.sort_by_key(|&server| std::cmp::Reverse(players(server)));
+                    HostGroup { host, servers }
+                })
+                .collect::<Vec<_>>();
+            hosts.sort_by(|a, b| {
+                b.servers
+                    .len()
+                    .cmp(&a.servers.len())
+                    .then_with(|| a.host.cmp(&b.host))
+            });
+            RegionGroup { region, hosts }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| {
+        a.region
+            .is_none()
+            .cmp(&b.region.is_none())
+            .then_with(|| b.server_count().cmp(&a.server_count()))
+            .then_with(|| a.region.cmp(&b.region))
+    });
+    groups
+}
+
+/// Most servers written to favourites.json, the H2M server browser breaks past
+/// [`DEFAULT_H2M_SERVER_CAP`]
+pub fn favorites_limit(args: &Filters, version: f64) -> usize {
+    args.limit.unwrap_or({
         if version < 1.0 {
             DEFAULT_H2M_SERVER_CAP
         } else {
             10000
         }
-    });
+    })
+}
 
+/// Servers matching `args` and whether new regions were added to the cache, the number found is
+/// sent to `msg_sender`. Location lookups and region groups follow `config`
+#[allow(clippy::too_many_arguments)]
+pub async fn matching_servers(
+    http: &Http,
+    args: &Filters,
+    cache: CacheService,
+    limit: usize,
+    version: f64,
+    config: &Config,
+    cancel: &CancellationToken,
+    msg_sender: &Sender<Message>,
+) -> Result<(Vec<Server>, bool), Error> {
     if version < 1.0 && limit >= DEFAULT_H2M_SERVER_CAP {
-        println!("{YELLOW}NOTE: Currently the in game server browser breaks when you add more than 100 servers to favorites{WHITE}")
+        let _ = msg_sender.send(Message::Str(format!("{YELLOW}NOTE: Currently the in game server browser breaks when you add more than 100 servers to favorites{WHITE}"))).await;
     }
 
-    let (mut servers, update_cache) = filter_server_list(args, cache, limit)
-        .await
-        .map_err(|err| io::Error::other(format!("{err:?}")))?;
+    let query = FilterQuery {
+        lookup_concurrency: config.filter.lookup_concurrency,
+        region_groups: config.region.clone(),
+        ..FilterQuery::from(args)
+    };
+    let found = filter_server_list(http, &query, cache, limit, cancel, msg_sender).await?;
+
+    let _ = msg_sender
+        .send(Message::Str(format!(
+            "{} match the prameters in the current query",
+            DisplayServerCount(found.0.len(), GREEN)
+        )))
+        .await;
+    Ok(found)
+}
 
-    println!(
-        "{} match the prameters in the current query",
-        DisplayServerCount(servers.len(), GREEN)
-    );
+/// Writes the `limit` servers with the most players to every favourites.json in `targets`, in `order`
+/// when given. `regions` and `groups` are only read for [`BrowserOrder::Region`]. When more match and
+/// `ask_trim` is set the user picks which servers around the cut are left out, nothing is written if
+/// they cancel
+#[allow(clippy::too_many_arguments)]
+pub async fn write_matching(
+    targets: &[PathBuf],
+    mut servers: Vec<Server>,
+    limit: usize,
+    order: Option<BrowserOrder>,
+    regions: &HashMap<IpAddr, [char; 2]>,
+    groups: &RegionSettings,
+    ask_trim: bool,
+    msg_sender: &Sender<Message>,
+) -> Result<(), Error> {
+    let mut ips = String::new();
 
-    if servers.len() > limit {
-        servers.sort_unstable_by_key(|server| server.info.as_ref().map_or(0, |info| info.clients));
+    let players = |server: &Server| server.info.as_ref().map_or(0, |info| info.clients);
+    let mut written = if servers.len() > limit {
+        servers.sort_by_key(|server| std::cmp::Reverse(players(server)));
+        let Some(keep) = pick_written(&servers, limit, ask_trim, msg_sender).await else {
+            return Ok(());
+        };
+        let busiest = keep
+            .iter()
+            .enumerate()
+            .all(|(rank, &keep)| keep == (rank < limit));
+        let written = servers
+            .iter()
+            .zip(keep)
+            .filter_map(|(server, keep)| keep.then_some(server))
+            .collect::<Vec<_>>();
+        let left_out = servers.len() - written.len();
+        let msg = if busiest {
+            strict::report(
+                ProblemKind::Truncated,
+                &format!(
+                    "Only {limit} of {} matching servers were written",
+                    servers.len()
+                ),
+            );
+            format!(
+                "Only {limit} of {} fit, left out {} with {} or fewer players",
+                DisplayServerCount(servers.len(), GREEN),
+                DisplayCountOf(left_out, "server", "servers"),
+                servers.get(limit).map_or(0, players)
+            )
+        } else {
+            format!(
+                "Left out {} of the {} that match, as picked",
+                DisplayCountOf(left_out, "server", "servers"),
+                servers.len()
+            )
+        };
+        let _ = msg_sender.send(Message::Info(msg)).await;
+        written
+    } else {
+        servers.iter().collect::<Vec<_>>()
+    };
+    if let Some(order) = order {
+        sort_for_browser(&mut written, order, regions, groups);
     }
-
-    for server in servers.iter().rev() {
-        ips.push_str(&format!("\"{}\",", server.source.socket_addr()));
-        ip_collected += 1;
-        if ip_collected == limit {
-            break;
-        }
+    for server in written.iter() {
+        ips.push_str(&format!(
+            "\"{}\",",
+            favorite_entry(server.source.socket_addr())
+        ));
     }
+    let ip_collected = written.len();
+
+    let msg = match write_targets(targets, serialize_json(ips).as_bytes()).await? {
+        WriteOutcome::Written => Message::Str(format!(
+            "{GREEN}{FAVORITES} updated with {}{WHITE}",
+            DisplayCountOf(ip_collected, "entry", "entries")
+        )),
+        WriteOutcome::Superseded => Message::Warn(String::from(SUPERSEDED)),
+    };
+    let _ = msg_sender.send(msg).await;
+    Ok(())
+}
 
-    serialize_json(&mut favorites_json, ips)?;
+/// Whether to write each of `servers`, ordered by most players, when more than `limit` match. The
+/// `limit` busiest are written unless `ask` is set and the user picks others, `None` when they cancel
+async fn pick_written(
+    servers: &[Server],
+    limit: usize,
+    ask: bool,
+    msg_sender: &Sender<Message>,
+) -> Option<Vec<bool>> {
+    let mut keep = (0..servers.len())
+        .map(|rank| rank < limit)
+        .collect::<Vec<_>>();
+    if !ask {
+        return Some(keep);
+    }
+    let window = trim_window(servers.len(), limit);
+    let (decision_tx, decision_rx) = oneshot::channel();
+    let request = TrimRequest {
+        limit,
+        kept_above: window.start,
+        left_out_below: servers.len() - window.end,
+        candidates: servers[window.clone()]
+            .iter()
+            .zip(window.clone())
+            .map(|(server, rank)| TrimCandidate {
+                addr: server.source.socket_addr(),
+                name: server
+                    .info
+                    .as_ref()
+                    .map_or_else(String::new, |info| strip_color_codes(&info.host_name)),
+                players: server.info.as_ref().map_or(0, |info| info.clients),
+                max_players: server.info.as_ref().map_or(0, |info| info.max_clients),
+                keep: rank < limit,
+            })
+            .collect(),
+        decision: decision_tx,
+    };
+    let _ = msg_sender.send(Message::Trim(request)).await;
+    match decision_rx.await {
+        Ok(TrimDecision::Keep(picked)) => keep[window].copy_from_slice(&picked),
+        Ok(TrimDecision::Cancel) => return None,
+        // dropped where nothing can answer, e.g. while a script runs
+        Err(_) => (),
+    }
+    Some(keep)
+}
 
-    println!(
-        "{GREEN}{FAVORITES} updated with {}{WHITE}",
-        DisplayCountOf(ip_collected, "entry", "entries")
-    );
-    Ok(update_cache)
+/// Sorts servers that are about to be written into `order`, ties keep their order. Servers missing from
+/// `regions` are of an unknown region, `groups` places continents in the built in regions
+pub fn sort_for_browser(
+    servers: &mut [&Server],
+    order: BrowserOrder,
+    regions: &HashMap<IpAddr, [char; 2]>,
+    groups: &RegionSettings,
+) {
+    let players =
+        |server: &Server| std::cmp::Reverse(server.info.as_ref().map_or(0, |info| info.clients));
+    match order {
+        BrowserOrder::Name => servers.sort_by_cached_key(|server| {
+            let name = server
+                .info
+                .as_ref()
+                .map(|info| parse_hostname(&info.host_name));
+            (name.is_none(), name)
+        }),
+        BrowserOrder::Players => servers.sort_by_key(|server| players(server)),
+        BrowserOrder::Region => servers.sort_by_key(|server| {
+            let region = regions.get(&server.source.socket_addr().ip());
+            let group = region.map_or(Region::BUILT_IN.len() + 1, |&code| {
+                Region::BUILT_IN
+                    .iter()
+                    .position(|region| region.matches(groups, code, None))
+                    .unwrap_or(Region::BUILT_IN.len())
+            });
+            (group, region.copied(), players(server))
+        }),
+    }
 }
 
 pub struct Server {
     pub source: Sourced,
     pub info: Option<GetInfo>,
+    /// Webfront of the IW4MAdmin instance that listed the server, only known for iw4 master servers
+    pub community: Option<Arc<str>>,
 }
 
 impl From<HostMeta> for Server {
@@ -153,8 +506,10 @@ impl From<HostMeta> for Server {
                 game_name: value.server.game,
                 game_type: value.server.game_type,
                 host_name: value.server.host_name,
+                map_name: value.server.map,
             }),
             source: Sourced::Iw4Cached(value.resolved_addr),
+            community: Some(value.community),
         }
     }
 }
@@ -268,6 +623,52 @@ impl UnresponsiveCounter {
     }
 }
 
+/// Webfront url without the scheme or a trailing slash, e.g. `https://admin.example.com/` ->
+/// `admin.example.com`
+fn community_name(webfront_url: &str) -> &str {
+    webfront_url
+        .split_once("://")
+        .map_or(webfront_url, |(_, rest)| rest)
+        .trim_end_matches('/')
+}
+
+/// Failed location lookups by cause
+#[derive(Default)]
+pub struct GeoFailures {
+    pub timeout: usize,
+    pub rate_limited: usize,
+    pub unreadable: usize,
+    pub rejected: usize,
+    pub cancelled: usize,
+    pub other: usize,
+}
+
+impl GeoFailures {
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.timeout
+            + self.rate_limited
+            + self.unreadable
+            + self.rejected
+            + self.cancelled
+            + self.other
+    }
+
+    /// Returns `true` for the first failure of its cause
+    fn add(&mut self, cause: GeoCause) -> bool {
+        let count = match cause {
+            GeoCause::Timeout => &mut self.timeout,
+            GeoCause::RateLimited => &mut self.rate_limited,
+            GeoCause::Unreadable => &mut self.unreadable,
+            GeoCause::Rejected => &mut self.rejected,
+            GeoCause::Cancelled => &mut self.cancelled,
+            GeoCause::Other => &mut self.other,
+        };
+        *count += 1;
+        *count == 1
+    }
+}
+
 pub enum Request {
     New(Sourced),
     Retry(GetInfoMetaData),
@@ -275,7 +676,8 @@ pub enum Request {
 
 pub async fn try_get_info(
     from: Request,
-    client: reqwest::Client,
+    http: &dyn HttpFetch,
+    timeout: Option<Duration>,
 ) -> Result<Server, GetInfoMetaData> {
     let meta_data = match from {
         Request::New(meta) => GetInfoMetaData::new(meta),
@@ -284,12 +686,13 @@ pub async fn try_get_info(
             err
         }
     };
-    let server_responce = match client.get(&meta_data.url).send().await {
+    let server_responce = match http.get(&meta_data.url, timeout).await {
         Ok(res) => res,
         Err(err) => return Err(meta_data.set_err_msg(err.without_url().to_string())),
     };
-    match server_responce.json::<GetInfo>().await {
+    match server_responce.json::<GetInfo>() {
         Ok(info) => Ok(Server {
+            community: meta_data.meta.community(),
             source: meta_data.meta,
             info: Some(info),
         }),
@@ -299,23 +702,37 @@ pub async fn try_get_info(
 
 pub struct HostMeta {
     pub resolved_addr: SocketAddr,
+    /// Host name the master listed in place of an IP, when `resolved_addr` was resolved from it
+    pub resolved_from: Option<Arc<str>>,
     pub server: ServerInfo,
+    /// See [`Server::community`]
+    pub community: Arc<str>,
 }
 
 impl HostMeta {
-    fn try_from(host_ip: &str, webfront_url: &str, server: ServerInfo) -> Option<Self> {
-        resolve_address(&server.ip, host_ip, webfront_url).map_or_else(
-            |err| {
-                error!(name: LOG_ONLY, "{err}");
-                None
-            },
-            |ip| {
-                Some(HostMeta {
-                    resolved_addr: SocketAddr::new(ip, server.port),
-                    server,
-                })
-            },
-        )
+    fn try_from(
+        host_ip: &str,
+        webfront_url: &str,
+        community: &Arc<str>,
+        server: ServerInfo,
+        prefer: IpFamily,
+    ) -> Option<Self> {
+        resolve_address(&server.ip, host_ip, webfront_url, prefer)
+            .map(|(ip, resolved_from)| (ip, resolved_from.map(Arc::from)))
+            .map_or_else(
+                |err| {
+                    error!(name: LOG_ONLY, "{err}");
+                    None
+                },
+                |(ip, resolved_from)| {
+                    Some(HostMeta {
+                        resolved_addr: SocketAddr::new(ip, server.port),
+                        resolved_from,
+                        server,
+                        community: Arc::clone(community),
+                    })
+                },
+            )
     }
 }
 
@@ -335,7 +752,8 @@ impl Sourced {
         }
     }
 
-    pub fn try_from_hmw_master(ip_port: String) -> Option<Self> {
+    /// Host names that resolve to both an IPv4 and IPv6 address use the `prefer`ed one
+    pub fn try_from_hmw_master(ip_port: String, prefer: IpFamily) -> Option<Self> {
         let (ip, port) = match ip_port
             .rsplit_once(':')
             .map(|(ip, port)| (ip.parse().map_err(|err| (err, ip)), port.parse::<u16>()))
@@ -346,7 +764,7 @@ impl Sourced {
                 return None;
             }
             Some((Err((err, ip_str)), Ok(port))) => {
-                let Some(ip) = try_resolve_from_str(ip_str) else {
+                let Some(ip) = try_resolve_from_str(ip_str, prefer) else {
                     error!(name: LOG_ONLY, "Unexpected hmw master server formatting: failed to parse ip address in: {ip_port}, {err}");
                     return None;
                 };
@@ -371,79 +789,123 @@ impl Sourced {
             Sourced::Iw4(meta) => meta.resolved_addr,
         }
     }
+
+    #[inline]
+    pub fn community(&self) -> Option<Arc<str>> {
+        match self {
+            Sourced::Iw4(meta) => Some(Arc::clone(&meta.community)),
+            _ => None,
+        }
+    }
 }
 
-pub async fn iw4_servers(cache: Option<&Mutex<Cache>>) -> reqwest::Result<Vec<Sourced>> {
-    match get_iw4_master().await {
+pub async fn iw4_servers(
+    http: &dyn HttpFetch,
+    cache: Option<&Mutex<Cache>>,
+    prefer: IpFamily,
+) -> Result<Vec<Sourced>, Error> {
+    match get_iw4_master(http, cache).await {
         Ok(mut hosts) => {
             hosts
                 .iter_mut()
                 .for_each(|host| host.servers.retain(|server| server.game == GAME_ID));
             hosts.retain(|host| !host.servers.is_empty());
-            Ok(hosts
+            let servers = hosts
                 .into_iter()
                 .flat_map(|host| {
+                    let community = Arc::from(community_name(&host.webfront_url));
                     host.servers
                         .into_iter()
                         .filter_map(|server| {
-                            HostMeta::try_from(&host.ip_address, &host.webfront_url, server)
-                                .map(Sourced::Iw4)
+                            HostMeta::try_from(
+                                &host.ip_address,
+                                &host.webfront_url,
+                                &community,
+                                server,
+                                prefer,
+                            )
+                            .map(Sourced::Iw4)
                         })
                         .collect::<Vec<_>>()
                 })
-                .collect())
+                .collect::<Vec<_>>();
+            if let Some(cache) = cache {
+                cache
+                    .lock()
+                    .await
+                    .record_resolved(servers.iter().filter_map(|server| match server {
+                        Sourced::Iw4(meta) => Some((
+                            Arc::clone(meta.resolved_from.as_ref()?),
+                            meta.resolved_addr.ip(),
+                        )),
+                        _ => None,
+                    }));
+            }
+            Ok(servers)
         }
         Err(err) => {
             if let Some(cache) = cache {
                 error!(name: LOG_ONLY, "{err}");
-                let cache = cache.lock().await;
-                return Ok(cache
-                    .iw4m
-                    .iter()
-                    .flat_map(|(&ip, ports)| {
-                        ports
-                            .iter()
-                            .map(|&port| Sourced::Iw4Cached(SocketAddr::new(ip, port)))
-                            .collect::<Vec<_>>()
-                    })
-                    .collect());
+                return Ok(cached_servers(&*cache.lock().await, Source::Iw4Master));
             }
             Err(err)
         }
     }
 }
 
-pub async fn hmw_servers(cache: Option<&Mutex<Cache>>) -> reqwest::Result<Vec<Sourced>> {
-    match get_hmw_master().await {
-        Ok(list) => Ok(list
-            .into_iter()
-            .filter_map(Sourced::try_from_hmw_master)
-            .collect()),
+pub async fn hmw_servers(
+    http: &dyn HttpFetch,
+    cache: Option<&Mutex<Cache>>,
+    prefer: IpFamily,
+) -> Result<Vec<Sourced>, Error> {
+    match get_hmw_master(http, cache).await {
+        Ok(list) => {
+            let mut resolved = Vec::new();
+            let servers = list
+                .into_iter()
+                .filter_map(|ip_port| {
+                    let host = dns_name(ip_port.rsplit_once(':')?.0).map(Arc::from);
+                    let server = Sourced::try_from_hmw_master(ip_port, prefer)?;
+                    resolved.extend(host.map(|host| (host, server.socket_addr().ip())));
+                    Some(server)
+                })
+                .collect();
+            if let Some(cache) = cache {
+                cache.lock().await.record_resolved(resolved);
+            }
+            Ok(servers)
+        }
         Err(err) => {
             if let Some(cache) = cache {
                 error!(name: LOG_ONLY, "{err}");
-                let cache = cache.lock().await;
-                return Ok(cache
-                    .hmw
-                    .iter()
-                    .flat_map(|(&ip, ports)| {
-                        ports
-                            .iter()
-                            .map(|&port| Sourced::HmwCached(SocketAddr::new(ip, port)))
-                            .collect::<Vec<_>>()
-                    })
-                    .collect());
+                return Ok(cached_servers(&*cache.lock().await, Source::HmwMaster));
             }
             Err(err)
         }
     }
 }
 
+/// Servers of `source` saved in the cache, used when its master server can not be reached
+fn cached_servers(cache: &Cache, source: Source) -> Vec<Sourced> {
+    let (ports, sourced): (_, fn(SocketAddr) -> Sourced) = match source {
+        Source::Iw4Master => (&cache.iw4m, Sourced::Iw4Cached),
+        Source::HmwMaster => (&cache.hmw, Sourced::HmwCached),
+    };
+    ports
+        .iter()
+        .flat_map(|(&ip, ports)| {
+            ports
+                .iter()
+                .map(move |&port| sourced(SocketAddr::new(ip, port)))
+        })
+        .collect()
+}
+
 pub async fn queue_info_requests(
     servers: Vec<Sourced>,
     tasks: &mut Vec<JoinHandle<Result<Server, GetInfoMetaData>>>,
     remove_duplicates: bool,
-    client: &Client,
+    http: &Http,
 ) {
     let mut dup = HashSet::new();
     for server in servers.into_iter() {
@@ -451,20 +913,30 @@ pub async fn queue_info_requests(
             continue;
         }
 
-        let client = client.clone();
+        let http = Arc::clone(http);
         tasks.push(tokio::spawn(async move {
-            try_get_info(Request::New(server), client).await
+            try_get_info(Request::New(server), &*http, Some(GET_INFO_TIMEOUT)).await
         }));
     }
 }
 
 fn to_server(disregard_meta: bool, vec: Vec<Sourced>) -> Vec<Server> {
-    let no_info = |source: Sourced| -> Server { Server { source, info: None } };
+    let no_info = |source: Sourced| -> Server {
+        Server {
+            community: source.community(),
+            source,
+            info: None,
+        }
+    };
     let with_info = |source: Sourced| -> Server {
         if let Sourced::Iw4(meta) = source {
             Server::from(meta)
         } else {
-            Server { source, info: None }
+            Server {
+                source,
+                info: None,
+                community: None,
+            }
         }
     };
 
@@ -472,117 +944,354 @@ fn to_server(disregard_meta: bool, vec: Vec<Sourced>) -> Vec<Server> {
     vec.into_iter().map(operation).collect()
 }
 
+/// Drops servers listed more than once under the same address, e.g. by both master servers. Returns
+/// how many were dropped
+fn dedup_by_address(servers: &mut Vec<Sourced>) -> usize {
+    let len = servers.len();
+    let mut seen = HashSet::with_capacity(len);
+    servers.retain(|server| seen.insert(server.socket_addr()));
+    len - servers.len()
+}
+
+/// Drops servers that report the same name, port, map, mode and player counts as one kept before
+/// them, which is how a server reachable over both IPv4 and IPv6, or advertised by more than one host,
+/// shows up. Empty servers are never dropped since many share a default name. The `prefer`ed address
+/// type is kept, returns how many were dropped
+pub fn dedup_servers(servers: &mut Vec<Server>, prefer: IpFamily) -> usize {
+    let len = servers.len();
+    let mut kept: Vec<Server> = Vec::with_capacity(len);
+    let mut seen = HashMap::new();
+    for server in servers.drain(..) {
+        let key = server
+            .info
+            .as_ref()
+            .filter(|info| info.clients > 0)
+            .map(|info| {
+                (
+                    parse_hostname(&info.host_name),
+                    server.source.socket_addr().port(),
+                    info.map_name.clone(),
+                    info.game_type.clone(),
+                    info.clients,
+                    info.max_clients,
+                )
+            });
+        let Some(key) = key else {
+            kept.push(server);
+            continue;
+        };
+        match seen.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(kept.len());
+                kept.push(server);
+            }
+            Entry::Occupied(entry) => {
+                let existing = &mut kept[*entry.get()];
+                if !prefer.matches(existing.source.socket_addr().ip())
+                    && prefer.matches(server.source.socket_addr().ip())
+                {
+                    *existing = server;
+                }
+            }
+        }
+    }
+    *servers = kept;
+    len - servers.len()
+}
+
+/// Returns `Err` when every selected master server failed and no servers were found
 #[instrument(level = "trace", skip_all)]
-async fn filter_server_list(
-    args: &Filters,
-    cache: Arc<Mutex<Cache>>,
+pub(crate) async fn filter_server_list(
+    http: &Http,
+    args: &FilterQuery,
+    cache: CacheService,
     limit: usize,
-) -> reqwest::Result<(Vec<Server>, bool)> {
+    cancel: &CancellationToken,
+    msg_sender: &Sender<Message>,
+) -> Result<(Vec<Server>, bool), Error> {
     let mut servers = Vec::new();
-
-    if let Some(ref list) = args.source {
-        if list.contains(&Source::Iw4Master) {
-            match iw4_servers(Some(&cache)).await {
-                Ok(iw4) => servers = iw4,
-                Err(err) => error!("{err}"),
+    let mut master_err = None;
+    let prefer = args.prefer.unwrap_or_default();
+    let deadline = args
+        .deadline
+        .map(|after| tokio::time::Instant::now() + after);
+    let mut timed_out = 0_usize;
+
+    let (use_iw4, use_hmw) = args.sources.as_ref().map_or((true, true), |list| {
+        (
+            list.contains(&Source::Iw4Master),
+            list.contains(&Source::HmwMaster),
+        )
+    });
+    // a master server that does not answer in time is treated like one that could not be reached
+    let past_deadline = |master| {
+        let err = Error::MasterApi {
+            master,
+            source: FetchError::Deadline,
+        };
+        error!(name: LOG_ONLY, "{err}");
+        err
+    };
+    if use_iw4 {
+        match before_deadline(deadline, iw4_servers(&**http, Some(&cache), prefer)).await {
+            Some(Ok(iw4)) => servers = iw4,
+            Some(Err(err)) => master_err = Some(err),
+            None => {
+                servers = cached_servers(&*cache.lock().await, Source::Iw4Master);
+                master_err = Some(past_deadline("iw4m"));
             }
         }
-        if list.contains(&Source::HmwMaster) {
-            match hmw_servers(Some(&cache)).await {
-                Ok(ref mut hmw) => servers.append(hmw),
-                Err(err) => error!("{err}"),
+    }
+    if use_hmw {
+        let hmw = match before_deadline(deadline, hmw_servers(&**http, Some(&cache), prefer)).await
+        {
+            Some(hmw) => hmw,
+            None => {
+                servers.append(&mut cached_servers(&*cache.lock().await, Source::HmwMaster));
+                Err(past_deadline("hmw"))
+            }
+        };
+        match hmw {
+            Ok(mut hmw) => servers.append(&mut hmw),
+            Err(err) => {
+                if let Some(iw4_err) = master_err.replace(err) {
+                    let _ = msg_sender
+                        .send(Message::Err(DisplayError(&iw4_err).to_string()))
+                        .await;
+                }
             }
         }
-    } else {
-        servers = iw4_servers(Some(&cache)).await.unwrap_or_else(|err| {
-            error!("{err}");
-            Vec::new()
-        });
-        match hmw_servers(Some(&cache)).await {
-            Ok(ref mut hmw) => servers.append(hmw),
-            Err(err) => error!("{err}"),
+    }
+    // servers only count as missing when both master servers sent a live list
+    let complete_listing = use_iw4
+        && use_hmw
+        && master_err.is_none()
+        && servers
+            .iter()
+            .all(|server| matches!(server, Sourced::Iw4(_) | Sourced::Hmw(_)));
+    if let Some(err) = master_err {
+        if servers.is_empty() {
+            return Err(err);
         }
-    };
+        let _ = msg_sender
+            .send(Message::Err(DisplayError(&err).to_string()))
+            .await;
+    }
+    if let Some(skipped) = skipped_report() {
+        let _ = msg_sender.send(Message::Warn(skipped)).await;
+    }
 
-    let cache_modified = if let Some(ref regions) = args.region {
-        println!(
-            "Determining region of {}...",
-            DisplayServerCount(servers.len(), GREEN)
-        );
+    let unreliable =
+        check_reliability(&mut servers, &cache, complete_listing, args.min_reliability).await;
+    if unreliable > 0 {
+        let _ = msg_sender
+            .send(Message::Info(format!(
+                "Left out {} listed less than {}% of the time",
+                DisplayCountOf(unreliable, "server", "servers"),
+                args.min_reliability.unwrap_or_default()
+            )))
+            .await;
+    }
+
+    let mut duplicates = 0;
+    if !args.keep_duplicates {
+        duplicates += dedup_by_address(&mut servers);
+    }
+
+    // --near-me keeps the regions this device is in, regions named by the user take precedence
+    let near_regions = if args.near_me && args.regions.is_none() {
+        near_me_regions(http, &cache, &args.region_groups, msg_sender).await
+    } else {
+        None
+    };
+    let cache_modified = if let Some(regions) = args.regions.as_ref().or(near_regions.as_ref()) {
+        // regions from filter.region or a preset can name a group that was removed since
+        for region in regions
+            .iter()
+            .filter(|region| !region.is_defined(&args.region_groups))
+        {
+            let _ = msg_sender
+                .send(Message::Warn(format!(
+                    "Region '{region}' is not in the region settings, no servers match it"
+                )))
+                .await;
+        }
+        let _ = msg_sender
+            .send(Message::Str(format!(
+                "Determining region of {}...",
+                DisplayServerCount(servers.len(), GREEN)
+            )))
+            .await;
 
         let mut server_list = Vec::new();
         let mut tasks = Vec::new();
         let mut check_again = Vec::new();
         let mut new_lookups = HashSet::new();
-        let client = reqwest::Client::new();
+        let mut cached_count = 0_usize;
+        let permits = (args.lookup_concurrency > 0)
+            .then(|| Arc::new(Semaphore::new(args.lookup_concurrency)));
+        // once the API answers 429 every lookup still waiting for a permit would get the same answer
+        let rate_limited = Arc::new(AtomicBool::new(false));
 
-        let mut cache = cache.lock().await;
+        // the cache is only held while reading and writing regions so other commands are not
+        // blocked while the location requests are in flight
+        let cache_guard = cache.lock().await;
 
         for sourced_data in servers {
             let socket_addr = sourced_data.socket_addr();
-            if let Some(cached_region) = cache.ip_to_region.get(&socket_addr.ip()) {
-                if regions.iter().any(|region| region.matches(*cached_region)) {
-                    server_list.push(sourced_data);
+            if let Some(&continent) = cache_guard.ip_to_region.get(&socket_addr.ip()) {
+                let country = cache_guard.ip_to_country.get(&socket_addr.ip()).copied();
+                // servers cached before countries were saved are looked up again for regions that
+                // narrow their continent down to countries
+                if country.is_some()
+                    || !regions
+                        .iter()
+                        .any(|region| region.needs_country(&args.region_groups, continent))
+                {
+                    cached_count += 1;
+                    if regions
+                        .iter()
+                        .any(|region| region.matches(&args.region_groups, continent, country))
+                    {
+                        server_list.push(sourced_data);
+                    }
+                    continue;
                 }
-                continue;
             }
             if new_lookups.insert(socket_addr.ip()) {
-                let client = client.clone();
+                let http = Arc::clone(http);
+                let cancel = cancel.clone();
+                let permits = permits.clone();
+                let rate_limited = Arc::clone(&rate_limited);
                 trace!("Requsting location data for: {}", socket_addr.ip());
                 tasks.push(tokio::spawn(async move {
-                    try_location_lookup(&socket_addr.ip(), client)
-                        .await
-                        .map(|location| (sourced_data, location.code))
+                    let ip = socket_addr.ip();
+                    let location = tokio::select! {
+                        biased;
+                        _ = cancel.cancelled() => Err(Error::GeoApi { ip, cause: GeoCause::Cancelled, reason: Cow::Borrowed("cancelled") }),
+                        location = async {
+                            let _permit = match permits {
+                                Some(permits) => permits.acquire_owned().await.ok(),
+                                None => None,
+                            };
+                            if rate_limited.load(Ordering::Relaxed) {
+                                return Err(Error::GeoApi {
+                                    ip,
+                                    cause: GeoCause::RateLimited,
+                                    reason: Cow::Borrowed("skipped, the lookup limit was reached"),
+                                });
+                            }
+                            let location = try_location_lookup(&ip, &*http).await;
+                            if let Err(Error::GeoApi { cause: GeoCause::RateLimited, .. }) = location {
+                                rate_limited.store(true, Ordering::Relaxed);
+                            }
+                            location
+                        } => location,
+                    };
+                    (sourced_data, location)
                 }))
             } else {
                 check_again.push(sourced_data)
             }
         }
 
-        let mut failure_count = 0_usize;
+        drop(cache_guard);
+
+        let mut failures = GeoFailures::default();
+        let mut resolved = Vec::with_capacity(tasks.len());
+        let mut progress = Progress::new("locations resolved", tasks.len(), msg_sender);
 
-        for task in tasks {
-            match task.await {
-                Ok(Ok((sourced_data, cont_code))) => {
-                    cache
-                        .ip_to_region
-                        .insert(sourced_data.socket_addr().ip(), cont_code);
-                    if regions.iter().any(|region| region.matches(cont_code)) {
-                        server_list.push(sourced_data)
+        let mut tasks = tasks.into_iter();
+        while let Some(mut task) = tasks.next() {
+            let Some(result) = before_deadline(deadline, &mut task).await else {
+                timed_out += abort_all(std::iter::once(task).chain(tasks.by_ref()));
+                break;
+            };
+            progress.inc();
+            match result {
+                Ok((sourced_data, Ok(location))) => resolved.push((sourced_data, location)),
+                Ok((sourced_data, Err(err))) => {
+                    let cause = match err {
+                        Error::GeoApi { cause, .. } => cause,
+                        _ => GeoCause::Other,
+                    };
+                    // only the first failure of each cause is logged, a degraded API fails them all
+                    if failures.add(cause) {
+                        error!(name: LOG_ONLY, "{err}");
+                    }
+                    if args.keep_unknown_region {
+                        server_list.push(sourced_data);
                     }
-                }
-                Ok(Err(err)) => {
-                    error!(name: LOG_ONLY, "{err}");
-                    failure_count += 1
                 }
                 Err(err) => {
-                    error!(name: LOG_ONLY, "{err:?}");
-                    failure_count += 1
+                    if failures.add(GeoCause::Other) {
+                        error!(name: LOG_ONLY, "{err:?}");
+                    }
                 }
             }
         }
+        drop(progress);
+        let failure_count = failures.total();
+        perf::record_geo(cached_count, new_lookups.len(), failure_count);
 
         if !new_lookups.is_empty() {
-            info!(
-                "Made {} new location {}",
-                new_lookups.len(),
-                SingularPlural(new_lookups.len(), "request", "requests")
-            );
+            let _ = msg_sender
+                .send(Message::Info(format!(
+                    "Made {} new location {}",
+                    new_lookups.len(),
+                    SingularPlural(new_lookups.len(), "request", "requests")
+                )))
+                .await;
+        }
+
+        let mut cache = cache.lock().await;
+        for (sourced_data, location) in resolved {
+            let ip = sourced_data.socket_addr().ip();
+            cache.ip_to_region.insert(ip, location.continent);
+            match location.country {
+                Some(country) => cache.ip_to_country.insert(ip, country),
+                None => cache.ip_to_country.remove(&ip),
+            };
+            if regions.iter().any(|region| {
+                region.matches(&args.region_groups, location.continent, location.country)
+            }) {
+                server_list.push(sourced_data)
+            }
         }
 
         for sourced_data in check_again {
-            if let Some(cached_region) = cache.ip_to_region.get(&sourced_data.socket_addr().ip()) {
-                if regions.iter().any(|region| region.matches(*cached_region)) {
+            let ip = sourced_data.socket_addr().ip();
+            match cache.ip_to_region.get(&ip) {
+                Some(&continent)
+                    if regions.iter().any(|region| {
+                        let country = cache.ip_to_country.get(&ip).copied();
+                        region.matches(&args.region_groups, continent, country)
+                    }) =>
+                {
                     server_list.push(sourced_data)
                 }
+                None if args.keep_unknown_region => server_list.push(sourced_data),
+                _ => (),
             }
         }
 
+        drop(cache);
+
         if failure_count > 0 {
-            eprintln!(
-                "{RED}Failed to resolve location for {failure_count} server {}{WHITE}",
-                SingularPlural(failure_count, "hoster", "hosters")
-            )
+            strict::report_geo_failures(failure_count);
+            let _ = msg_sender
+                .send(Message::Str(
+                    DisplayGeoFailures(&failures, args.keep_unknown_region).to_string(),
+                ))
+                .await;
+            if failures.rate_limited > 0 && key_status().0 == KeySource::Bundled {
+                let _ = msg_sender
+                    .send(Message::Info(String::from(
+                        "Every user shares the bundled location key, add a free key of your own from \
+                        findip.net with `geo-key set <KEY>`",
+                    )))
+                    .await;
+            }
         }
 
         servers = server_list;
@@ -591,23 +1300,19 @@ async fn filter_server_list(
         false
     };
 
-    let servers = if args.excludes.is_some()
+    let mut servers = if args.excludes.is_some()
         || args.includes.is_some()
         || args.player_min.is_some()
         || args.team_size_max.is_some()
         || args.with_bots
         || args.without_bots
+        || args.active_now
         || !args.include_unresponsive
     {
         let mut tasks = Vec::with_capacity(servers.len());
         let mut host_list = Vec::with_capacity(servers.len());
 
-        let client = reqwest::Client::builder()
-            .timeout(tokio::time::Duration::from_secs(3))
-            .build()
-            .unwrap();
-
-        queue_info_requests(servers, &mut tasks, true, &client).await;
+        queue_info_requests(servers, &mut tasks, !args.keep_duplicates, http).await;
 
         let use_backup_server_info =
             !args.with_bots && !args.without_bots && args.include_unresponsive;
@@ -617,20 +1322,36 @@ async fn filter_server_list(
         let max_attempts = args.retry_max.unwrap_or(DEFUALT_INFO_RETRIES);
 
         while !tasks.is_empty() {
-            println!("{}", DisplayGetInfoCount(tasks.len(), sent_retires));
+            let _ = msg_sender
+                .send(Message::Str(
+                    DisplayGetInfoCount(tasks.len(), sent_retires).to_string(),
+                ))
+                .await;
             let mut retries = Vec::new();
-            for task in tasks {
-                match task.await {
+            let mut progress = Progress::new("servers responded", tasks.len(), msg_sender);
+            let mut pending = tasks.into_iter();
+            while let Some(mut task) = pending.next() {
+                let Some(result) = before_deadline(deadline, &mut task).await else {
+                    timed_out += abort_all(
+                        std::iter::once(task)
+                            .chain(pending.by_ref())
+                            .chain(retries.drain(..)),
+                    );
+                    break;
+                };
+                progress.inc();
+                match result {
                     Ok(Ok(server)) => host_list.push(server),
                     Ok(Err(mut err)) => {
                         if err.retries < max_attempts {
-                            let client = client.clone();
+                            let http = Arc::clone(http);
                             retries.push(tokio::task::spawn(async move {
                                 tokio::time::sleep(tokio::time::Duration::from_millis(
                                     RETRY_TIME_SCALE * (err.retries + 1) as u64,
                                 ))
                                 .await;
-                                try_get_info(Request::Retry(err), client).await
+                                try_get_info(Request::Retry(err), &*http, Some(GET_INFO_TIMEOUT))
+                                    .await
                             }));
                         } else {
                             did_not_respond.add(&err.meta);
@@ -651,139 +1372,451 @@ async fn filter_server_list(
         }
 
         if did_not_respond.total() > 0 {
-            if use_backup_server_info {
-                println!(
+            let msg = if use_backup_server_info {
+                format!(
                     "Included outdated server data for {YELLOW}{used_backup_data}{WHITE} \
                     of {} that did not respond to 'getInfo' request",
                     DisplayServerCount(did_not_respond.total(), RED)
                 )
             } else {
-                eprintln!("{did_not_respond}");
-            }
+                did_not_respond.to_string()
+            };
+            let _ = msg_sender.send(Message::Str(msg)).await;
         }
 
-        let include = args.includes.as_ref().map(|s| lowercase_vec(s));
-        let exclude = args.excludes.as_ref().map(|s| lowercase_vec(s));
+        let usual = sample_populations(&host_list, &cache, args.active_now).await;
+        retain_matching_usual(&mut host_list, args, &usual);
+        if !args.keep_duplicates {
+            duplicates += dedup_servers(&mut host_list, prefer);
+        }
+        if args.active_now {
+            keep_busiest_now(&mut host_list, &usual, limit, msg_sender).await;
+        }
+        host_list
+    } else {
+        to_server(servers.len() <= limit, servers)
+    };
 
-        for i in (0..host_list.len()).rev() {
-            let server = &host_list[i];
+    if args.measure_pings {
+        keep_nearest(&mut servers, &cache, limit, msg_sender).await;
+    }
 
-            let Some(ref info) = server.info else {
-                host_list.swap_remove(i);
-                continue;
-            };
+    if timed_out > 0 {
+        let msg = format!(
+            "{} skipped due to timeout, the filter deadline of {}s was reached",
+            DisplayCountOf(timed_out, "server", "servers"),
+            args.deadline.unwrap_or_default().as_secs()
+        );
+        strict::report(ProblemKind::TimedOut, &msg);
+        let _ = msg_sender.send(Message::Warn(msg)).await;
+    }
 
-            if let Some(team_size_max) = args.team_size_max {
-                if info.max_clients > team_size_max * 2 {
-                    host_list.swap_remove(i);
-                    continue;
-                }
-            }
+    if duplicates > 0 {
+        let _ = msg_sender
+            .send(Message::Info(format!(
+                "Skipped {}, use --keep-duplicates to keep them",
+                DisplayCountOf(duplicates, "duplicate server", "duplicate servers")
+            )))
+            .await;
+    }
 
-            if let Some(player_min) = args.player_min {
-                if info.clients < player_min {
-                    host_list.swap_remove(i);
-                    continue;
-                }
-            }
+    Ok((servers, cache_modified))
+}
 
-            if args.with_bots && info.bots == 0 {
-                host_list.swap_remove(i);
-                continue;
-            }
+/// Awaits `task` until `deadline`, `None` when the deadline was reached first
+async fn before_deadline<F: std::future::Future>(
+    deadline: Option<tokio::time::Instant>,
+    task: F,
+) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, task).await.ok(),
+        None => Some(task.await),
+    }
+}
 
-            if args.without_bots && info.bots != 0 {
-                host_list.swap_remove(i);
-                continue;
-            }
+/// Stops every task in `tasks`, returns how many there were
+fn abort_all<T>(tasks: impl Iterator<Item = JoinHandle<T>>) -> usize {
+    tasks.map(|task| task.abort()).count()
+}
 
-            let mut hostname_l = None;
-            if let Some(ref strings) = include {
-                hostname_l = Some(parse_hostname(&info.host_name));
-                if !strings
-                    .iter()
-                    .any(|string| hostname_l.as_ref().unwrap().contains(string))
-                {
-                    host_list.swap_remove(i);
-                    continue;
-                }
+/// Counts which servers are listed when `complete_listing` is set, then removes the servers scored
+/// under `min_reliability`. Returns the number removed
+async fn check_reliability(
+    servers: &mut Vec<Sourced>,
+    cache: &CacheService,
+    complete_listing: bool,
+    min_reliability: Option<u8>,
+) -> usize {
+    if !complete_listing && min_reliability.is_none() {
+        return 0;
+    }
+    let mut cache_guard = cache.lock().await;
+    if complete_listing {
+        let listed = servers.iter().map(Sourced::socket_addr).collect();
+        cache_guard.reliability.record_listing(&listed);
+        cache.mark_changed();
+    }
+    let Some(min) = min_reliability else {
+        return 0;
+    };
+    let len = servers.len();
+    servers.retain(|server| {
+        cache_guard
+            .reliability
+            .score(server.socket_addr())
+            .is_none_or(|score| score >= min)
+    });
+    len - servers.len()
+}
+
+/// Adds the player counts of `servers` to the population history and the server book. When `active_now` is set the players
+/// each server usually has at this hour are returned, read before the new samples are added
+async fn sample_populations(
+    servers: &[Server],
+    cache: &CacheService,
+    active_now: bool,
+) -> HashMap<SocketAddr, f32> {
+    let now = SystemTime::now();
+    let mut cache_guard = cache.lock().await;
+    let usual = if active_now {
+        servers
+            .iter()
+            .map(|server| server.source.socket_addr())
+            .filter_map(|addr| Some((addr, cache_guard.populations.usual_players(addr, now)?)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    for server in servers {
+        if let Some(ref info) = server.info {
+            let socket_addr = server.source.socket_addr();
+            cache_guard
+                .populations
+                .record(socket_addr, info.clients, now);
+            cache_guard
+                .book
+                .record_players(socket_addr, PlayerSample::new(info, now));
+        }
+    }
+    drop(cache_guard);
+    if !servers.is_empty() {
+        cache.mark_changed();
+    }
+    usual
+}
+
+/// Orders `servers` by the players they have now or usually have at this hour, whichever is more, most
+/// last like [`write_matching`] expects. Only the `limit` busiest are kept
+async fn keep_busiest_now(
+    servers: &mut Vec<Server>,
+    usual: &HashMap<SocketAddr, f32>,
+    limit: usize,
+    msg_sender: &Sender<Message>,
+) {
+    let expected = |server: &Server| {
+        let now = server
+            .info
+            .as_ref()
+            .map_or(0.0, |info| f32::from(info.clients));
+        usual
+            .get(&server.source.socket_addr())
+            .map_or(now, |&usual| now.max(usual))
+    };
+    servers.sort_by(|a, b| expected(a).total_cmp(&expected(b)));
+    if servers.len() <= limit {
+        return;
+    }
+    let left_out = servers.len() - limit;
+    servers.drain(..left_out);
+    strict::report(
+        ProblemKind::Truncated,
+        &format!(
+            "Only {limit} of {} matching servers were written",
+            limit + left_out
+        ),
+    );
+    let _ = msg_sender
+        .send(Message::Info(format!(
+            "Kept the {limit} servers most likely to be busy now, left out {}",
+            DisplayCountOf(left_out, "server", "servers")
+        )))
+        .await;
+}
+
+/// Regions that include the location of this device, which is looked up once per session. `None` when
+/// the location could not be looked up or no region includes it
+async fn near_me_regions(
+    http: &Http,
+    cache: &CacheService,
+    groups: &RegionSettings,
+    msg_sender: &Sender<Message>,
+) -> Option<Vec<Region>> {
+    let cached = cache.lock().await.own_location;
+    let location = match cached {
+        Some(location) => location,
+        None => match own_location(&**http).await {
+            Ok(location) => {
+                cache.lock().await.own_location = Some(location);
+                location
             }
-            if let Some(ref strings) = exclude {
-                if hostname_l.is_none() {
-                    hostname_l = Some(parse_hostname(&info.host_name));
-                }
-                if strings
-                    .iter()
-                    .any(|string| hostname_l.as_ref().unwrap().contains(string))
-                {
-                    host_list.swap_remove(i);
-                }
+            Err(err) => {
+                error!(name: LOG_ONLY, "{err}");
+                let _ = msg_sender
+                    .send(Message::Warn(format!(
+                        "Could not estimate your location, servers are not narrowed down by region. {}",
+                        DisplayError(&err)
+                    )))
+                    .await;
+                return None;
             }
-        }
-        host_list
+        },
+    };
+    let code = RegionCode {
+        continent: location.continent,
+        country: location.country,
+    };
+    let regions = Region::BUILT_IN
+        .into_iter()
+        .chain(custom_regions(groups))
+        .filter(|region| region.matches(groups, location.continent, location.country))
+        .collect::<Vec<_>>();
+    let msg = if regions.is_empty() {
+        format!(
+            "No region includes your location ({code}), servers are not narrowed down by region"
+        )
     } else {
-        to_server(servers.len() <= limit, servers)
+        let names = regions
+            .iter()
+            .map(Region::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("Your location is {code}, keeping servers in {names}")
     };
+    let _ = msg_sender.send(Message::Info(msg)).await;
+    (!regions.is_empty()).then_some(regions)
+}
 
-    Ok((servers, cache_modified))
+/// Location of the public address this device connects from
+async fn own_location(http: &dyn HttpFetch) -> Result<Location, Error> {
+    let public = http
+        .get(PUBLIC_IP_URL, None)
+        .await
+        .and_then(HttpResponse::error_for_status)
+        .and_then(|response| response.json::<PublicIp>())
+        .map_err(|source| Error::Network {
+            what: "Public address",
+            source,
+        })?;
+    try_location_lookup(&public.ip, http).await
+}
+
+/// Measures the ping of `servers` not measured in the last [`PING_MAX_AGE`] and orders them by it,
+/// lowest last like [`write_matching`] expects. Servers that did not reply go first, only the `limit`
+/// nearest are kept
+///
+/// [`PING_MAX_AGE`]: crate::utils::caching::PING_MAX_AGE
+async fn keep_nearest(
+    servers: &mut Vec<Server>,
+    cache: &CacheService,
+    limit: usize,
+    msg_sender: &Sender<Message>,
+) {
+    let addrs = servers
+        .iter()
+        .map(|server| server.source.socket_addr())
+        .collect::<Vec<_>>();
+    let _ = msg_sender
+        .send(Message::Str(format!(
+            "Measuring ping of {}...",
+            DisplayServerCount(addrs.len(), GREEN)
+        )))
+        .await;
+    refresh_pings(cache, &addrs, false).await;
+    cache.mark_changed();
+
+    let cache_guard = cache.lock().await;
+    let mut unreachable = 0_usize;
+    servers.sort_by_cached_key(|server| {
+        let ping = cache_guard.fresh_ping(server.source.socket_addr());
+        unreachable += usize::from(ping.is_none());
+        let players = server.info.as_ref().map_or(0, |info| info.clients);
+        (std::cmp::Reverse(ping.unwrap_or(Duration::MAX)), players)
+    });
+    drop(cache_guard);
+    if unreachable > 0 {
+        let _ = msg_sender
+            .send(Message::Info(format!(
+                "{} did not answer a ping and {} ranked last",
+                DisplayCountOf(unreachable, "server", "servers"),
+                SingularPlural(unreachable, "was", "were")
+            )))
+            .await;
+    }
+    if servers.len() <= limit {
+        return;
+    }
+    let left_out = servers.len() - limit;
+    servers.drain(..left_out);
+    strict::report(
+        ProblemKind::Truncated,
+        &format!(
+            "Only {limit} of {} matching servers were written",
+            limit + left_out
+        ),
+    );
+    let _ = msg_sender
+        .send(Message::Info(format!(
+            "Kept the {limit} servers with the lowest ping, left out {}",
+            DisplayCountOf(left_out, "server", "servers")
+        )))
+        .await;
+}
+
+/// Drops every server without info or whose info does not meet the criteria of `query`, in a single
+/// pass that keeps the order of `servers`. Include and exclude terms are lowercased once up front and
+/// every host name is parsed into the same buffer
+pub fn retain_matching(servers: &mut Vec<Server>, query: &FilterQuery) {
+    retain_matching_usual(servers, query, &HashMap::new())
+}
+
+/// [`retain_matching`] where a server passes `player_min` if it usually has that many players at this
+/// hour, going by `usual`
+pub fn retain_matching_usual(
+    servers: &mut Vec<Server>,
+    query: &FilterQuery,
+    usual: &HashMap<SocketAddr, f32>,
+) {
+    let include = query.includes.as_ref().map(|s| lowercase_vec(s));
+    let exclude = query.excludes.as_ref().map(|s| lowercase_vec(s));
+    let mut hostname_l = String::new();
+
+    servers.retain(|server| {
+        let Some(ref info) = server.info else {
+            return false;
+        };
+        if query
+            .team_size_max
+            .is_some_and(|team_size_max| info.max_clients > team_size_max * 2)
+            || query.player_min.is_some_and(|player_min| {
+                info.clients < player_min
+                    && !usual
+                        .get(&server.source.socket_addr())
+                        .is_some_and(|&usual| usual >= f32::from(player_min))
+            })
+            || (query.with_bots && info.bots == 0)
+            || (query.without_bots && info.bots != 0)
+        {
+            return false;
+        }
+        if include.is_none() && exclude.is_none() {
+            return true;
+        }
+        parse_hostname_into(&info.host_name, &mut hostname_l);
+        let contains_any = |strings: &[String]| strings.iter().any(|s| hostname_l.contains(s));
+        if include
+            .as_deref()
+            .is_some_and(|strings| !contains_any(strings))
+        {
+            return false;
+        }
+        !exclude.as_deref().is_some_and(contains_any)
+    });
+}
+
+/// Continent and country codes of a server
+#[derive(Clone, Copy, Debug)]
+pub struct Location {
+    pub continent: [char; 2],
+    pub country: Option<[char; 2]>,
 }
 
 #[instrument(level = "trace", skip_all)]
-pub async fn try_location_lookup(
-    ip: &IpAddr,
-    client: reqwest::Client,
-) -> Result<Continent, Cow<'static, str>> {
-    let location_api_url = format!("{MASTER_LOCATION_URL}/{}{FIND_IP_NET_PRIVATE_KEY}", ip);
-
-    let api_response = client
-        .get(location_api_url.as_str())
-        .send()
-        .await
-        .map_err(|err| format!("{}, ip: {ip}", err.without_url()))?;
+pub async fn try_location_lookup(ip: &IpAddr, http: &dyn HttpFetch) -> Result<Location, Error> {
+    let geo_err = |cause, reason: Cow<'static, str>| Error::GeoApi {
+        ip: *ip,
+        cause,
+        reason,
+    };
+    let Some(key) = take_lookup() else {
+        return Err(geo_err(
+            GeoCause::RateLimited,
+            Cow::Borrowed("the shared key's lookups for today are used up"),
+        ));
+    };
+    let location_api_url = format!("{MASTER_LOCATION_URL}/{ip}{}", key.query);
+    let fetch_err = |err: FetchError| {
+        let err = err.without_url();
+        let cause = GeoCause::of(&err);
+        if cause == GeoCause::RateLimited {
+            record_rate_limited(key.source);
+        }
+        geo_err(cause, Cow::Owned(err.to_string()))
+    };
 
-    match api_response.json::<ServerLocation>().await {
+    let api_response = http.get(&location_api_url, None).await.map_err(fetch_err)?;
+
+    match api_response.json::<ServerLocation>() {
         Ok(json) => {
-            if let Some(code) = json.continent {
-                return Ok(code);
+            if let Some(continent) = json.continent {
+                return Ok(Location {
+                    continent: continent.code,
+                    country: json.country.map(|country| country.code),
+                });
             }
-            Err(json
-                .message
-                .map(Cow::Owned)
-                .unwrap_or(Cow::Borrowed("unknown error")))
+            Err(geo_err(
+                GeoCause::Rejected,
+                json.message
+                    .map(Cow::Owned)
+                    .unwrap_or(Cow::Borrowed("unknown error")),
+            ))
         }
-        Err(err) => Err(Cow::Owned(format!("{}, ip: {ip}", err.without_url()))),
+        Err(err) => Err(fetch_err(err)),
     }
 }
 
+/// `ip` when it is a host name rather than an IP address
+fn dns_name(ip: &str) -> Option<&str> {
+    let ip = ip.trim_matches('/').trim_matches(':');
+    (!ip.is_empty() && ip != LOCAL_HOST && ip.parse::<IpAddr>().is_err()).then_some(ip)
+}
+
+/// Also returns the host name the address was resolved from, if `server_ip` was one that resolved
 #[instrument(level = "trace", skip_all)]
-fn resolve_address(
-    server_ip: &str,
+fn resolve_address<'a>(
+    server_ip: &'a str,
     host_ip: &str,
     webfront_url: &str,
-) -> Result<IpAddr, AddrParseError> {
-    let ip_trim = server_ip.trim_matches('/').trim_matches(':');
-    if !ip_trim.is_empty() && ip_trim != LOCAL_HOST {
-        if let Ok(ip) = ip_trim.parse::<IpAddr>() {
-            return if ip.is_unspecified() {
-                parse_possible_ipv6(host_ip, webfront_url)
-            } else {
-                Ok(ip)
-            };
+    prefer: IpFamily,
+) -> Result<(IpAddr, Option<&'a str>), AddrParseError> {
+    if let Some(name) = dns_name(server_ip) {
+        if let Some(ip) = try_resolve_from_str(name, prefer) {
+            trace!("Found socket address of: {ip}, from: {name}");
+            return Ok((ip, Some(name)));
         }
-        if let Some(ip) = try_resolve_from_str(ip_trim) {
-            trace!("Found socket address of: {ip}, from: {ip_trim}");
-            return Ok(ip);
+    } else {
+        let ip_trim = server_ip.trim_matches('/').trim_matches(':');
+        if let Ok(ip) = ip_trim.parse::<IpAddr>() {
+            if !ip.is_unspecified() {
+                return Ok((ip, None));
+            }
         }
     }
 
-    parse_possible_ipv6(host_ip, webfront_url)
+    parse_possible_ipv6(host_ip, webfront_url).map(|ip| (ip, None))
 }
 
-fn try_resolve_from_str(ip: &str) -> Option<IpAddr> {
-    if let Ok(mut socket_addr) = (ip, 80).to_socket_addrs() {
-        return socket_addr.next().map(|socket| socket.ip());
-    }
-    None
+/// Falls back to the first address when `ip` does not resolve to the `prefer`ed address type
+fn try_resolve_from_str(ip: &str, prefer: IpFamily) -> Option<IpAddr> {
+    let resolved = (ip, 80)
+        .to_socket_addrs()
+        .ok()?
+        .map(|socket| socket.ip())
+        .collect::<Vec<_>>();
+    resolved
+        .iter()
+        .copied()
+        .find(|&ip| prefer.matches(ip))
+        .or_else(|| resolved.first().copied())
 }
 
 #[instrument(level = "trace", skip_all)]
@@ -0,0 +1,363 @@
+use crate::{
+    cli::{BrowserOrder, Filters, Region},
+    commands::{
+        filter::{favorites_limit, matching_servers, write_matching, FAVORITES},
+        handler::{remember_filter, CommandContext, CommandHandle, Message},
+    },
+    utils::{
+        display::DisplayError,
+        input::{
+            line::{
+                AsyncCtxCallback, EventLoop, InputEventHook, InputHook, LineCallback, LineReader,
+            },
+            style::{GREY, WHITE, YELLOW},
+        },
+        regions::{custom_regions, RegionSettings},
+    },
+    H2M_MAX_CLIENT_NUM,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{cell::RefCell, collections::HashMap};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Region,
+    PlayerMin,
+    Includes,
+    Limit,
+    /// Waiting for the answer to write the matches, counting may still be running
+    Confirm,
+}
+
+impl Step {
+    fn next(self) -> Self {
+        match self {
+            Step::Region => Step::PlayerMin,
+            Step::PlayerMin => Step::Includes,
+            Step::Includes => Step::Limit,
+            Step::Limit | Step::Confirm => Step::Confirm,
+        }
+    }
+}
+
+struct Wizard {
+    step: Step,
+    /// Options given with `--interactive` and the answers so far
+    args: Filters,
+    /// `args` with the `filter` settings applied, shown as the answer used when nothing is entered
+    defaults: Filters,
+    /// The `region` settings when the wizard started
+    region_groups: RegionSettings,
+    version: f64,
+    cancel: CancellationToken,
+    /// Tells the counting task whether to write the matches
+    decision: Option<oneshot::Sender<bool>>,
+}
+
+impl Wizard {
+    fn prompt(&self) -> String {
+        let regions;
+        let (question, default) = match self.step {
+            Step::Region => {
+                regions = format!(
+                    "Regions, any of {}",
+                    region_names(&self.region_groups).join(" ")
+                );
+                (
+                    regions.as_str(),
+                    self.defaults.region.as_ref().map_or_else(
+                        || String::from("all"),
+                        |regions| {
+                            let names = regions.iter().map(Region::to_string);
+                            names.collect::<Vec<_>>().join(" ")
+                        },
+                    ),
+                )
+            }
+            Step::PlayerMin => (
+                "Minimum players",
+                self.defaults.player_min.unwrap_or(0).to_string(),
+            ),
+            Step::Includes => (
+                "Server name contains any of",
+                self.defaults
+                    .includes
+                    .as_ref()
+                    .map_or_else(|| String::from("any name"), |terms| terms.join(" ")),
+            ),
+            Step::Limit => (
+                "Most servers to add",
+                match self.defaults.limit {
+                    None if self.version >= 1.0 => String::from("no limit"),
+                    _ => favorites_limit(&self.defaults, self.version).to_string(),
+                },
+            ),
+            Step::Confirm => {
+                return format!(
+                    "Write the matches to {FAVORITES}? ({YELLOW}y{WHITE}/{YELLOW}n{WHITE})"
+                )
+            }
+        };
+        format!("{question} {GREY}[{default}]{WHITE}")
+    }
+
+    /// Stores a non empty answer to the current question
+    fn answer(&mut self, input: &str) -> Result<(), String> {
+        match self.step {
+            Step::Region => {
+                let mut regions = Vec::new();
+                for word in input.split([' ', ',']).filter(|word| !word.is_empty()) {
+                    if matches!(word, "all" | "any") {
+                        self.args.region = None;
+                        self.defaults.region = None;
+                        return Ok(());
+                    }
+                    let region = word
+                        .parse::<Region>()
+                        .ok()
+                        .filter(|region| region.is_defined(&self.region_groups))
+                        .ok_or_else(|| {
+                            let names = region_names(&self.region_groups);
+                            format!("Unknown region '{word}', use {}", names.join(", "))
+                        })?;
+                    if !regions.contains(&region) {
+                        regions.push(region);
+                    }
+                }
+                self.args.region = Some(regions);
+            }
+            Step::PlayerMin => {
+                self.args.player_min = Some(
+                    input
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&players| players as i64 <= H2M_MAX_CLIENT_NUM)
+                        .ok_or_else(|| {
+                            format!("Enter a number of players from 0 to {H2M_MAX_CLIENT_NUM}")
+                        })?,
+                )
+            }
+            Step::Includes => {
+                let terms = shellwords::split(input).map_err(|err| err.to_string())?;
+                self.args.includes = Some(terms);
+            }
+            Step::Limit => {
+                self.args.limit = Some(
+                    input
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&limit| limit > 0)
+                        .ok_or("Enter a number of servers greater than 0")?,
+                )
+            }
+            Step::Confirm => (),
+        }
+        Ok(())
+    }
+
+    /// Stops counting and leaves favourites.json as it is
+    fn abort(&mut self) {
+        self.cancel.cancel();
+        if let Some(decision) = self.decision.take() {
+            let _ = decision.send(false);
+        }
+    }
+}
+
+/// Asks for the options most lists are built from one at a time, favourites.json only changes once the
+/// count of matching servers is confirmed
+pub fn filter_wizard(args: Filters, context: &CommandContext) -> CommandHandle {
+    let mut defaults = args.clone();
+    context.config().filter.apply(&mut defaults);
+    let wizard = Wizard {
+        step: Step::Region,
+        args,
+        defaults,
+        region_groups: context.config().region.clone(),
+        version: context.game().version.unwrap_or(1.0),
+        cancel: CancellationToken::new(),
+        decision: None,
+    };
+    println!(
+        "{GREY}Press enter to keep the answer in brackets, ctrl + c stops without writing{WHITE}"
+    );
+
+    let first_prompt = wizard.prompt();
+    let init: Box<LineCallback> = Box::new(move |handle| {
+        handle.set_prompt(first_prompt.clone());
+        handle.set_completion(false);
+        Ok(())
+    });
+
+    let wizard = RefCell::new(wizard);
+    let input_hook: Box<InputEventHook> = Box::new(move |handle, event| {
+        let mut wizard = wizard.borrow_mut();
+        let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        else {
+            return Ok((EventLoop::Continue, false));
+        };
+        let finish = |handle: &mut LineReader| {
+            handle.restore_prompt();
+            handle.set_completion(true);
+        };
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                if !handle.line.input().is_empty() {
+                    handle.ctrl_c_line()?;
+                    return Ok((EventLoop::Continue, false));
+                }
+                wizard.abort();
+                handle.ctrl_c_line()?;
+                finish(handle);
+                handle
+                    .print_background_msg(Message::Warn(format!("{FAVORITES} was not changed")))?;
+                Ok((EventLoop::Continue, true))
+            }
+            KeyCode::Char(c) if wizard.step == Step::Confirm => {
+                let write = match c.to_ascii_lowercase() {
+                    'y' => true,
+                    'n' => false,
+                    _ => return Ok((EventLoop::Continue, false)),
+                };
+                if !write {
+                    wizard.abort();
+                    handle.print_background_msg(Message::Info(format!(
+                        "{FAVORITES} was not changed"
+                    )))?;
+                } else if let Some(decision) = wizard.decision.take() {
+                    let _ = decision.send(true);
+                }
+                finish(handle);
+                Ok((EventLoop::Continue, true))
+            }
+            KeyCode::Char(c) => {
+                handle.insert_char(c);
+                Ok((EventLoop::Continue, false))
+            }
+            KeyCode::Backspace => {
+                handle.remove_char()?;
+                Ok((EventLoop::Continue, false))
+            }
+            KeyCode::Enter if wizard.step != Step::Confirm => {
+                let input = handle.line.take_input();
+                handle.new_line()?;
+                if !input.trim().is_empty() {
+                    if let Err(err) = wizard.answer(input.trim()) {
+                        handle.print_background_msg(Message::Err(err))?;
+                        return Ok((EventLoop::Continue, false));
+                    }
+                }
+                wizard.step = wizard.step.next();
+                handle.set_prompt(wizard.prompt());
+                if wizard.step != Step::Confirm {
+                    return Ok((EventLoop::Continue, false));
+                }
+                let (decision_tx, decision_rx) = oneshot::channel();
+                wizard.decision = Some(decision_tx);
+                let args = wizard.args.clone();
+                let cancel = wizard.cancel.clone();
+                let count: Box<AsyncCtxCallback> = Box::new(move |context| {
+                    Box::pin(async move {
+                        count_then_write(context, args, cancel, decision_rx).await;
+                        Ok(())
+                    })
+                });
+                Ok((EventLoop::AsyncCallback(count), false))
+            }
+            _ => Ok((EventLoop::Continue, false)),
+        }
+    });
+    CommandHandle::InsertHook(InputHook::with_new_uid(Some(init), input_hook))
+}
+
+/// Built in regions followed by the regions from the `region` settings
+fn region_names(groups: &RegionSettings) -> Vec<String> {
+    Region::BUILT_IN
+        .into_iter()
+        .chain(custom_regions(groups))
+        .map(|region| region.to_string())
+        .collect()
+}
+
+/// Counts the servers matching `args` in the background, then writes them if `decision` is `true`
+async fn count_then_write(
+    context: &mut CommandContext,
+    args: Filters,
+    cancel: CancellationToken,
+    decision: oneshot::Receiver<bool>,
+) {
+    let msg_sender = context.msg_sender();
+    let tokens = args.to_args();
+    let _ = msg_sender
+        .send(Message::Str(format!(
+            "{GREY}Same as `{YELLOW}filter {}{GREY}`{WHITE}",
+            shellwords::join(&tokens.iter().map(String::as_str).collect::<Vec<_>>())
+        )))
+        .await;
+    remember_filter(context, &tokens).await;
+
+    let mut args = args;
+    context.config().filter.apply(&mut args);
+    let version = context.game().version.unwrap_or(1.0);
+    let config = context.config_service().current();
+    let targets = context.favorites_targets();
+    let cache = context.cache().clone();
+    let http = context.http();
+
+    tokio::spawn(async move {
+        let limit = favorites_limit(&args, version);
+        let found = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return,
+            found = matching_servers(
+                &http,
+                &args,
+                cache.clone(),
+                limit,
+                version,
+                &config,
+                &cancel,
+                &msg_sender,
+            ) => found,
+        };
+        let (servers, update_cache) = match found {
+            Ok(found) => found,
+            Err(err) => {
+                let _ = msg_sender
+                    .send(Message::Err(DisplayError(&err).to_string()))
+                    .await;
+                return;
+            }
+        };
+        if update_cache {
+            cache.mark_changed();
+        }
+        if decision.await != Ok(true) {
+            return;
+        }
+        let regions = match args.browser_order {
+            Some(BrowserOrder::Region) => cache.lock().await.ip_to_region.clone(),
+            _ => HashMap::new(),
+        };
+        if let Err(err) = write_matching(
+            &targets,
+            servers,
+            limit,
+            args.browser_order,
+            &regions,
+            &config.region,
+            !args.yes,
+            &msg_sender,
+        )
+        .await
+        {
+            let _ = msg_sender
+                .send(Message::Err(DisplayError(&err).to_string()))
+                .await;
+        }
+    });
+}
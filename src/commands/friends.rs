@@ -0,0 +1,289 @@
+use crate::{
+    cli::FriendCmd,
+    commands::{
+        handler::{CommandContext, CommandHandle, Message},
+        reconnect::{join_server, number_key_hook},
+        services::CacheService,
+    },
+    parse_hostname, strip_color_codes,
+    utils::{
+        caching::Cache,
+        events::EventBus,
+        game_state::GameState,
+        getstatus::get_status,
+        input::style::{GREEN, WHITE},
+    },
+    LOG_ONLY,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc::Sender, watch, Mutex, Notify, Semaphore},
+    task::JoinSet,
+};
+use tracing::{error, info};
+
+const FRIENDS_FILE: &str = "friends.json";
+const SCAN_INTERVAL: Duration = Duration::from_secs(180);
+const SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+const SCAN_CONCURRENCY: usize = 64;
+/// Online friends past this can not be joined with a single key press
+pub const JOIN_KEYS: usize = 9;
+
+pub struct FriendSeen {
+    pub server: SocketAddr,
+    /// Raw server name, color codes included
+    pub host_name: Arc<str>,
+    /// Name as displayed in game, color codes included
+    pub player_name: String,
+}
+
+/// Players tracked by name, [`presence_loop`] reports when one shows up on or leaves a cached server
+pub struct Friends {
+    /// Lowercase with color codes removed
+    names: Vec<String>,
+    online: HashMap<String, FriendSeen>,
+    last_scan: Option<Instant>,
+    rescan: Arc<Notify>,
+    local_dir: Option<PathBuf>,
+}
+
+impl Friends {
+    pub fn new(local_dir: Option<&Path>) -> Self {
+        let names = local_dir
+            .and_then(|dir| std::fs::read(dir.join(FRIENDS_FILE)).ok())
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+        Friends {
+            names,
+            online: HashMap::new(),
+            last_scan: None,
+            rescan: Arc::new(Notify::new()),
+            local_dir: local_dir.map(Path::to_path_buf),
+        }
+    }
+
+    #[inline]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    #[inline]
+    pub fn last_scan(&self) -> Option<Instant> {
+        self.last_scan
+    }
+
+    /// Online friends sorted by name, the order used to number them for joining
+    pub fn online(&self) -> Vec<(&str, &FriendSeen)> {
+        let mut online = self
+            .online
+            .iter()
+            .map(|(name, seen)| (name.as_str(), seen))
+            .collect::<Vec<_>>();
+        online.sort_unstable_by_key(|&(name, _)| name);
+        online
+    }
+
+    /// Returns `false` if `name` was already a friend
+    pub fn add(&mut self, name: &str) -> io::Result<bool> {
+        let name = parse_hostname(name.trim());
+        if self.names.contains(&name) {
+            return Ok(false);
+        }
+        self.names.push(name);
+        self.save()?;
+        self.rescan.notify_one();
+        Ok(true)
+    }
+
+    /// Returns `false` if `name` was not a friend
+    pub fn remove(&mut self, name: &str) -> io::Result<bool> {
+        let name = parse_hostname(name.trim());
+        let len = self.names.len();
+        self.names.retain(|friend| *friend != name);
+        if self.names.len() == len {
+            return Ok(false);
+        }
+        self.online.remove(&name);
+        self.save().map(|_| true)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(ref dir) = self.local_dir else {
+            return Ok(());
+        };
+        let file = std::fs::File::create(dir.join(FRIENDS_FILE))?;
+        serde_json::to_writer_pretty(file, &self.names)?;
+        Ok(())
+    }
+}
+
+/// Every known server with its current name
+async fn cached_servers(cache: &Mutex<Cache>) -> Vec<(SocketAddr, Arc<str>)> {
+    let cache = cache.lock().await;
+    cache
+        .book
+        .servers()
+        .iter()
+        .filter_map(|(&socket_addr, entry)| Some((socket_addr, Arc::clone(entry.host_name()?))))
+        .collect()
+}
+
+async fn scan(
+    servers: Vec<(SocketAddr, Arc<str>)>,
+    names: &[String],
+) -> HashMap<String, FriendSeen> {
+    let permits = Arc::new(Semaphore::new(SCAN_CONCURRENCY));
+    let names = Arc::new(names.iter().cloned().collect::<HashSet<_>>());
+    let mut tasks = JoinSet::new();
+    for (server, host_name) in servers {
+        let permits = Arc::clone(&permits);
+        let names = Arc::clone(&names);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let Ok(status) = get_status(server, SCAN_TIMEOUT).await else {
+                return Vec::new();
+            };
+            status
+                .players
+                .into_iter()
+                .filter_map(|player| {
+                    let name = parse_hostname(&player.name);
+                    names.contains(&name).then(|| {
+                        let seen = FriendSeen {
+                            server,
+                            host_name: Arc::clone(&host_name),
+                            player_name: player.name,
+                        };
+                        (name, seen)
+                    })
+                })
+                .collect()
+        });
+    }
+    let mut found = HashMap::new();
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok(seen) => found.extend(seen),
+            Err(err) => error!(name: LOG_ONLY, "{err}"),
+        }
+    }
+    found
+}
+
+/// Scans cached servers for friends every [`SCAN_INTERVAL`], or right after a friend is added, and
+/// reports friends coming online, changing servers or going offline. Scans are skipped while the
+/// game is loading a map so the burst of `getstatus` requests does not compete with it
+pub async fn presence_loop(
+    friends: Arc<Mutex<Friends>>,
+    cache: CacheService,
+    msg_sender: Arc<Sender<Message>>,
+    events: EventBus,
+    game_state: watch::Receiver<GameState>,
+) {
+    let rescan = Arc::clone(&friends.lock().await.rescan);
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            _ = rescan.notified() => interval.reset(),
+        }
+        if matches!(*game_state.borrow(), GameState::Loading { .. }) {
+            continue;
+        }
+        let names = friends.lock().await.names.clone();
+        if names.is_empty() {
+            continue;
+        }
+        let mut found = scan(cached_servers(&cache).await, &names).await;
+
+        let mut messages = Vec::new();
+        {
+            let mut friends = friends.lock().await;
+            found.retain(|name, _| friends.names.contains(name));
+            for (name, seen) in found.iter() {
+                if friends
+                    .online
+                    .get(name)
+                    .is_some_and(|prev| prev.server == seen.server)
+                {
+                    continue;
+                }
+                let (player_name, host_name) = (
+                    strip_color_codes(&seen.player_name),
+                    strip_color_codes(&seen.host_name),
+                );
+                events.notify(format!("{player_name} is online"), &*host_name);
+                messages.push(format!(
+                    "{GREEN}{player_name}{WHITE} is online, playing on {host_name}"
+                ));
+            }
+            for name in friends.online.keys() {
+                if !found.contains_key(name) {
+                    messages.push(format!("{name} went offline"));
+                }
+            }
+            friends.online = found;
+            friends.last_scan = Some(Instant::now());
+        }
+        for message in messages {
+            if msg_sender.send(Message::Info(message)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub async fn friend(context: &mut CommandContext, command: FriendCmd) -> CommandHandle {
+    let friends_arc = context.friends();
+    let mut friends = friends_arc.lock().await;
+    match command {
+        FriendCmd::Add { name } => match friends.add(&name) {
+            Ok(true) => info!("Added '{name}', cached servers are being checked for them"),
+            Ok(false) => info!("'{name}' is already a friend"),
+            Err(err) => error!("Could not save {FRIENDS_FILE}, {err}"),
+        },
+        FriendCmd::Remove { name } => match friends.remove(&name) {
+            Ok(true) => info!("Removed '{name}'"),
+            Ok(false) => info!("'{name}' is not a friend"),
+            Err(err) => error!("Could not save {FRIENDS_FILE}, {err}"),
+        },
+    }
+    CommandHandle::Processed
+}
+
+pub async fn friends(context: &mut CommandContext) -> CommandHandle {
+    let friends_arc = context.friends();
+    let friends = friends_arc.lock().await;
+    if friends.names().is_empty() {
+        info!("No friends added, use `friend add <NAME>`");
+        return CommandHandle::Processed;
+    }
+    print!("{friends}");
+
+    let joinable = friends
+        .online()
+        .into_iter()
+        .take(JOIN_KEYS)
+        .map(|(_, seen)| seen.server)
+        .collect::<Vec<_>>();
+    if joinable.is_empty() {
+        return CommandHandle::Processed;
+    }
+    println!(
+        "Press {} to join, any other key to continue",
+        if joinable.len() == 1 {
+            String::from("1")
+        } else {
+            format!("1-{}", joinable.len())
+        }
+    );
+
+    CommandHandle::InsertHook(number_key_hook(joinable, join_server))
+}
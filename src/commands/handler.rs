@@ -1,48 +1,116 @@
 use crate::{
-    cli::{CacheCmd, Command, Filters, UserCommand},
+    cli::{
+        CacheCmd, Channel, Command, ConfigCmd, ConsoleArgs, Filters, GeoKeyCmd, LogTarget,
+        OpenTarget, PerfCmd, Setting, Toggle, UserCommand, Verbosity, WindowCmd,
+    },
     commands::{
+        chat::{alerts, ChatLog},
+        dashboard::dashboard,
+        diff::diff,
+        doctor::doctor,
+        favorites::{favorites, favorites_targets, parse_preset},
         filter::build_favorites,
-        launch_h2m::{h2m_running, initalize_listener, launch_h2m_pseudo, LaunchError},
+        filter_wizard::filter_wizard,
+        friends::{friend, friends, Friends},
+        import::import,
+        journal::journal,
+        launch_h2m::{
+            find_console_log, h2m_running, initalize_listener, initalize_log_tail,
+            launch_h2m_pseudo, terminate_process, ConsoleHistory, PTY,
+        },
+        logs::{app_log_prefix, logs, newest_log},
+        rcon::rcon,
         reconnect::reconnect,
+        report::{report, REPORT_COMMANDS_MAX},
+        script::{self, Script},
+        search::{add, search},
+        server_info::server_info,
+        services::{CacheService, ConfigService, ConsoleService, GameControl},
+        session::{session, SessionLog},
+        status::{clear, status},
+        tasks::{tasks, TaskList},
+        trim::TrimRequest,
+        update::{changelog, update},
+        whois::whois,
     },
-    exe_details,
+    compare_versions,
+    error::Error,
+    exe_details, select_game_executable,
     utils::{
-        caching::{build_cache, Cache},
-        display::{ConnectionHelp, HmwUpdateHelp},
+        caching::{build_cache, write_cache_file, Cache},
+        config::{Config, SettingSource, Settings, CONFIG_FILE, DEFAULT_VALUE},
+        display::{
+            ConnectionHelp, DisplayError, DisplayGeoKey, DisplayLogLevels, DisplayPerf,
+            DisplayPromptPlaceholders, DisplaySettingSources, DisplaySuggestion, HmwUpdateHelp,
+        },
+        events::EventBus,
+        game_state::{set_state, GameState},
+        geo_key::{clear_key, key_status, set_key, KeySource, GEO_KEY_ENV},
+        http::{new_http, Http, NetworkClient},
         input::{
             line::{
                 AsyncCtxCallback, EventLoop, InputEventHook, InputHook, InputHookErr, LineCallback,
-                LineData,
             },
-            style::{RED, WHITE, YELLOW},
+            style::{init_color, GREY, RED, WHITE, YELLOW},
         },
-        json_data::Version,
+        json_data::ChannelVersion,
+        lang::init_language,
+        perf,
+        profile::Profile,
+        progress::spin_while,
+        subscriber::{log_levels, set_log_level},
+        suggest::suggest,
+        window::{apply_tray, forget_placement, hide_console},
     },
-    CACHED_DATA, REQUIRED_FILES,
+    GAME_EXECUTABLES, LOG_ONLY,
 };
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use std::{
+    collections::VecDeque,
     ffi::OsString,
     fmt::Display,
+    future::Future,
     path::{Path, PathBuf},
+    pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::{Duration, Instant, SystemTime},
 };
 use tokio::{
     sync::{mpsc::Sender, Mutex, RwLock},
     task::JoinError,
 };
-use tracing::{error, info};
-use winptyrs::PTY;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
 pub enum Message {
     Str(String),
     Info(String),
     Err(String),
     Warn(String),
+    /// Replaces the progress line drawn above the prompt, `None` removes it
+    Progress(Option<String>),
+    /// Replaces the full screen view drawn over the prompt, `None` returns to the prompt
+    Screen(Option<String>),
+    /// Asks which servers to leave out of favourites.json, dropping it keeps the most players
+    Trim(TrimRequest),
+}
+
+impl Message {
+    /// Prints text messages, `Progress`, `Screen` and `Trim` are left to the caller since they depend on
+    /// the prompt
+    pub fn print(self) {
+        match self {
+            Message::Str(msg) => println!("{msg}"),
+            Message::Info(msg) => info!("{msg}"),
+            Message::Warn(msg) => warn!("{msg}"),
+            Message::Err(msg) => error!("{msg}"),
+            Message::Progress(_) | Message::Screen(_) | Message::Trim(_) => (),
+        }
+    }
 }
 
 pub struct GameDetails {
@@ -50,15 +118,18 @@ pub struct GameDetails {
     pub version: Option<f64>,
     pub hash_curr: Option<String>,
     pub hash_latest: Option<String>,
+    /// Arguments passed through to the game client on launch
+    pub launch_args: Vec<String>,
 }
 
 impl GameDetails {
     pub fn default(exe_dir: &Path) -> Self {
         GameDetails {
-            path: exe_dir.join(REQUIRED_FILES[3]),
+            path: exe_dir.join(GAME_EXECUTABLES[0].0),
             version: None,
             hash_curr: None,
             hash_latest: None,
+            launch_args: Vec::new(),
         }
     }
 
@@ -68,9 +139,24 @@ impl GameDetails {
             version,
             hash_curr,
             hash_latest: None,
+            launch_args: Vec::new(),
         }
     }
 
+    /// Only the H2M/HMW client is versioned by the HMW manifest, other clients can not be compared against it
+    pub fn tracks_hmw_manifest(&self) -> bool {
+        self.path
+            .file_name()
+            .is_some_and(|name| name == GAME_EXECUTABLES[0].0)
+    }
+
+    fn switch_exe(&mut self, path: PathBuf) {
+        let (version, hash_curr) = exe_details(&path);
+        self.path = path;
+        self.version = version;
+        self.hash_curr = hash_curr;
+    }
+
     fn update(&mut self, from: (Option<f64>, Option<String>)) {
         if from.0.is_some() {
             self.version = from.0;
@@ -85,6 +171,12 @@ pub struct AppDetails {
     pub ver_curr: &'static str,
     pub ver_latest: Option<String>,
     pub update_msg: Option<String>,
+    pub min_supported: Option<String>,
+    /// Published SHA-256 of the `ver_latest` executable, only kept when it is a well formed digest
+    pub exe_sha256: Option<String>,
+    pub channel: Channel,
+    /// Plain text release notes of `ver_latest`, only fetched when it is newer than `ver_curr`
+    pub changelog: Option<String>,
 }
 
 impl Default for AppDetails {
@@ -93,58 +185,112 @@ impl Default for AppDetails {
             ver_curr: env!("CARGO_PKG_VERSION"),
             ver_latest: None,
             update_msg: None,
+            min_supported: None,
+            exe_sha256: None,
+            channel: Channel::default(),
+            changelog: None,
         }
     }
 }
 
-impl From<Version> for AppDetails {
-    fn from(value: Version) -> Self {
+impl AppDetails {
+    pub fn new(version: ChannelVersion, channel: Channel) -> Self {
         AppDetails {
             ver_curr: env!("CARGO_PKG_VERSION"),
-            ver_latest: Some(value.latest),
-            update_msg: Some(value.message),
+            ver_latest: Some(version.latest),
+            update_msg: Some(version.message),
+            min_supported: version.min_supported,
+            exe_sha256: version.sha256.filter(|hash| {
+                let valid = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+                if !valid {
+                    debug!(name: LOG_ONLY, "Ignored malformed executable digest: {hash}");
+                }
+                valid
+            }),
+            channel,
+            changelog: None,
         }
     }
+
+    /// `true` if the channel's latest version is newer than the running one
+    pub fn update_available(&self) -> bool {
+        self.ver_latest
+            .as_deref()
+            .is_some_and(|latest| compare_versions(latest, self.ver_curr).is_gt())
+    }
+
+    pub fn unsupported(&self) -> bool {
+        self.min_supported
+            .as_deref()
+            .is_some_and(|min| compare_versions(self.ver_curr, min).is_lt())
+    }
 }
 
 pub struct CommandContext {
-    cache: Arc<Mutex<Cache>>,
-    cache_needs_update: Arc<AtomicBool>,
-    forward_logs: Arc<AtomicBool>,
-    h2m_console_history: Arc<Mutex<Vec<String>>>,
+    cache: CacheService,
+    console: ConsoleService,
+    game_control: GameControl,
+    forward_logs: bool,
+    notifications: Arc<AtomicBool>,
+    events: EventBus,
+    session_log: Arc<Mutex<SessionLog>>,
+    chat_log: Arc<Mutex<ChatLog>>,
+    friends: Arc<Mutex<Friends>>,
+    cancel: CancellationToken,
+    tasks: TaskList,
+    settings: Settings,
+    config_service: ConfigService,
+    recent_commands: VecDeque<String>,
     pty_handle: Option<Arc<RwLock<PTY>>>,
     local_dir: Option<PathBuf>,
     msg_sender: Arc<Sender<Message>>,
     game: GameDetails,
     app: AppDetails,
+    http: Http,
+    network: NetworkClient,
 }
 
 impl CommandContext {
     #[inline]
-    pub fn cache(&self) -> Arc<Mutex<Cache>> {
-        Arc::clone(&self.cache)
+    pub fn cache(&self) -> &CacheService {
+        &self.cache
+    }
+    #[inline]
+    pub fn console(&self) -> &ConsoleService {
+        &self.console
     }
     #[inline]
-    pub fn cache_needs_update(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.cache_needs_update)
+    pub fn game_control(&self) -> &GameControl {
+        &self.game_control
     }
+    /// If published console lines should be printed, set while the user is following the H2M console
     #[inline]
-    pub fn forward_logs(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.forward_logs)
+    pub fn forward_logs(&self) -> bool {
+        self.forward_logs
     }
     pub async fn check_h2m_connection(&mut self) -> Result<(), String> {
         if let Some(ref lock) = self.pty_handle {
             let handle = lock.read().await;
             return match handle.is_alive() {
                 Ok(true) => Ok(()),
-                Ok(false) => Err(String::from("No connection to H2M is active")),
+                Ok(false) => {
+                    self.game_control.set_connected(false);
+                    Err(String::from("No connection to H2M is active"))
+                }
                 Err(err) => {
                     drop(handle);
                     self.pty_handle = None;
+                    self.game_control.set_connected(false);
                     Err(err.to_string_lossy().to_string())
                 }
             };
         }
+        if cfg!(all(
+            not(feature = "windows-console"),
+            not(target_os = "linux")
+        )) {
+            return Err(Error::Unsupported("Game control").to_string());
+        }
         Err(String::from("No Pseudoconsole set"))
     }
     #[inline]
@@ -156,8 +302,101 @@ impl CommandContext {
         self.local_dir = Some(local_dir)
     }
     #[inline]
-    pub fn h2m_console_history(&self) -> Arc<Mutex<Vec<String>>> {
-        Arc::clone(&self.h2m_console_history)
+    pub fn notifications(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.notifications)
+    }
+    #[inline]
+    pub fn events(&self) -> EventBus {
+        self.events.clone()
+    }
+    #[inline]
+    pub fn session_log(&self) -> Arc<Mutex<SessionLog>> {
+        Arc::clone(&self.session_log)
+    }
+    #[inline]
+    pub fn chat_log(&self) -> Arc<Mutex<ChatLog>> {
+        Arc::clone(&self.chat_log)
+    }
+    #[inline]
+    pub fn friends(&self) -> Arc<Mutex<Friends>> {
+        Arc::clone(&self.friends)
+    }
+    /// Token of the command currently running in the foreground, triggered when the user presses 'ctrl + c'
+    #[inline]
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+    #[inline]
+    pub fn new_cancel_token(&mut self) -> CancellationToken {
+        self.cancel = CancellationToken::new();
+        self.cancel.clone()
+    }
+    /// Background tasks the main loop is running
+    #[inline]
+    pub fn tasks(&self) -> &TaskList {
+        &self.tasks
+    }
+    #[inline]
+    pub fn tasks_mut(&mut self) -> &mut TaskList {
+        &mut self.tasks
+    }
+    #[inline]
+    pub fn app(&self) -> &AppDetails {
+        &self.app
+    }
+    #[inline]
+    pub fn http(&self) -> Http {
+        Arc::clone(&self.http)
+    }
+    #[inline]
+    pub fn game(&self) -> &GameDetails {
+        &self.game
+    }
+    #[inline]
+    pub fn config(&self) -> &Config {
+        &self.settings.effective
+    }
+    /// The effective settings for tasks that outlive the command, kept in step with [`Self::config`]
+    #[inline]
+    pub fn config_service(&self) -> &ConfigService {
+        &self.config_service
+    }
+    /// The client network requests are sent with, rebuilt when the `network` settings change
+    #[inline]
+    pub fn network(&self) -> &NetworkClient {
+        &self.network
+    }
+    /// Commands entered this session, oldest first
+    #[inline]
+    pub fn recent_commands(&self) -> &VecDeque<String> {
+        &self.recent_commands
+    }
+    #[inline]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    #[inline]
+    pub fn prompt_template(&self) -> &str {
+        &self.settings.effective.display.prompt
+    }
+    /// Applies effective values that take effect immediately
+    fn apply_settings(&mut self) {
+        let config = &self.settings.effective;
+        self.game_control
+            .set_auto_relaunch(config.launch.auto_relaunch);
+        self.game_control
+            .set_focus_on_connect(config.launch.focus_on_connect);
+        self.notifications
+            .store(config.display.notifications, Ordering::SeqCst);
+        self.config_service.replace(config.clone());
+        init_color(config.display.color);
+        apply_tray(&config.window);
+        if let Err(err) = self.network.configure(&config.network) {
+            warn!("{err}");
+        }
+        for problem in init_language(self.local_dir.as_deref(), &config.display.language) {
+            warn!("{problem}");
+        }
     }
     #[inline]
     pub fn pty_handle(&self) -> Option<Arc<RwLock<PTY>>> {
@@ -172,24 +411,134 @@ impl CommandContext {
         self.game.version
     }
     #[inline]
+    pub fn game_path(&self) -> &Path {
+        &self.game.path
+    }
+    /// favourites.json files written by `filter`, `favorites import` and `favorites check --fix`
+    pub fn favorites_targets(&self) -> Vec<PathBuf> {
+        favorites_targets(
+            self.game.path.parent().expect("has parent"),
+            &self.config().filter.favorites_path,
+        )
+    }
+    #[inline]
+    pub fn launch_args(&self) -> &[String] {
+        &self.game.launch_args
+    }
+    /// Takes the result of the version check that was still running when the context was built
+    pub fn set_app_version(&mut self, res: AppVersionResult) {
+        self.app = resolve_app_version(res, &self.events);
+    }
+    /// Takes the latest HMW manifest hash that was still being fetched when the context was built
+    pub fn set_hmw_hash(&mut self, res: HmwHashResult) {
+        resolve_hmw_hash(&mut self.game, res);
+    }
+    /// Attaches to the game launched at startup, once its pseudoconsole is ready
+    pub async fn attach_launched(&mut self, res: LaunchResult) {
+        let Some(pty) = resolve_launch(res) else {
+            return;
+        };
+        self.init_pty(pty);
+        self.game_control.start_session().await;
+        if let Err(err) = listener_routine(self).await {
+            error!("{err}")
+        }
+    }
+    #[inline]
     fn init_pty(&mut self, pty: PTY) {
-        self.pty_handle = Some(Arc::new(RwLock::new(pty)))
+        self.pty_handle = Some(Arc::new(RwLock::new(pty)));
+        self.game_control.set_connected(true);
+        set_state(self.game_control.state(), GameState::Launching);
+    }
+}
+
+/// Name of the background task started by `cache`
+pub const CACHE_TASK: &str = "cache";
+
+pub type LaunchResult = Result<Result<PTY, Error>, JoinError>;
+pub type AppVersionResult = Result<Result<AppDetails, Error>, JoinError>;
+pub type HmwHashResult = Result<Result<Option<String>, Error>, JoinError>;
+
+fn resolve_launch(res: LaunchResult) -> Option<PTY> {
+    match res {
+        Ok(Ok(handle)) => return Some(handle),
+        Err(join_err) => error!("Could not launch H2M as child process: {join_err}"),
+        Ok(Err(err)) => error!(
+            "Could not launch H2M as child process: {}",
+            DisplayError(&err)
+        ),
     }
+    None
 }
 
-type LaunchResult = Result<Result<PTY, LaunchError>, JoinError>;
-type AppVersionResult = Result<reqwest::Result<AppDetails>, JoinError>;
-type HmwHashResult = Result<reqwest::Result<Option<String>>, JoinError>;
+fn resolve_app_version(res: AppVersionResult, events: &EventBus) -> AppDetails {
+    let app = match res {
+        Ok(Ok(app)) => app,
+        Err(join_err) => {
+            error!("Could not get latest MatchWire version: {join_err}");
+            return AppDetails::default();
+        }
+        Ok(Err(err)) => {
+            error!("{}", DisplayError(&err));
+            return AppDetails::default();
+        }
+    };
+    if let Some(ref msg) = app.update_msg {
+        if app.update_available() {
+            info!(name: LOG_ONLY, "{msg}")
+        }
+    }
+    if app.unsupported() {
+        warn!(
+            "MatchWire v{} is no longer supported, use command 'update'",
+            app.ver_curr
+        );
+    }
+    if app.update_available() {
+        events.notify(
+            format!(
+                "MatchWire v{} is available",
+                app.ver_latest.as_deref().unwrap_or_default()
+            ),
+            "Use command 'update' to install it",
+        );
+    }
+    app
+}
+
+fn resolve_hmw_hash(game: &mut GameDetails, res: HmwHashResult) {
+    match res {
+        Ok(Ok(option_hash)) => {
+            if let Some(ref hash_latest) = option_hash {
+                if let Some(ref hash_curr) = game.hash_curr {
+                    if game.tracks_hmw_manifest() && hash_curr != hash_latest {
+                        info!(name: LOG_ONLY, "{HmwUpdateHelp}")
+                    }
+                }
+                game.hash_latest = option_hash;
+            } else {
+                error!("hmw manifest.json formatting has changed");
+            }
+        }
+        Ok(Err(err)) => error!("{}", DisplayError(&err)),
+        Err(err) => error!("{err:?}"),
+    }
+}
 
 #[derive(Default)]
 pub struct CommandContextBuilder {
     cache: Option<Cache>,
+    cache_service: Option<CacheService>,
+    game_control: Option<GameControl>,
     launch_res: Option<LaunchResult>,
     game: Option<GameDetails>,
     msg_sender: Option<Sender<Message>>,
     local_dir: Option<PathBuf>,
     app_ver_res: Option<AppVersionResult>,
     hmw_hash_res: Option<HmwHashResult>,
+    settings: Option<Settings>,
+    http: Option<Http>,
+    network: Option<NetworkClient>,
 }
 
 impl CommandContextBuilder {
@@ -200,6 +549,21 @@ impl CommandContextBuilder {
         self.cache = Some(cache);
         self
     }
+    /// Shares a cache that is already in use, instead of [`Self::cache`]
+    pub fn cache_service(mut self, service: CacheService) -> Self {
+        self.cache_service = Some(service);
+        self
+    }
+    /// Shares the state of a game that is already being controlled, can not be combined with
+    /// [`Self::launch_res`]
+    pub fn game_control(mut self, control: GameControl) -> Self {
+        self.game_control = Some(control);
+        self
+    }
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
     pub fn msg_sender(mut self, sender: Sender<Message>) -> Self {
         self.msg_sender = Some(sender);
         self
@@ -224,77 +588,92 @@ impl CommandContextBuilder {
         self.game = Some(details);
         self
     }
+    /// Defaults to sending requests over the network
+    pub fn http(mut self, http: Http) -> Self {
+        self.http = Some(http);
+        self
+    }
+    /// Shares the client `http` sends requests with, so it follows changes to the `network` settings
+    pub fn network(mut self, network: NetworkClient) -> Self {
+        self.network = Some(network);
+        self
+    }
 
     pub fn build(self) -> Result<CommandContext, &'static str> {
-        let handle = if let Some(Ok(Ok(handle))) = self.launch_res {
-            Some(handle)
-        } else {
-            if let Some(join_res) = self.launch_res {
-                let err = match join_res {
-                    Err(join_err) => join_err.to_string(),
-                    Ok(Err(launch_err)) => launch_err.to_string(),
-                    Ok(Ok(_)) => unreachable!("by happy path"),
-                };
-                error!("Could not launch H2M as child process: {err}");
-            }
-            None
-        };
-
-        let app = if let Some(Ok(Ok(app))) = self.app_ver_res {
-            if let (Some(latest), Some(msg)) = (&app.ver_latest, &app.update_msg) {
-                if app.ver_curr != latest {
-                    info!("{msg}")
-                }
-            }
-            app
-        } else {
-            if let Some(join_res) = self.app_ver_res {
-                let err = match join_res {
-                    Err(join_err) => join_err.to_string(),
-                    Ok(Err(reqwest_err)) => reqwest_err.to_string(),
-                    Ok(Ok(_)) => unreachable!("by happy path"),
-                };
-                error!("Could not get latest MatchWire version: {err}");
-            }
-            AppDetails::default()
-        };
-
+        let launched = self.launch_res.is_some();
+        let handle = self.launch_res.and_then(resolve_launch);
+        let settings = self.settings.unwrap_or_default();
+        let config = &settings.effective;
+        let events = EventBus::default();
+        let app = self
+            .app_ver_res
+            .map(|res| resolve_app_version(res, &events))
+            .unwrap_or_default();
         let mut game = self.game.ok_or("game details is required")?;
         if let Some(res) = self.hmw_hash_res {
-            match res {
-                Ok(Ok(option_hash)) => {
-                    if let Some(ref hash_latest) = option_hash {
-                        if let Some(ref hash_curr) = game.hash_curr {
-                            if hash_curr != hash_latest {
-                                info!("{HmwUpdateHelp}")
-                            }
-                        }
-                        game.hash_latest = option_hash;
-                    } else {
-                        error!("hmw manifest.json formatting has changed");
-                    }
-                }
-                Ok(Err(err)) => error!("{err}"),
-                Err(err) => error!("{err:?}"),
-            }
+            resolve_hmw_hash(&mut game, res);
+        }
+
+        let msg_sender = self
+            .msg_sender
+            .map(Arc::new)
+            .ok_or("msg_sender is required")?;
+        let session_log = SessionLog::new(self.local_dir.as_deref(), &msg_sender);
+        let mut chat_log = ChatLog::new(self.local_dir.as_deref());
+        let friends = Friends::new(self.local_dir.as_deref());
+        if let Some(name) = game
+            .path
+            .parent()
+            .and_then(|game_dir| Profile::read(game_dir).ok())
+            .and_then(|profile| profile.name)
+        {
+            chat_log.set_name_alert(&name);
         }
 
+        let cache = match (self.cache, self.cache_service) {
+            (Some(cache), None) => CacheService::new(cache),
+            (None, Some(service)) => service,
+            (Some(_), Some(_)) => return Err("cache and cache_service are exclusive"),
+            (None, None) => return Err("cache is required"),
+        };
+        let game_control = match (self.game_control, launched) {
+            (Some(_), true) => return Err("game_control and launch_res are exclusive"),
+            (Some(control), false) => control,
+            (None, _) => GameControl::new(
+                handle.is_some(),
+                config.launch.auto_relaunch,
+                config.launch.focus_on_connect,
+            ),
+        };
+
+        let network = self
+            .network
+            .unwrap_or_else(|| NetworkClient::new(&config.network));
+
         Ok(CommandContext {
-            cache: self
-                .cache
-                .map(|cache| Arc::new(Mutex::new(cache)))
-                .ok_or("cache is required")?,
-            msg_sender: self
-                .msg_sender
-                .map(Arc::new)
-                .ok_or("msg_sender is required")?,
+            cache,
+            console: ConsoleService::new(events.clone()),
+            game_control,
+            msg_sender,
             app,
             game,
+            http: self
+                .http
+                .unwrap_or_else(|| new_http(None, None, network.clone())),
+            network,
             local_dir: self.local_dir,
             pty_handle: handle.map(|pty| Arc::new(RwLock::new(pty))),
-            cache_needs_update: Arc::new(AtomicBool::new(false)),
-            forward_logs: Arc::new(AtomicBool::new(false)),
-            h2m_console_history: Arc::new(Mutex::new(Vec::<String>::new())),
+            forward_logs: false,
+            cancel: CancellationToken::new(),
+            tasks: TaskList::default(),
+            notifications: Arc::new(AtomicBool::new(config.display.notifications)),
+            events,
+            config_service: ConfigService::new(config.clone()),
+            settings,
+            recent_commands: VecDeque::new(),
+            session_log: Arc::new(Mutex::new(session_log)),
+            chat_log: Arc::new(Mutex::new(chat_log)),
+            friends: Arc::new(Mutex::new(friends)),
         })
     }
 }
@@ -302,120 +681,416 @@ impl CommandContextBuilder {
 pub enum CommandHandle {
     Processed,
     InsertHook(InputHook),
+    Background(BackgroundTask),
+    /// Commands for the main loop to execute one after another
+    Script(Script),
     Exit,
 }
 
+/// Long running command work that is driven on a separate task so the prompt stays usable,
+/// output is expected to be sent through `msg_sender`
+pub struct BackgroundTask {
+    pub name: &'static str,
+    pub cancel: CancellationToken,
+    pub task: Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+
+impl BackgroundTask {
+    pub fn new(
+        name: &'static str,
+        cancel: CancellationToken,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) -> Self {
+        BackgroundTask {
+            name,
+            cancel,
+            task: Box::pin(task),
+        }
+    }
+}
+
+/// Joins `args` with the value of any `--password` and the key of `geo-key set` hidden, so neither
+/// reaches a bug report. The command name may be any of its aliases, in any case
+pub fn redact_passwords(args: &[String]) -> String {
+    const PASSWORD: &str = "--password";
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+    for (i, arg) in args.iter().enumerate() {
+        if std::mem::take(&mut hide_next) {
+            redacted.push("***");
+        } else if arg == PASSWORD
+            || (i == 1 && arg.eq_ignore_ascii_case("set") && is_command(&args[0], "geo-key"))
+        {
+            hide_next = true;
+            redacted.push(arg);
+        } else if arg.starts_with(PASSWORD) && arg[PASSWORD.len()..].starts_with('=') {
+            redacted.push("--password=***");
+        } else {
+            redacted.push(arg);
+        }
+    }
+    redacted.join(" ")
+}
+
+/// If `arg` names the command `name` by its name or one of its aliases, ignoring case
+fn is_command(arg: &str, name: &str) -> bool {
+    UserCommand::command()
+        .find_subcommand(name)
+        .is_some_and(|command| {
+            std::iter::once(command.get_name())
+                .chain(command.get_all_aliases())
+                .any(|alias| alias.eq_ignore_ascii_case(arg))
+        })
+}
+
+/// Saves the arguments of a `filter` command so `favorites check --fix` can run it again
+pub(crate) async fn remember_filter(context: &CommandContext, args: &[String]) {
+    let args = shellwords::join(&args.iter().map(String::as_str).collect::<Vec<_>>());
+    let mut cache = context.cache.lock().await;
+    if cache.last_filter.as_ref() != Some(&args) {
+        cache.last_filter = Some(args);
+        context.cache.mark_changed();
+    }
+}
+
 pub async fn try_execute_command(
     mut user_args: Vec<String>,
     context: &mut CommandContext,
 ) -> CommandHandle {
+    if context.recent_commands.len() == REPORT_COMMANDS_MAX {
+        context.recent_commands.pop_front();
+    }
+    context
+        .recent_commands
+        .push_back(redact_passwords(&user_args));
     let mut input_tokens = vec![String::new()];
     input_tokens.append(&mut user_args);
-    match UserCommand::try_parse_from(input_tokens) {
-        Ok(cli) => match cli.command {
-            Command::Filter { args } => new_favorites_with(args, context).await,
-            Command::Reconnect { args } => reconnect(args, context).await,
-            Command::Launch => launch_handler(context).await,
-            Command::Cache { option } => modify_cache(context, option).await,
-            Command::Console => open_h2m_console(context).await,
-            Command::GameDir => open_dir(context.game.path.parent()),
-            Command::LocalEnv => open_dir(context.local_dir.as_deref()),
-            Command::Version => print_version(&context.app, &context.game),
-            Command::Quit => quit(context).await,
-        },
+    let started = Instant::now();
+    let cli = match UserCommand::try_parse_from(&input_tokens) {
+        Ok(cli) => cli,
         Err(err) => {
+            if err.kind() == clap::error::ErrorKind::InvalidSubcommand {
+                if let Some(suggestion) = suggest(&input_tokens[1..]) {
+                    error!("{}", DisplaySuggestion(&suggestion));
+                    return CommandHandle::Processed;
+                }
+            }
+            if err.use_stderr() {
+                error!(name: LOG_ONLY, "{}", err.kind());
+            }
             if let Err(prt_err) = err.print() {
                 error!("{err} {prt_err}");
             }
-            CommandHandle::Processed
+            return CommandHandle::Processed;
+        }
+    };
+    let name = UserCommand::command()
+        .find_subcommand(&input_tokens[1])
+        .map_or_else(|| input_tokens[1].clone(), |cmd| cmd.get_name().to_string());
+    let command_handle = match cli.command {
+        Command::Filter { args: Some(args) } if args.interactive => filter_wizard(args, context),
+        Command::Filter { args } => {
+            // a preview is not what favourites.json holds, `favorites check` refills from the last filter
+            if !args.as_ref().is_some_and(|args| args.preview) {
+                remember_filter(context, &input_tokens[2..]).await;
+            }
+            new_favorites_with(args, context)
         }
+        Command::Reconnect { args } => reconnect(args, context).await,
+        Command::Launch { exe, args } => launch_handler(context, exe, args).await,
+        Command::Cache { option } => modify_cache(context, option).await,
+        Command::Console { args } => open_h2m_console(context, args).await,
+        Command::GameDir => open_dir(context.game.path.parent()),
+        Command::Open { target } => open(context, target),
+        Command::Window { option } => window(context, option),
+        Command::LocalEnv => open_dir(context.local_dir.as_deref()),
+        Command::Version => print_version(&context.app, &context.game),
+        Command::Alerts { command } => alerts(context, command).await,
+        Command::Profile => profile(context),
+        Command::Session { option } => session(context, option).await,
+        Command::Status => status(context).await,
+        Command::Dashboard {
+            interval,
+            ping_refresh,
+        } => dashboard(context, interval, ping_refresh).await,
+        Command::Favorites { command } => favorites(context, command).await,
+        Command::Import { from } => import(context, from),
+        Command::GeoKey { command } => geo_key(context, command),
+        Command::Friend { command } => friend(context, command).await,
+        Command::Friends => friends(context).await,
+        Command::Info { server, dvars } => server_info(context, server, dvars).await,
+        Command::Rcon {
+            server,
+            command,
+            password,
+            save,
+            forget,
+        } => rcon(context, server, command, password, save, forget).await,
+        Command::Journal { today, server } => journal(context, today, server).await,
+        Command::Whois { query } => whois(context, query).await,
+        Command::Search { query } => search(context, query).await,
+        Command::Add { picks } => add(context, picks).await,
+        Command::Perf { option } => show_perf(context, option),
+        Command::Tasks { command } => tasks(context, command),
+        Command::Diff => diff(context),
+        Command::Clear => clear(context).await,
+        Command::Prompt { template, reset } => set_prompt_template(context, template, reset),
+        Command::Settings { key, value } => settings(context, key, value),
+        Command::Config { option } => config(context, option).await,
+        Command::LogLevel { level, target } => log_level(level, target),
+        Command::Logs { command } => logs(context, command),
+        Command::Report => report(context).await,
+        Command::Doctor => doctor(context).await,
+        Command::Update => update(context).await,
+        Command::Changelog { version } => changelog(context, version).await,
+        Command::Set { setting, state } => set_toggle(context, setting, state),
+        Command::Attach { log } => attach(context, log).await,
+        Command::QuitGame => quit_game(context).await,
+        Command::Run { args } => script::run(args),
+        Command::Quit => quit(context).await,
+    };
+    time_command(name, started, command_handle)
+}
+
+/// Records how long the command took, background tasks are timed until they finish. Commands that
+/// wait for input are not timed, the wait would dwarf the work
+fn time_command(name: String, started: Instant, command_handle: CommandHandle) -> CommandHandle {
+    match command_handle {
+        CommandHandle::Processed => perf::record_command(&name, started.elapsed()),
+        CommandHandle::Background(BackgroundTask {
+            name: task_name,
+            cancel,
+            task,
+        }) => {
+            return CommandHandle::Background(BackgroundTask::new(task_name, cancel, async move {
+                task.await;
+                perf::record_command(&name, started.elapsed());
+            }))
+        }
+        CommandHandle::InsertHook(_) | CommandHandle::Script(_) | CommandHandle::Exit => (),
     }
+    command_handle
 }
 
-async fn new_favorites_with(args: Option<Filters>, context: &CommandContext) -> CommandHandle {
-    let cache = context.cache();
-    let exe_dir = context.game.path.parent().expect("has parent");
+pub(crate) fn new_favorites_with(args: Option<Filters>, context: &CommandContext) -> CommandHandle {
+    let cache = context.cache().clone();
+    let msg_sender = context.msg_sender();
+    let cancel = context.cancel_token();
+    let targets = context.favorites_targets();
+    let version = context.game.version.unwrap_or(1.0);
+    let mut args = args.unwrap_or_default();
+    // a group left in `filter.region` after it was removed is only warned about while filtering
+    if let Some(region) = args
+        .region
+        .iter()
+        .flatten()
+        .find(|region| !region.is_defined(&context.config().region))
+    {
+        error!("Unknown region '{region}', use na, eu, apac or a region from the region settings");
+        return CommandHandle::Processed;
+    }
+    context.config().filter.apply(&mut args);
+    let config = context.config_service().current();
+    let http = context.http();
+
+    CommandHandle::Background(BackgroundTask::new("filter", cancel.clone(), async move {
+        match build_favorites(
+            &http,
+            &targets,
+            &args,
+            cache.clone(),
+            version,
+            &config,
+            cancel,
+            &msg_sender,
+        )
+        .await
+        {
+            Ok(true) => cache.mark_changed(),
+            Ok(false) => (),
+            Err(err) => {
+                let _ = msg_sender
+                    .send(Message::Err(DisplayError(&err).to_string()))
+                    .await;
+            }
+        }
+    }))
+}
 
-    let new_entries_found = build_favorites(
-        exe_dir,
-        &args.unwrap_or_default(),
-        cache,
+/// Runs the filter set by `launch.refresh-on-launch` in place, so the in game server browser shows a fresh
+/// list. Problems are only reported, the game is launched either way
+async fn refresh_before_launch(context: &CommandContext) {
+    let last_filter = context.cache.lock().await.last_filter.clone();
+    let Some(args) = context.config().launch_refresh(last_filter.as_deref()) else {
+        return;
+    };
+    let mut filters = match args.and_then(|args| parse_preset(&args)) {
+        Ok(filters) => filters.unwrap_or_default(),
+        Err(err) => {
+            warn!("Favorites were not refreshed, {err}");
+            return;
+        }
+    };
+    context.config().filter.apply(&mut filters);
+    // nothing can be picked behind the spinner
+    filters.yes = true;
+    let http = context.http();
+    let targets = context.favorites_targets();
+    let (msg_sender, messages) = tokio::sync::mpsc::channel(50);
+    let refresh = build_favorites(
+        &http,
+        &targets,
+        &filters,
+        context.cache.clone(),
         context.game.version.unwrap_or(1.0),
-    )
-    .await
-    .unwrap_or_else(|err| {
-        error!("{err}");
-        false
-    });
-    if new_entries_found {
-        context.cache_needs_update().store(true, Ordering::Release);
+        context.config(),
+        context.cancel_token(),
+        &msg_sender,
+    );
+    match spin_while("Refreshing favorites...", messages, refresh).await {
+        Ok(true) => context.cache.mark_changed(),
+        Ok(false) => (),
+        Err(err) => warn!("Favorites were not refreshed, {}", DisplayError(&err)),
     }
-
-    CommandHandle::Processed
 }
 
 async fn modify_cache(context: &CommandContext, arg: CacheCmd) -> CommandHandle {
-    let Some(ref local_dir) = context.local_dir else {
+    if context.local_dir.is_none() {
         error!("Can not create cache with out a valid save directory");
         return CommandHandle::Processed;
-    };
+    }
+    CommandHandle::Background(cache_task(context, arg).await)
+}
+
+/// Builds a new cache and swaps it in once it is complete, `CacheCmd::Update` starts from the current
+/// server book and regions. The new cache is only saved when there is a local data directory
+pub async fn cache_task(context: &CommandContext, arg: CacheCmd) -> BackgroundTask {
+    let local_dir = context.local_dir.clone();
+    let cache_arc = context.cache().clone();
+    let msg_sender = context.msg_sender();
+    let http = context.http();
 
-    let cache_file = match arg {
+    // servers are requested without holding the cache so it stays usable while the update runs
+    let backups = match arg {
         CacheCmd::Update => {
-            let cache_arc = context.cache();
             let cache = cache_arc.lock().await;
-
-            match build_cache(Some(&cache.connection_history), Some(&cache.ip_to_region)).await {
-                Ok(data) => data,
-                Err((err, _)) => {
-                    error!("{err}, cache remains unchanged");
-                    return CommandHandle::Processed;
-                }
-            }
+            Some((
+                cache.book.clone(),
+                cache.ip_to_region.clone(),
+                cache.populations.clone(),
+                cache.reliability.clone(),
+            ))
         }
-        CacheCmd::Reset => match build_cache(None, None).await {
+        CacheCmd::Reset => None,
+    };
+
+    BackgroundTask::new(CACHE_TASK, context.cancel_token(), async move {
+        let start = Instant::now();
+        let (book, regions, populations, reliability) = match backups {
+            Some((book, regions, populations, reliability)) => (
+                Some(book),
+                Some(regions),
+                Some(populations),
+                Some(reliability),
+            ),
+            None => (None, None, None, None),
+        };
+        let (mut cache_file, mut summary) = match build_cache(
+            &http,
+            book,
+            regions.as_ref(),
+            populations,
+            reliability,
+            Some(&msg_sender),
+        )
+        .await
+        {
             Ok(data) => data,
             Err((err, _)) => {
-                error!("{err}, cache remains unchanged");
-                return CommandHandle::Processed;
+                let _ = msg_sender
+                    .send(Message::Err(format!("{err}, cache remains unchanged")))
+                    .await;
+                return;
             }
-        },
-    };
+        };
 
-    match std::fs::File::create(local_dir.join(CACHED_DATA)) {
-        Ok(file) => {
-            if let Err(err) = serde_json::to_writer_pretty(file, &cache_file) {
-                error!("{err}")
+        let mut cache = cache_arc.lock().await;
+        if arg == CacheCmd::Update {
+            // keep servers joined while the update was running
+            cache_file.book.adopt_history(&cache.book);
+            cache_file.cache.pings = std::mem::take(&mut cache.pings);
+            cache_file.cache.countries = std::mem::take(&mut cache.ip_to_country);
+            cache_file.cache.resolved = std::mem::take(&mut cache.host_ips);
+            cache_file.cache.moves = std::mem::take(&mut cache.host_moves);
+            cache_file.last_filter = cache.last_filter.take();
+            cache_file.snapshot = cache.snapshot.take();
+        }
+        summary.compare(&cache, &cache_file.cache);
+        summary.elapsed = start.elapsed();
+
+        if let Some(ref dir) = local_dir {
+            if let Err(err) = write_cache_file(dir, &cache_file).await {
+                let _ = msg_sender
+                    .send(Message::Err(DisplayError(&err).to_string()))
+                    .await;
             }
         }
-        Err(err) => error!("{err}"),
-    }
-    let cache = context.cache();
-    let mut cache = cache.lock().await;
-    *cache = Cache::from(cache_file);
-    CommandHandle::Processed
+        *cache = Cache::from(cache_file);
+        drop(cache);
+        let _ = msg_sender.send(Message::Info(summary.to_string())).await;
+    })
 }
 
-pub async fn launch_handler(context: &mut CommandContext) -> CommandHandle {
-    match launch_h2m_pseudo(&context.game.path) {
+pub async fn launch_handler(
+    context: &mut CommandContext,
+    exe: Option<String>,
+    args: Vec<String>,
+) -> CommandHandle {
+    if let Some(name) = exe {
+        let game_dir = context
+            .game
+            .path
+            .parent()
+            .expect("game exe path always has a parent directory");
+        match select_game_executable(game_dir, &name).await {
+            Ok(path) => {
+                if path != context.game.path {
+                    context.game.switch_exe(path);
+                }
+            }
+            Err(err) => {
+                error!("{}", DisplayError(&err));
+                return CommandHandle::Processed;
+            }
+        }
+    }
+    if !context.game_control.connected() {
+        refresh_before_launch(context).await;
+    }
+    match launch_h2m_pseudo(&context.game.path, &args) {
         Ok(conpty) => {
             info!("Launching H2M-mod...");
+            context.game.launch_args = args;
             context.game.update(exe_details(&context.game.path));
             context.init_pty(conpty);
+            context.game_control.start_session().await;
+            *context.session_log.lock().await =
+                SessionLog::new(context.local_dir(), &context.msg_sender);
             if let Err(err) = listener_routine(context).await {
                 error!("{err}")
             }
         }
         Err(err) => match err {
-            LaunchError::Running(msg) => {
+            Error::GameRunning => {
                 if context.check_h2m_connection().await.is_ok() {
                     info!("Connection already active")
                 } else {
-                    error!("{msg}");
+                    error!("{err}");
                     println!("{ConnectionHelp}");
+                    println!("Or use command `{YELLOW}attach{WHITE}` to follow the console log of the running game");
                 }
             }
-            LaunchError::SpawnErr(err) => error!("{}", err.to_string_lossy()),
+            err => error!("{}", DisplayError(&err)),
         },
     };
     CommandHandle::Processed
@@ -464,28 +1139,44 @@ pub async fn listener_routine(context: &mut CommandContext) -> Result<(), String
     Ok(())
 }
 
-struct DisplayLogs<'a>(&'a [String]);
+/// `(history, args)` applies `--since` and `--grep` before `--tail`
+struct DisplayLogs<'a>(&'a ConsoleHistory, &'a ConsoleArgs);
 
-impl<'a> Display for DisplayLogs<'a> {
+impl Display for DisplayLogs<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for line in self.0 {
+        let since = self
+            .1
+            .since
+            .and_then(|age| SystemTime::now().checked_sub(age));
+        let lines = self
+            .0
+            .search(since, self.1.grep.as_ref())
+            .collect::<Vec<_>>();
+        let skip = self.1.tail.map_or(0, |n| lines.len().saturating_sub(n));
+        for line in &lines[skip..] {
             writeln!(f, "{line}")?;
         }
+        if lines.is_empty() && (self.1.grep.is_some() || self.1.since.is_some()) {
+            writeln!(
+                f,
+                "{YELLOW}No console lines matched the given filters{WHITE}"
+            )?;
+        }
         Ok(())
     }
 }
 
 #[inline]
 pub fn end_forward(context: &mut CommandContext) {
-    context.forward_logs().store(false, Ordering::SeqCst);
+    context.forward_logs = false;
 }
 
-async fn open_h2m_console(context: &mut CommandContext) -> CommandHandle {
+async fn open_h2m_console(context: &mut CommandContext, args: ConsoleArgs) -> CommandHandle {
     if context.check_h2m_connection().await.is_ok() && h2m_running() {
         {
-            let history = context.h2m_console_history.lock().await;
-            context.forward_logs.store(true, Ordering::SeqCst);
-            print!("{}", DisplayLogs(&history));
+            let history = context.console.history().await;
+            context.forward_logs = true;
+            print!("{}", DisplayLogs(&history, &args));
         }
 
         let uid = InputHook::new_uid();
@@ -506,7 +1197,7 @@ async fn open_h2m_console(context: &mut CommandContext) -> CommandHandle {
                     handle.ctrl_c_line()?;
                     return Ok((EventLoop::Continue, false));
                 }
-                handle.set_prompt(LineData::default_prompt());
+                handle.restore_prompt();
                 handle.set_completion(true);
                 Ok((EventLoop::Callback(Box::new(end_forward)), true))
             }
@@ -522,7 +1213,7 @@ async fn open_h2m_console(context: &mut CommandContext) -> CommandHandle {
                 ..
             }) => {
                 if handle.line.input().is_empty() {
-                    handle.set_prompt(LineData::default_prompt());
+                    handle.restore_prompt();
                     handle.set_completion(true);
                     return Ok((EventLoop::Callback(Box::new(end_forward)), true));
                 }
@@ -564,28 +1255,440 @@ async fn open_h2m_console(context: &mut CommandContext) -> CommandHandle {
         return CommandHandle::InsertHook(InputHook::from(uid, Some(init), input_hook));
     }
 
-    let history = context.h2m_console_history.lock().await;
-    if !history.is_empty() {
+    let history = context.console.history().await;
+    if context.console.log_attached() {
+        println!("{YELLOW}Displaying logs read from the H2M console log file{WHITE}");
+        print!("{}", DisplayLogs(&history, &args));
+    } else if !history.is_empty() {
         println!("{YELLOW}No active connection to H2M, displaying old logs{WHITE}");
         std::thread::sleep(std::time::Duration::from_secs(2));
-        print!("{}", DisplayLogs(&history));
+        print!("{}", DisplayLogs(&history, &args));
     } else {
         println!("{YELLOW}No active connection to H2M{WHITE}");
     }
     CommandHandle::Processed
 }
 
-fn open_dir(path: Option<&Path>) -> CommandHandle {
+/// File manager used to show folders to the user
+const OPEN_DIR_WITH: Option<&str> = if cfg!(feature = "windows-console") {
+    Some("explorer")
+} else if cfg!(target_os = "linux") {
+    Some("xdg-open")
+} else {
+    None
+};
+
+pub fn open_dir(path: Option<&Path>) -> CommandHandle {
     if let Some(dir) = path {
-        if let Err(err) = std::process::Command::new("explorer").arg(dir).spawn() {
-            error!("{err}")
-        };
+        match OPEN_DIR_WITH {
+            Some(program) => {
+                if let Err(err) = std::process::Command::new(program).arg(dir).spawn() {
+                    error!("{err}")
+                }
+            }
+            None => error!(
+                "{}, the folder is located at: {}",
+                Error::Unsupported("Opening folders"),
+                dir.display()
+            ),
+        }
     } else {
         error!("Could not find local dir");
     }
     CommandHandle::Processed
 }
 
+/// Opens the folder holding `file` with it selected, or just the folder when selecting is not
+/// supported or the file does not exist yet
+pub fn reveal_file(file: &Path) -> CommandHandle {
+    if !cfg!(feature = "windows-console") || !file.exists() {
+        return open_dir(file.parent());
+    }
+    if let Err(err) = std::process::Command::new("explorer")
+        .arg("/select,")
+        .arg(file)
+        .spawn()
+    {
+        error!("{err}")
+    }
+    CommandHandle::Processed
+}
+
+fn open(context: &CommandContext, target: OpenTarget) -> CommandHandle {
+    match target {
+        OpenTarget::Gamedir => open_dir(context.game.path.parent()),
+        OpenTarget::Localdata => open_dir(context.local_dir()),
+        OpenTarget::Logs => {
+            let Some(dir) = context.local_dir() else {
+                return open_dir(None);
+            };
+            match newest_log(dir, &app_log_prefix()) {
+                Ok(Some(file)) => reveal_file(&file.path),
+                Ok(None) => open_dir(Some(dir)),
+                Err(err) => {
+                    error!("Failed to read {}, {err}", dir.display());
+                    CommandHandle::Processed
+                }
+            }
+        }
+        OpenTarget::Favorites => reveal_file(&context.favorites_targets().swap_remove(0)),
+    }
+}
+
+/// Shorthand for `settings <key> true|false`, the change is saved the same way
+fn set_toggle(context: &mut CommandContext, setting: Setting, state: Toggle) -> CommandHandle {
+    let key = match setting {
+        Setting::AutoRelaunch => "launch.auto-relaunch",
+        Setting::FocusOnConnect => "launch.focus-on-connect",
+        Setting::Notifications => "display.notifications",
+    };
+    let enabled = matches!(state, Toggle::On);
+    if store_setting(context, key, &enabled.to_string()) {
+        info!("{setting} {state}");
+    }
+    CommandHandle::Processed
+}
+
+/// Setting `prompt` changes
+const PROMPT_KEY: &str = "display.prompt";
+
+fn set_prompt_template(
+    context: &mut CommandContext,
+    template: Option<String>,
+    reset: bool,
+) -> CommandHandle {
+    let value = match template {
+        // quoted so templates that look like numbers or booleans stay strings
+        Some(template) => toml::Value::String(template).to_string(),
+        None if reset => String::from(DEFAULT_VALUE),
+        None => {
+            println!("Current prompt: '{}'", context.prompt_template());
+            println!("{}", DisplayPromptPlaceholders);
+            return CommandHandle::Processed;
+        }
+    };
+    if store_setting(context, PROMPT_KEY, &value) {
+        info!("Prompt set to: '{}'", context.prompt_template());
+    }
+    CommandHandle::Processed
+}
+
+/// Changes `key` in config.toml and applies it, returns if the change was made
+fn store_setting(context: &mut CommandContext, key: &str, value: &str) -> bool {
+    if let Err(err) = context.settings.set(key, value) {
+        error!("{err}");
+        return false;
+    }
+    context.apply_settings();
+    let source = context.settings.source(key);
+    if !matches!(source, SettingSource::File | SettingSource::Default) {
+        warn!("'{key}' is overridden by {source}, the saved value applies once the override is removed");
+    }
+    match context.local_dir {
+        Some(ref dir) => {
+            if let Err(err) = context.settings.write(dir) {
+                error!("Failed to save {CONFIG_FILE}, {err}");
+                return false;
+            }
+        }
+        None => warn!("No local data directory, '{key}' will reset when the app closes"),
+    }
+    true
+}
+
+fn settings(
+    context: &mut CommandContext,
+    key: Option<String>,
+    value: Option<String>,
+) -> CommandHandle {
+    let Some(key) = key else {
+        if let Some(ref dir) = context.local_dir {
+            println!("{GREY}# {}{WHITE}", Config::path(dir).display());
+        }
+        match toml::to_string_pretty(context.config()) {
+            Ok(config) => print!("{config}"),
+            Err(err) => error!("{err}"),
+        }
+        return CommandHandle::Processed;
+    };
+    if key == "sources" && value.is_none() {
+        print!("{}", DisplaySettingSources(&context.settings.sources()));
+        return CommandHandle::Processed;
+    }
+    let Some(value) = value else {
+        match context.config().get(&key) {
+            Ok(Some(value)) => println!(
+                "{key} = {value} {GREY}# {}{WHITE}",
+                context.settings.source(&key)
+            ),
+            Ok(None) => println!("{key} {GREY}is not set, the default is used{WHITE}"),
+            Err(err) => error!("{err}"),
+        }
+        return CommandHandle::Processed;
+    };
+    if !store_setting(context, &key, &value) {
+        return CommandHandle::Processed;
+    }
+    info!("{key} set to {value}");
+    CommandHandle::Processed
+}
+
+fn window(context: &CommandContext, option: WindowCmd) -> CommandHandle {
+    match option {
+        WindowCmd::Tray => {
+            if let Err(err) = hide_console() {
+                error!("{err}");
+            }
+        }
+        WindowCmd::Forget => {
+            let Some(dir) = context.local_dir() else {
+                error!("No local data directory to forget the window placement from");
+                return CommandHandle::Processed;
+            };
+            match forget_placement(dir) {
+                Ok(()) => info!(
+                    "Window size and position forgotten, the next run opens with the defaults"
+                ),
+                Err(err) => error!("{err}"),
+            }
+        }
+    }
+    CommandHandle::Processed
+}
+
+fn geo_key(context: &CommandContext, command: Option<GeoKeyCmd>) -> CommandHandle {
+    let Some(command) = command else {
+        let (source, usage) = key_status();
+        println!("{}", DisplayGeoKey(source, usage));
+        return CommandHandle::Processed;
+    };
+    let Some(dir) = context.local_dir() else {
+        error!("No local data directory to save the key in");
+        return CommandHandle::Processed;
+    };
+    match command {
+        GeoKeyCmd::Set { key } => match set_key(dir, &key) {
+            Ok(()) if key_status().0 == KeySource::Env => {
+                info!("Key saved, it is used once {GEO_KEY_ENV} is unset")
+            }
+            Ok(()) => info!("Key saved, location lookups now use it"),
+            Err(err) => error!("{err}"),
+        },
+        GeoKeyCmd::Clear => match clear_key(dir) {
+            Ok(true) => info!("Saved key removed"),
+            Ok(false) => info!("No key was saved"),
+            Err(err) => error!("{err}"),
+        },
+    }
+    CommandHandle::Processed
+}
+
+fn show_perf(context: &CommandContext, option: Option<PerfCmd>) -> CommandHandle {
+    if option == Some(PerfCmd::Reset) {
+        perf::reset();
+        if let Some(ref dir) = context.local_dir {
+            if let Err(err) = perf::save(dir) {
+                error!("{err}");
+            }
+        }
+        info!("Recorded timings cleared");
+        return CommandHandle::Processed;
+    }
+    let stats = perf::snapshot();
+    if stats.is_empty() {
+        println!("Nothing recorded yet, timings are added as commands run");
+        return CommandHandle::Processed;
+    }
+    println!(
+        "{}",
+        DisplayPerf(&stats, context.config().filter.lookup_concurrency)
+    );
+    CommandHandle::Processed
+}
+
+fn log_level(level: Option<Verbosity>, target: LogTarget) -> CommandHandle {
+    let Some(level) = level else {
+        println!("{}", DisplayLogLevels(log_levels()));
+        return CommandHandle::Processed;
+    };
+    match set_log_level(target, level) {
+        Ok(()) => info!("{target} log level set to {level}"),
+        Err(err) => error!("{err}"),
+    }
+    CommandHandle::Processed
+}
+
+/// Splits `editor` into the program and arguments to open config.toml with, like a shell would unless it
+/// is the path of an existing file. Falls back to notepad when `editor` is unset or blank
+pub fn editor_command(editor: Option<OsString>) -> Result<(OsString, Vec<String>), String> {
+    const FALLBACK: &str = "notepad";
+    let Some(editor) = editor.filter(|editor| !editor.to_string_lossy().trim().is_empty()) else {
+        return Ok((OsString::from(FALLBACK), Vec::new()));
+    };
+    if Path::new(&editor).is_file() {
+        return Ok((editor, Vec::new()));
+    }
+    let mut words = shellwords::split(&editor.to_string_lossy())
+        .map_err(|err| format!("Could not split $EDITOR into a program and arguments, {err}"))?
+        .into_iter();
+    let program = words
+        .next()
+        .map_or_else(|| OsString::from(FALLBACK), OsString::from);
+    Ok((program, words.collect()))
+}
+
+async fn config(context: &mut CommandContext, option: ConfigCmd) -> CommandHandle {
+    let Some(dir) = context.local_dir.clone() else {
+        error!("Could not find local dir");
+        return CommandHandle::Processed;
+    };
+    let path = Config::path(&dir);
+    if option == ConfigCmd::Path {
+        println!("{}", path.display());
+        return CommandHandle::Processed;
+    }
+    let (editor, editor_args) = match editor_command(std::env::var_os("EDITOR")) {
+        Ok(command) => command,
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    };
+    let editor_name = editor.to_string_lossy().to_string();
+    println!("{GREY}Waiting for {editor_name} to close...{WHITE}");
+    let status = tokio::task::spawn_blocking(move || {
+        std::process::Command::new(editor)
+            .args(editor_args)
+            .arg(path)
+            .status()
+    })
+    .await
+    .map_err(|err| err.to_string())
+    .and_then(|status| status.map_err(|err| err.to_string()));
+    match status {
+        Ok(status) if !status.success() => warn!("Editor exited with {status}"),
+        Ok(_) => (),
+        Err(err) => {
+            error!("Failed to open {CONFIG_FILE} with {editor_name}, {err}");
+            return CommandHandle::Processed;
+        }
+    }
+    match context.settings.reload(&dir) {
+        Ok(warnings) => {
+            warnings.iter().for_each(|warning| warn!("{warning}"));
+            context.apply_settings();
+            info!("Reloaded {CONFIG_FILE}");
+        }
+        Err(err) => error!("{err}, keeping previous settings"),
+    }
+    CommandHandle::Processed
+}
+
+async fn attach(context: &mut CommandContext, log: Option<PathBuf>) -> CommandHandle {
+    if context.check_h2m_connection().await.is_ok() {
+        info!("Connection already active");
+        return CommandHandle::Processed;
+    }
+    if !h2m_running() {
+        error!("H2M-mod is not running, use command `{YELLOW}launch{WHITE}` to start the game");
+        return CommandHandle::Processed;
+    }
+    if context.console.log_attached() {
+        info!("Already following the H2M console log");
+        return CommandHandle::Processed;
+    }
+    let game_dir = context
+        .game
+        .path
+        .parent()
+        .expect("game exe path always has a parent directory");
+    let Some(path) = log.or_else(|| find_console_log(game_dir)) else {
+        error!("No H2M console log found, start H2M with `+set logfile 2` so it writes its console to disk");
+        return CommandHandle::Processed;
+    };
+    match initalize_log_tail(context, path.clone()).await {
+        Ok(()) => {
+            info!("Following H2M console log: {}", path.display());
+            println!(
+                "{YELLOW}Commands can not be sent to a game that was not launched by {}{WHITE}",
+                env!("CARGO_PKG_NAME")
+            );
+        }
+        Err(err) => error!("Failed to open {}, {err}", path.display()),
+    }
+    CommandHandle::Processed
+}
+
+fn profile(context: &CommandContext) -> CommandHandle {
+    let game_dir = context
+        .game
+        .path
+        .parent()
+        .expect("game exe path always has a parent directory");
+    match Profile::read(game_dir) {
+        Ok(profile) => println!("{profile}"),
+        Err(err) => error!("Could not read player profile, {err}"),
+    }
+    CommandHandle::Processed
+}
+
+async fn quit_game(context: &mut CommandContext) -> CommandHandle {
+    const QUIT_TIMEOUT: Duration = Duration::from_secs(10);
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    if let Err(err) = context.check_h2m_connection().await {
+        if h2m_running() {
+            error!("{err}");
+            println!("{ConnectionHelp}");
+        } else {
+            info!("H2M-mod is not running");
+        }
+        return CommandHandle::Processed;
+    }
+    let lock = context.pty_handle().unwrap();
+    if let Err(err) = lock.read().await.write(OsString::from("quit\r\n")) {
+        error!(
+            "Failed to send quit command to h2m console, {}",
+            err.to_string_lossy()
+        );
+    } else {
+        info!("Closing H2M-mod...");
+    }
+
+    // the console is only locked while polling, so the listener and other commands keep running
+    let game_control = context.game_control.clone();
+    let msg_sender = context.msg_sender();
+    CommandHandle::Background(BackgroundTask::new(
+        "quit-game",
+        context.cancel_token(),
+        async move {
+            let is_alive = || async { matches!(lock.read().await.is_alive(), Ok(true)) };
+            let start = Instant::now();
+            while is_alive().await && start.elapsed() < QUIT_TIMEOUT {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+
+            if is_alive().await {
+                let _ = msg_sender
+                    .send(Message::Warn(String::from(
+                        "H2M-mod did not close in time, terminating process",
+                    )))
+                    .await;
+                if let Err(err) = terminate_process(lock.read().await.get_pid()) {
+                    let _ = msg_sender.send(Message::Err(err)).await;
+                    return;
+                }
+            }
+
+            game_control.set_connected(false);
+            game_control.end_session().await;
+            let _ = msg_sender
+                .send(Message::Info(String::from("H2M-mod closed")))
+                .await;
+        },
+    ))
+}
+
 fn print_version(app: &AppDetails, game: &GameDetails) -> CommandHandle {
     println!("{app}");
     if game.version.is_some() || game.hash_curr.is_some() {
@@ -619,7 +1722,7 @@ async fn quit(context: &mut CommandContext) -> CommandHandle {
                 ..
             }) => Ok((EventLoop::Break, true)),
             _ => {
-                handle.set_prompt(LineData::default_prompt());
+                handle.restore_prompt();
                 Ok((EventLoop::Continue, true))
             }
         });
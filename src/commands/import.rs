@@ -0,0 +1,292 @@
+use crate::{
+    cli::ImportSource,
+    commands::{
+        favorites::{probe, read_favorites, write_favorites, WriteOutcome, SUPERSEDED},
+        filter::{DEFAULT_H2M_SERVER_CAP, FAVORITES},
+        handler::{BackgroundTask, CommandContext, CommandHandle, Message},
+    },
+    utils::{
+        display::{DisplayCountOf, DisplayError},
+        game_dir::steam_game_dirs,
+        input::style::{GREEN, WHITE},
+    },
+    LOCAL_DATA,
+};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tracing::error;
+
+/// Steam folder of Modern Warfare 2 (2009), which IW4x is installed into
+const IW4X_STEAM_DIR: &str = "Call of Duty Modern Warfare 2";
+/// Relative to the IW4x game directory
+const IW4X_FAVORITES: &str = "players/favourites.json";
+/// Games under `%LOCALAPPDATA%/Plutonium/storage` that keep a favourites list
+const PLUTONIUM_GAMES: [&str; 3] = ["iw5", "t6", "t4"];
+
+/// Servers read from another client, names are kept with their color codes
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportedList {
+    pub favorites: Vec<SocketAddr>,
+    /// Joined servers, most recent last
+    pub history: Vec<SocketAddr>,
+    pub names: HashMap<SocketAddr, String>,
+}
+
+impl ImportedList {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.favorites.is_empty() && self.history.is_empty()
+    }
+
+    fn extend(&mut self, other: ImportedList) {
+        self.favorites.extend(other.favorites);
+        self.history.extend(other.history);
+        self.names.extend(other.names);
+    }
+}
+
+/// Reads any of the list formats clients use, entries that are not valid servers are skipped:
+/// - a json list of `IP:PORT` strings, like favourites.json
+/// - a json list of objects with an `address`, or an `ip` and `port`, and an optional `name`
+/// - a json object with `favorites` or `favourites` and a `history` list of either kind
+/// - text with an `IP:PORT` on each line, optionally followed by the server name
+pub fn parse_import(text: &str) -> ImportedList {
+    let mut list = ImportedList::default();
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(entries)) => list.favorites = read_entries(&entries, &mut list.names),
+        Ok(Value::Object(lists)) => {
+            for (key, entries) in lists {
+                let Value::Array(entries) = entries else {
+                    continue;
+                };
+                match key.to_ascii_lowercase().as_str() {
+                    "favorites" | "favourites" => {
+                        list.favorites = read_entries(&entries, &mut list.names)
+                    }
+                    "history" => list.history = read_entries(&entries, &mut list.names),
+                    _ => (),
+                }
+            }
+        }
+        Ok(_) => (),
+        Err(_) => {
+            for line in text.lines().map(str::trim) {
+                let (addr, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                let Ok(addr) = addr.parse() else {
+                    continue;
+                };
+                list.favorites.push(addr);
+                if !name.trim().is_empty() {
+                    list.names.insert(addr, name.trim().to_string());
+                }
+            }
+        }
+    }
+    list
+}
+
+fn read_entries(entries: &[Value], names: &mut HashMap<SocketAddr, String>) -> Vec<SocketAddr> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (addr, name) = read_entry(entry)?;
+            if let Some(name) = name.filter(|name| !name.trim().is_empty()) {
+                names.insert(addr, name.to_string());
+            }
+            Some(addr)
+        })
+        .collect()
+}
+
+fn read_entry(entry: &Value) -> Option<(SocketAddr, Option<&str>)> {
+    if let Value::String(addr) = entry {
+        return Some((addr.trim().parse().ok()?, None));
+    }
+    let field = |keys: &[&str]| keys.iter().find_map(|&key| entry.get(key));
+    let addr = match field(&["address", "addr", "server"]) {
+        Some(addr) => addr.as_str()?.trim().parse().ok()?,
+        None => {
+            let ip = field(&["ip", "host"])?.as_str()?.trim().parse().ok()?;
+            let port = match field(&["port"])? {
+                Value::Number(port) => u16::try_from(port.as_u64()?).ok()?,
+                Value::String(port) => port.trim().parse().ok()?,
+                _ => return None,
+            };
+            SocketAddr::new(ip, port)
+        }
+    };
+    let name = field(&["name", "hostname", "host_name"]).and_then(Value::as_str);
+    Some((addr, name))
+}
+
+/// Lists of `source` that exist on this machine
+pub fn source_files(source: &ImportSource) -> Vec<PathBuf> {
+    let candidates = match source {
+        ImportSource::Path(path) => return vec![path.clone()],
+        ImportSource::Iw4x => steam_game_dirs(IW4X_STEAM_DIR)
+            .into_iter()
+            .map(|dir| dir.join(IW4X_FAVORITES))
+            .collect::<Vec<_>>(),
+        ImportSource::Plutonium => std::env::var_os(LOCAL_DATA)
+            .map(|dir| Path::new(&dir).join("Plutonium").join("storage"))
+            .into_iter()
+            .flat_map(|storage| {
+                PLUTONIUM_GAMES.map(|game| storage.join(game).join("players").join(FAVORITES))
+            })
+            .collect(),
+    };
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|path| path.is_file() && seen.insert(path.clone()))
+        .collect()
+}
+
+pub fn import(context: &CommandContext, source: ImportSource) -> CommandHandle {
+    let files = source_files(&source);
+    if files.is_empty() {
+        let client = match source {
+            ImportSource::Iw4x => "IW4x",
+            ImportSource::Plutonium => "Plutonium",
+            ImportSource::Path(_) => unreachable!("a path is always read"),
+        };
+        error!(
+            "No {client} server list was found, use `import --from <PATH>` with the path of the list"
+        );
+        return CommandHandle::Processed;
+    }
+
+    let cache = context.cache().clone();
+    let msg_sender = context.msg_sender();
+    let targets = context.favorites_targets();
+    let capped = context.h2m_version().is_some_and(|version| version < 1.0);
+
+    CommandHandle::Background(BackgroundTask::new(
+        "import",
+        context.cancel_token(),
+        async move {
+            let mut imported = ImportedList::default();
+            for file in files.iter() {
+                match tokio::fs::read_to_string(file).await {
+                    Ok(text) => imported.extend(parse_import(&text)),
+                    Err(err) => {
+                        let msg = format!("Could not read {}, {err}", file.display());
+                        let _ = msg_sender.send(Message::Err(msg)).await;
+                    }
+                }
+            }
+            if imported.is_empty() {
+                let _ = msg_sender
+                    .send(Message::Err(String::from(
+                        "Nothing to import, no valid servers were found",
+                    )))
+                    .await;
+                return;
+            }
+
+            // servers listed without a name are asked for it, only named servers go in the cache
+            let unnamed = imported
+                .favorites
+                .iter()
+                .chain(imported.history.iter())
+                .filter(|server| !imported.names.contains_key(server))
+                .copied()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            for (server, host_name) in probe(&unnamed).await {
+                imported.names.extend(host_name.map(|name| (server, name)));
+            }
+
+            let (recorded, joined) = {
+                let mut cache = cache.lock().await;
+                let now = SystemTime::now();
+                let recorded = imported
+                    .names
+                    .iter()
+                    .filter(|&(&server, name)| {
+                        cache.book.record(server, Arc::from(name.as_str()), now)
+                    })
+                    .count();
+                let joined = imported
+                    .history
+                    .iter()
+                    .filter(|&&server| {
+                        cache.book.get(server).is_some() && cache.book.push_history(server)
+                    })
+                    .count();
+                (recorded, joined)
+            };
+            if recorded > 0 || joined > 0 {
+                cache.mark_changed();
+            }
+
+            let mut servers = if targets[0].is_file() {
+                match read_favorites(&targets[0]).await {
+                    Ok(servers) => servers,
+                    Err(err) => {
+                        let _ = msg_sender.send(Message::Err(err)).await;
+                        return;
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+            let mut seen = servers.iter().copied().collect::<HashSet<_>>();
+            let mut new = imported
+                .favorites
+                .iter()
+                .copied()
+                .filter(|server| seen.insert(*server))
+                .collect::<Vec<_>>();
+            let room = if capped {
+                DEFAULT_H2M_SERVER_CAP.saturating_sub(servers.len())
+            } else {
+                usize::MAX
+            };
+            if new.len() > room {
+                let _ = msg_sender
+                    .send(Message::Warn(format!(
+                        "Only {room} of {} new servers were added, the in game server browser \
+                        breaks with more than {DEFAULT_H2M_SERVER_CAP}",
+                        new.len()
+                    )))
+                    .await;
+                new.truncate(room);
+            }
+            let added = new.len();
+            servers.append(&mut new);
+            if added > 0 {
+                let msg = match write_favorites(&targets, &servers).await {
+                    Ok(WriteOutcome::Written) => None,
+                    Ok(WriteOutcome::Superseded) => Some(Message::Warn(String::from(SUPERSEDED))),
+                    Err(err) => Some(Message::Err(DisplayError(&err).to_string())),
+                };
+                if let Some(msg) = msg {
+                    let _ = msg_sender.send(msg).await;
+                    return;
+                }
+            }
+
+            let from = files
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let msg = format!(
+                "{GREEN}Imported {from}{WHITE}\n\
+                {} added to {FAVORITES}, {} named in the cache, {} added to the join history",
+                DisplayCountOf(added, "server", "servers"),
+                DisplayCountOf(recorded, "server", "servers"),
+                DisplayCountOf(joined, "server", "servers"),
+            );
+            let _ = msg_sender.send(Message::Str(msg)).await;
+        },
+    ))
+}
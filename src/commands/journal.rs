@@ -0,0 +1,255 @@
+use crate::{
+    commands::{
+        handler::{CommandContext, CommandHandle},
+        session::{MatchEvent, SessionLog},
+    },
+    parse_hostname, utc_date_string,
+    utils::{display::DisplayPlaySession, game_state::GameState, names::Names},
+    LOG_ONLY,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{watch, Mutex};
+use tracing::{error, info};
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+/// Sessions displayed when no filter is given
+const RECENT_SESSIONS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Visit {
+    /// Host name with color codes removed, or the address if it was joined directly
+    pub server: String,
+    /// Seconds since unix epoch
+    pub joined: u64,
+    pub left: u64,
+    pub maps: Vec<String>,
+}
+
+impl Visit {
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.left.saturating_sub(self.joined))
+    }
+}
+
+/// Line of `journal.jsonl`, written as the [`GameState`] changes so a server still being played on is
+/// only recorded once it is left
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Launch {
+        time: u64,
+    },
+    Visit(Visit),
+    /// `exit_code` is only set when the game crashed
+    Exit {
+        time: u64,
+        exit_code: Option<u32>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlaySession {
+    /// Launch time, or when the first server was joined if the game was not launched by us
+    pub started: u64,
+    pub ended: Option<u64>,
+    pub exit_code: Option<u32>,
+    pub visits: Vec<Visit>,
+}
+
+impl PlaySession {
+    fn new(started: u64) -> Self {
+        PlaySession {
+            started,
+            ended: None,
+            exit_code: None,
+            visits: Vec::new(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        let end = self
+            .ended
+            .or_else(|| self.visits.last().map(|visit| visit.left))
+            .unwrap_or(self.started);
+        Duration::from_secs(end.saturating_sub(self.started))
+    }
+}
+
+/// Groups journal entries into sessions, a launch starts a new session and an exit ends one
+pub fn group_sessions(entries: impl IntoIterator<Item = JournalEntry>) -> Vec<PlaySession> {
+    let mut sessions = Vec::new();
+    let mut current: Option<PlaySession> = None;
+    for entry in entries {
+        match entry {
+            JournalEntry::Launch { time } => {
+                sessions.extend(current.replace(PlaySession::new(time)));
+            }
+            JournalEntry::Visit(visit) => current
+                .get_or_insert_with(|| PlaySession::new(visit.joined))
+                .visits
+                .push(visit),
+            JournalEntry::Exit { time, exit_code } => {
+                let mut session = current.take().unwrap_or_else(|| PlaySession::new(time));
+                session.ended = Some(time);
+                session.exit_code = exit_code;
+                sessions.push(session);
+            }
+        }
+    }
+    sessions.extend(current);
+    sessions
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+fn append_entry(path: &Path, entry: &JournalEntry) -> io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    serde_json::to_writer(&mut file, entry)?;
+    writeln!(file)
+}
+
+fn read_journal(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Maps started on the server between `joined` and `left`, in the order they were played
+async fn maps_played(session_log: &Mutex<SessionLog>, joined: u64, left: u64) -> Vec<String> {
+    let session_log = session_log.lock().await;
+    let mut maps: Vec<String> = Vec::new();
+    for record in session_log.records.iter() {
+        if record.time < joined || record.time > left {
+            continue;
+        }
+        if let MatchEvent::MapChange { ref map, .. } = record.event {
+            if maps.last() != Some(map) {
+                maps.push(map.clone());
+            }
+        }
+    }
+    maps
+}
+
+/// Follows the game state and appends launches, servers left and exits to the journal
+pub async fn journal_loop(
+    mut game_state: watch::Receiver<GameState>,
+    session_log: Arc<Mutex<SessionLog>>,
+    local_dir: Option<PathBuf>,
+) {
+    let Some(path) = local_dir.map(|dir| dir.join(JOURNAL_FILE)) else {
+        return;
+    };
+    let mut prev = GameState::Idle;
+    let mut visit: Option<(Arc<str>, u64)> = None;
+    loop {
+        let state = game_state.borrow_and_update().clone();
+        let now = unix_secs(SystemTime::now());
+        let mut entries = Vec::new();
+
+        if visit
+            .as_ref()
+            .is_some_and(|(server, _)| state.server() != Some(server))
+        {
+            let (server, joined) = visit.take().expect("checked above");
+            entries.push(JournalEntry::Visit(Visit {
+                server: server.to_string(),
+                joined,
+                left: now,
+                maps: maps_played(&session_log, joined, now).await,
+            }));
+        }
+        match state {
+            GameState::Launching if matches!(prev, GameState::Idle | GameState::Crashed { .. }) => {
+                entries.push(JournalEntry::Launch { time: now })
+            }
+            GameState::Loading { ref server } | GameState::InGame { ref server }
+                if visit.is_none() =>
+            {
+                visit = Some((Arc::clone(server), now))
+            }
+            GameState::Idle if prev != GameState::Idle => entries.push(JournalEntry::Exit {
+                time: now,
+                exit_code: None,
+            }),
+            GameState::Crashed { exit_code } => entries.push(JournalEntry::Exit {
+                time: now,
+                exit_code: Some(exit_code),
+            }),
+            _ => (),
+        }
+
+        for entry in entries {
+            if let Err(err) = append_entry(&path, &entry) {
+                error!(name: LOG_ONLY, "Could not write to {JOURNAL_FILE}, {err}");
+            }
+        }
+        prev = state;
+        if game_state.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+pub async fn journal(
+    context: &CommandContext,
+    today: bool,
+    server: Option<String>,
+) -> CommandHandle {
+    let Some(dir) = context.local_dir() else {
+        error!("No local environment directory, play sessions are not recorded");
+        return CommandHandle::Processed;
+    };
+    let mut sessions = match read_journal(&dir.join(JOURNAL_FILE)) {
+        Ok(entries) => group_sessions(entries),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => {
+            error!("Could not read {JOURNAL_FILE}, {err}");
+            return CommandHandle::Processed;
+        }
+    };
+
+    if today {
+        let today = utc_date_string(SystemTime::now());
+        sessions.retain(|session| {
+            utc_date_string(UNIX_EPOCH + Duration::from_secs(session.started)) == today
+        });
+    }
+    if let Some(ref name) = server {
+        let name = parse_hostname(name);
+        for session in sessions.iter_mut() {
+            session
+                .visits
+                .retain(|visit| parse_hostname(&visit.server).contains(&name));
+        }
+        sessions.retain(|session| !session.visits.is_empty());
+    }
+    if !today && server.is_none() {
+        sessions.drain(..sessions.len().saturating_sub(RECENT_SESSIONS));
+    }
+
+    if sessions.is_empty() {
+        info!("No play sessions recorded");
+        return CommandHandle::Processed;
+    }
+    let names = Names::from(&context.config().display);
+    for session in sessions.iter() {
+        println!("{}", DisplayPlaySession(session, names));
+    }
+    CommandHandle::Processed
+}
@@ -1,35 +1,81 @@
 use crate::{
     commands::{
+        chat::{process_chat, ChatLog, ChatMessage},
         filter::{try_get_info, GetInfoMetaData, Request, Sourced},
         handler::{CommandContext, Message},
+        services::{CacheService, ConsoleService, GameControl},
+        session::{MatchEvent, SessionLog},
+    },
+    parse_hostname, strip_ansi_private_modes, strip_ansi_sequences, strip_color_codes,
+    utc_date_string,
+    utils::{
+        game_state::{set_state, transition, GameState, BUSY_MARKERS},
+        http::{Http, HttpFetch},
     },
-    parse_hostname, strip_ansi_private_modes, strip_ansi_sequences,
-    utils::caching::Cache,
     LOG_ONLY,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    ffi::{CStr, OsStr, OsString},
+    collections::VecDeque,
+    ffi::OsStr,
+    io::{self, BufWriter, SeekFrom, Write},
     net::{AddrParseError, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt},
+    sync::{mpsc::Sender, Mutex},
+};
+use tracing::{error, trace, warn};
+
+#[cfg(feature = "windows-console")]
+use crate::{
+    commands::reconnect::{connect_to, focus_after_connect, send_queued},
+    error::Error,
+};
+#[cfg(feature = "windows-console")]
+use std::{
+    ffi::{CStr, OsString},
     os::windows::ffi::{OsStrExt, OsStringExt},
-    path::Path,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
 };
-use tokio::sync::{mpsc::Sender, Mutex};
-use tracing::{error, trace};
+#[cfg(feature = "windows-console")]
+use tokio::sync::RwLock;
+#[cfg(feature = "windows-console")]
 use winapi::{
-    shared::{minwindef::DWORD, windef::HWND},
+    shared::{
+        minwindef::{DWORD, FALSE},
+        windef::HWND,
+    },
     um::{
-        winnt::WCHAR,
-        winuser::{EnumWindows, GetClassNameA, GetWindowTextW, IsWindowVisible},
+        handleapi::CloseHandle,
+        processthreadsapi::{OpenProcess, TerminateProcess},
+        winnt::{PROCESS_TERMINATE, WCHAR},
+        winuser::{
+            EnumWindows, GetClassNameA, GetWindowTextW, IsIconic, IsWindowVisible,
+            SetForegroundWindow, ShowWindow, SW_RESTORE,
+        },
         winver::{GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW},
     },
 };
-use winptyrs::{AgentConfig, MouseMode, PTYArgs, PTYBackend, PTY};
+#[cfg(feature = "windows-console")]
+use winptyrs::{AgentConfig, MouseMode, PTYArgs, PTYBackend};
 
+#[cfg(not(feature = "windows-console"))]
+pub use crate::utils::unsupported::{focus_h2m_window, get_exe_version};
+#[cfg(all(not(feature = "windows-console"), not(target_os = "linux")))]
+pub use crate::utils::unsupported::{
+    h2m_running, initalize_listener, launch_h2m_pseudo, pty_spawn_check, terminate_process, PTY,
+};
+#[cfg(all(not(feature = "windows-console"), target_os = "linux"))]
+pub use crate::utils::wine::{
+    h2m_running, initalize_listener, launch_h2m_pseudo, pty_spawn_check, terminate_process, PTY,
+};
+#[cfg(feature = "windows-console")]
+pub use winptyrs::PTY;
+
+#[cfg(feature = "windows-console")]
 #[repr(C)]
 #[allow(non_snake_case, non_camel_case_types)]
 struct VS_FIXEDFILEINFO {
@@ -48,9 +94,11 @@ struct VS_FIXEDFILEINFO {
     dwFileDateLS: DWORD,
 }
 
+#[cfg(feature = "windows-console")]
 const H2M_WINDOW_NAME: &str = "h2m";
 // console class = "ConsoleWindowClass" || "CASCADIA_HOSTING_WINDOW_CLASS"
 // game class = "H1" || splash screen class = "H2M Splash Screen"
+#[cfg(feature = "windows-console")]
 const H2M_WINDOW_CLASS_NAMES: [&str; 2] = ["H1", "H2M Splash Screen"];
 const JOIN_STR: &str = "Joining ";
 const JOIN_BYTES: [u16; 8] = [74, 111, 105, 110, 105, 110, 103, 32];
@@ -60,13 +108,25 @@ const CONNECT_STR: &str = "connect "; // | "CONNECT "
 const CONNECT_BYTES_LOWER: [u16; 8] = [99, 111, 110, 110, 101, 99, 116, 32];
 const CONNECT_BYTES_UPPER: [u16; 8] = [67, 79, 78, 78, 69, 67, 84, 32];
 const ERROR_BYTES: [u16; 9] = [27, 91, 51, 56, 59, 53, 59, 49, 109];
+#[cfg(feature = "windows-console")]
 const ESCAPE_CHAR: char = '\x1b';
+#[cfg(feature = "windows-console")]
 const COLOR_CMD: char = 'm';
+#[cfg(feature = "windows-console")]
 const CARRIAGE_RETURN: u16 = 13;
+#[cfg(feature = "windows-console")]
 const NEW_LINE: u16 = 10;
 // const RESET_COLOR: [u16; 3] = [27, 91, 109];
 // const ESCAPE: u16 = 27;
 // const COLOR_CMD_BYTE: u16 = 109;
+#[cfg(feature = "windows-console")]
+const CRASH_REPORT_PREFIX: &str = "h2m_crash_";
+#[cfg(feature = "windows-console")]
+const CRASH_REPORT_LINES: usize = 250;
+pub const CONSOLE_LOG_PREFIX: &str = "h2m_console_";
+/// Longest wait for a relaunched game to reach the menu, the last server is joined after it either way
+#[cfg(feature = "windows-console")]
+const RELAUNCH_CONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(35);
 
 #[inline]
 fn case_insensitve_cmp_direct(window: &[u16]) -> bool {
@@ -79,6 +139,152 @@ fn case_insensitve_cmp_direct(window: &[u16]) -> bool {
     true
 }
 
+/// Upper bound of console lines kept in memory
+pub const CONSOLE_HISTORY_MAX: usize = 10_000;
+
+/// Ring buffer of H2M console output, once full the oldest lines are dropped. Each line keeps the time
+/// it was read so output can be looked up by age
+#[derive(Default)]
+pub struct ConsoleHistory {
+    lines: VecDeque<(SystemTime, String)>,
+}
+
+impl ConsoleHistory {
+    #[inline]
+    pub fn push(&mut self, line: String) {
+        self.push_at(SystemTime::now(), line)
+    }
+
+    /// Lines stay in the order they were read, a `read` before the newest line is moved up to it
+    pub fn push_at(&mut self, read: SystemTime, line: String) {
+        if self.lines.len() == CONSOLE_HISTORY_MAX {
+            self.lines.pop_front();
+        }
+        let read = self
+            .lines
+            .back()
+            .map_or(read, |&(newest, _)| read.max(newest));
+        self.lines.push_back((read, line));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.lines.iter().map(|(_, line)| line)
+    }
+
+    /// Iterates over the newest `n` lines
+    pub fn last_n(&self, n: usize) -> impl Iterator<Item = &String> {
+        self.lines
+            .range(self.lines.len().saturating_sub(n)..)
+            .map(|(_, line)| line)
+    }
+
+    /// Iterates over lines read at or after `time`, oldest first
+    pub fn since(&self, time: SystemTime) -> impl Iterator<Item = &(SystemTime, String)> {
+        let start = self.lines.partition_point(|&(read, _)| read < time);
+        self.lines.range(start..)
+    }
+
+    /// Lines read at or after `since` that match `pattern`, ansi escape sequences are ignored while
+    /// matching
+    pub fn search<'a>(
+        &'a self,
+        since: Option<SystemTime>,
+        pattern: Option<&'a regex::Regex>,
+    ) -> impl Iterator<Item = &'a String> {
+        self.since(since.unwrap_or(UNIX_EPOCH))
+            .map(|(_, line)| line)
+            .filter(move |line| match pattern {
+                Some(pattern) if line.contains('\x1b') => {
+                    pattern.is_match(&strip_ansi_sequences(line))
+                }
+                Some(pattern) => pattern.is_match(line),
+                None => true,
+            })
+    }
+
+    /// Joins the newest `n` lines, used to forward freshly read output
+    pub(crate) fn join_last_n(&self, n: usize) -> String {
+        self.last_n(n)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Persists H2M console output to `h2m_console_YYYY-MM-DD.log` in the local data directory, a new file is
+/// started each day and only the newest `retention` files are kept
+pub struct ConsoleLog {
+    dir: PathBuf,
+    retention: usize,
+    date: String,
+    file: Option<BufWriter<std::fs::File>>,
+}
+
+impl ConsoleLog {
+    pub fn new(dir: PathBuf, retention: usize) -> Self {
+        ConsoleLog {
+            dir,
+            retention,
+            date: String::new(),
+            file: None,
+        }
+    }
+
+    /// Appends `lines` with ansi escape sequences removed
+    pub fn write_lines<'a>(&mut self, lines: impl Iterator<Item = &'a String>) -> io::Result<()> {
+        let date = utc_date_string(SystemTime::now());
+        if self.file.is_none() || date != self.date {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(format!("{CONSOLE_LOG_PREFIX}{date}.log")))?;
+            self.file = Some(BufWriter::new(file));
+            self.date = date;
+            self.prune()?;
+        }
+        let file = self.file.as_mut().expect("opened above");
+        for line in lines {
+            writeln!(file, "{}", strip_ansi_sequences(line))?;
+        }
+        file.flush()
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        if self.retention == 0 {
+            return Ok(());
+        }
+        let mut logs = std::fs::read_dir(&self.dir)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|name| {
+                        name.starts_with(CONSOLE_LOG_PREFIX) && name.ends_with(".log")
+                    })
+            })
+            .collect::<Vec<_>>();
+        // dates are zero padded so newest sorts last
+        logs.sort_unstable();
+        let excess = logs.len().saturating_sub(self.retention);
+        for path in &logs[..excess] {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct HostName {
     pub parsed: String,
@@ -154,7 +360,10 @@ impl HostName {
         Ok(HostNameRequestMeta::new(host_name, socket_addr))
     }
 
-    async fn from_request(value: &[u16]) -> Result<HostNameRequestMeta, HostRequestErr> {
+    async fn from_request(
+        value: &[u16],
+        http: &dyn HttpFetch,
+    ) -> Result<HostNameRequestMeta, HostRequestErr> {
         let input = String::from_utf16_lossy(value).to_lowercase();
         let ip_str = input
             .split_once(CONNECT_STR)
@@ -162,13 +371,12 @@ impl HostName {
             .expect("`Connection::Direct` is found, meaning `CONNECT_BYTES` were found in the `value` array")
             .trim();
         let socket_addr = ip_str.parse::<SocketAddr>()?;
-        let server_info = try_get_info(
-            Request::New(Sourced::Hmw(socket_addr)),
-            reqwest::Client::new(),
-        )
-        .await?;
+        let server_info = try_get_info(Request::New(Sourced::Hmw(socket_addr)), http, None).await?;
         let host_name = server_info.info.expect("request returned `Ok`").host_name;
-        Ok(HostNameRequestMeta::new(host_name, Some(socket_addr)))
+        Ok(HostNameRequestMeta::new(
+            host_name.to_string(),
+            Some(socket_addr),
+        ))
     }
 }
 
@@ -177,51 +385,62 @@ enum Connection {
     Direct,
 }
 
+/// Returns the kind of connection announced by the console `line`, if any
+fn connection_kind(line: &[u16], connecting_bytes: &[u16]) -> Option<Connection> {
+    if line.starts_with(&ERROR_BYTES) {
+        return None;
+    }
+    line.windows(connecting_bytes.len()).find_map(|window| {
+        if window == connecting_bytes {
+            Some(Connection::Browser)
+        } else if case_insensitve_cmp_direct(window) {
+            Some(Connection::Direct)
+        } else {
+            None
+        }
+    })
+}
+
+/// Server announced by a connection line, the host name when it is printed, otherwise the address
+fn joining_server(line: &[u16], kind: &Connection, version: f64) -> Arc<str> {
+    let server = match kind {
+        Connection::Browser => HostName::from_browser(line, version)
+            .ok()
+            .map(|meta| strip_color_codes(&meta.host_name.raw)),
+        Connection::Direct => String::from_utf16_lossy(line)
+            .to_lowercase()
+            .split_once(CONNECT_STR)
+            .map(|(_, ip)| ip.trim().to_string()),
+    };
+    Arc::from(server.as_deref().unwrap_or("unknown server"))
+}
+
 async fn add_to_history(
-    cache_arc: &Arc<Mutex<Cache>>,
-    update_cache: &Arc<AtomicBool>,
+    cache: &CacheService,
     background_msg: &Arc<Sender<Message>>,
     wide_encode: &[u16],
     kind: Connection,
     version: f64,
+    http: &Http,
 ) {
-    async fn cache_insert(
-        cache_arc: &Arc<Mutex<Cache>>,
-        update_cache: &Arc<AtomicBool>,
-        host_name_meta: HostNameRequestMeta,
-    ) {
-        let mut cache = cache_arc.lock().await;
-        let mut modified = true;
-        if let Some(ip) = host_name_meta.socket_addr {
-            cache
-                .host_to_connect
-                .entry(host_name_meta.host_name.raw.clone())
-                .and_modify(|cache_ip| {
-                    if *cache_ip == ip {
-                        modified = false;
-                    } else {
-                        *cache_ip = ip
-                    }
-                })
-                .or_insert(ip);
-        }
-        if let Some(index) = cache
-            .connection_history
-            .iter()
-            .position(|prev| prev.raw == host_name_meta.host_name.raw)
-        {
-            let history_last = cache.connection_history.len() - 1;
-            if index != history_last {
-                let entry = cache.connection_history.remove(index);
-                cache.connection_history.push(entry);
-                modified = true;
-            }
-        } else {
-            cache.connection_history.push(host_name_meta.host_name);
-            modified = true
+    async fn cache_insert(cache_service: &CacheService, host_name_meta: HostNameRequestMeta) {
+        let mut cache = cache_service.lock().await;
+        let HostNameRequestMeta {
+            host_name,
+            socket_addr,
+        } = host_name_meta;
+        let Some(socket_addr) = socket_addr.or_else(|| cache.book.address_of(&host_name.raw))
+        else {
+            let name = host_name.parsed;
+            warn!(name: LOG_ONLY, "No known address for '{name}', not added to history");
+            return;
         };
+        let new_name = cache
+            .book
+            .record(socket_addr, Arc::from(host_name.raw), SystemTime::now());
+        let modified = cache.book.push_history(socket_addr) || new_name;
         if modified {
-            update_cache.store(true, Ordering::Relaxed);
+            cache_service.mark_changed();
         }
     }
 
@@ -234,14 +453,14 @@ async fn add_to_history(
                     return;
                 }
             };
-            cache_insert(cache_arc, update_cache, meta).await;
+            cache_insert(cache, meta).await;
         }
         Connection::Direct => {
-            let cache_arc = cache_arc.clone();
-            let update_cache = update_cache.clone();
+            let cache = cache.clone();
             let wide_encode = wide_encode.to_vec();
+            let http = Arc::clone(http);
             tokio::task::spawn(async move {
-                let meta = match HostName::from_request(&wide_encode).await {
+                let meta = match HostName::from_request(&wide_encode, &*http).await {
                     Ok(data) => data,
                     Err(request_err) => {
                         match request_err {
@@ -252,22 +471,32 @@ async fn add_to_history(
                         return;
                     }
                 };
-                cache_insert(&cache_arc, &update_cache, meta).await;
+                cache_insert(&cache, meta).await;
             });
         }
     }
 }
 
+#[cfg(feature = "windows-console")]
 pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), String> {
     context.check_h2m_connection().await?;
 
-    let console_history_arc = context.h2m_console_history();
-    let cache_arc = context.cache();
-    let cache_needs_update = context.cache_needs_update();
-    let forward_logs_arc = context.forward_logs();
+    let console = context.console().clone();
+    let cache = context.cache().clone();
+    let game = context.game_control().clone();
+    let events = context.events();
     let msg_sender_arc = context.msg_sender();
+    let session_log_arc = context.session_log();
+    let chat_log_arc = context.chat_log();
+    let local_dir = context.local_dir().map(Path::to_path_buf);
+    let game_path = context.game_path().to_path_buf();
+    let launch_args = context.launch_args().to_vec();
     let pty = context.pty_handle().unwrap();
     let version = context.h2m_version().unwrap_or(1.0);
+    let http = context.http();
+    let mut console_log = local_dir
+        .clone()
+        .map(|dir| ConsoleLog::new(dir, context.config().log.retention));
 
     tokio::spawn(async move {
         let mut buffer = OsString::new();
@@ -280,13 +509,88 @@ pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), Stri
 
         const BUFFER_SIZE: u32 = 16384; // 16 KB
         const PROCESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+        const READ_ERROR_MAX: u8 = 5;
+        /// Output without a line ending that grows past this is flushed as a line of its own
+        const PARTIAL_LINE_MAX: usize = 4096;
+
+        let mut read_errors = 0;
 
         tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
         'task: loop {
             tokio::time::sleep(PROCESS_INTERVAL).await;
             let handle = pty.read().await;
             if !matches!(handle.is_alive(), Ok(true)) {
-                break;
+                let exit_status = handle.get_exitstatus();
+                drop(handle);
+                game.set_connected(false);
+                game.end_session().await;
+
+                // exit code of 0 means the user closed the game
+                let Ok(Some(exit_code)) = exit_status else {
+                    set_state(game.state(), GameState::Idle);
+                    break;
+                };
+                if exit_code == 0 {
+                    set_state(game.state(), GameState::Idle);
+                    break;
+                }
+                set_state(game.state(), GameState::Crashed { exit_code });
+
+                let _ = msg_sender_arc
+                    .send(Message::Err(format!(
+                        "H2M-mod crashed with exit code: {exit_code:#X}"
+                    )))
+                    .await;
+                events.notify("H2M-mod crashed", format!("Exit code: {exit_code:#X}"));
+
+                if let Some(ref dir) = local_dir {
+                    let trailing = console
+                        .history()
+                        .await
+                        .last_n(CRASH_REPORT_LINES)
+                        .map(|line| strip_ansi_sequences(line).into_owned())
+                        .collect::<Vec<_>>();
+                    let msg = match write_crash_report(dir, exit_code, version, &trailing).await {
+                        Ok(path) => {
+                            Message::Info(format!("Crash report saved to: {}", path.display()))
+                        }
+                        Err(err) => Message::Err(format!("Failed to write crash report, {err}")),
+                    };
+                    let _ = msg_sender_arc.send(msg).await;
+                }
+
+                if !game.auto_relaunch() {
+                    break;
+                }
+
+                match launch_h2m_pseudo(&game_path, &launch_args) {
+                    Ok(conpty) => {
+                        *pty.write().await = conpty;
+                        game.set_connected(true);
+                        set_state(game.state(), GameState::Launching);
+                        game.start_session().await;
+                        let _ = msg_sender_arc
+                            .send(Message::Info(String::from(
+                                "Relaunching H2M-mod, the last joined server will be rejoined once the game has loaded",
+                            )))
+                            .await;
+                        tokio::spawn(rejoin_last_server(
+                            cache.clone(),
+                            game.clone(),
+                            pty.clone(),
+                            msg_sender_arc.clone(),
+                        ));
+                        buffer.clear();
+                        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                        continue;
+                    }
+                    Err(err) => {
+                        let _ = msg_sender_arc
+                            .send(Message::Err(format!("Failed to relaunch H2M-mod: {err}")))
+                            .await;
+                        break;
+                    }
+                }
             }
 
             let start_time = tokio::time::Instant::now();
@@ -300,21 +604,38 @@ pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), Stri
                         buffer.push(os_string);
                     }
                     Err(err) => {
-                        let _ = msg_sender_arc.send(Message::Err(format!("{err:?}"))).await;
-                        break 'task;
+                        read_errors += 1;
+                        if read_errors >= READ_ERROR_MAX {
+                            let _ = msg_sender_arc
+                                .send(Message::Err(format!(
+                                    "Failed to read H2M console {read_errors} times in a row, last error: {}",
+                                    err.to_string_lossy()
+                                )))
+                                .await;
+                            break 'task;
+                        }
+                        warn!(name: LOG_ONLY, "H2M console read failed, {}", err.to_string_lossy());
+                        break;
                     }
                 }
+                read_errors = 0;
 
                 tokio::task::yield_now().await;
             }
 
             if buffer.is_empty() {
+                // nothing printed since the last read, loading has finished
+                transition(game.state(), |state| state.after_batch(false));
+                if game.state().borrow().accepts_commands() {
+                    send_queued(&game, &handle, &msg_sender_arc).await;
+                }
                 continue;
             }
 
             let mut wide_encode_buf = Vec::new();
-            let mut console_history = console_history_arc.lock().await;
-            let start = console_history.len();
+            let mut console_history = console.history().await;
+            let mut new_lines = 0;
+            let mut saw_busy_marker = false;
 
             'byte_iter: for byte in buffer.encode_wide() {
                 if byte != CARRIAGE_RETURN && byte != NEW_LINE {
@@ -322,33 +643,35 @@ pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), Stri
                     continue;
                 }
 
-                let mut connect_kind = Connection::Browser;
-                if wide_encode_buf
-                    .windows(connecting_bytes.len())
-                    .any(|window| {
-                        window == connecting_bytes || {
-                            let direct = case_insensitve_cmp_direct(window);
-                            if direct {
-                                connect_kind = Connection::Direct;
-                            }
-                            direct
-                        }
-                    })
-                    && !wide_encode_buf.starts_with(&ERROR_BYTES)
-                {
+                if let Some(connect_kind) = connection_kind(&wide_encode_buf, &connecting_bytes) {
+                    let server = joining_server(&wide_encode_buf, &connect_kind, version);
+                    set_state(game.state(), GameState::Loading { server });
                     add_to_history(
-                        &cache_arc,
-                        &cache_needs_update,
+                        &cache,
                         &msg_sender_arc,
                         &wide_encode_buf,
                         connect_kind,
                         version,
+                        &http,
                     )
                     .await;
                 }
 
                 let cur = String::from_utf16_lossy(&wide_encode_buf);
                 let line = strip_ansi_private_modes(&cur);
+                if !saw_busy_marker && BUSY_MARKERS.iter().any(|marker| line.contains(marker)) {
+                    saw_busy_marker = true;
+                }
+                let plain = strip_ansi_sequences(&line);
+                let chat = ChatMessage::parse(&plain);
+                if chat.is_none() {
+                    transition(game.state(), |state| state.after_line(&plain));
+                }
+                if let Some(event) = MatchEvent::parse(&plain) {
+                    session_log_arc.lock().await.record(event);
+                } else if let Some(chat) = chat {
+                    process_chat(&chat_log_arc, &msg_sender_arc, chat).await;
+                }
                 if !line.is_empty() {
                     // don't store lines that that _only_ contain ansi escape commands,
                     // unless a color command is found then append it to the next line
@@ -376,19 +699,33 @@ pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), Stri
                         }
                     }
                     console_history.push(line.into_owned());
+                    new_lines += 1;
                 }
 
                 wide_encode_buf.clear();
             }
 
-            if forward_logs_arc.load(Ordering::Acquire) && start < console_history.len() {
-                let msg = console_history[start..].join("\n");
-                if msg_sender_arc.send(Message::Str(msg)).await.is_err() {
-                    forward_logs_arc.store(false, Ordering::SeqCst);
+            if wide_encode_buf.len() > PARTIAL_LINE_MAX {
+                console_history.push(String::from_utf16_lossy(&wide_encode_buf));
+                new_lines += 1;
+                wide_encode_buf.clear();
+            }
+
+            if let Some(ref mut log) = console_log {
+                if let Err(err) = log.write_lines(console_history.last_n(new_lines)) {
+                    error!(name: LOG_ONLY, "Failed to write H2M console log, {err}");
+                    console_log = None;
                 }
             }
 
+            console.publish_last(&console_history, new_lines);
+            drop(console_history);
             buffer = OsString::from_wide(&wide_encode_buf);
+
+            transition(game.state(), |state| state.after_batch(saw_busy_marker));
+            if game.state().borrow().accepts_commands() {
+                send_queued(&game, &handle, &msg_sender_arc).await;
+            }
         }
         let _ = msg_sender_arc
             .send(Message::Warn(String::from(
@@ -399,39 +736,351 @@ pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), Stri
     Ok(())
 }
 
-pub enum LaunchError {
-    Running(&'static str),
-    SpawnErr(OsString),
+/// Log files H2M can write its console output to when launched with `+set logfile 2`,
+/// relative to the game directory
+const CONSOLE_LOG_CANDIDATES: [&str; 3] = [
+    "h2m-mod/console_mp.log",
+    "hmw-mod/console_mp.log",
+    "main/console_mp.log",
+];
+
+/// Returns the most recently modified console log found in `game_dir`
+pub fn find_console_log(game_dir: &Path) -> Option<PathBuf> {
+    CONSOLE_LOG_CANDIDATES
+        .iter()
+        .map(|candidate| game_dir.join(candidate))
+        .filter_map(|path| {
+            let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((path, modified))
+        })
+        .max_by_key(|&(_, modified)| modified)
+        .map(|(path, _)| path)
 }
 
-pub fn launch_h2m_pseudo(game_path: &Path) -> Result<PTY, LaunchError> {
-    // MARK: FIXME
-    // can we figure out a way to never inherit pseudo process name
-    if h2m_running() {
-        return Err(LaunchError::Running("H2M is already running"));
+/// Handles console output that arrives as text, from a followed log file or the stdout of Wine
+pub(crate) struct ConsoleLines {
+    console: ConsoleService,
+    cache: CacheService,
+    game: GameControl,
+    msg_sender_arc: Arc<Sender<Message>>,
+    session_log_arc: Arc<Mutex<SessionLog>>,
+    chat_log_arc: Arc<Mutex<ChatLog>>,
+    connecting_bytes: [u16; 8],
+    version: f64,
+    http: Http,
+}
+
+impl ConsoleLines {
+    pub(crate) fn new(context: &CommandContext) -> Self {
+        let version = context.h2m_version().unwrap_or(1.0);
+        ConsoleLines {
+            console: context.console().clone(),
+            cache: context.cache().clone(),
+            game: context.game_control().clone(),
+            msg_sender_arc: context.msg_sender(),
+            session_log_arc: context.session_log(),
+            chat_log_arc: context.chat_log(),
+            connecting_bytes: if version < 1.0 {
+                JOIN_BYTES
+            } else {
+                CONNECTING_BYTES
+            },
+            version,
+            http: context.http(),
+        }
     }
 
-    let pty_args = PTYArgs {
+    /// Called when the game printed nothing for a while, which means loading has finished
+    pub(crate) fn quiet(&self) {
+        transition(self.game.state(), |state| state.after_batch(false));
+    }
+
+    #[inline]
+    pub(crate) fn game(&self) -> &GameControl {
+        &self.game
+    }
+
+    /// Records every non blank line in the console history, then publishes them for whoever is
+    /// following the console
+    pub(crate) async fn push<'a>(&self, lines: impl Iterator<Item = &'a str>) {
+        let mut console_history = self.console.history().await;
+        let mut new_lines = 0;
+        let mut saw_busy_marker = false;
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let wide_encode = line.encode_utf16().collect::<Vec<_>>();
+            if let Some(connect_kind) = connection_kind(&wide_encode, &self.connecting_bytes) {
+                let server = joining_server(&wide_encode, &connect_kind, self.version);
+                set_state(self.game.state(), GameState::Loading { server });
+                add_to_history(
+                    &self.cache,
+                    &self.msg_sender_arc,
+                    &wide_encode,
+                    connect_kind,
+                    self.version,
+                    &self.http,
+                )
+                .await;
+            }
+            let plain = strip_ansi_sequences(line);
+            saw_busy_marker |= BUSY_MARKERS.iter().any(|marker| plain.contains(marker));
+            let chat = ChatMessage::parse(&plain);
+            if chat.is_none() {
+                transition(self.game.state(), |state| state.after_line(&plain));
+            }
+            if let Some(event) = MatchEvent::parse(&plain) {
+                self.session_log_arc.lock().await.record(event);
+            } else if let Some(chat) = chat {
+                process_chat(&self.chat_log_arc, &self.msg_sender_arc, chat).await;
+            }
+            console_history.push(strip_ansi_private_modes(line).into_owned());
+            new_lines += 1;
+        }
+        transition(self.game.state(), |state| {
+            state.after_batch(saw_busy_marker)
+        });
+
+        self.console.publish_last(&console_history, new_lines);
+    }
+}
+
+/// Removes and decodes the complete lines at the start of `partial`. Only whole lines are decoded, so a
+/// character split between two reads stays buffered until the rest of it arrives
+pub fn take_complete_lines(partial: &mut Vec<u8>) -> Option<String> {
+    let end = partial.iter().rposition(|&byte| byte == b'\n')?;
+    let complete = partial.drain(..=end).collect::<Vec<_>>();
+    Some(String::from_utf8_lossy(&complete).into_owned())
+}
+
+/// Follows the console log of an H2M instance that was not spawned by us. Only output is
+/// available this way, commands can not be sent to the game
+pub async fn initalize_log_tail(context: &mut CommandContext, path: PathBuf) -> io::Result<()> {
+    let mut file = tokio::fs::File::open(&path).await?;
+    let mut pos = file.seek(SeekFrom::End(0)).await?;
+
+    let console_lines = ConsoleLines::new(context);
+    let msg_sender_arc = context.msg_sender();
+    console_lines.console.set_log_attached(true);
+    // the game was already running, where it is at is only known once the log shows it
+    set_state(console_lines.game().state(), GameState::InMenu);
+
+    tokio::spawn(async move {
+        const PROCESS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+        let mut partial = Vec::new();
+
+        loop {
+            tokio::time::sleep(PROCESS_INTERVAL).await;
+            if !h2m_running() {
+                break;
+            }
+
+            let len = match file.metadata().await {
+                Ok(meta) => meta.len(),
+                Err(err) => {
+                    let _ = msg_sender_arc.send(Message::Err(err.to_string())).await;
+                    break;
+                }
+            };
+            if len < pos {
+                // log was truncated by a new game session
+                pos = 0;
+                partial.clear();
+            }
+            if len == pos {
+                console_lines.quiet();
+                continue;
+            }
+
+            let mut bytes = Vec::with_capacity((len - pos) as usize);
+            let read = match file.seek(SeekFrom::Start(pos)).await {
+                Ok(_) => (&mut file).take(len - pos).read_to_end(&mut bytes).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = read {
+                let _ = msg_sender_arc.send(Message::Err(err.to_string())).await;
+                break;
+            }
+            pos += bytes.len() as u64;
+            partial.extend_from_slice(&bytes);
+
+            let Some(complete) = take_complete_lines(&mut partial) else {
+                continue;
+            };
+            console_lines.push(complete.lines()).await;
+        }
+        console_lines.console.set_log_attached(false);
+        set_state(console_lines.game().state(), GameState::Idle);
+        let _ = msg_sender_arc
+            .send(Message::Warn(String::from(
+                "No longer following H2M console log",
+            )))
+            .await;
+    });
+    Ok(())
+}
+
+/// `trailing` is the end of the console output, copied out so the history is not locked while writing
+#[cfg(feature = "windows-console")]
+async fn write_crash_report(
+    local_dir: &Path,
+    exit_code: u32,
+    version: f64,
+    trailing: &[String],
+) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = local_dir.join(format!("{CRASH_REPORT_PREFIX}{secs}.log"));
+
+    let mut report = format!(
+        "{} v{}\nH2M-mod v{version} exited with code: {exit_code:#X}\nTrailing H2M console output:\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+    for line in trailing {
+        report.push_str(line);
+        report.push('\n');
+    }
+    tokio::fs::write(&path, report).await?;
+    Ok(path)
+}
+
+#[cfg(feature = "windows-console")]
+async fn rejoin_last_server(
+    cache_arc: CacheService,
+    game: GameControl,
+    pty: Arc<RwLock<PTY>>,
+    msg_sender_arc: Arc<Sender<Message>>,
+) {
+    let mut game_state = game.state().subscribe();
+    let launched = tokio::time::timeout(
+        RELAUNCH_CONNECT_DELAY,
+        game_state.wait_for(|state| *state != GameState::Launching),
+    );
+    let stopped = match launched.await {
+        Ok(Ok(state)) => matches!(*state, GameState::Idle | GameState::Crashed { .. }),
+        Ok(Err(_)) => true,
+        Err(_) => false,
+    };
+    if stopped {
+        return;
+    }
+
+    let last_server = {
+        let cache = cache_arc.lock().await;
+        cache.book.history_entry(1)
+    };
+
+    let msg = match last_server {
+        Some(ip_port) => match connect_to(ip_port, &pty).await {
+            Ok(()) => match focus_after_connect(&game) {
+                Ok(()) => return,
+                Err(err) => Message::Warn(err.to_string()),
+            },
+            Err(err) => Message::Err(err),
+        },
+        None => Message::Warn(String::from("Could not find last joined server in cache")),
+    };
+    let _ = msg_sender_arc.send(msg).await;
+}
+
+/// Joins `args` into a single command line, quoting arguments so `CommandLineToArgvW` splits them back
+/// into the same `args`
+pub fn join_cmdline(args: &[String]) -> String {
+    let mut cmdline = String::new();
+    for arg in args {
+        if !cmdline.is_empty() {
+            cmdline.push(' ');
+        }
+        if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+            cmdline.push_str(arg);
+            continue;
+        }
+        cmdline.push('"');
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    // backslashes are only escapes when followed by a quote
+                    cmdline.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                    cmdline.push('"');
+                    backslashes = 0;
+                }
+                _ => {
+                    cmdline.extend(std::iter::repeat_n('\\', backslashes));
+                    cmdline.push(c);
+                    backslashes = 0;
+                }
+            }
+        }
+        cmdline.extend(std::iter::repeat_n('\\', backslashes * 2));
+        cmdline.push('"');
+    }
+    cmdline
+}
+
+#[cfg(feature = "windows-console")]
+fn build_cmdline(args: &[String]) -> Option<OsString> {
+    (!args.is_empty()).then(|| OsString::from(join_cmdline(args)))
+}
+
+#[cfg(feature = "windows-console")]
+fn pty_args() -> PTYArgs {
+    PTYArgs {
         cols: 250,
         rows: 50,
         mouse_mode: MouseMode::WINPTY_MOUSE_MODE_NONE,
         timeout: 20000,
         agent_config: AgentConfig::WINPTY_FLAG_PLAIN_OUTPUT,
-    };
+    }
+}
+
+#[cfg(feature = "windows-console")]
+fn pty_err(err: OsString) -> Error {
+    Error::Pty(err.to_string_lossy().into_owned())
+}
+
+/// Runs `cmd /C exit` inside a pseudoconsole set up the same way as the one H2M is launched in
+#[cfg(feature = "windows-console")]
+pub fn pty_spawn_check() -> Result<(), Error> {
+    let mut conpty = PTY::new_with_backend(&pty_args(), PTYBackend::ConPTY).map_err(pty_err)?;
+    let shell = std::env::var_os("COMSPEC")
+        .unwrap_or_else(|| OsString::from(r"C:\Windows\System32\cmd.exe"));
+    conpty
+        .spawn(shell, Some(OsString::from("/C exit")), None, None)
+        .map_err(pty_err)?;
+    Ok(())
+}
+
+#[cfg(feature = "windows-console")]
+pub fn launch_h2m_pseudo(game_path: &Path, args: &[String]) -> Result<PTY, Error> {
+    // MARK: FIXME
+    // can we figure out a way to never inherit pseudo process name
+    if h2m_running() {
+        return Err(Error::GameRunning);
+    }
 
     // MARK: FIXME
     // why does the pseudo terminal spawn with no cols or rows
 
-    let mut conpty =
-        PTY::new_with_backend(&pty_args, PTYBackend::ConPTY).map_err(LaunchError::SpawnErr)?;
+    let mut conpty = PTY::new_with_backend(&pty_args(), PTYBackend::ConPTY).map_err(pty_err)?;
 
+    // the game loads its files relative to the working directory, which is not the game directory
+    // when it was found through `launch.game-dir` or discovery
     conpty
-        .spawn(game_path.into(), None, None, None)
-        .map_err(LaunchError::SpawnErr)?;
+        .spawn(
+            game_path.into(),
+            build_cmdline(args),
+            game_path.parent().map(Into::into),
+            None,
+        )
+        .map_err(pty_err)?;
 
     Ok(conpty)
 }
 
+#[cfg(feature = "windows-console")]
 pub fn h2m_running() -> bool {
     let mut result: bool = false;
     unsafe {
@@ -440,6 +1089,50 @@ pub fn h2m_running() -> bool {
     result
 }
 
+/// Brings the H2M window to the foreground, restoring it first if it is minimized
+#[cfg(feature = "windows-console")]
+pub fn focus_h2m_window() -> Result<(), &'static str> {
+    let mut hwnd: HWND = std::ptr::null_mut();
+    unsafe {
+        EnumWindows(Some(find_window_callback), &mut hwnd as *mut _ as isize);
+        if hwnd.is_null() {
+            return Err("Could not find the H2M window");
+        }
+        if IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        if SetForegroundWindow(hwnd) == 0 {
+            return Err("Windows did not allow the H2M window to be focused");
+        }
+    }
+    Ok(())
+}
+
+/// Forcefully ends the process with the given `pid`, the process exits with code `0` so the listener
+/// treats it the same as the user closing the game
+#[cfg(feature = "windows-console")]
+pub fn terminate_process(pid: u32) -> Result<(), String> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, FALSE, pid);
+        if handle.is_null() {
+            return Err(format!(
+                "Failed to open process {pid}, {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let result = TerminateProcess(handle, 0);
+        CloseHandle(handle);
+        if result == 0 {
+            return Err(format!(
+                "Failed to terminate process {pid}, {}",
+                io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "windows-console")]
 #[allow(clippy::identity_op)]
 pub fn get_exe_version(path: &Path) -> Option<f64> {
     let wide_path: Vec<u16> = OsStr::new(path)
@@ -498,12 +1191,13 @@ pub fn get_exe_version(path: &Path) -> Option<f64> {
     }
 }
 
-unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: isize) -> i32 {
+#[cfg(feature = "windows-console")]
+unsafe fn is_h2m_window(hwnd: HWND) -> bool {
     let mut title: [u16; 512] = [0; 512];
     let length = GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32);
 
     if length <= 0 && IsWindowVisible(hwnd) == 0 {
-        return 1;
+        return false;
     }
 
     let window_title = OsString::from_wide(&title[..length as usize])
@@ -511,23 +1205,27 @@ unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: isize) -> i3
         .to_ascii_lowercase();
 
     if !window_title.contains(H2M_WINDOW_NAME) {
-        return 1;
+        return false;
     }
 
     let mut class_name: [i8; 256] = [0; 256];
     let length = GetClassNameA(hwnd, class_name.as_mut_ptr(), class_name.len() as i32);
 
     if length <= 0 {
-        return 1;
+        return false;
     }
 
     let class_name_str = CStr::from_ptr(class_name.as_ptr()).to_str().unwrap_or("");
 
     // Check if the window class name indicates it is the game window or the game's splash screen
-    if H2M_WINDOW_CLASS_NAMES
+    H2M_WINDOW_CLASS_NAMES
         .iter()
         .any(|&h2m_class| class_name_str == h2m_class)
-    {
+}
+
+#[cfg(feature = "windows-console")]
+unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: isize) -> i32 {
+    if is_h2m_window(hwnd) {
         let result = &mut *(lparam as *mut bool);
         *result = true;
         return 0; // Break
@@ -535,3 +1233,14 @@ unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: isize) -> i3
 
     1 // Continue
 }
+
+#[cfg(feature = "windows-console")]
+unsafe extern "system" fn find_window_callback(hwnd: HWND, lparam: isize) -> i32 {
+    if is_h2m_window(hwnd) {
+        let result = &mut *(lparam as *mut HWND);
+        *result = hwnd;
+        return 0; // Break
+    }
+
+    1 // Continue
+}
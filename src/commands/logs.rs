@@ -0,0 +1,170 @@
+use crate::{
+    cli::{LogViewArgs, LogsCmd},
+    commands::handler::{open_dir, CommandContext, CommandHandle},
+    utils::{
+        display::{DisplayLogFiles, DisplayLogPage},
+        subscriber::LOG_EXTENSION,
+    },
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::{error, info, level_filters::LevelFilter, Level};
+
+/// Used when the terminal size can not be read
+const PAGE_SIZE_DEFAULT: usize = 40;
+
+pub struct LogFile {
+    pub path: PathBuf,
+    pub len: u64,
+    pub modified: SystemTime,
+}
+
+impl LogFile {
+    #[inline]
+    pub fn name(&self) -> std::borrow::Cow<'_, str> {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+    }
+}
+
+/// Every `.log` file directly inside `dir`, most recently modified first
+pub fn log_files(dir: &Path) -> io::Result<Vec<LogFile>> {
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == LOG_EXTENSION)
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(std::fs::Metadata::is_file)?;
+            Some(LogFile {
+                path: entry.path(),
+                len: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort_unstable_by_key(|file| std::cmp::Reverse(file.modified));
+    Ok(files)
+}
+
+/// An event from the app log along with any lines it continues onto
+pub struct LogEntry<'a> {
+    pub level: Option<Level>,
+    pub lines: Vec<&'a str>,
+}
+
+/// Lines of the app log start with an RFC 3339 UTC timestamp followed by the level
+fn entry_level(line: &str) -> Option<Level> {
+    let mut tokens = line.split_whitespace();
+    if !tokens.next()?.ends_with('Z') {
+        return None;
+    }
+    tokens.next()?.parse().ok()
+}
+
+pub fn parse_log_entries(contents: &str) -> Vec<LogEntry<'_>> {
+    let mut entries = Vec::<LogEntry>::new();
+    for line in contents.lines() {
+        match (entry_level(line), entries.last_mut()) {
+            (None, Some(entry)) => entry.lines.push(line),
+            (level, _) => entries.push(LogEntry {
+                level,
+                lines: vec![line],
+            }),
+        }
+    }
+    entries
+}
+
+pub struct LogPage<'a> {
+    pub entries: &'a [&'a LogEntry<'a>],
+    pub page: usize,
+    pub page_count: usize,
+}
+
+fn view(dir: &Path, args: LogViewArgs) -> io::Result<()> {
+    let Some(file) = newest_log(dir, &app_log_prefix())? else {
+        info!("No MatchWire log file found in {}", dir.display());
+        return Ok(());
+    };
+    let contents = std::fs::read_to_string(&file.path)?;
+    let entries = parse_log_entries(&contents);
+    let max_level = args.level.map(LevelFilter::from);
+    let matches = entries
+        .iter()
+        .filter(|entry| match (max_level, entry.level) {
+            (Some(max), Some(level)) => level <= max,
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .filter(|entry| match args.grep {
+            Some(ref pattern) => entry.lines.iter().any(|line| pattern.is_match(line)),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    if matches.is_empty() {
+        info!("No entries in {} matched the given filters", file.name());
+        return Ok(());
+    }
+    let page_size = crossterm::terminal::size()
+        .map(|(_, height)| (height as usize).saturating_sub(4).max(1))
+        .unwrap_or(PAGE_SIZE_DEFAULT);
+    let page_count = matches.len().div_ceil(page_size);
+    let page = (args.page as usize).min(page_count);
+    let end = matches.len() - (page - 1) * page_size;
+    let start = end.saturating_sub(page_size);
+    println!("{}", file.name());
+    print!(
+        "{}",
+        DisplayLogPage(LogPage {
+            entries: &matches[start..end],
+            page,
+            page_count,
+        })
+    );
+    Ok(())
+}
+
+/// Most recently modified log file whose name starts with `prefix`
+pub fn newest_log(dir: &Path, prefix: &str) -> io::Result<Option<LogFile>> {
+    Ok(log_files(dir)?
+        .into_iter()
+        .find(|file| file.name().starts_with(prefix)))
+}
+
+/// Prefix of the files written by the app's own logger
+pub fn app_log_prefix() -> String {
+    format!("{}.", env!("CARGO_PKG_NAME"))
+}
+
+pub fn logs(context: &CommandContext, command: Option<LogsCmd>) -> CommandHandle {
+    let Some(dir) = context.local_dir() else {
+        error!("Could not find local dir");
+        return CommandHandle::Processed;
+    };
+    match command.unwrap_or(LogsCmd::List) {
+        LogsCmd::List => match log_files(dir) {
+            Ok(files) if files.is_empty() => info!("No log files in {}", dir.display()),
+            Ok(files) => {
+                println!("{}", dir.display());
+                print!("{}", DisplayLogFiles(&files));
+            }
+            Err(err) => error!("Failed to read {}, {err}", dir.display()),
+        },
+        LogsCmd::Open => return open_dir(Some(dir)),
+        LogsCmd::View { args } => {
+            if let Err(err) = view(dir, args) {
+                error!("Failed to read log file, {err}");
+            }
+        }
+    }
+    CommandHandle::Processed
+}
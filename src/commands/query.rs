@@ -0,0 +1,297 @@
+use crate::{
+    cli::{Filters, IpFamily, Region, Source},
+    commands::{
+        filter::{filter_server_list, Server, Sourced, DEFAULT_DEADLINE_SECS},
+        handler::Message,
+        services::CacheService,
+    },
+    error::Error,
+    utils::{caching::Cache, http::Http, json_data::GetInfo, regions::RegionSettings},
+};
+use std::{net::SocketAddr, time::Duration};
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+
+/// Criteria a server must meet to be included in the results of a filter, `None` and empty fields
+/// do not restrict the results
+#[derive(Debug, Clone, Default)]
+pub struct FilterQuery {
+    pub limit: Option<usize>,
+    pub player_min: Option<u8>,
+    pub team_size_max: Option<u8>,
+    pub with_bots: bool,
+    pub without_bots: bool,
+    pub include_unresponsive: bool,
+    /// Skip removing servers listed more than once
+    pub keep_duplicates: bool,
+    /// Rank servers by the players they usually have at this hour of the day, see
+    /// [`PopulationHistory`](crate::utils::population::PopulationHistory)
+    pub active_now: bool,
+    /// Keep servers in the regions that include this device's location, estimated from its public IP
+    pub near_me: bool,
+    /// Rank `near_me` servers by their ping, lowest first, instead of by players
+    pub measure_pings: bool,
+    /// Percent of master server lists a server must have been in, see
+    /// [`ReliabilityBook`](crate::utils::reliability::ReliabilityBook)
+    pub min_reliability: Option<u8>,
+    /// Address type kept for servers reachable over both, IPv4 when not set
+    pub prefer: Option<IpFamily>,
+    pub regions: Option<Vec<Region>>,
+    /// Codes of the regions named in the `region` settings, built in regions left out keep their defaults
+    pub region_groups: RegionSettings,
+    /// Keep servers whose region could not be looked up when filtering by region
+    pub keep_unknown_region: bool,
+    pub sources: Option<Vec<Source>>,
+    /// Case-insensitive terms, the server name must contain at least one
+    pub includes: Option<Vec<String>>,
+    /// Case-insensitive terms, the server name must not contain any
+    pub excludes: Option<Vec<String>>,
+    pub retry_max: Option<u8>,
+    /// Servers not checked once this much time has passed are skipped and the rest are returned
+    pub deadline: Option<Duration>,
+    /// Maximum number of location lookups in flight at once, 0 is unlimited
+    pub lookup_concurrency: usize,
+}
+
+impl From<&Filters> for FilterQuery {
+    fn from(value: &Filters) -> Self {
+        FilterQuery {
+            limit: value.limit,
+            player_min: value.player_min,
+            team_size_max: value.team_size_max,
+            with_bots: value.with_bots,
+            without_bots: value.without_bots,
+            include_unresponsive: value.include_unresponsive,
+            keep_duplicates: value.keep_duplicates,
+            active_now: value.active_now,
+            near_me: value.near_me,
+            measure_pings: value.near_me && !value.no_ping,
+            min_reliability: value.min_reliability,
+            prefer: value.prefer,
+            regions: value.region.clone(),
+            region_groups: RegionSettings::new(),
+            keep_unknown_region: value.keep_unknown_region,
+            sources: value.source.clone(),
+            includes: value.includes.clone(),
+            excludes: value.excludes.clone(),
+            retry_max: value.retry_max,
+            deadline: Some(Duration::from_secs(
+                value.deadline.unwrap_or(DEFAULT_DEADLINE_SECS),
+            ))
+            .filter(|deadline| !deadline.is_zero()),
+            lookup_concurrency: 0,
+        }
+    }
+}
+
+impl FilterQuery {
+    pub fn builder() -> FilterQueryBuilder {
+        FilterQueryBuilder::default()
+    }
+
+    /// Runs the query against the master servers, matches are sorted by player count, most first, or by
+    /// ping, lowest first, when [`FilterQuery::measure_pings`] is set
+    ///
+    /// Returns `Err` only when none of the selected master servers could be reached
+    pub async fn execute(&self, http: &Http) -> Result<Vec<ServerEntry>, Error> {
+        self.execute_with(http, CacheService::new(Cache::new()), None)
+            .await
+    }
+
+    /// Same as [`FilterQuery::execute`] but reuses `cache` for region lookups and master server
+    /// fallbacks, progress and non-fatal errors are sent to `msg_sender` when one is given
+    pub async fn execute_with(
+        &self,
+        http: &Http,
+        cache: CacheService,
+        msg_sender: Option<&Sender<Message>>,
+    ) -> Result<Vec<ServerEntry>, Error> {
+        let closed;
+        let msg_sender = match msg_sender {
+            Some(sender) => sender,
+            None => {
+                closed = tokio::sync::mpsc::channel(1).0;
+                &closed
+            }
+        };
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let (mut servers, _) = filter_server_list(
+            http,
+            self,
+            cache,
+            limit,
+            &CancellationToken::new(),
+            msg_sender,
+        )
+        .await?;
+
+        // nearest servers are already ranked, last like `write_matching` expects
+        if self.measure_pings {
+            servers.reverse();
+        } else {
+            servers.sort_by_key(|server| {
+                std::cmp::Reverse(server.info.as_ref().map_or(0, |info| info.clients))
+            });
+        }
+        servers.truncate(limit);
+        Ok(servers.into_iter().map(ServerEntry::from).collect())
+    }
+}
+
+/// Builds a [`FilterQuery`], repeated calls to list setters such as [`FilterQueryBuilder::region`]
+/// add to the list
+#[derive(Debug, Default)]
+pub struct FilterQueryBuilder {
+    query: FilterQuery,
+}
+
+impl FilterQueryBuilder {
+    pub fn region(mut self, region: Region) -> Self {
+        let regions = self.query.regions.get_or_insert_with(Vec::new);
+        if !regions.contains(&region) {
+            regions.push(region);
+        }
+        self
+    }
+
+    pub fn source(mut self, source: Source) -> Self {
+        let sources = self.query.sources.get_or_insert_with(Vec::new);
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+        self
+    }
+
+    pub fn min_players(mut self, players: u8) -> Self {
+        self.query.player_min = Some(players);
+        self
+    }
+
+    pub fn max_team_size(mut self, size: u8) -> Self {
+        self.query.team_size_max = Some(size);
+        self
+    }
+
+    /// Only servers with bots, overrides [`FilterQueryBuilder::without_bots`]
+    pub fn with_bots(mut self) -> Self {
+        self.query.with_bots = true;
+        self.query.without_bots = false;
+        self
+    }
+
+    /// Only servers without bots, overrides [`FilterQueryBuilder::with_bots`]
+    pub fn without_bots(mut self) -> Self {
+        self.query.without_bots = true;
+        self.query.with_bots = false;
+        self
+    }
+
+    pub fn include_unresponsive(mut self, include: bool) -> Self {
+        self.query.include_unresponsive = include;
+        self
+    }
+
+    pub fn keep_duplicates(mut self, keep: bool) -> Self {
+        self.query.keep_duplicates = keep;
+        self
+    }
+
+    pub fn active_now(mut self, active_now: bool) -> Self {
+        self.query.active_now = active_now;
+        self
+    }
+
+    /// Keep servers near this device, ranked by ping when `measure_pings` is set
+    pub fn near_me(mut self, measure_pings: bool) -> Self {
+        self.query.near_me = true;
+        self.query.measure_pings = measure_pings;
+        self
+    }
+
+    pub fn min_reliability(mut self, percent: u8) -> Self {
+        self.query.min_reliability = Some(percent);
+        self
+    }
+
+    pub fn keep_unknown_region(mut self, keep: bool) -> Self {
+        self.query.keep_unknown_region = keep;
+        self
+    }
+
+    pub fn prefer(mut self, family: IpFamily) -> Self {
+        self.query.prefer = Some(family);
+        self
+    }
+
+    pub fn include(mut self, term: impl Into<String>) -> Self {
+        self.query
+            .includes
+            .get_or_insert_with(Vec::new)
+            .push(term.into());
+        self
+    }
+
+    pub fn exclude(mut self, term: impl Into<String>) -> Self {
+        self.query
+            .excludes
+            .get_or_insert_with(Vec::new)
+            .push(term.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.query.limit = Some(limit);
+        self
+    }
+
+    pub fn retry_max(mut self, retries: u8) -> Self {
+        self.query.retry_max = Some(retries);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.query.deadline = Some(deadline);
+        self
+    }
+
+    pub fn lookup_concurrency(mut self, concurrency: usize) -> Self {
+        self.query.lookup_concurrency = concurrency;
+        self
+    }
+
+    pub fn region_groups(mut self, groups: RegionSettings) -> Self {
+        self.query.region_groups = groups;
+        self
+    }
+
+    pub fn build(self) -> FilterQuery {
+        self.query
+    }
+
+    pub async fn execute(self, http: &Http) -> Result<Vec<ServerEntry>, Error> {
+        self.query.execute(http).await
+    }
+}
+
+/// A server that matched a [`FilterQuery`]
+#[derive(Debug)]
+pub struct ServerEntry {
+    pub socket_addr: SocketAddr,
+    pub source: Source,
+    /// `None` when the query did not need to request server info
+    pub info: Option<GetInfo>,
+}
+
+impl From<Server> for ServerEntry {
+    fn from(value: Server) -> Self {
+        let source = match value.source {
+            Sourced::Hmw(_) | Sourced::HmwCached(_) => Source::HmwMaster,
+            Sourced::Iw4(_) | Sourced::Iw4Cached(_) => Source::Iw4Master,
+        };
+        ServerEntry {
+            socket_addr: value.source.socket_addr(),
+            source,
+            info: value.info,
+        }
+    }
+}
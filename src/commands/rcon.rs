@@ -0,0 +1,228 @@
+use crate::{
+    cli::ConnectTarget,
+    commands::{
+        handler::{CommandContext, CommandHandle},
+        reconnect::resolve_or_pick,
+    },
+    strip_color_codes,
+    utils::{
+        dpapi::{protect_secret, unprotect_secret},
+        getstatus::{connected_socket, MAX_PACKET, OOB_HEADER},
+        input::{
+            line::AsyncCtxCallback,
+            style::{GREEN, WHITE},
+        },
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{collections::HashMap, net::SocketAddr, path::Path, time::Duration};
+use tracing::{error, info};
+
+const RCON_FILE: &str = "rcon.json";
+const PRINT_HEADER: &[u8] = b"print";
+/// Time to wait for the first reply packet
+const REPLY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Long replies are split over several packets, the reply is complete once none arrive for this long
+const REPLY_QUIET: Duration = Duration::from_millis(300);
+/// Replies that mean the command was not run, checked case insensitively
+const REJECTED: [&str; 3] = ["bad rcon", "invalid password", "no rconpassword"];
+
+/// `password` and `command` are sent as is, rcon has no escaping
+pub fn rcon_packet(password: &str, command: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(OOB_HEADER.len() + password.len() + command.len() + 6);
+    packet.extend_from_slice(&OOB_HEADER);
+    packet.extend_from_slice(b"rcon ");
+    packet.extend_from_slice(password.as_bytes());
+    packet.push(b' ');
+    packet.extend_from_slice(command.as_bytes());
+    packet
+}
+
+/// Text of a `print` reply, `None` if `packet` is not one
+pub fn parse_rcon_reply(packet: &[u8]) -> Option<&[u8]> {
+    let body = packet
+        .strip_prefix(&OOB_HEADER)?
+        .strip_prefix(PRINT_HEADER)?;
+    Some(body.strip_prefix(b"\n").unwrap_or(body))
+}
+
+/// Sends `command` and collects the reply, which may span several packets
+pub async fn send_rcon(
+    server: SocketAddr,
+    password: &str,
+    command: &str,
+) -> Result<String, String> {
+    let socket = connected_socket(server)
+        .await
+        .map_err(|err| format!("Could not reach {server}, {err}"))?;
+    socket
+        .send(&rcon_packet(password, command))
+        .await
+        .map_err(|err| format!("Could not send to {server}, {err}"))?;
+
+    let mut buf = vec![0; MAX_PACKET];
+    let mut reply = Vec::new();
+    let mut wait = REPLY_TIMEOUT;
+    loop {
+        match tokio::time::timeout(wait, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) => {
+                if let Some(text) = parse_rcon_reply(&buf[..len]) {
+                    reply.extend_from_slice(text);
+                }
+                wait = REPLY_QUIET;
+            }
+            Ok(Err(err)) => return Err(format!("Could not read the reply from {server}, {err}")),
+            Err(_) if reply.is_empty() && wait == REPLY_TIMEOUT => {
+                return Err(format!(
+                    "{server} did not reply, make sure rcon is enabled and the port is correct"
+                ))
+            }
+            Err(_) => break,
+        }
+    }
+
+    let reply = strip_color_codes(&String::from_utf8_lossy(&reply));
+    let lowercase = reply.trim_start().to_lowercase();
+    if REJECTED.iter().any(|msg| lowercase.starts_with(msg)) {
+        return Err(format!("{server} rejected the command: {}", reply.trim()));
+    }
+    Ok(reply)
+}
+
+/// Saved passwords keyed by `IP:PORT`, values are base64 of the protected bytes
+async fn read_passwords(local_dir: &Path) -> HashMap<String, String> {
+    tokio::fs::read(local_dir.join(RCON_FILE))
+        .await
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+async fn write_passwords(
+    local_dir: &Path,
+    passwords: &HashMap<String, String>,
+) -> Result<(), String> {
+    let path = local_dir.join(RCON_FILE);
+    let json = serde_json::to_vec_pretty(passwords).expect("strings always serialize");
+    tokio::fs::write(&path, json)
+        .await
+        .map_err(|err| format!("Could not save {RCON_FILE}, {err}"))
+}
+
+async fn saved_password(local_dir: &Path, server: SocketAddr) -> Result<Option<String>, String> {
+    let passwords = read_passwords(local_dir).await;
+    let Some(encoded) = passwords.get(&server.to_string()) else {
+        return Ok(None);
+    };
+    let protected = STANDARD
+        .decode(encoded)
+        .map_err(|err| format!("Saved password for {server} is corrupt, {err}"))?;
+    let password = unprotect_secret(&protected)?;
+    String::from_utf8(password)
+        .map(Some)
+        .map_err(|_| format!("Saved password for {server} is corrupt"))
+}
+
+async fn save_password(local_dir: &Path, server: SocketAddr, password: &str) -> Result<(), String> {
+    let protected = protect_secret(password.as_bytes())?;
+    let mut passwords = read_passwords(local_dir).await;
+    passwords.insert(server.to_string(), STANDARD.encode(protected));
+    write_passwords(local_dir, &passwords).await
+}
+
+/// Returns `false` if no password was saved for `server`
+async fn forget_password(local_dir: &Path, server: SocketAddr) -> Result<bool, String> {
+    let mut passwords = read_passwords(local_dir).await;
+    if passwords.remove(&server.to_string()).is_none() {
+        return Ok(false);
+    }
+    write_passwords(local_dir, &passwords).await.map(|_| true)
+}
+
+pub async fn rcon(
+    context: &mut CommandContext,
+    target: ConnectTarget,
+    command: Vec<String>,
+    password: Option<String>,
+    save: bool,
+    forget: bool,
+) -> CommandHandle {
+    let rerun = (command.clone(), password.clone());
+    let on_pick = move |server| -> Box<AsyncCtxCallback> {
+        let (command, password) = rerun.clone();
+        Box::new(move |context| {
+            Box::pin(async move {
+                let target = ConnectTarget::SocketAddr(server);
+                rcon(context, target, command, password, save, forget).await;
+                Ok(())
+            })
+        })
+    };
+    let server = match resolve_or_pick(context, &target, on_pick).await {
+        Ok(server) => server,
+        Err(handle) => return handle,
+    };
+    let local_dir = context.local_dir();
+
+    if forget {
+        match local_dir.map(|dir| forget_password(dir, server)) {
+            Some(forgot) => match forgot.await {
+                Ok(true) => info!("Saved rcon password for {server} removed"),
+                Ok(false) => info!("No rcon password is saved for {server}"),
+                Err(err) => error!("{err}"),
+            },
+            None => info!("No rcon password is saved for {server}"),
+        }
+        return CommandHandle::Processed;
+    }
+
+    let password = match (password, local_dir) {
+        (Some(password), _) => password,
+        (None, Some(dir)) => {
+            match saved_password(dir, server).await {
+                Ok(Some(password)) => password,
+                Ok(None) => {
+                    error!("No rcon password is saved for {server}, use `--password <PASSWORD> --save`");
+                    return CommandHandle::Processed;
+                }
+                Err(err) => {
+                    error!("{err}");
+                    return CommandHandle::Processed;
+                }
+            }
+        }
+        (None, None) => {
+            error!("Saved passwords are unavailable without a local environment, use `--password`");
+            return CommandHandle::Processed;
+        }
+    };
+
+    match send_rcon(server, &password, &command.join(" ")).await {
+        Ok(reply) => {
+            let reply = reply.trim_end();
+            if reply.is_empty() {
+                info!("{server} ran the command, the reply was empty");
+            } else {
+                println!("{reply}");
+            }
+        }
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    }
+
+    if save {
+        let saved = match local_dir {
+            Some(dir) => save_password(dir, server, &password).await,
+            None => Err(String::from(
+                "Passwords can not be saved without a local environment",
+            )),
+        };
+        match saved {
+            Ok(()) => println!("{GREEN}Rcon password for {server} saved{WHITE}"),
+            Err(err) => error!("{err}"),
+        }
+    }
+    CommandHandle::Processed
+}
@@ -1,81 +1,288 @@
 use crate::{
-    cli::HistoryArgs,
+    cli::{ConnectTarget, HistoryArgs},
     commands::{
-        handler::{CommandContext, CommandHandle},
-        launch_h2m::HostName,
+        dashboard::probe_all,
+        friends::JOIN_KEYS,
+        handler::{CommandContext, CommandHandle, Message},
+        launch_h2m::{focus_h2m_window, PTY},
+        services::GameControl,
     },
+    parse_hostname, strip_color_codes,
     utils::{
-        display::{ConnectionHelp, DisplayHistoryErr},
-        input::style::{WHITE, YELLOW},
+        caching::{refresh_pings, Cache},
+        display::{ConnectionHelp, DisplayDuration, DisplayHistoryErr, ServerChoices},
+        input::{
+            line::{AsyncCtxCallback, EventLoop, InputEventHook, InputHook},
+            style::{WHITE, YELLOW},
+        },
+        server_book::PlayerSample,
     },
 };
-use std::{borrow::Cow, collections::HashMap, ffi::OsString, fmt::Display, net::SocketAddr};
-use tokio::sync::RwLock;
-use tracing::{error, info};
-use winptyrs::PTY;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::{
+    collections::{HashSet, VecDeque},
+    ffi::OsString,
+    fmt::Display,
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::{mpsc::Sender, RwLock};
+use tracing::{error, info, warn};
 
 pub const HISTORY_MAX: usize = 6;
+/// Player counts older than this are requested again when history is shown
+const PLAYERS_MAX_AGE: Duration = Duration::from_secs(5 * 60);
 
-struct DisplayHistory<'a>(&'a [HostName], &'a [Cow<'static, str>]);
+/// Host name, last known players and connect command of each history entry
+struct DisplayHistory<'a>(&'a [(String, String, String)]);
 
-impl<'a> Display for DisplayHistory<'a> {
+impl Display for DisplayHistory<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut longest_host_len = 0;
-        let mut longest_connect_len = 0;
-        let set = self
-            .0
-            .iter()
-            .rev()
-            .take(HISTORY_MAX)
-            .enumerate()
-            .map(|(i, host)| {
-                let host_ip = self.1[i].as_ref();
-                let name_len = host.parsed.chars().count();
-                let ip_len = host_ip.chars().count();
-                longest_host_len = longest_host_len.max(name_len);
-                longest_connect_len = longest_connect_len.max(ip_len);
-                (i + 1, host.parsed.as_str(), name_len, ip_len, host_ip)
-            })
-            .collect::<Vec<_>>();
-        let width = longest_connect_len + longest_host_len + 8;
+        let [name_len, players_len, connect_len] =
+            self.0
+                .iter()
+                .fold([0; 3], |longest, (host_name, players, connect)| {
+                    [
+                        longest[0].max(host_name.chars().count()),
+                        longest[1].max(players.chars().count()),
+                        longest[2].max(connect.chars().count()),
+                    ]
+                });
+        let num_len = self.0.len().to_string().len() + 1;
+        let width = num_len + name_len + players_len + connect_len + 8;
         writeln!(f)?;
         writeln!(f, "{}", "-".repeat(width))?;
-        for (num, host_name, host_len, ip_len, ip) in set {
-            let spacing = width - 7 - host_len - ip_len;
-            writeln!(f, "| {num}.{host_name}{} {ip} |", " ".repeat(spacing))?;
+        for (i, (host_name, players, connect)) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "| {:<num_len$}{host_name:<name_len$}  {players:<players_len$}  {connect:>connect_len$} |",
+                format!("{}.", i + 1)
+            )?;
         }
         writeln!(f, "{}", "-".repeat(width))?;
         Ok(())
     }
 }
 
-async fn display_history<'a>(
-    history: &'a [HostName],
-    host_to_connect: &'a HashMap<String, SocketAddr>,
-) {
-    let ips = history
+/// Addresses of the entries shown by `reconnect --history`
+fn history_servers(cache: &Cache) -> Vec<SocketAddr> {
+    cache.book.history().iter().rev().copied().collect()
+}
+
+fn display_history(cache: &Cache) {
+    let rows = history_servers(cache)
+        .into_iter()
+        .map(|ip| {
+            let host_name = cache
+                .book
+                .host_name(ip)
+                .map(|name| parse_hostname(name))
+                .unwrap_or_else(|| String::from("Unknown server"));
+            let players = match cache.book.get(ip).and_then(|entry| entry.players) {
+                Some(sample) => {
+                    let count = format!("{}/{} players", sample.clients, sample.max_clients);
+                    match sample.at.elapsed() {
+                        Ok(age) if age >= PLAYERS_MAX_AGE => {
+                            format!("{count} {} ago", DisplayDuration(age))
+                        }
+                        _ => count,
+                    }
+                }
+                None => String::from("players unknown"),
+            };
+            let connect = match cache.fresh_ping(ip) {
+                Some(ping) => format!("connect {ip} ({}ms)", ping.as_millis()),
+                None => format!("connect {ip}"),
+            };
+            (host_name, players, connect)
+        })
+        .collect::<Vec<_>>();
+    println!("{}", DisplayHistory(&rows));
+}
+
+/// Tab completion values for `reconnect --connect` and `whois`, servers in history come first followed
+/// by every other known IP:PORT
+pub fn connect_recs(cache: &Cache) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let history = cache.book.history().iter().rev();
+    let mut known = cache.book.servers().keys().collect::<Vec<_>>();
+    known.sort_unstable();
+    history
+        .chain(known)
+        .filter(|&&socket_addr| seen.insert(socket_addr))
+        .map(SocketAddr::to_string)
+        .collect()
+}
+
+enum Resolved {
+    Server(SocketAddr),
+    /// A server name matched these equally well, most recently seen first
+    Choices(Vec<SocketAddr>),
+}
+
+fn resolve_name(cache: &Cache, query: &str) -> Result<Resolved, String> {
+    let mut found = cache.book.candidates(query);
+    match found.len() {
+        0 => Err(format!("No known server name matches: '{query}'")),
+        1 => Ok(Resolved::Server(found.remove(0))),
+        _ => Ok(Resolved::Choices(found)),
+    }
+}
+
+/// Address of a numbered history entry, an `IP:PORT` or a known server name
+async fn resolve_server(
+    context: &CommandContext,
+    target: &ConnectTarget,
+) -> Result<Resolved, String> {
+    let cache_arc = context.cache().clone();
+    let cache = cache_arc.lock().await;
+    match target {
+        ConnectTarget::SocketAddr(socket_addr) => Ok(Resolved::Server(*socket_addr)),
+        ConnectTarget::HostName(query) => resolve_name(&cache, query),
+        &ConnectTarget::History(num) => cache
+            .book
+            .history_entry(num as usize)
+            .map(Resolved::Server)
+            .ok_or_else(|| DisplayHistoryErr(cache.book.history().len()).to_string()),
+    }
+}
+
+/// Resolves `target` like [`resolve_server`]. When a name matches several servers they are listed
+/// numbered, and `on_pick` runs with the one whose number key is pressed
+pub(crate) async fn resolve_or_pick<F>(
+    context: &CommandContext,
+    target: &ConnectTarget,
+    on_pick: F,
+) -> Result<SocketAddr, CommandHandle>
+where
+    F: Fn(SocketAddr) -> Box<AsyncCtxCallback> + 'static,
+{
+    match resolve_server(context, target).await {
+        Ok(Resolved::Server(server)) => Ok(server),
+        Ok(Resolved::Choices(servers)) => Err(pick_server(
+            &*context.cache().lock().await,
+            servers,
+            on_pick,
+        )),
+        Err(err) => {
+            error!("{err}");
+            Err(CommandHandle::Processed)
+        }
+    }
+}
+
+/// Lists `servers` numbered with their names, only the first [`JOIN_KEYS`] can be picked
+fn pick_server<F>(cache: &Cache, mut servers: Vec<SocketAddr>, on_pick: F) -> CommandHandle
+where
+    F: Fn(SocketAddr) -> Box<AsyncCtxCallback> + 'static,
+{
+    let more = servers.len().saturating_sub(JOIN_KEYS);
+    servers.truncate(JOIN_KEYS);
+    let choices = servers
         .iter()
-        .rev()
-        .take(HISTORY_MAX)
-        .map(|entry| {
-            host_to_connect
-                .get(&entry.raw)
-                .map(|ip| Cow::Owned(format!("connect {ip}")))
-                .unwrap_or(Cow::Borrowed("Server not found in cache"))
+        .map(|&server| {
+            let host_name = cache
+                .book
+                .host_name(server)
+                .map_or_else(String::new, |name| strip_color_codes(name));
+            (server, host_name)
         })
         .collect::<Vec<_>>();
-    println!("{}", DisplayHistory(history, &ips));
+    print!("{}", ServerChoices(&choices, more));
+    CommandHandle::InsertHook(number_key_hook(servers, on_pick))
+}
+
+/// Hook that runs `on_pick` with the entry of `choices` whose number is pressed, any other key
+/// dismisses it
+pub fn number_key_hook<F>(choices: Vec<SocketAddr>, on_pick: F) -> InputHook
+where
+    F: Fn(SocketAddr) -> Box<AsyncCtxCallback> + 'static,
+{
+    let input_hook: Box<InputEventHook> = Box::new(move |handle, event| {
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        else {
+            return Ok((EventLoop::Continue, false));
+        };
+        let selected = match code {
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                match c.to_digit(10) {
+                    Some(num @ 1..) => choices.get(num as usize - 1).copied(),
+                    _ => {
+                        handle.insert_char(c);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let Some(server) = selected else {
+            return Ok((EventLoop::Continue, true));
+        };
+        Ok((EventLoop::AsyncCallback(on_pick(server)), true))
+    });
+    InputHook::with_new_uid(None, input_hook)
+}
+
+/// Callback that joins `server`, used when it is picked from a numbered list
+pub fn join_server(server: SocketAddr) -> Box<AsyncCtxCallback> {
+    Box::new(move |context| {
+        Box::pin(async move {
+            let args = HistoryArgs {
+                history: false,
+                connect: Some(ConnectTarget::SocketAddr(server)),
+            };
+            reconnect(args, context).await;
+            Ok(())
+        })
+    })
 }
 
 pub async fn reconnect(args: HistoryArgs, context: &mut CommandContext) -> CommandHandle {
-    let cache_arc = context.cache();
+    let cache_arc = context.cache().clone();
     let mut cache = cache_arc.lock().await;
-    if cache.connection_history.is_empty() {
+    let from_history = matches!(args.connect, None | Some(ConnectTarget::History(_)));
+    if from_history && cache.book.history().is_empty() {
         info!("No joined servers in history, connect to a server to add it to history");
         return CommandHandle::Processed;
     }
     if args.history {
-        display_history(&cache.connection_history, &cache.host_to_connect).await;
+        let servers = history_servers(&cache);
+        let stale = servers
+            .iter()
+            .copied()
+            .filter(|&server| {
+                !cache
+                    .book
+                    .get(server)
+                    .and_then(|entry| entry.players)
+                    .is_some_and(|sample| {
+                        sample.at.elapsed().is_ok_and(|age| age < PLAYERS_MAX_AGE)
+                    })
+            })
+            .collect::<Vec<_>>();
+        drop(cache);
+        let http = context.http();
+        // servers that do not answer keep their last count, shown with how old it is
+        let (answers, ()) = tokio::join!(
+            probe_all(&http, &stale),
+            refresh_pings(&cache_arc, &servers, false)
+        );
+        let mut cache = cache_arc.lock().await;
+        let now = SystemTime::now();
+        for (server, info) in answers {
+            if let Some(info) = info {
+                cache
+                    .book
+                    .record_players(server, PlayerSample::new(&info, now));
+                cache_arc.mark_changed();
+            }
+        }
+        display_history(&cache);
         return CommandHandle::Processed;
     }
     if let Err(err) = context.check_h2m_connection().await {
@@ -83,29 +290,50 @@ pub async fn reconnect(args: HistoryArgs, context: &mut CommandContext) -> Comma
         println!("{ConnectionHelp}");
         return CommandHandle::Processed;
     }
-    let history_len = cache.connection_history.len();
-    if let Some(num) = args.connect {
-        if num > 1 {
-            if num as usize > history_len {
-                error!("{}", DisplayHistoryErr(history_len));
+    let connect = match args.connect {
+        Some(ConnectTarget::SocketAddr(socket_addr)) => Some(socket_addr),
+        Some(ConnectTarget::HostName(ref query)) => match resolve_name(&cache, query) {
+            Ok(Resolved::Server(socket_addr)) => Some(socket_addr),
+            Ok(Resolved::Choices(servers)) => return pick_server(&cache, servers, join_server),
+            Err(err) => {
+                error!("{err}");
+                return CommandHandle::Processed;
+            }
+        },
+        Some(ConnectTarget::History(num)) => {
+            let Some(socket_addr) = cache.book.history_entry(num as usize) else {
+                error!("{}", DisplayHistoryErr(cache.book.history().len()));
                 return CommandHandle::Processed;
+            };
+            if cache.book.push_history(socket_addr) {
+                cache_arc.mark_changed();
             }
-            let entry = cache.connection_history.remove(history_len - num as usize);
-            cache.connection_history.push(entry);
+            Some(socket_addr)
         }
-    }
-    let connect = cache
-        .host_to_connect
-        .get(&cache.connection_history.last().unwrap().raw)
-        .copied();
+        None => cache.book.history_entry(1),
+    };
 
     drop(cache);
 
     if let Some(ip_port) = connect {
+        let game = context.game_control();
+        if !game.state().borrow().accepts_commands() {
+            queue_command(
+                &mut *game.command_queue().lock().await,
+                QueuedCommand::Connect(ip_port),
+            );
+            println!("{YELLOW}Queued:{WHITE} H2M is loading, connect {ip_port} will be sent once the game is idle");
+            return CommandHandle::Processed;
+        }
         let lock = context.pty_handle().unwrap();
-        connect_to(ip_port, &lock)
-            .await
-            .unwrap_or_else(|err| error!("{err}"));
+        match connect_to(ip_port, &lock).await {
+            Ok(()) => {
+                if let Err(err) = focus_after_connect(game) {
+                    warn!("{err}")
+                }
+            }
+            Err(err) => error!("{err}"),
+        }
     } else {
         error!("Could not find server in cache");
         println!("use command '{YELLOW}cache{WHITE} update' to attempt to locate missing server");
@@ -113,10 +341,50 @@ pub async fn reconnect(args: HistoryArgs, context: &mut CommandContext) -> Comma
     CommandHandle::Processed
 }
 
-/// Before calling be sure to guard against invalid handles by checking `.check_h2m_connection().is_ok()`
-async fn connect_to(ip_port: SocketAddr, lock: &RwLock<PTY>) -> Result<(), String> {
-    let handle = lock.read().await;
-    let send_command = |command: &str| match handle.write(OsString::from(command)) {
+/// Game console commands that are held back while H2M is busy loading
+pub enum QueuedCommand {
+    Connect(SocketAddr),
+}
+
+impl QueuedCommand {
+    pub fn send(&self, handle: &PTY) -> Result<(), String> {
+        match self {
+            QueuedCommand::Connect(ip_port) => send_connect(*ip_port, handle),
+        }
+    }
+}
+
+/// Sends every queued command in order, the console listeners call this once the game accepts commands
+pub async fn send_queued(game: &GameControl, handle: &PTY, msg_sender: &Sender<Message>) {
+    let mut queue = game.command_queue().lock().await;
+    while let Some(command) = queue.pop_front() {
+        let msg = match command.send(handle) {
+            Ok(()) => {
+                if matches!(command, QueuedCommand::Connect(_)) {
+                    if let Err(err) = focus_after_connect(game) {
+                        let _ = msg_sender.send(Message::Warn(err.to_string())).await;
+                    }
+                }
+                Message::Info(format!("Sent queued command: {command}"))
+            }
+            Err(err) => Message::Err(format!("Failed to send queued {command}, {err}")),
+        };
+        let _ = msg_sender.send(msg).await;
+    }
+}
+
+/// Queues `command`, a queued connect replaces any connect that was queued before it
+pub fn queue_command(queue: &mut VecDeque<QueuedCommand>, command: QueuedCommand) {
+    match command {
+        QueuedCommand::Connect(_) => {
+            queue.retain(|queued| !matches!(queued, QueuedCommand::Connect(_)))
+        }
+    }
+    queue.push_back(command);
+}
+
+fn send_command(handle: &PTY, command: &str) -> Result<(), String> {
+    match handle.write(OsString::from(command)) {
         Ok(chars) => {
             if chars == 0 {
                 Err(String::from("Failed to send command to h2m console"))
@@ -125,9 +393,25 @@ async fn connect_to(ip_port: SocketAddr, lock: &RwLock<PTY>) -> Result<(), Strin
             }
         }
         Err(err) => Err(err.to_string_lossy().to_string()),
-    };
+    }
+}
 
-    send_command("disconnect\r\n")?;
+fn send_connect(ip_port: SocketAddr, handle: &PTY) -> Result<(), String> {
+    send_command(handle, "disconnect\r\n")?;
     std::thread::sleep(std::time::Duration::from_millis(10));
-    send_command(&format!("connect {ip_port}\r\n"))
+    send_command(handle, &format!("connect {ip_port}\r\n"))
+}
+
+/// Focuses the game window if `focus-on-connect` is enabled
+pub fn focus_after_connect(game: &GameControl) -> Result<(), &'static str> {
+    if !game.focus_on_connect() {
+        return Ok(());
+    }
+    focus_h2m_window()
+}
+
+/// Before calling be sure to guard against invalid handles by checking `.check_h2m_connection().is_ok()`
+pub async fn connect_to(ip_port: SocketAddr, lock: &RwLock<PTY>) -> Result<(), String> {
+    let handle = lock.read().await;
+    send_connect(ip_port, &handle)
 }
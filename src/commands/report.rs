@@ -0,0 +1,129 @@
+use crate::{
+    commands::{
+        handler::{CommandContext, CommandHandle},
+        launch_h2m::CONSOLE_LOG_PREFIX,
+        logs::{app_log_prefix, newest_log},
+    },
+    utils::{caching::Cache, geo_key::redact, lenient::DEBUG_PARSE_FILE},
+};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// Number of recently entered commands included in a report
+pub const REPORT_COMMANDS_MAX: usize = 50;
+const REPORT_PREFIX: &str = "report_";
+
+/// Counts describing the cache, the cached servers themselves are left out
+struct CacheSummary {
+    created: SystemTime,
+    servers: usize,
+    regions: usize,
+    history: usize,
+    iw4m: usize,
+    hmw: usize,
+}
+
+impl From<&Cache> for CacheSummary {
+    fn from(cache: &Cache) -> Self {
+        CacheSummary {
+            created: cache.created,
+            servers: cache.book.len(),
+            regions: cache.ip_to_region.len(),
+            history: cache.book.history().len(),
+            iw4m: cache.iw4m.values().map(Vec::len).sum(),
+            hmw: cache.hmw.values().map(Vec::len).sum(),
+        }
+    }
+}
+
+fn write_summary(
+    writer: &mut impl Write,
+    context: &CommandContext,
+    cache: &CacheSummary,
+) -> io::Result<()> {
+    let app = context.app();
+    let game = context.game();
+    writeln!(writer, "{} v{}", env!("CARGO_PKG_NAME"), app.ver_curr)?;
+    if let Some(ref latest) = app.ver_latest {
+        writeln!(writer, "Latest release: v{latest}")?;
+    }
+    writeln!(writer, "Game executable: {}", game.path.display())?;
+    if let Some(version) = game.version {
+        writeln!(writer, "Game version: v{version}")?;
+    }
+    if let Some(ref hash) = game.hash_curr {
+        writeln!(writer, "Game hash: {hash}")?;
+    }
+    if let Some(ref hash) = game.hash_latest {
+        writeln!(writer, "Latest HMW hash: {hash}")?;
+    }
+    if !game.launch_args.is_empty() {
+        writeln!(writer, "Launch args: {}", game.launch_args.join(" "))?;
+    }
+    writeln!(writer)?;
+    let created = cache
+        .created
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    writeln!(writer, "Cache created: {created} (unix secs)")?;
+    writeln!(writer, "Known servers: {}", cache.servers)?;
+    writeln!(writer, "Cached regions: {}", cache.regions)?;
+    writeln!(writer, "Connection history entries: {}", cache.history)?;
+    writeln!(writer, "Cached iw4m servers: {}", cache.iw4m)?;
+    writeln!(writer, "Cached hmw servers: {}", cache.hmw)
+}
+
+fn write_report(dir: &Path, context: &CommandContext, cache: &CacheSummary) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("{REPORT_PREFIX}{secs}.zip"));
+    let mut zip = ZipWriter::new(std::fs::File::create(&path)?);
+    let options = SimpleFileOptions::default();
+
+    zip.start_file("summary.txt", options)?;
+    write_summary(&mut zip, context, cache)?;
+
+    zip.start_file("commands.txt", options)?;
+    for command in context.recent_commands() {
+        writeln!(zip, "{command}")?;
+    }
+
+    for prefix in [app_log_prefix().as_str(), CONSOLE_LOG_PREFIX] {
+        if let Some(log) = newest_log(dir, prefix)? {
+            zip.start_file(log.name(), options)?;
+            let contents = std::fs::read(&log.path)?;
+            zip.write_all(redact(&String::from_utf8_lossy(&contents)).as_bytes())?;
+        }
+    }
+
+    // written by `--debug-parse`
+    if let Ok(contents) = std::fs::read(dir.join(DEBUG_PARSE_FILE)) {
+        zip.start_file(DEBUG_PARSE_FILE, options)?;
+        zip.write_all(&contents)?;
+    }
+
+    zip.finish()?;
+    Ok(path)
+}
+
+pub async fn report(context: &CommandContext) -> CommandHandle {
+    let Some(dir) = context.local_dir() else {
+        error!("Could not find local dir");
+        return CommandHandle::Processed;
+    };
+    let cache = CacheSummary::from(&*context.cache().lock().await);
+    match write_report(dir, context, &cache) {
+        Ok(path) => {
+            info!("Report saved to: {}", path.display());
+            println!("Attach this file when opening an issue on GitHub");
+        }
+        Err(err) => error!("Failed to write report, {err}"),
+    }
+    CommandHandle::Processed
+}
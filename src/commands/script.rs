@@ -0,0 +1,44 @@
+use crate::{cli::ScriptArgs, commands::handler::CommandHandle};
+use std::path::PathBuf;
+use tracing::error;
+
+/// Lines starting with this are skipped
+const COMMENT: char = '#';
+
+/// Commands read from a file, the main loop executes them in order as if they were typed at the prompt
+pub struct Script {
+    pub path: PathBuf,
+    pub lines: Vec<String>,
+    pub stop_on_error: bool,
+}
+
+impl Script {
+    pub fn read(path: PathBuf, stop_on_error: bool) -> std::io::Result<Self> {
+        let contents = std::fs::read_to_string(&path)?;
+        Ok(Script {
+            lines: parse_script(&contents),
+            path,
+            stop_on_error,
+        })
+    }
+}
+
+/// Splits `contents` into commands, blank lines and `#` comments are removed
+pub fn parse_script(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(COMMENT))
+        .map(String::from)
+        .collect()
+}
+
+pub fn run(args: ScriptArgs) -> CommandHandle {
+    match Script::read(args.file, args.stop_on_error) {
+        Ok(script) => CommandHandle::Script(script),
+        Err(err) => {
+            error!("Could not read script, {err}");
+            CommandHandle::Processed
+        }
+    }
+}
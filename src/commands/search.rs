@@ -0,0 +1,167 @@
+use crate::{
+    commands::{
+        favorites::{read_favorites, write_favorites, WriteOutcome, SUPERSEDED},
+        filter::{DEFAULT_H2M_SERVER_CAP, FAVORITES},
+        handler::{CommandContext, CommandHandle},
+        trim::parse_picks,
+        whois::lookup,
+    },
+    strip_color_codes,
+    utils::{
+        display::{DisplayCountOf, DisplaySearch},
+        input::style::{GREEN, GREY, WHITE},
+        server_book::PlayerSample,
+    },
+};
+use std::{collections::HashSet, net::SocketAddr};
+use tracing::{error, info, warn};
+
+/// Matches past this are only counted and can not be picked
+pub const SEARCH_MAX: usize = 50;
+
+pub struct SearchResult {
+    pub server: SocketAddr,
+    /// Without color codes
+    pub name: Option<String>,
+    pub region: Option<[char; 2]>,
+    /// Players when the server last answered
+    pub players: Option<PlayerSample>,
+}
+
+/// Lists cached servers matching `query`, numbered so `add 1,4 7-9` can append the ones picked to
+/// favourites.json
+pub async fn search(context: &mut CommandContext, query: String) -> CommandHandle {
+    let (results, total) = {
+        let cache_arc = context.cache().clone();
+        let mut cache = cache_arc.lock().await;
+        let matches = lookup(&cache, query.trim());
+        let total = matches.len();
+        let results = matches
+            .into_iter()
+            .take(SEARCH_MAX)
+            .map(|server| {
+                let entry = cache.book.get(server);
+                SearchResult {
+                    server,
+                    name: entry
+                        .and_then(|entry| entry.host_name())
+                        .map(|name| strip_color_codes(name)),
+                    region: cache.ip_to_region.get(&server.ip()).copied(),
+                    players: entry.and_then(|entry| entry.players),
+                }
+            })
+            .collect::<Vec<_>>();
+        cache.last_search = results.iter().map(|result| result.server).collect();
+        (results, total)
+    };
+    if results.is_empty() {
+        info!("No cached server matches '{query}'");
+        return CommandHandle::Processed;
+    }
+    println!("{}", DisplaySearch(&results));
+    if total > SEARCH_MAX {
+        println!(
+            "{GREY}{} more servers match, be more specific{WHITE}",
+            total - SEARCH_MAX
+        );
+    }
+    info!("Use `add <NUMBERS>` to append servers to {FAVORITES}, e.g. `add 1,3 5-7`");
+    CommandHandle::Processed
+}
+
+/// What appending picked servers to favourites.json did
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Appended {
+    pub added: usize,
+    /// Already in the list, or picked twice
+    pub duplicates: usize,
+    /// Left out because the list was full
+    pub over_cap: usize,
+}
+
+/// Appends the servers of `picked` that are not in `current` yet, as long as there is room under `cap`
+pub fn append_picked(
+    current: &mut Vec<SocketAddr>,
+    picked: impl IntoIterator<Item = SocketAddr>,
+    cap: Option<usize>,
+) -> Appended {
+    let mut appended = Appended::default();
+    let mut seen = current.iter().copied().collect::<HashSet<_>>();
+    for server in picked {
+        if !seen.insert(server) {
+            appended.duplicates += 1;
+        } else if cap.is_some_and(|cap| current.len() >= cap) {
+            appended.over_cap += 1;
+        } else {
+            current.push(server);
+            appended.added += 1;
+        }
+    }
+    appended
+}
+
+pub async fn add(context: &mut CommandContext, picks: Vec<String>) -> CommandHandle {
+    let results = context.cache().lock().await.last_search.clone();
+    if results.is_empty() {
+        error!("Nothing to add from, list servers with `search <QUERY>` first");
+        return CommandHandle::Processed;
+    }
+    let picked = match parse_picks(&picks.join(" "), results.len()) {
+        Ok(picked) => picked,
+        Err(err) => {
+            error!("{err}");
+            return CommandHandle::Processed;
+        }
+    };
+
+    let targets = context.favorites_targets();
+    let mut servers = if targets[0].exists() {
+        match read_favorites(&targets[0]).await {
+            Ok(servers) => servers,
+            Err(err) => {
+                error!("{err}");
+                return CommandHandle::Processed;
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    let cap = context
+        .h2m_version()
+        .is_some_and(|version| version < 1.0)
+        .then_some(DEFAULT_H2M_SERVER_CAP);
+    let appended = append_picked(&mut servers, picked.iter().map(|&i| results[i]), cap);
+
+    if appended.added > 0 {
+        match write_favorites(&targets, &servers).await {
+            Ok(WriteOutcome::Written) => (),
+            Ok(WriteOutcome::Superseded) => {
+                warn!("{SUPERSEDED}");
+                return CommandHandle::Processed;
+            }
+            Err(err) => {
+                error!("{err}");
+                return CommandHandle::Processed;
+            }
+        }
+    }
+    println!(
+        "{GREEN}Added {} to {FAVORITES}, it now lists {}{WHITE}",
+        DisplayCountOf(appended.added, "server", "servers"),
+        servers.len()
+    );
+    if appended.duplicates > 0 {
+        info!(
+            "{} already listed",
+            DisplayCountOf(appended.duplicates, "server was", "servers were")
+        );
+    }
+    if appended.over_cap > 0 {
+        warn!(
+            "{} left out, the in game server browser breaks with more than \
+            {DEFAULT_H2M_SERVER_CAP} servers",
+            DisplayCountOf(appended.over_cap, "server was", "servers were")
+        );
+    }
+    CommandHandle::Processed
+}
@@ -0,0 +1,79 @@
+use crate::{
+    cli::ConnectTarget,
+    commands::{
+        handler::{CommandContext, CommandHandle},
+        reconnect::resolve_or_pick,
+    },
+    utils::{
+        display::DisplayServerDetails,
+        getstatus::{get_status, ServerStatus},
+        input::line::AsyncCtxCallback,
+        names::Names,
+    },
+};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+const STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Dvars listed after the summary, when the server reports them, paired with their label
+pub const KEY_DVARS: [(&str, &str); 7] = [
+    ("shortversion", "Version"),
+    ("fs_game", "Mod"),
+    ("g_hardcore", "Hardcore"),
+    ("sv_privateClients", "Private slots"),
+    ("sv_maxPing", "Max ping"),
+    ("sv_voice", "Voice chat"),
+    ("sv_pure", "Pure"),
+];
+
+pub struct ServerDetails {
+    pub server: SocketAddr,
+    /// Round trip of the `getstatus` request
+    pub latency: Duration,
+    pub status: ServerStatus,
+    /// List every dvar instead of [`KEY_DVARS`]
+    pub all_dvars: bool,
+}
+
+pub async fn server_info(
+    context: &mut CommandContext,
+    target: ConnectTarget,
+    all_dvars: bool,
+) -> CommandHandle {
+    let on_pick = move |server| -> Box<AsyncCtxCallback> {
+        Box::new(move |context| {
+            Box::pin(async move {
+                server_info(context, ConnectTarget::SocketAddr(server), all_dvars).await;
+                Ok(())
+            })
+        })
+    };
+    let server = match resolve_or_pick(context, &target, on_pick).await {
+        Ok(server) => server,
+        Err(handle) => return handle,
+    };
+    let start = Instant::now();
+    match get_status(server, STATUS_TIMEOUT).await {
+        Ok(mut status) => {
+            let latency = start.elapsed();
+            status
+                .players
+                .sort_by_key(|player| std::cmp::Reverse(player.score));
+            context.cache().lock().await.record_ping(server, latency);
+            let details = ServerDetails {
+                server,
+                latency,
+                status,
+                all_dvars,
+            };
+            let names = Names::from(&context.config().display);
+            print!("{}", DisplayServerDetails(&details, names));
+        }
+        Err(err) => error!("{err}"),
+    }
+    CommandHandle::Processed
+}
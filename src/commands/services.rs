@@ -0,0 +1,216 @@
+use crate::{
+    commands::{launch_h2m::ConsoleHistory, reconnect::QueuedCommand},
+    utils::{
+        caching::Cache,
+        config::Config,
+        events::{AppEvent, EventBus},
+        game_state::GameState,
+    },
+};
+use std::{
+    collections::VecDeque,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{watch, Mutex, MutexGuard};
+
+/// The server cache and whether it changed since it was last saved. Derefs to the cache's lock
+#[derive(Clone)]
+pub struct CacheService {
+    cache: Arc<Mutex<Cache>>,
+    needs_update: Arc<AtomicBool>,
+}
+
+impl CacheService {
+    pub fn new(cache: Cache) -> Self {
+        CacheService {
+            cache: Arc::new(Mutex::new(cache)),
+            needs_update: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Marks the cache to be saved with the next write
+    #[inline]
+    pub fn mark_changed(&self) {
+        self.needs_update.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_changed(&self) -> bool {
+        self.needs_update.load(Ordering::Acquire)
+    }
+
+    /// Clears the changed mark, returns if it was set
+    #[inline]
+    pub fn take_changed(&self) -> bool {
+        self.needs_update
+            .compare_exchange(true, false, Ordering::Acquire, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+impl Deref for CacheService {
+    type Target = Mutex<Cache>;
+    fn deref(&self) -> &Self::Target {
+        &self.cache
+    }
+}
+
+/// The effective settings, for tasks that keep running after the command that started them returned
+#[derive(Clone)]
+pub struct ConfigService {
+    config: Arc<watch::Sender<Arc<Config>>>,
+}
+
+impl ConfigService {
+    pub fn new(config: Config) -> Self {
+        ConfigService {
+            config: Arc::new(watch::Sender::new(Arc::new(config))),
+        }
+    }
+
+    /// Settings as they are now, later changes are not reflected in the returned value
+    #[inline]
+    pub fn current(&self) -> Arc<Config> {
+        Arc::clone(&self.config.borrow())
+    }
+
+    #[inline]
+    pub fn replace(&self, config: Config) {
+        self.config.send_replace(Arc::new(config));
+    }
+}
+
+/// Output of the H2M console, either read from the pseudoconsole or from a followed log file
+#[derive(Clone)]
+pub struct ConsoleService {
+    history: Arc<Mutex<ConsoleHistory>>,
+    log_attached: Arc<AtomicBool>,
+    events: EventBus,
+}
+
+impl ConsoleService {
+    pub fn new(events: EventBus) -> Self {
+        ConsoleService {
+            history: Arc::new(Mutex::new(ConsoleHistory::default())),
+            log_attached: Arc::new(AtomicBool::new(false)),
+            events,
+        }
+    }
+
+    #[inline]
+    pub async fn history(&self) -> MutexGuard<'_, ConsoleHistory> {
+        self.history.lock().await
+    }
+
+    /// Publishes the last `n` lines of `history` for whoever is following the console
+    pub fn publish_last(&self, history: &ConsoleHistory, n: usize) {
+        if n > 0 {
+            let lines = history.join_last_n(n);
+            self.events.publish(AppEvent::ConsoleLine(Arc::from(lines)));
+        }
+    }
+
+    /// If output is read from a console log rather than a pseudoconsole
+    #[inline]
+    pub fn log_attached(&self) -> bool {
+        self.log_attached.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn set_log_attached(&self, attached: bool) {
+        self.log_attached.store(attached, Ordering::SeqCst);
+    }
+}
+
+/// What the game is doing and what is waiting to be sent to it
+#[derive(Clone)]
+pub struct GameControl {
+    state: Arc<watch::Sender<GameState>>,
+    connected: Arc<AtomicBool>,
+    auto_relaunch: Arc<AtomicBool>,
+    focus_on_connect: Arc<AtomicBool>,
+    session_start: Arc<Mutex<Option<Instant>>>,
+    command_queue: Arc<Mutex<VecDeque<QueuedCommand>>>,
+}
+
+impl GameControl {
+    /// `connected` is set when the game was launched before the context was built
+    pub fn new(connected: bool, auto_relaunch: bool, focus_on_connect: bool) -> Self {
+        let state = if connected {
+            GameState::Launching
+        } else {
+            GameState::Idle
+        };
+        GameControl {
+            state: Arc::new(watch::Sender::new(state)),
+            connected: Arc::new(AtomicBool::new(connected)),
+            auto_relaunch: Arc::new(AtomicBool::new(auto_relaunch)),
+            focus_on_connect: Arc::new(AtomicBool::new(focus_on_connect)),
+            session_start: Arc::new(Mutex::new(connected.then(Instant::now))),
+            command_queue: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    #[inline]
+    pub fn state(&self) -> &watch::Sender<GameState> {
+        &self.state
+    }
+
+    /// If a pseudoconsole to the game is open
+    #[inline]
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn auto_relaunch(&self) -> bool {
+        self.auto_relaunch.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn set_auto_relaunch(&self, enabled: bool) {
+        self.auto_relaunch.store(enabled, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn focus_on_connect(&self) -> bool {
+        self.focus_on_connect.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    pub fn set_focus_on_connect(&self, enabled: bool) {
+        self.focus_on_connect.store(enabled, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub async fn start_session(&self) {
+        *self.session_start.lock().await = Some(Instant::now());
+    }
+
+    #[inline]
+    pub async fn end_session(&self) {
+        *self.session_start.lock().await = None;
+    }
+
+    /// How long the game has been running, `None` when it is not
+    #[inline]
+    pub async fn session_elapsed(&self) -> Option<Duration> {
+        self.session_start.lock().await.map(|start| start.elapsed())
+    }
+
+    /// Game console commands held back while H2M is busy loading
+    #[inline]
+    pub fn command_queue(&self) -> &Mutex<VecDeque<QueuedCommand>> {
+        &self.command_queue
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    cli::SessionCmd,
+    commands::handler::{CommandContext, CommandHandle, Message},
+    utils::{display::DisplaySessionStats, names::Names},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::Sender;
+
+const SESSIONS_DIR: &str = "sessions";
+const SESSION_FILE_PREFIX: &str = "session_";
+
+const KILL_PREFIX: &str = "K;";
+const ROUND_WIN_PREFIX: &str = "W;";
+const INIT_GAME_PREFIX: &str = "InitGame:";
+
+/// Match events recognized in the game log lines H2M echoes to its console, along with the connection
+/// summaries of the ping monitor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MatchEvent {
+    Kill {
+        attacker: String,
+        victim: String,
+        weapon: String,
+    },
+    RoundWin {
+        team: String,
+    },
+    MapChange {
+        map: String,
+        game_type: String,
+    },
+    /// Probes of the server being played on since the previous summary, see
+    /// [`crate::utils::ping_monitor`]
+    Connection {
+        server: String,
+        sent: u16,
+        lost: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        average_ms: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        worst_ms: Option<u32>,
+        /// Set when pings spiked or probes went unanswered
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lag: Option<LagSide>,
+    },
+}
+
+/// Where a bad connection most likely comes from, found by probing another server at the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LagSide {
+    /// Other servers answered normally
+    Server,
+    /// Other servers were slow or unreachable too
+    Local,
+    /// No other server could be probed
+    Unknown,
+}
+
+impl MatchEvent {
+    /// Lines use the games_mp.log formats:  
+    /// `K;victim_guid;victim_num;victim_team;victim_name;attacker_guid;attacker_num;attacker_team;attacker_name;weapon;...`  
+    /// `W;team;...`  
+    /// `InitGame: \g_gametype\war\mapname\mp_rust\...`
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let line = line
+            .split_once(' ')
+            .filter(|(timestamp, _)| {
+                !timestamp.is_empty() && timestamp.chars().all(|c| c.is_ascii_digit() || c == ':')
+            })
+            .map_or(line, |(_, rest)| rest.trim_start());
+
+        if let Some(kill) = line.strip_prefix(KILL_PREFIX) {
+            let fields = kill.split(';').collect::<Vec<_>>();
+            if fields.len() < 9 {
+                return None;
+            }
+            return Some(MatchEvent::Kill {
+                victim: fields[3].to_string(),
+                attacker: fields[7].to_string(),
+                weapon: fields[8].to_string(),
+            });
+        }
+        if let Some(win) = line.strip_prefix(ROUND_WIN_PREFIX) {
+            let team = win.split(';').next().filter(|team| !team.is_empty())?;
+            return Some(MatchEvent::RoundWin {
+                team: team.to_string(),
+            });
+        }
+        if let Some(init) = line.strip_prefix(INIT_GAME_PREFIX) {
+            let mut pairs = init.trim().trim_start_matches('\\').split('\\');
+            let (mut map, mut game_type) = (None, None);
+            while let (Some(key), Some(value)) = (pairs.next(), pairs.next()) {
+                match key {
+                    "mapname" => map = Some(value),
+                    "g_gametype" => game_type = Some(value),
+                    _ => (),
+                }
+            }
+            return Some(MatchEvent::MapChange {
+                map: map?.to_string(),
+                game_type: game_type.unwrap_or_default().to_string(),
+            });
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRecord {
+    /// Seconds since unix epoch
+    pub time: u64,
+    #[serde(flatten)]
+    pub event: MatchEvent,
+}
+
+/// Match events of the current play session, each event is also appended to the session file
+pub struct SessionLog {
+    pub started: SystemTime,
+    pub records: Vec<MatchRecord>,
+    writer: Option<mpsc::Sender<MatchRecord>>,
+}
+
+impl SessionLog {
+    pub fn new(local_dir: Option<&Path>, msg_sender: &Arc<Sender<Message>>) -> Self {
+        let started = SystemTime::now();
+        let secs = started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        SessionLog {
+            started,
+            records: Vec::new(),
+            writer: local_dir.map(|dir| {
+                spawn_writer(
+                    dir.join(SESSIONS_DIR)
+                        .join(format!("{SESSION_FILE_PREFIX}{secs}.jsonl")),
+                    Arc::clone(msg_sender),
+                )
+            }),
+        }
+    }
+
+    pub fn record(&mut self, event: MatchEvent) {
+        let record = MatchRecord {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            event,
+        };
+        if let Some(ref writer) = self.writer {
+            if writer.send(record.clone()).is_err() {
+                self.writer = None;
+            }
+        }
+        self.records.push(record);
+    }
+}
+
+/// Appends records to `path` from a thread of its own, the file is created along with the first record
+/// and kept open until the [`SessionLog`] is dropped
+fn spawn_writer(path: PathBuf, msg_sender: Arc<Sender<Message>>) -> mpsc::Sender<MatchRecord> {
+    let (record_tx, record_rx) = mpsc::channel::<MatchRecord>();
+    std::thread::spawn(move || {
+        let mut file = None;
+        for record in record_rx {
+            if let Err(err) = append_record(&mut file, &path, &record) {
+                let _ = msg_sender.blocking_send(Message::Err(format!(
+                    "Failed to save match events of this session, {err}"
+                )));
+                break;
+            }
+        }
+    });
+    record_tx
+}
+
+fn append_record(
+    file: &mut Option<BufWriter<File>>,
+    path: &Path,
+    record: &MatchRecord,
+) -> io::Result<()> {
+    let writer = match file {
+        Some(writer) => writer,
+        None => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let opened = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            file.insert(BufWriter::new(opened))
+        }
+    };
+    serde_json::to_writer(&mut *writer, record)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Summed up [`MatchEvent::Connection`] records
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    pub sent: u32,
+    pub lost: u32,
+    pub average_ms: Option<u32>,
+    pub worst_ms: Option<u32>,
+    /// `(time, server, side)` of every summary with lag, in order
+    pub lag: Vec<(u64, String, LagSide)>,
+}
+
+pub struct SessionStats {
+    pub duration: std::time::Duration,
+    pub maps: Vec<String>,
+    pub kills: usize,
+    /// Sorted by count, descending
+    pub top_players: Vec<(String, usize)>,
+    /// Sorted by count, descending
+    pub top_weapons: Vec<(String, usize)>,
+    pub round_wins: Vec<(String, usize)>,
+    pub connection: ConnectionStats,
+}
+
+const TOP_COUNT: usize = 5;
+
+fn sorted_counts(counts: HashMap<&str, usize>, take: usize) -> Vec<(String, usize)> {
+    let mut counts = counts
+        .into_iter()
+        .map(|(key, count)| (key.to_string(), count))
+        .collect::<Vec<_>>();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(take);
+    counts
+}
+
+impl From<&SessionLog> for SessionStats {
+    fn from(log: &SessionLog) -> Self {
+        let mut maps = Vec::new();
+        let mut kills = 0;
+        let (mut players, mut weapons, mut wins) = (HashMap::new(), HashMap::new(), HashMap::new());
+        let mut connection = ConnectionStats::default();
+        // sum of every answered ping, for the average
+        let mut answered = (0_u64, 0_u64);
+        for record in log.records.iter() {
+            match record.event {
+                MatchEvent::Kill {
+                    ref attacker,
+                    ref weapon,
+                    ..
+                } => {
+                    kills += 1;
+                    *players.entry(attacker.as_str()).or_insert(0) += 1;
+                    *weapons.entry(weapon.as_str()).or_insert(0) += 1;
+                }
+                MatchEvent::RoundWin { ref team } => *wins.entry(team.as_str()).or_insert(0) += 1,
+                MatchEvent::MapChange { ref map, .. } => maps.push(map.clone()),
+                MatchEvent::Connection {
+                    ref server,
+                    sent,
+                    lost,
+                    average_ms,
+                    worst_ms,
+                    lag,
+                } => {
+                    connection.sent += u32::from(sent);
+                    connection.lost += u32::from(lost);
+                    if let Some(average) = average_ms {
+                        let replies = u64::from(sent.saturating_sub(lost));
+                        answered.0 += u64::from(average) * replies;
+                        answered.1 += replies;
+                    }
+                    connection.worst_ms = connection.worst_ms.max(worst_ms);
+                    if let Some(side) = lag {
+                        connection.lag.push((record.time, server.clone(), side));
+                    }
+                }
+            }
+        }
+        SessionStats {
+            duration: log.started.elapsed().unwrap_or_default(),
+            maps,
+            kills,
+            top_players: sorted_counts(players, TOP_COUNT),
+            top_weapons: sorted_counts(weapons, TOP_COUNT),
+            round_wins: sorted_counts(wins, usize::MAX),
+            connection: ConnectionStats {
+                average_ms: (answered.1 > 0).then(|| (answered.0 / answered.1) as u32),
+                ..connection
+            },
+        }
+    }
+}
+
+pub async fn session(context: &CommandContext, option: SessionCmd) -> CommandHandle {
+    match option {
+        SessionCmd::Stats => {
+            let session_arc = context.session_log();
+            let session = session_arc.lock().await;
+            let names = Names::from(&context.config().display);
+            println!(
+                "{}",
+                DisplaySessionStats(&SessionStats::from(&*session), names)
+            );
+        }
+    }
+    CommandHandle::Processed
+}
@@ -0,0 +1,144 @@
+use crate::{
+    commands::{
+        filter::{try_get_info, Request, Sourced},
+        handler::{AppDetails, CommandContext, CommandHandle, GameDetails},
+        launch_h2m::h2m_running,
+    },
+    parse_hostname,
+    utils::{http::HttpFetch, input::line::clear_terminal, json_data::GetInfo},
+};
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+use tracing::error;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub struct GameStatus {
+    pub running: bool,
+    pub attached: bool,
+    pub log_attached: bool,
+    pub session: Option<Duration>,
+    pub queued: usize,
+    pub server: Option<ServerStatus>,
+    pub cache_age: Option<Duration>,
+}
+
+pub struct ServerStatus {
+    pub host_name: String,
+    pub socket_addr: Option<SocketAddr>,
+    pub region: Option<[char; 2]>,
+    pub info: Option<GetInfo>,
+}
+
+/// Compact status line printed after the screen is cleared
+pub struct StatusHeader {
+    pub running: bool,
+    pub attached: bool,
+    pub log_attached: bool,
+    pub cache_age: Option<Duration>,
+}
+
+impl StatusHeader {
+    pub async fn read(context: &mut CommandContext) -> Self {
+        StatusHeader {
+            running: h2m_running(),
+            attached: console_attached(context).await,
+            log_attached: context.console().log_attached(),
+            cache_age: SystemTime::now()
+                .duration_since(context.cache().lock().await.created)
+                .ok(),
+        }
+    }
+}
+
+async fn console_attached(context: &mut CommandContext) -> bool {
+    context.check_h2m_connection().await.is_ok() && context.game_control().connected()
+}
+
+pub async fn clear(context: &mut CommandContext) -> CommandHandle {
+    if let Err(err) = clear_terminal(&mut std::io::stdout()) {
+        error!("{err}");
+        return CommandHandle::Processed;
+    }
+    println!("{}", StatusHeader::read(context).await);
+    CommandHandle::Processed
+}
+
+/// Overview printed once startup is complete
+pub struct StartupPanel<'a> {
+    pub app: &'a AppDetails,
+    pub game: &'a GameDetails,
+    pub header: StatusHeader,
+    pub cached_servers: usize,
+}
+
+pub async fn print_startup_panel(context: &mut CommandContext) {
+    let header = StatusHeader::read(context).await;
+    let cached_servers = context.cache().lock().await.book.len();
+    if crossterm::cursor::position().is_ok_and(|pos| pos != (0, 0)) {
+        println!();
+    }
+    println!(
+        "{}",
+        StartupPanel {
+            app: context.app(),
+            game: context.game(),
+            header,
+            cached_servers,
+        }
+    );
+}
+
+async fn probe_server(http: &dyn HttpFetch, socket_addr: SocketAddr) -> Option<GetInfo> {
+    try_get_info(
+        Request::New(Sourced::HmwCached(socket_addr)),
+        http,
+        Some(PROBE_TIMEOUT),
+    )
+    .await
+    .ok()?
+    .info
+}
+
+pub async fn status(context: &mut CommandContext) -> CommandHandle {
+    let attached = console_attached(context).await;
+
+    let (server, cache_age) = {
+        let cache_arc = context.cache().clone();
+        let cache = cache_arc.lock().await;
+        let server = cache.book.history_entry(1).map(|socket_addr| ServerStatus {
+            host_name: cache
+                .book
+                .host_name(socket_addr)
+                .map_or_else(|| socket_addr.to_string(), |name| parse_hostname(name)),
+            socket_addr: Some(socket_addr),
+            region: cache.ip_to_region.get(&socket_addr.ip()).copied(),
+            info: None,
+        });
+        (server, SystemTime::now().duration_since(cache.created).ok())
+    };
+
+    let server = match server {
+        Some(mut server) => {
+            if let Some(addr) = server.socket_addr {
+                server.info = probe_server(&*context.http(), addr).await;
+            }
+            Some(server)
+        }
+        None => None,
+    };
+
+    let status = GameStatus {
+        running: h2m_running(),
+        attached,
+        log_attached: context.console().log_attached(),
+        session: context.game_control().session_elapsed().await,
+        queued: context.game_control().command_queue().lock().await.len(),
+        server,
+        cache_age,
+    };
+    println!("{status}");
+    CommandHandle::Processed
+}
@@ -0,0 +1,85 @@
+use crate::{
+    cli::TasksCmd,
+    commands::handler::{CommandContext, CommandHandle},
+    utils::display::DisplayTasks,
+};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+pub struct RunningTask {
+    pub id: usize,
+    pub name: &'static str,
+    pub started: Instant,
+    cancel: CancellationToken,
+}
+
+/// Background tasks spawned by the main loop, listed until they finish or are cancelled
+#[derive(Default)]
+pub struct TaskList {
+    next_id: usize,
+    running: Vec<RunningTask>,
+}
+
+impl TaskList {
+    /// Lists a task that was just spawned, returns the id it is cancelled by
+    pub fn insert(&mut self, name: &'static str, cancel: CancellationToken) -> usize {
+        self.next_id += 1;
+        self.running.push(RunningTask {
+            id: self.next_id,
+            name,
+            started: Instant::now(),
+            cancel,
+        });
+        self.next_id
+    }
+
+    pub fn finish(&mut self, id: usize) {
+        self.running.retain(|task| task.id != id);
+    }
+
+    /// Triggers the token of task `id` and stops listing it, returns its name if it was running
+    pub fn cancel(&mut self, id: usize) -> Option<&'static str> {
+        let i = self.running.iter().position(|task| task.id == id)?;
+        let task = self.running.remove(i);
+        task.cancel.cancel();
+        Some(task.name)
+    }
+
+    /// Triggers the token of every task, returns how many were running
+    pub fn cancel_all(&mut self) -> usize {
+        let count = self.running.len();
+        self.running.drain(..).for_each(|task| task.cancel.cancel());
+        count
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.running.iter().any(|task| task.name == name)
+    }
+
+    #[inline]
+    pub fn running(&self) -> &[RunningTask] {
+        &self.running
+    }
+}
+
+pub fn tasks(context: &mut CommandContext, command: Option<TasksCmd>) -> CommandHandle {
+    match command.unwrap_or(TasksCmd::List) {
+        TasksCmd::List if context.tasks().running().is_empty() => {
+            info!("No tasks are running in the background")
+        }
+        TasksCmd::List => {
+            println!("{}", DisplayTasks(context.tasks().running()));
+            info!("Use `tasks cancel <ID>` to stop one of them");
+        }
+        TasksCmd::Cancel { id } => match context.tasks_mut().cancel(id) {
+            Some(name) => warn!("Cancelled '{name}'"),
+            None => error!("No background task has the id {id}, see `tasks`"),
+        },
+    }
+    CommandHandle::Processed
+}
@@ -0,0 +1,180 @@
+use crate::{
+    commands::{filter::FAVORITES, handler::Message},
+    utils::{
+        display::DisplayTrim,
+        input::{
+            line::{EventLoop, InputEventHook, InputHook, LineCallback, LineReader},
+            style::{GREY, WHITE},
+        },
+    },
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{cell::RefCell, net::SocketAddr, ops::Range};
+use tokio::sync::oneshot;
+
+/// Servers listed on either side of the cut
+pub const TRIM_MARGIN: usize = 8;
+
+pub struct TrimCandidate {
+    pub addr: SocketAddr,
+    pub name: String,
+    pub players: u8,
+    pub max_players: u8,
+    pub keep: bool,
+}
+
+/// Sent by `filter` when more servers match than `limit`, answered once the user is done picking
+pub struct TrimRequest {
+    pub limit: usize,
+    /// Servers ranked above the candidates, always kept
+    pub kept_above: usize,
+    /// Servers ranked below the candidates, always left out
+    pub left_out_below: usize,
+    /// Most players first
+    pub candidates: Vec<TrimCandidate>,
+    pub decision: oneshot::Sender<TrimDecision>,
+}
+
+impl TrimRequest {
+    pub fn kept(&self) -> usize {
+        self.kept_above + self.candidates.iter().filter(|server| server.keep).count()
+    }
+}
+
+pub enum TrimDecision {
+    /// Whether to keep each candidate, in the order they were sent
+    Keep(Vec<bool>),
+    /// Leave favourites.json as it is
+    Cancel,
+}
+
+/// Ranks of the servers listed when `len` servers match and `limit` are written, ranked by most players
+pub fn trim_window(len: usize, limit: usize) -> Range<usize> {
+    limit.saturating_sub(TRIM_MARGIN)..len.min(limit + TRIM_MARGIN)
+}
+
+/// Parses numbers and ranges like `1,4 7-9` into indexes of a list of `len` entries numbered from 1
+pub fn parse_picks(input: &str, len: usize) -> Result<Vec<usize>, String> {
+    let mut picks = Vec::new();
+    for word in input.split([' ', ',']).filter(|word| !word.is_empty()) {
+        let parse = |num: &str| {
+            num.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|num| (1..=len).contains(num))
+                .ok_or_else(|| format!("'{word}' is not a number from 1 to {len}"))
+        };
+        let (start, end) = match word.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(word)?, parse(word)?),
+        };
+        if start > end {
+            return Err(format!("'{word}' counts down, use {end}-{start}"));
+        }
+        for num in start..=end {
+            if !picks.contains(&(num - 1)) {
+                picks.push(num - 1);
+            }
+        }
+    }
+    Ok(picks)
+}
+
+fn prompt(request: &TrimRequest) -> String {
+    format!(
+        "Numbers to keep or leave out, enter writes {GREY}[{}/{} kept]{WHITE}",
+        request.kept(),
+        request.limit
+    )
+}
+
+/// Lists the candidates of `request` and toggles the ones entered by number until enter is pressed on an
+/// empty line, ctrl + c leaves favourites.json unchanged
+pub fn trim_hook(request: TrimRequest) -> InputHook {
+    let listing = DisplayTrim(&request).to_string();
+    let first_prompt = prompt(&request);
+    let init: Box<LineCallback> = Box::new(move |handle| {
+        handle.print_background_msg(Message::Str(listing.clone()))?;
+        handle.set_prompt(first_prompt.clone());
+        handle.set_completion(false);
+        Ok(())
+    });
+
+    let request = RefCell::new(Some(request));
+    let input_hook: Box<InputEventHook> = Box::new(move |handle, event| {
+        let mut request_guard = request.borrow_mut();
+        let Some(request) = request_guard.as_mut() else {
+            return Ok((EventLoop::Continue, true));
+        };
+        let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        else {
+            return Ok((EventLoop::Continue, false));
+        };
+        let finish = |handle: &mut LineReader| {
+            handle.restore_prompt();
+            handle.set_completion(true);
+        };
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                if !handle.line.input().is_empty() {
+                    handle.ctrl_c_line()?;
+                    return Ok((EventLoop::Continue, false));
+                }
+                handle.ctrl_c_line()?;
+                finish(handle);
+                let request = request_guard.take().expect("checked above");
+                let _ = request.decision.send(TrimDecision::Cancel);
+                handle
+                    .print_background_msg(Message::Warn(format!("{FAVORITES} was not changed")))?;
+                Ok((EventLoop::Continue, true))
+            }
+            KeyCode::Char(c) => {
+                handle.insert_char(c);
+                Ok((EventLoop::Continue, false))
+            }
+            KeyCode::Backspace => {
+                handle.remove_char()?;
+                Ok((EventLoop::Continue, false))
+            }
+            KeyCode::Enter => {
+                let input = handle.line.take_input();
+                handle.new_line()?;
+                if input.trim().is_empty() {
+                    let over = request.kept().saturating_sub(request.limit);
+                    if over > 0 {
+                        handle.print_background_msg(Message::Err(format!(
+                            "Only {} servers fit, leave out {over} more",
+                            request.limit
+                        )))?;
+                        return Ok((EventLoop::Continue, false));
+                    }
+                    finish(handle);
+                    let request = request_guard.take().expect("checked above");
+                    let keep = request
+                        .candidates
+                        .iter()
+                        .map(|server| server.keep)
+                        .collect();
+                    let _ = request.decision.send(TrimDecision::Keep(keep));
+                    return Ok((EventLoop::Continue, true));
+                }
+                match parse_picks(&input, request.candidates.len()) {
+                    Ok(picks) => {
+                        for i in picks {
+                            request.candidates[i].keep = !request.candidates[i].keep;
+                        }
+                        handle
+                            .print_background_msg(Message::Str(DisplayTrim(request).to_string()))?;
+                        handle.set_prompt(prompt(request));
+                    }
+                    Err(err) => handle.print_background_msg(Message::Err(err))?,
+                }
+                Ok((EventLoop::Continue, false))
+            }
+            _ => Ok((EventLoop::Continue, false)),
+        }
+    });
+    InputHook::with_new_uid(Some(init), input_hook)
+}
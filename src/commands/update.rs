@@ -0,0 +1,273 @@
+use crate::{
+    commands::{
+        handler::{AppDetails, CommandContext, CommandHandle},
+        launch_h2m::h2m_running,
+    },
+    error::FetchError,
+    get_latest_version,
+    utils::{
+        display::{DisplayBytes, DisplayChangelog, DisplayError},
+        http::HttpFetch,
+        input::{
+            line::{EventLoop, InputEventHook, InputHook, LineCallback},
+            style::{GREEN, RED, WHITE, YELLOW},
+        },
+        json_data::{Release, ReleaseAsset},
+        markdown,
+    },
+};
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use sha2::{Digest, Sha256};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{error, info};
+
+const RELEASE_API_URL: &str = "https://api.github.com/repos/WardLordRuby/match_wire/releases/tags/";
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+/// Extension given to the replaced executable, it can not be deleted while it is still running
+const OLD_EXE_EXTENSION: &str = "old";
+/// Upper bound on the buffer reserved up front, the size listed in the release is not trusted beyond it
+const MAX_PREALLOCATION: u64 = 64 * 1024 * 1024;
+
+fn asset_name() -> String {
+    format!("{}.exe", env!("CARGO_PKG_NAME"))
+}
+
+/// Removes the executable left behind by a previous `update`
+pub fn remove_replaced_exe() {
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+    let _ = std::fs::remove_file(exe.with_extension(OLD_EXE_EXTENSION));
+}
+
+pub async fn fetch_release(http: &dyn HttpFetch, version: &str) -> Result<Release, FetchError> {
+    let url = format!("{RELEASE_API_URL}v{}", version.trim_start_matches('v'));
+    http.get(&url, None)
+        .await?
+        .error_for_status()?
+        .json::<Release>()
+}
+
+/// Release notes of `version` as plain text, `None` if the release has no notes
+pub async fn fetch_changelog(
+    http: &dyn HttpFetch,
+    version: &str,
+) -> Result<Option<String>, FetchError> {
+    let release = fetch_release(http, version).await?;
+    Ok(release
+        .body
+        .map(|body| markdown::to_plain_text(&body))
+        .filter(|notes| !notes.is_empty()))
+}
+
+async fn fetch_asset(
+    http: &dyn HttpFetch,
+    version: &str,
+) -> Result<Option<ReleaseAsset>, FetchError> {
+    let name = asset_name();
+    let release = fetch_release(http, version).await?;
+    Ok(release.assets.into_iter().find(|asset| asset.name == name))
+}
+
+/// Downloads `asset` and checks it against the size and digest published with the release, and against
+/// `exe_sha256` from the version check when it lists one, a release with neither is rejected. The executable is streamed in chunks so it is
+/// fetched with reqwest directly rather than [`HttpFetch`]
+async fn download(
+    client: &reqwest::Client,
+    asset: &ReleaseAsset,
+    exe_sha256: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let mut response = client
+        .get(&asset.browser_download_url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|err| err.to_string())?;
+    let mut bytes = Vec::with_capacity(asset.size.min(MAX_PREALLOCATION) as usize);
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        if (bytes.len() + chunk.len()) as u64 > asset.size {
+            return Err(format!(
+                "Download exceeded the expected {} bytes",
+                asset.size
+            ));
+        }
+        hasher.update(&chunk);
+        bytes.extend_from_slice(&chunk);
+    }
+    if bytes.len() as u64 != asset.size {
+        return Err(format!(
+            "Downloaded {} bytes, expected {}",
+            bytes.len(),
+            asset.size
+        ));
+    }
+    let release_digest = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"));
+    if release_digest.is_none() && exe_sha256.is_none() {
+        return Err(String::from(
+            "Release does not publish a checksum, refusing to install an unverified executable",
+        ));
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    for expected in [exe_sha256, release_digest].into_iter().flatten() {
+        if !hash.eq_ignore_ascii_case(expected) {
+            return Err(format!(
+                "Checksum mismatch, expected {expected} found {hash}"
+            ));
+        }
+    }
+    Ok(bytes)
+}
+
+/// Windows allows a running executable to be renamed but not replaced, so the running exe is moved aside
+/// and the new one written in its place
+fn swap_exe(exe: &Path, bytes: &[u8]) -> io::Result<()> {
+    let new = exe.with_extension("new");
+    let old = exe.with_extension(OLD_EXE_EXTENSION);
+    std::fs::write(&new, bytes)?;
+    let _ = std::fs::remove_file(&old);
+    std::fs::rename(exe, &old)?;
+    if let Err(err) = std::fs::rename(&new, exe) {
+        let _ = std::fs::rename(&old, exe);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn restart_hook(exe: PathBuf, game_running: bool) -> CommandHandle {
+    if game_running {
+        println!("{RED}Restarting will also close H2M-mod{WHITE}");
+    }
+    let init: Box<LineCallback> = Box::new(|handle| {
+        handle.set_prompt(format!(
+            "Press ({YELLOW}y{WHITE}) to restart now, any other key to keep using this version"
+        ));
+        Ok(())
+    });
+    let input_hook: Box<InputEventHook> = Box::new(move |handle, event| match event {
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('y'),
+            ..
+        }) => {
+            match std::process::Command::new("cmd")
+                .args(["/C", "start", ""])
+                .arg(&exe)
+                .spawn()
+            {
+                Ok(_) => Ok((EventLoop::Break, true)),
+                Err(err) => {
+                    handle.restore_prompt();
+                    error!("Failed to restart, {err}");
+                    Ok((EventLoop::Continue, true))
+                }
+            }
+        }
+        _ => {
+            handle.restore_prompt();
+            info!("The update will be used the next time MatchWire starts");
+            Ok((EventLoop::Continue, true))
+        }
+    });
+    CommandHandle::InsertHook(InputHook::with_new_uid(Some(init), input_hook))
+}
+
+pub async fn update(context: &mut CommandContext) -> CommandHandle {
+    let channel = context.config().update.channel;
+    let http = context.http();
+    let app = match get_latest_version(&*http, channel).await {
+        Ok(app) => app,
+        Err(err) => {
+            error!("{}", DisplayError(&err));
+            return CommandHandle::Processed;
+        }
+    };
+    let Some(latest) = app.ver_latest.clone().filter(|_| app.update_available()) else {
+        info!(
+            "{} v{} is up to date on the {channel} channel",
+            env!("CARGO_PKG_NAME"),
+            app.ver_curr
+        );
+        return CommandHandle::Processed;
+    };
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            error!("Could not find the running executable, {err}");
+            return CommandHandle::Processed;
+        }
+    };
+    let asset = match fetch_asset(&*http, &latest).await {
+        Ok(Some(asset)) => asset,
+        Ok(None) => {
+            error!("Release v{latest} does not include {}", asset_name());
+            return CommandHandle::Processed;
+        }
+        Err(err) => {
+            error!("Failed to find release v{latest}, {err}");
+            return CommandHandle::Processed;
+        }
+    };
+    println!("Downloading v{latest} ({})...", DisplayBytes(asset.size));
+    let client = context.network().client();
+    let bytes = match download(&client, &asset, app.exe_sha256.as_deref()).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            error!("Update failed, {err}");
+            return CommandHandle::Processed;
+        }
+    };
+    if let Err(err) = swap_exe(&exe, &bytes) {
+        error!("Failed to replace {}, {err}", exe.display());
+        return CommandHandle::Processed;
+    }
+    println!("{GREEN}Updated to v{latest}{WHITE}");
+    let game_running = context.check_h2m_connection().await.is_ok() && h2m_running();
+    restart_hook(exe, game_running)
+}
+
+/// Prints the release notes of `version`, or of the latest version on the configured channel. Notes
+/// fetched by the startup version check are reused
+pub async fn changelog(context: &CommandContext, version: Option<String>) -> CommandHandle {
+    let app = context.app();
+    let http = context.http();
+    let version = match version {
+        Some(version) => version.trim_start_matches('v').to_string(),
+        None => {
+            if let (Some(latest), Some(notes)) = (&app.ver_latest, &app.changelog) {
+                println!("{}", DisplayChangelog(latest, notes));
+                return CommandHandle::Processed;
+            }
+            match get_latest_version(&*http, context.config().update.channel).await {
+                Ok(AppDetails {
+                    ver_latest: Some(latest),
+                    ..
+                }) => latest,
+                Ok(_) => {
+                    error!("The version server did not list a latest version");
+                    return CommandHandle::Processed;
+                }
+                Err(err) => {
+                    error!("{}", DisplayError(&err));
+                    return CommandHandle::Processed;
+                }
+            }
+        }
+    };
+    match fetch_changelog(&*http, &version).await {
+        Ok(Some(notes)) => println!("{}", DisplayChangelog(&version, &notes)),
+        Ok(None) => info!("Release v{version} has no release notes"),
+        Err(err) if err.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            error!("No release found for v{version}")
+        }
+        Err(err) => error!("Failed to fetch release notes for v{version}, {err}"),
+    }
+    CommandHandle::Processed
+}
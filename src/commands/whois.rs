@@ -0,0 +1,138 @@
+use crate::{
+    cli::Source,
+    commands::{
+        handler::{CommandContext, CommandHandle},
+        reconnect::HISTORY_MAX,
+    },
+    strip_color_codes,
+    utils::{
+        caching::Cache,
+        input::style::{GREY, WHITE},
+        server_book::PlayerSample,
+    },
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::{Duration, SystemTime},
+};
+use tracing::info;
+
+/// Matches past this are only counted
+const WHOIS_MAX: usize = 10;
+
+pub struct ServerRecord {
+    pub server: SocketAddr,
+    /// Names with color codes removed, the current one last
+    pub aliases: Vec<String>,
+    pub region: Option<[char; 2]>,
+    pub last_seen: Option<SystemTime>,
+    pub ping: Option<Duration>,
+    /// Players when the server last answered, not requested again by `whois`
+    pub players: Option<PlayerSample>,
+    /// Entry number in `reconnect --history`
+    pub history: Option<usize>,
+    pub listed_by: Vec<Source>,
+}
+
+impl ServerRecord {
+    /// `None` if `server` is neither in the server book nor listed by a master server
+    fn new(cache: &Cache, server: SocketAddr) -> Option<Self> {
+        let entry = cache.book.get(server);
+        let listed_by = [
+            (Source::HmwMaster, &cache.hmw),
+            (Source::Iw4Master, &cache.iw4m),
+        ]
+        .into_iter()
+        .filter(|(_, listed)| {
+            listed
+                .get(&server.ip())
+                .is_some_and(|ports| ports.contains(&server.port()))
+        })
+        .map(|(source, _)| source)
+        .collect::<Vec<_>>();
+        if entry.is_none() && listed_by.is_empty() {
+            return None;
+        }
+        Some(ServerRecord {
+            server,
+            aliases: entry
+                .map(|entry| {
+                    entry
+                        .aliases
+                        .iter()
+                        .map(|alias| strip_color_codes(alias))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            region: cache.ip_to_region.get(&server.ip()).copied(),
+            last_seen: entry.map(|entry| entry.last_seen),
+            ping: cache.fresh_ping(server),
+            players: entry.and_then(|entry| entry.players),
+            history: cache
+                .book
+                .history()
+                .iter()
+                .rev()
+                .position(|&joined| joined == server)
+                .map(|i| i + 1),
+            listed_by,
+        })
+    }
+}
+
+/// Servers `query` refers to: a history entry, an `IP:PORT`, every known port of an `IP`, or servers
+/// with a name containing it
+pub(crate) fn lookup(cache: &Cache, query: &str) -> Vec<SocketAddr> {
+    if let Ok(num @ 1..=HISTORY_MAX) = query.parse::<usize>() {
+        return cache.book.history_entry(num).into_iter().collect();
+    }
+    if let Ok(server) = query.parse::<SocketAddr>() {
+        return vec![server];
+    }
+    if let Ok(ip) = query.parse::<IpAddr>() {
+        let listed = [&cache.hmw, &cache.iw4m]
+            .into_iter()
+            .filter_map(|listed| listed.get(&ip))
+            .flatten()
+            .map(|&port| SocketAddr::new(ip, port));
+        let mut servers = cache
+            .book
+            .servers()
+            .keys()
+            .copied()
+            .filter(|server| server.ip() == ip)
+            .chain(listed)
+            .collect::<Vec<_>>();
+        servers.sort_unstable();
+        servers.dedup();
+        return servers;
+    }
+    cache.book.search(&query.to_lowercase())
+}
+
+/// Every name a server was seen under, its region, the master servers listing it and its players when
+/// it last answered, all without asking the server
+pub async fn whois(context: &mut CommandContext, query: String) -> CommandHandle {
+    let records = {
+        let cache_arc = context.cache().clone();
+        let cache = cache_arc.lock().await;
+        lookup(&cache, query.trim())
+            .into_iter()
+            .filter_map(|server| ServerRecord::new(&cache, server))
+            .collect::<Vec<_>>()
+    };
+    if records.is_empty() {
+        info!("Nothing is known about '{query}', use `info` to query the server directly");
+        return CommandHandle::Processed;
+    }
+    for record in records.iter().take(WHOIS_MAX) {
+        println!("{record}");
+    }
+    if records.len() > WHOIS_MAX {
+        println!(
+            "{GREY}{} more servers match, be more specific{WHITE}",
+            records.len() - WHOIS_MAX
+        );
+    }
+    CommandHandle::Processed
+}
@@ -0,0 +1,151 @@
+use crate::utils::lang::{text, tr};
+use reqwest::StatusCode;
+use std::{borrow::Cow, io, net::IpAddr, path::PathBuf, time::Duration};
+
+/// Failures shared across commands. Each kind names what failed, [`Error::hint`] says what the user
+/// can try next
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{}", tr("error.network", &[("what", what), ("source", source)]))]
+    Network {
+        what: &'static str,
+        #[source]
+        source: FetchError,
+    },
+    #[error("{}", tr("error.master_api", &[("master", master), ("source", source)]))]
+    MasterApi {
+        master: &'static str,
+        #[source]
+        source: FetchError,
+    },
+    #[error("{}", tr("error.geo_api", &[("ip", ip), ("reason", reason)]))]
+    GeoApi {
+        ip: IpAddr,
+        cause: GeoCause,
+        reason: Cow<'static, str>,
+    },
+    #[error("{0}")]
+    Cache(Cow<'static, str>),
+    #[error("{}", tr(
+        "error.io",
+        &[("action", action), ("path", &path.display()), ("source", source)]
+    ))]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{}", text("error.game_running"))]
+    GameRunning,
+    #[error("{}", tr("error.pty", &[("message", .0)]))]
+    Pty(String),
+    #[error("{0}")]
+    GameDir(Cow<'static, str>),
+    #[error("{0}")]
+    GameClient(Cow<'static, str>),
+    #[error("{}", tr("error.unsupported", &[("feature", .0)]))]
+    Unsupported(&'static str),
+    #[error("{}", tr("error.wine", &[("message", .0)]))]
+    Wine(String),
+}
+
+impl Error {
+    pub fn io(action: &'static str, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            action,
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// What the user can try next, in the current language
+    pub fn hint(&self) -> Option<String> {
+        let key = match self {
+            Error::Network { .. } => "hint.network",
+            Error::MasterApi { .. } => "hint.master_api",
+            Error::GeoApi { .. } => "hint.geo_api",
+            Error::Cache(_) => "hint.cache",
+            Error::Io { .. } => "hint.io",
+            Error::GameRunning => return None,
+            Error::Pty(_) => "hint.pty",
+            Error::GameDir(_) => "hint.game_dir",
+            Error::GameClient(_) => "hint.game_client",
+            Error::Unsupported(_) => "hint.unsupported",
+            Error::Wine(_) => "hint.wine",
+        };
+        Some(tr(key, &[("app", &env!("CARGO_PKG_NAME"))]))
+    }
+}
+
+/// Why a location lookup failed, `filter` sums up failures by it instead of reporting each server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoCause {
+    Timeout,
+    /// The API answered 429, lookups were skipped after it did or the bundled key's lookups for the day
+    /// are used up
+    RateLimited,
+    /// The reply was not the expected json
+    Unreadable,
+    /// The API answered with an error message instead of a location
+    Rejected,
+    Cancelled,
+    Other,
+}
+
+impl GeoCause {
+    pub fn of(err: &FetchError) -> Self {
+        match err {
+            FetchError::Request(err) if err.is_timeout() => GeoCause::Timeout,
+            _ if err.status() == Some(StatusCode::TOO_MANY_REQUESTS) => GeoCause::RateLimited,
+            FetchError::Decode(_) => GeoCause::Unreadable,
+            _ => GeoCause::Other,
+        }
+    }
+}
+
+/// Failure of a request made through [`HttpFetch`](crate::utils::http::HttpFetch)
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("{}", tr("fetch.status", &[("status", .0)]))]
+    Status(StatusCode),
+    #[error("{}", tr("fetch.decode", &[("source", .0)]))]
+    Decode(#[from] serde_json::Error),
+    /// No answer came before the `filter` deadline
+    #[error("{}", text("fetch.deadline"))]
+    Deadline,
+    /// Retries included, no answer came within the given time
+    #[error("{}", tr("fetch.timed_out", &[("secs", &.0.as_secs())]))]
+    TimedOut(Duration),
+    /// Not sent, the app was started with `--offline`
+    #[error("{}", text("fetch.offline"))]
+    Offline,
+    #[error("{}", tr("fetch.missing_fixture", &[("path", &.0.display())]))]
+    MissingFixture(PathBuf),
+    #[error("{}", tr("fetch.fixture", &[("path", &path.display()), ("source", source)]))]
+    Fixture {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl FetchError {
+    /// Strips the url from network errors, use before displaying errors of urls that hold api keys
+    pub fn without_url(self) -> Self {
+        match self {
+            FetchError::Request(err) => FetchError::Request(err.without_url()),
+            err => err,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            FetchError::Request(err) => err.status(),
+            FetchError::Status(status) => Some(*status),
+            _ => None,
+        }
+    }
+}
@@ -1,37 +1,92 @@
 pub mod cli;
 pub mod command_scheme;
+pub mod error;
 pub mod location_api_key;
 pub mod commands {
+    pub mod chat;
+    pub mod dashboard;
+    pub mod diff;
+    pub mod doctor;
+    pub mod favorites;
     pub mod filter;
+    pub mod filter_wizard;
+    pub mod friends;
     pub mod handler;
+    pub mod import;
+    pub mod journal;
     pub mod launch_h2m;
+    pub mod logs;
+    pub mod query;
+    pub mod rcon;
     pub mod reconnect;
+    pub mod report;
+    pub mod script;
+    pub mod search;
+    pub mod server_info;
+    pub mod services;
+    pub mod session;
+    pub mod status;
+    pub mod tasks;
+    pub mod trim;
+    pub mod update;
+    pub mod whois;
 }
 pub mod utils {
     pub mod input {
         pub mod completion;
         pub mod line;
+        pub mod prompt;
         pub mod style;
     }
     pub mod caching;
+    pub mod config;
     pub mod display;
+    pub mod dpapi;
+    pub mod events;
+    pub mod game_dir;
+    pub mod game_state;
+    pub mod geo_key;
+    pub mod getstatus;
+    pub mod http;
     pub mod json_data;
+    pub mod lang;
+    pub mod lenient;
+    pub mod markdown;
+    pub mod names;
+    pub mod perf;
+    pub mod ping_monitor;
+    pub mod players2;
+    pub mod population;
+    pub mod profile;
+    pub mod progress;
+    pub mod regions;
+    pub mod reliability;
+    pub mod server_book;
+    pub mod strict;
     pub mod subscriber;
+    pub mod suggest;
+    pub mod toast;
+    #[cfg(not(feature = "windows-console"))]
+    pub mod unsupported;
+    pub mod window;
+    #[cfg(all(target_os = "linux", not(feature = "windows-console")))]
+    pub mod wine;
 }
 
-use clap::CommandFactory;
-use cli::UserCommand;
-use commands::{handler::AppDetails, launch_h2m::get_exe_version};
-use crossterm::cursor;
+use cli::Channel;
+use commands::{handler::AppDetails, launch_h2m::get_exe_version, update::fetch_changelog};
+use error::{Error, FetchError};
 use sha2::{Digest, Sha256};
 use std::{
     borrow::Cow,
     collections::HashSet,
     io::{self, BufRead, BufReader, Read},
     path::{Path, PathBuf},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use tracing::debug;
 use utils::{
+    http::HttpFetch,
     input::style::{GREEN, RED, WHITE},
     json_data::{HmwManifest, Version},
 };
@@ -40,30 +95,27 @@ pub const LOG_ONLY: &str = "log_only";
 
 pub const VERSION_URL: &str =
     "https://gist.githubusercontent.com/WardLordRuby/a7b22837f3e9561f087a4b8a7ac2a905/raw/";
+/// Longest the version check and its release notes may take each, retries included, so a slow host can
+/// not keep the check loading
+const VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
 const HMW_LATEST_URL: &str = "https://price.horizonmw.org/manifest.json";
 const MOD_FILES_MODULE_NAME: &str = "mod";
 
 pub const H2M_MAX_CLIENT_NUM: i64 = 18;
 pub const H2M_MAX_TEAM_SIZE: i64 = 9;
 
-pub const REQUIRED_FILES: [&str; 5] = [
-    "h1_mp64_ship.exe",
-    "h2m-mod",
-    "players2",
-    "h2m-mod.exe",
-    "h2m-revived.exe",
+pub const REQUIRED_FILES: [&str; 2] = ["h1_mp64_ship.exe", "players2"];
+
+/// `(executable, mod_files_dir)` of every supported game client, listed in order of launch preference
+pub const GAME_EXECUTABLES: [(&str, &str); 3] = [
+    ("h2m-mod.exe", "h2m-mod"),
+    ("h2m-revived.exe", "h2m-mod"),
+    ("hmw-mod.exe", "hmw-mod"),
 ];
 
 pub const LOCAL_DATA: &str = "LOCALAPPDATA";
 pub const CACHED_DATA: &str = "cache.json";
 
-#[macro_export]
-macro_rules! new_io_error {
-    ($kind:expr, $msg:expr) => {
-        Err(std::io::Error::new($kind, $msg))
-    };
-}
-
 #[macro_export]
 macro_rules! break_if {
     ($expr:expr, is_some_err) => {
@@ -81,32 +133,81 @@ macro_rules! break_if {
     };
 }
 
-pub async fn get_latest_version() -> reqwest::Result<AppDetails> {
-    let client = reqwest::Client::new();
-    let version = client
-        .get(VERSION_URL)
-        .timeout(Duration::from_secs(6))
-        .send()
-        .await?
-        .json::<Version>()
-        .await?;
-    Ok(AppDetails::from(version))
+pub async fn get_latest_version(
+    http: &dyn HttpFetch,
+    channel: Channel,
+) -> Result<AppDetails, Error> {
+    let version = tokio::time::timeout(VERSION_CHECK_TIMEOUT, async {
+        http.get(VERSION_URL, None).await?.json::<Version>()
+    })
+    .await
+    .unwrap_or(Err(FetchError::TimedOut(VERSION_CHECK_TIMEOUT)))
+    .map_err(|source| Error::Network {
+        what: "Version check",
+        source,
+    })?;
+    let mut app = AppDetails::new(version.into_channel(channel), channel);
+    if let Some(latest) = app.ver_latest.as_deref().filter(|_| app.update_available()) {
+        match tokio::time::timeout(VERSION_CHECK_TIMEOUT, fetch_changelog(http, latest))
+            .await
+            .unwrap_or(Err(FetchError::TimedOut(VERSION_CHECK_TIMEOUT)))
+        {
+            Ok(notes) => app.changelog = notes,
+            Err(err) => {
+                debug!(name: LOG_ONLY, "Could not fetch release notes for v{latest}: {err}")
+            }
+        }
+    }
+    Ok(app)
 }
 
-pub async fn get_latest_hmw_hash() -> reqwest::Result<Option<String>> {
-    let client = reqwest::Client::new();
-    let mut latest = client
-        .get(HMW_LATEST_URL)
-        .timeout(Duration::from_secs(6))
-        .send()
-        .await?
-        .json::<HmwManifest>()
-        .await?;
+/// Compares dotted version numbers, a pre-release (`1.2.0-beta.1`) is older than its release
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    fn split(version: &str) -> (&str, Option<&str>) {
+        let version = version.trim().trim_start_matches('v');
+        match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        }
+    }
+    fn cmp_parts(a: &str, b: &str) -> std::cmp::Ordering {
+        let (mut a, mut b) = (a.split('.'), b.split('.'));
+        loop {
+            let ord = match (a.next(), b.next()) {
+                (None, None) => return std::cmp::Ordering::Equal,
+                (Some(_), None) => std::cmp::Ordering::Greater,
+                (None, Some(_)) => std::cmp::Ordering::Less,
+                (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                },
+            };
+            if ord.is_ne() {
+                return ord;
+            }
+        }
+    }
+    let ((core_a, pre_a), (core_b, pre_b)) = (split(a), split(b));
+    cmp_parts(core_a, core_b).then_with(|| match (pre_a, pre_b) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (Some(a), Some(b)) => cmp_parts(a, b),
+    })
+}
+
+pub async fn get_latest_hmw_hash(http: &dyn HttpFetch) -> Result<Option<String>, Error> {
+    let mut latest = async { http.get(HMW_LATEST_URL, None).await?.json::<HmwManifest>() }
+        .await
+        .map_err(|source| Error::Network {
+            what: "HMW manifest",
+            source,
+        })?;
     Ok(latest
         .modules
         .iter_mut()
         .find(|module| module.name == MOD_FILES_MODULE_NAME)
-        .and_then(|module| module.files_with_hashes.remove(REQUIRED_FILES[3])))
+        .and_then(|module| module.files_with_hashes.remove(GAME_EXECUTABLES[0].0)))
 }
 
 #[derive(Debug)]
@@ -124,7 +225,7 @@ pub enum OperationResult<'a> {
 /// `Operation::All` and `Operation::Any` map to `OperationResult::bool(_result_)`  
 /// `Operation::Count` maps to `OperationResult::Count((_num_found_, _HashSet<_&input_list_>))`  
 /// when matching you will always have to `_ => unreachable()` for the return type you will never get
-pub fn does_dir_contain<'a, T>(
+pub async fn does_dir_contain<'a, T>(
     dir: &Path,
     operation: Operation,
     list: &'a [T],
@@ -132,10 +233,15 @@ pub fn does_dir_contain<'a, T>(
 where
     T: std::borrow::Borrow<str> + std::cmp::Eq + std::hash::Hash,
 {
-    let entries = std::fs::read_dir(dir)?;
-    let file_names = entries
-        .filter_map(|entry| Some(entry.ok()?.file_name()))
-        .collect::<Vec<_>>();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    let mut file_names = Vec::new();
+    loop {
+        match entries.next_entry().await {
+            Ok(Some(entry)) => file_names.push(entry.file_name()),
+            Ok(None) => break,
+            Err(_) => continue,
+        }
+    }
     let str_names = file_names
         .iter()
         .filter_map(|f| f.to_str())
@@ -166,43 +272,83 @@ where
     }
 }
 
-pub fn contains_required_files(exe_dir: &Path) -> Result<PathBuf, &'static str> {
-    match does_dir_contain(exe_dir, Operation::Count, &REQUIRED_FILES)
-        .expect("Failed to read contents of current dir")
+/// Returns the file names of all supported game clients found in `exe_dir` that also have their
+/// mod files installed, in order of `GAME_EXECUTABLES`
+pub async fn find_game_executables(exe_dir: &Path) -> io::Result<Vec<&'static str>> {
+    let search = GAME_EXECUTABLES
+        .iter()
+        .flat_map(|&(exe, mod_dir)| [exe, mod_dir])
+        .collect::<Vec<_>>();
+    match does_dir_contain(exe_dir, Operation::Count, &search).await? {
+        OperationResult::Count((_, files)) => Ok(GAME_EXECUTABLES
+            .iter()
+            .filter(|(exe, mod_dir)| files.contains(exe) && files.contains(mod_dir))
+            .map(|&(exe, _)| exe)
+            .collect()),
+        _ => unreachable!(),
+    }
+}
+
+/// Resolves a user given executable name (the `.exe` extension is optional) to a supported game client
+/// found in `exe_dir`
+pub async fn select_game_executable(exe_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    let name = name.trim().to_lowercase();
+    let name = if name.ends_with(".exe") {
+        name
+    } else {
+        format!("{name}.exe")
+    };
+    if !GAME_EXECUTABLES.iter().any(|&(exe, _)| exe == name) {
+        return Err(Error::GameClient(Cow::Owned(format!(
+            "'{name}' is not a supported game client, expected one of: {}",
+            GAME_EXECUTABLES.map(|(exe, _)| exe).join(", ")
+        ))));
+    }
+    let found = find_game_executables(exe_dir)
+        .await
+        .map_err(|err| Error::io("read", exe_dir, err))?;
+    if !found.contains(&name.as_str()) {
+        return Err(Error::GameClient(Cow::Owned(if found.is_empty() {
+            format!("'{name}' or its mod files were not found in the game directory")
+        } else {
+            format!(
+                "'{name}' or its mod files were not found in the game directory, found: {}",
+                found.join(", ")
+            )
+        })));
+    }
+    Ok(exe_dir.join(name))
+}
+
+pub async fn contains_required_files(exe_dir: &Path) -> Result<PathBuf, Error> {
+    let files = match does_dir_contain(exe_dir, Operation::Count, &REQUIRED_FILES)
+        .await
+        .map_err(|err| Error::io("read", exe_dir, err))?
     {
-        OperationResult::Count((_, files)) => {
-            if !files.contains(REQUIRED_FILES[0]) {
-                return Err(concat!(
-                    "Move ",
-                    env!("CARGO_PKG_NAME"),
-                    ".exe into your 'Call of Duty Modern Warfare Remastered' directory",
-                ));
-            }
-            if !files.contains(REQUIRED_FILES[1]) {
-                return Err(
-                    "H2M mod files not found, H2M mod files are available to download for free through the Horizon MW launcher\n\
-                    https://discord.com/invite/HorizonMW"
-                );
-            }
-            let found_game = if files.contains(REQUIRED_FILES[3]) {
-                REQUIRED_FILES[3]
-            } else if files.contains(REQUIRED_FILES[4]) {
-                REQUIRED_FILES[4]
-            } else {
-                return Err(
-                    "h2m-mod.exe not found, H2M mod files are available to download for free through the Horizon MW launcher\n\
-                    https://discord.com/invite/HorizonMW"
-                );
-            };
-            if !files.contains(REQUIRED_FILES[2]) {
-                std::fs::create_dir(exe_dir.join(REQUIRED_FILES[2]))
-                    .expect("Failed to create players2 folder");
-                println!("{GREEN}players2 folder is missing, a new one was created{WHITE}");
-            }
-            Ok(exe_dir.join(found_game))
-        }
+        OperationResult::Count((_, files)) => files,
         _ => unreachable!(),
+    };
+    if !files.contains(REQUIRED_FILES[0]) {
+        return Err(Error::GameDir(Cow::Borrowed(
+            "h1_mp64_ship.exe was not found in the current directory",
+        )));
+    }
+    let found_game = find_game_executables(exe_dir)
+        .await
+        .map_err(|err| Error::io("read", exe_dir, err))?
+        .into_iter()
+        .next()
+        .ok_or(Error::GameClient(Cow::Borrowed(
+            "No supported game client found (h2m-mod.exe, h2m-revived.exe or hmw-mod.exe along with its mod files)",
+        )))?;
+    if !files.contains(REQUIRED_FILES[1]) {
+        let players2 = exe_dir.join(REQUIRED_FILES[1]);
+        tokio::fs::create_dir(&players2)
+            .await
+            .map_err(|err| Error::io("create", &players2, err))?;
+        println!("{GREEN}players2 folder is missing, a new one was created{WHITE}");
     }
+    Ok(exe_dir.join(found_game))
 }
 
 fn hash_file_hex(path: &Path) -> io::Result<String> {
@@ -256,21 +402,21 @@ pub async fn await_user_for_end() {
 }
 
 /// Validates local/app_dir exists and modifies input if valid
-pub fn check_app_dir_exists(local: &mut PathBuf) -> io::Result<()> {
+pub async fn check_app_dir_exists(local: &mut PathBuf) -> io::Result<()> {
     const PREV_NAME: &str = "h2m_favorites";
     let app_name = env!("CARGO_PKG_NAME");
     let local_dir = local.clone();
 
-    match does_dir_contain(local, Operation::Count, &[app_name, PREV_NAME]) {
+    match does_dir_contain(local, Operation::Count, &[app_name, PREV_NAME]).await {
         Ok(OperationResult::Count((_, files))) => {
             local.push(app_name);
 
             if !files.contains(app_name) {
-                std::fs::create_dir(&local)?;
+                tokio::fs::create_dir(&local).await?;
             }
 
             if files.contains(PREV_NAME) {
-                std::fs::remove_dir_all(local_dir.join(PREV_NAME))?;
+                tokio::fs::remove_dir_all(local_dir.join(PREV_NAME)).await?;
             }
             Ok(())
         }
@@ -284,8 +430,16 @@ pub fn lowercase_vec(vec: &[String]) -> Vec<String> {
 }
 
 pub fn parse_hostname(name: &str) -> String {
+    let mut host_name = String::with_capacity(name.len());
+    parse_hostname_into(name, &mut host_name);
+    host_name
+}
+
+/// Same as [`parse_hostname`] but writes into `buf` after clearing it, so one allocation can be
+/// reused across many host names
+pub fn parse_hostname_into(name: &str, buf: &mut String) {
     const COLOR_ESCAPE_CODE: char = '^';
-    let mut host_name = String::new();
+    buf.clear();
     let mut chars = name.chars().peekable();
     while let Some(c) = chars.next() {
         if c == COLOR_ESCAPE_CODE {
@@ -293,10 +447,54 @@ pub fn parse_hostname(name: &str) -> String {
                 chars.next();
             }
         } else {
-            host_name.push(c.to_ascii_lowercase());
+            buf.push(c.to_ascii_lowercase());
         }
     }
-    host_name
+}
+
+/// Removes cod color codes (`^1`, `^:`, ...) while preserving case
+pub fn strip_color_codes(input: &str) -> String {
+    const COLOR_ESCAPE_CODE: char = '^';
+    let mut stripped = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == COLOR_ESCAPE_CODE {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Splits `time` into its UTC `(year, month, day)` and `(hour, minute, second)`
+pub fn utc_date_time(time: SystemTime) -> ((i64, u32, u32), (u32, u32, u32)) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |dur| dur.as_secs() as i64);
+    let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400) as u32);
+
+    // civil from days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        (year, month, day),
+        (secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60),
+    )
+}
+
+/// `YYYY-MM-DD` of `time` in UTC
+pub fn utc_date_string(time: SystemTime) -> String {
+    let ((year, month, day), _) = utc_date_time(time);
+    format!("{year:04}-{month:02}-{day:02}")
 }
 
 pub fn strip_ansi_sequences(input: &str) -> Cow<'_, str> {
@@ -310,20 +508,10 @@ pub fn strip_ansi_private_modes(input: &str) -> Cow<'_, str> {
     re.replace_all(input, "")
 }
 
-pub fn print_help() {
-    if cursor::position().unwrap() != (0, 0) {
-        println!()
-    }
-    UserCommand::command()
-        .print_help()
-        .expect("Failed to print help");
-    println!();
-}
-
 pub async fn splash_screen() -> io::Result<()> {
     #[cfg(not(debug_assertions))]
     {
-        use crossterm::{execute, terminal};
+        use crossterm::{cursor, execute, terminal};
         use std::io::Write;
 
         // font: 4Max - patorjk.com
@@ -1,35 +1,72 @@
+use clap::Parser;
 use crossterm::{cursor, event::EventStream, execute, terminal};
 use match_wire::{
     await_user_for_end, break_if, check_app_dir_exists,
+    cli::{AppArgs, CacheCmd},
     commands::{
+        friends::presence_loop,
         handler::{
-            listener_routine, try_execute_command, AppDetails, CommandContextBuilder,
-            CommandHandle, GameDetails,
+            cache_task, listener_routine, try_execute_command, AppDetails, AppVersionResult,
+            BackgroundTask, CommandContext, CommandContextBuilder, CommandHandle, GameDetails,
+            HmwHashResult, LaunchResult, Message, CACHE_TASK,
         },
-        launch_h2m::{launch_h2m_pseudo, LaunchError},
+        journal::journal_loop,
+        launch_h2m::{launch_h2m_pseudo, PTY},
+        reconnect::connect_recs,
+        script::Script,
+        status::print_startup_panel,
+        tasks::TaskList,
+        trim::trim_hook,
+        update::remove_replaced_exe,
     },
-    get_latest_hmw_hash, get_latest_version, print_help, splash_screen,
+    error::Error,
+    get_latest_hmw_hash, get_latest_version, splash_screen,
     utils::{
-        caching::{build_cache, read_cache, write_cache, Cache},
-        display::DisplayPanic,
+        caching::{read_cache, write_cache, Cache},
+        config::Settings,
+        display::{DisplayCountOf, DisplayError, DisplayPanic, SingularPlural},
+        events::{forward_game_state, notification_loop, AppEvent},
+        game_dir::GAME_DIR_KEY,
+        geo_key::{init_geo_key, save_geo_keys},
+        http::{new_http, offline_http, Http, NetworkClient},
         input::{
             completion::CommandScheme,
-            line::{EventLoop, LineReader},
-            style::{RED, WHITE},
+            line::{is_ctrl_c, EventLoop, LineReader},
+            prompt::{render_prompt, ConsoleState},
+            style::{init_color, GREY, PROMPT_END, RED, WHITE},
+        },
+        lang::init_language,
+        lenient::enable_debug_parse,
+        perf,
+        ping_monitor::ping_monitor_loop,
+        strict::{self, ProblemKind},
+        subscriber::{errors_logged, init_subscriber},
+        window::{
+            apply_tray, hide_console, init_tray_commands, load_placement, remove_tray,
+            save_placement, title_loop,
         },
-        subscriber::init_subscriber,
     },
-    CACHED_DATA, LOCAL_DATA, LOG_ONLY,
+    LOCAL_DATA, LOG_ONLY,
+};
+use std::{io, path::PathBuf, sync::Arc};
+use tokio::{
+    sync::mpsc,
+    task::{JoinError, JoinHandle, JoinSet},
 };
-use std::{io, path::PathBuf, sync::atomic::Ordering};
-use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_stream::StreamExt;
 use tracing::{error, info, instrument, warn};
-use winptyrs::PTY;
 
 const COMPLETION: CommandScheme = CommandScheme::init();
+const PROMPT_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
 fn main() {
+    let app_args = AppArgs::parse();
+    init_color(!app_args.no_color);
+    let strict = app_args.strict;
+    if strict {
+        strict::enable(app_args.max_geo_failures);
+    }
+
     let prev = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
         error!(name: "PANIC", "{}", DisplayPanic(info));
@@ -51,59 +88,186 @@ fn main() {
         .expect("Failed to create single-threaded runtime");
 
     main_runtime.block_on(async {
-        let startup_data = match app_startup().await {
+        let network = NetworkClient::default();
+        let http = if app_args.offline {
+            offline_http()
+        } else {
+            new_http(
+                app_args.offline_fixtures.clone(),
+                app_args.record.clone(),
+                network.clone(),
+            )
+        };
+        let overrides = app_args.setting_overrides();
+        let startup_data = match app_startup(
+            overrides,
+            app_args.game_dir.clone(),
+            http,
+            &network,
+            app_args.offline,
+        )
+        .await
+        {
             Ok(data) => data,
             Err(err) => {
                 eprintln!("{RED}{err}{WHITE}");
-                await_user_for_end().await;
+                if strict {
+                    strict::report(ProblemKind::Error, &err.to_string());
+                } else {
+                    await_user_for_end().await;
+                }
                 return;
             }
         };
 
+        if app_args.debug_parse {
+            match startup_data.local_dir {
+                Some(ref dir) => enable_debug_parse(dir),
+                None => error!("--debug-parse needs the local data directory, nothing will be saved"),
+            }
+        }
+
         startup_data.splash_task.await.unwrap().unwrap();
 
         let (message_tx, mut message_rx) = mpsc::channel(50);
 
         let mut command_context = CommandContextBuilder::new()
             .cache(startup_data.cache)
-            .launch_res(startup_data.launch_task.await)
-            .app_ver_res(startup_data.version_task.await)
-            .hmw_hash_res(startup_data.hmw_hash_task.await)
+            .settings(startup_data.settings)
             .game_details(startup_data.game)
+            .http(startup_data.http)
+            .network(network)
             .msg_sender(message_tx)
             .local_dir(startup_data.local_dir)
             .build()
             .unwrap();
 
+        // network work started during startup finishes behind the prompt
+        let (startup_tx, mut startup_rx) = mpsc::channel(4);
+        let mut loading = Loading::default();
+        if let Some(version_task) = startup_data.version_task {
+            forward(version_task, &startup_tx, StartupEvent::Version);
+            loading.push(VERSION_CHECK);
+        }
+        if let Some(hmw_hash_task) = startup_data.hmw_hash_task {
+            forward(hmw_hash_task, &startup_tx, StartupEvent::HmwHash);
+            loading.push(HMW_MANIFEST);
+        }
+        if let Some(launch_task) = startup_data.launch_task {
+            forward(launch_task, &startup_tx, StartupEvent::Launch);
+            loading.push(GAME_LAUNCH);
+        }
+        drop(startup_tx);
+
+        // the tray menu runs its commands as if they were entered at the prompt
+        let (tray_tx, mut tray_rx) = mpsc::channel(4);
+        init_tray_commands(tray_tx);
+        apply_tray(&command_context.config().window);
+        if command_context.config().window.start_in_tray {
+            hide_console().unwrap_or_else(|err| warn!("{err}"));
+        }
+
         let (update_cache_tx, mut update_cache_rx) = mpsc::channel(20);
 
         tokio::spawn({
-            let cache_needs_update = command_context.cache_needs_update();
+            let cache = command_context.cache().clone();
             async move {
                 loop {
-                    if cache_needs_update.compare_exchange(true, false, Ordering::Acquire, Ordering::SeqCst).is_ok()
-                        && update_cache_tx.send(true).await.is_err() {
-                            break;
+                    if cache.take_changed() && update_cache_tx.send(true).await.is_err() {
+                        break;
                     }
                     tokio::time::sleep(tokio::time::Duration::from_secs(240)).await;
                 }
             }
         });
 
+        tokio::spawn(presence_loop(
+            command_context.friends(),
+            command_context.cache().clone(),
+            command_context.msg_sender(),
+            command_context.events(),
+            command_context.game_control().state().subscribe(),
+        ));
+
+        tokio::spawn(notification_loop(
+            command_context.events().subscribe(),
+            command_context.notifications(),
+        ));
+
+        tokio::spawn(forward_game_state(
+            command_context.game_control().state().subscribe(),
+            command_context.events(),
+        ));
+
+        tokio::spawn(title_loop(
+            command_context.game_control().state().subscribe(),
+            command_context.config_service().clone(),
+        ));
+
+        tokio::spawn(journal_loop(
+            command_context.game_control().state().subscribe(),
+            command_context.session_log(),
+            command_context.local_dir().map(PathBuf::from),
+        ));
+
+        tokio::spawn(ping_monitor_loop(
+            command_context.game_control().state().subscribe(),
+            command_context.cache().clone(),
+            command_context.config_service().clone(),
+            command_context.session_log(),
+            command_context.msg_sender(),
+        ));
+
         listener_routine(&mut command_context).await.unwrap_or_else(|err| warn!(name: LOG_ONLY, "{err}"));
 
+        #[cfg(feature = "windows-console")]
         let mut close_listener = tokio::signal::windows::ctrl_close().unwrap();
+        #[cfg(not(feature = "windows-console"))]
+        let mut close_listener = match_wire::utils::unsupported::CtrlClose;
 
-        print_help();
+        print_startup_panel(&mut command_context).await;
 
         execute!(term, cursor::Show).unwrap();
 
         let mut reader = EventStream::new();
         let mut line_handle = LineReader::new(String::new(), &mut term, &COMPLETION).unwrap();
+        refresh_completion(&mut line_handle, &command_context).await;
+        refresh_prompt(&mut line_handle, &command_context).await.unwrap();
 
         terminal::enable_raw_mode().unwrap();
 
+        let mut prompt_interval = tokio::time::interval(PROMPT_REFRESH_INTERVAL);
+        let mut events_rx = command_context.events().subscribe();
+
+        let mut background = JoinSet::new();
+
+        if startup_data.rebuild_cache {
+            let task = cache_task(&command_context, CacheCmd::Update).await;
+            run_in_background(&mut background, command_context.tasks_mut(), task);
+            loading.push(SERVER_CACHE);
+        }
+        line_handle.print_background_msg(loading.status()).unwrap();
+
+        let mut pending_script = app_args.run_script.and_then(|path| {
+            Script::read(path, app_args.stop_on_error)
+                .map_err(|err| error!("Could not read script, {err}"))
+                .ok()
+        });
+
         loop {
+            match pending_script.take() {
+                Some(script) => {
+                    if run_script(script, &mut command_context, &mut reader, &mut message_rx).await
+                        || strict
+                    {
+                        break;
+                    }
+                    refresh_completion(&mut line_handle, &command_context).await;
+                }
+                // `--strict` quits after the script, even one that could not be read
+                None if strict => break,
+                None => (),
+            }
             if line_handle.command_entered() {
                 break_if!(line_handle.clear_unwanted_inputs(&mut reader).await, is_err);
             }
@@ -115,6 +279,10 @@ fn main() {
                 biased;
 
                 _ = close_listener.recv() => {
+                    // closing the window leaves a few seconds to exit, enough to keep the latest history
+                    save_cache_if_changed(&command_context).await;
+                    save_stats(&command_context);
+                    save_window(&command_context);
                     info!(name: LOG_ONLY, "app shutdown");
                     terminal::disable_raw_mode().unwrap();
                     return;
@@ -123,6 +291,21 @@ fn main() {
                 Some(event_result) = reader.next() => {
                     match event_result {
                         Ok(event) => {
+                            if !background.is_empty()
+                                && is_ctrl_c(&event)
+                                && line_handle.line.input().is_empty()
+                                && line_handle.next_input_hook().is_none()
+                            {
+                                let cancelled = command_context.tasks_mut().cancel_all();
+                                background.abort_all();
+                                loading.done(SERVER_CACHE);
+                                break_if!(line_handle.print_background_msg(loading.status()), is_err);
+                                break_if!(line_handle.print_background_msg(Message::Warn(format!(
+                                    "Cancelled {cancelled} background {}",
+                                    SingularPlural(cancelled, "task", "tasks")
+                                ))), is_err);
+                                continue;
+                            }
                             match line_handle.process_input_event(event) {
                                 Ok(EventLoop::Continue) => (),
                                 Ok(EventLoop::Break) => break,
@@ -135,15 +318,37 @@ fn main() {
                                 },
                                 Ok(EventLoop::TryProcessCommand) => {
                                     let command_handle = match shellwords::split(line_handle.last_line()) {
-                                        Ok(user_args) => try_execute_command(user_args, &mut command_context).await,
+                                        Ok(user_args) => {
+                                            let cancel = command_context.new_cancel_token();
+                                            let command = try_execute_command(user_args, &mut command_context);
+                                            tokio::pin!(command);
+                                            loop {
+                                                tokio::select! {
+                                                    command_handle = &mut command => break command_handle,
+                                                    Some(Ok(event)) = reader.next() => {
+                                                        if is_ctrl_c(&event) {
+                                                            cancel.cancel();
+                                                            warn!("Command cancelled");
+                                                            break CommandHandle::Processed;
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
                                         Err(err) => {
                                             error!("{err}");
                                             continue;
                                         }
                                     };
+                                    refresh_completion(&mut line_handle, &command_context).await;
+                                    break_if!(refresh_prompt(&mut line_handle, &command_context).await, is_err);
                                     match command_handle {
                                         CommandHandle::Processed => (),
                                         CommandHandle::InsertHook(input_hook) => line_handle.register_input_hook(input_hook),
+                                        CommandHandle::Background(task) => {
+                                            run_in_background(&mut background, command_context.tasks_mut(), task)
+                                        }
+                                        CommandHandle::Script(script) => pending_script = Some(script),
                                         CommandHandle::Exit => break,
                                     }
                                 }
@@ -161,126 +366,518 @@ fn main() {
                 }
 
                 Some(msg) = message_rx.recv() => {
+                    // what is still loading is shown again once a task removes its own progress line
+                    let msg = match msg {
+                        Message::Progress(None) => loading.status(),
+                        Message::Trim(request) => {
+                            line_handle.register_input_hook(trim_hook(request));
+                            continue;
+                        }
+                        msg => msg,
+                    };
+                    break_if!(line_handle.print_background_msg(msg), is_err)
+                }
+
+                Some(event) = startup_rx.recv() => {
+                    break_if!(line_handle.move_to_beginning(line_handle.line_len()), is_err);
+                    loading.done(event.name());
+                    match event {
+                        StartupEvent::Version(res) => {
+                            command_context.set_app_version(res);
+                            if command_context.app().update_available() {
+                                println!("{}", command_context.app());
+                            }
+                        }
+                        StartupEvent::HmwHash(res) => command_context.set_hmw_hash(res),
+                        StartupEvent::Launch(res) => command_context.attach_launched(res).await,
+                    }
+                    break_if!(line_handle.print_background_msg(loading.status()), is_err)
+                }
+
+                Some(finished) = background.join_next() => {
+                    let msg = match finished {
+                        Ok((id, name, completed)) => {
+                            command_context.tasks_mut().finish(id);
+                            completed.then(|| {
+                                command_context.events().publish(AppEvent::TaskFinished(name));
+                                Message::Info(format!("'{name}' finished"))
+                            })
+                        }
+                        Err(err) if err.is_cancelled() => None,
+                        Err(err) => Some(Message::Err(err.to_string())),
+                    };
+                    if background.is_empty() {
+                        // a task that panicked is still listed
+                        command_context.tasks_mut().clear();
+                    }
+                    if !command_context.tasks().is_running(CACHE_TASK) {
+                        loading.done(SERVER_CACHE);
+                    }
+                    break_if!(line_handle.print_background_msg(loading.status()), is_err);
+                    let Some(msg) = msg else { continue };
+                    refresh_completion(&mut line_handle, &command_context).await;
                     break_if!(line_handle.print_background_msg(msg), is_err)
                 }
 
+                _ = prompt_interval.tick() => {
+                    match refresh_prompt(&mut line_handle, &command_context).await {
+                        Ok(true) => (),
+                        Ok(false) => line_handle.skip_render(),
+                        Err(err) => {
+                            error!("{err}");
+                            break;
+                        }
+                    }
+                }
+
+                Ok(event) = events_rx.recv() => match event {
+                    AppEvent::ConsoleLine(lines) if command_context.forward_logs() => {
+                        break_if!(line_handle.print_background_msg(Message::Str(lines.to_string())), is_err)
+                    }
+                    AppEvent::GameStateChanged(_) => {
+                        match refresh_prompt(&mut line_handle, &command_context).await {
+                            Ok(true) => (),
+                            Ok(false) => line_handle.skip_render(),
+                            Err(err) => {
+                                error!("{err}");
+                                break;
+                            }
+                        }
+                    }
+                    AppEvent::CacheUpdated => refresh_completion(&mut line_handle, &command_context).await,
+                    _ => (),
+                },
+
+                Some(line) = tray_rx.recv() => {
+                    break_if!(line_handle.print_background_msg(Message::Info(format!("Running '{line}' from the tray icon"))), is_err);
+                    // its own token, so `tasks cancel` can not stop the previous command along with it
+                    command_context.new_cancel_token();
+                    let command_handle = try_execute_command(vec![String::from(line)], &mut command_context).await;
+                    refresh_completion(&mut line_handle, &command_context).await;
+                    break_if!(refresh_prompt(&mut line_handle, &command_context).await, is_err);
+                    match command_handle {
+                        CommandHandle::Processed | CommandHandle::Script(_) => (),
+                        CommandHandle::InsertHook(input_hook) => line_handle.register_input_hook(input_hook),
+                        CommandHandle::Background(task) => {
+                            run_in_background(&mut background, command_context.tasks_mut(), task)
+                        }
+                        CommandHandle::Exit => break,
+                    }
+                }
+
                 Some(_) = update_cache_rx.recv() => {
                     write_cache(&command_context).await
-                        .unwrap_or_else(|err| error!("{err}"));
+                        .unwrap_or_else(|err| error!("{}", DisplayError(&err)));
                 }
             }
         }
-        if command_context.cache_needs_update().load(Ordering::SeqCst) {
-            if let Err(err) =  write_cache(&command_context).await {
-                error!(name: LOG_ONLY, "{err}");
-            }
-        }
+        save_cache_if_changed(&command_context).await;
+        save_stats(&command_context);
+        save_window(&command_context);
         info!(name: LOG_ONLY, "app shutdown");
         terminal::disable_raw_mode().unwrap();
     });
+    if strict {
+        std::process::exit(strict::finish());
+    }
+}
+
+async fn save_cache_if_changed(context: &CommandContext) {
+    if context.cache().is_changed() {
+        if let Err(err) = write_cache(context).await {
+            error!(name: LOG_ONLY, "{err}");
+        }
+    }
+}
+
+/// Saves command timings and today's location lookup counts
+fn save_stats(context: &CommandContext) {
+    if let Some(dir) = context.local_dir() {
+        if let Err(err) = perf::save(dir) {
+            error!(name: LOG_ONLY, "Could not save timings, {err}");
+        }
+        if let Err(err) = save_geo_keys(dir) {
+            error!(name: LOG_ONLY, "Could not save location lookup counts, {err}");
+        }
+    }
+}
+
+/// Removes the tray icon and saves where the console window is for the next run
+fn save_window(context: &CommandContext) {
+    remove_tray();
+    if let Some(dir) = context.local_dir() {
+        if let Err(err) = save_placement(dir, &context.config().window) {
+            error!(name: LOG_ONLY, "Could not save the window placement, {err}");
+        }
+    }
+}
+
+/// Re-renders the prompt template with the current game state, returns `false` if nothing changed
+async fn refresh_prompt(
+    line_handle: &mut LineReader<'_>,
+    context: &CommandContext,
+) -> io::Result<bool> {
+    let console = ConsoleState::new(
+        context.game_control().connected(),
+        context.console().log_attached(),
+    );
+    let game = context.game_control().state().borrow().clone();
+    line_handle.set_home_prompt(render_prompt(context.prompt_template(), console, &game))
+}
+
+/// Updates completion values that come from the cache
+async fn refresh_completion(line_handle: &mut LineReader<'_>, context: &CommandContext) {
+    let recs = connect_recs(&*context.cache().lock().await);
+    line_handle
+        .completion
+        .set_dynamic_recs("whois", recs.iter().cloned());
+    line_handle.completion.set_dynamic_recs("connect", recs);
+}
+
+/// Executes each line of `script` like it was entered at the prompt, background commands are awaited
+/// before moving on to the next line. Returns `true` if a line asked to quit
+async fn run_script(
+    script: Script,
+    context: &mut CommandContext,
+    reader: &mut EventStream,
+    message_rx: &mut mpsc::Receiver<Message>,
+) -> bool {
+    let path = script.path.display();
+    info!(
+        "Running {} from '{path}'",
+        DisplayCountOf(script.lines.len(), "command", "commands")
+    );
+    for (i, line) in script.lines.iter().enumerate() {
+        println!("{GREY}{PROMPT_END}{line}{WHITE}");
+        strict::set_line(i + 1, line);
+        let errors = errors_logged();
+        let cancel = context.new_cancel_token();
+        let command_handle = match shellwords::split(line) {
+            Ok(user_args) => {
+                let command = try_execute_command(user_args, context);
+                tokio::pin!(command);
+                loop {
+                    tokio::select! {
+                        command_handle = &mut command => break Some(command_handle),
+                        Some(Ok(event)) = reader.next() => {
+                            if is_ctrl_c(&event) {
+                                cancel.cancel();
+                                break None;
+                            }
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("{err}");
+                Some(CommandHandle::Processed)
+            }
+        };
+        match command_handle {
+            None => {
+                warn!("Script cancelled");
+                return false;
+            }
+            Some(CommandHandle::Processed) => (),
+            Some(CommandHandle::InsertHook(_)) => {
+                error!("'{line}' needs interactive input and can not be used in a script")
+            }
+            Some(CommandHandle::Script(_)) => error!("Scripts can not run other scripts"),
+            Some(CommandHandle::Exit) => return true,
+            Some(CommandHandle::Background(BackgroundTask {
+                cancel, mut task, ..
+            })) => loop {
+                tokio::select! {
+                    biased;
+
+                    () = &mut task => break,
+                    Some(msg) = message_rx.recv() => msg.print(),
+                    Some(Ok(event)) = reader.next() => {
+                        if is_ctrl_c(&event) {
+                            cancel.cancel();
+                            warn!("Script cancelled");
+                            return false;
+                        }
+                    }
+                }
+            },
+        }
+        while let Ok(msg) = message_rx.try_recv() {
+            msg.print();
+        }
+        if script.stop_on_error && errors_logged() > errors {
+            error!("Stopped running '{path}', line {} failed", i + 1);
+            return false;
+        }
+    }
+    info!("Finished running '{path}'");
+    false
+}
+
+const SERVER_CACHE: &str = "server cache";
+const VERSION_CHECK: &str = "version check";
+const HMW_MANIFEST: &str = "HMW manifest";
+const GAME_LAUNCH: &str = "game launch";
+
+/// Startup work that was still running when the prompt was shown
+enum StartupEvent {
+    Version(AppVersionResult),
+    HmwHash(HmwHashResult),
+    Launch(LaunchResult),
+}
+
+impl StartupEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            StartupEvent::Version(_) => VERSION_CHECK,
+            StartupEvent::HmwHash(_) => HMW_MANIFEST,
+            StartupEvent::Launch(_) => GAME_LAUNCH,
+        }
+    }
+}
+
+/// Sends the result of `task` to the main loop once it finishes
+fn forward<T: Send + 'static>(
+    task: JoinHandle<T>,
+    startup_tx: &mpsc::Sender<StartupEvent>,
+    event: fn(Result<T, JoinError>) -> StartupEvent,
+) {
+    let startup_tx = startup_tx.clone();
+    tokio::spawn(async move {
+        let _ = startup_tx.send(event(task.await)).await;
+    });
+}
+
+/// Names of the startup work still running, shown on the progress line until all of it is done
+#[derive(Default)]
+struct Loading(Vec<&'static str>);
+
+impl Loading {
+    fn push(&mut self, name: &'static str) {
+        self.0.push(name);
+    }
+
+    fn done(&mut self, name: &str) {
+        self.0.retain(|loading| *loading != name);
+    }
+
+    /// Progress line listing what is still loading, `None` once everything is done
+    fn status(&self) -> Message {
+        Message::Progress(
+            (!self.0.is_empty()).then(|| format!("{GREY}Loading {}...{WHITE}", self.0.join(", "))),
+        )
+    }
+}
+
+/// Spawns `task` and lists it in `tasks`, it is joined as `(id, name, completed)`. Once its token is
+/// triggered the task is dropped, even if it does not check the token itself
+fn run_in_background(
+    background: &mut JoinSet<(usize, &'static str, bool)>,
+    tasks: &mut TaskList,
+    BackgroundTask { name, cancel, task }: BackgroundTask,
+) {
+    let id = tasks.insert(name, cancel.clone());
+    background.spawn(async move {
+        let completed = tokio::select! {
+            () = task => true,
+            () = cancel.cancelled() => false,
+        };
+        (id, name, completed)
+    });
 }
 
 struct StartupData {
     cache: Cache,
+    /// The cache file could not be read, `cache` only holds what was recovered from it
+    rebuild_cache: bool,
+    settings: Settings,
     local_dir: Option<PathBuf>,
     game: GameDetails,
     splash_task: JoinHandle<io::Result<()>>,
-    launch_task: JoinHandle<Result<PTY, LaunchError>>,
-    version_task: JoinHandle<reqwest::Result<AppDetails>>,
-    hmw_hash_task: JoinHandle<reqwest::Result<Option<String>>>,
+    launch_task: Option<JoinHandle<Result<PTY, Error>>>,
+    /// `None` when started with `--offline`, as is `hmw_hash_task`
+    version_task: Option<JoinHandle<Result<AppDetails, Error>>>,
+    hmw_hash_task: Option<JoinHandle<Result<Option<String>, Error>>>,
+    http: Http,
 }
 
 #[instrument(level = "trace", skip_all)]
-async fn app_startup() -> Result<StartupData, String> {
-    let exe_dir =
-        std::env::current_dir().map_err(|err| format!("Failed to get current dir, {err:?}"))?;
+async fn app_startup(
+    overrides: Vec<(String, String)>,
+    game_dir: Option<PathBuf>,
+    http: Http,
+    network: &NetworkClient,
+    offline: bool,
+) -> Result<StartupData, String> {
+    let mut local_dir = None;
+    if let Some(path) = std::env::var_os(LOCAL_DATA) {
+        let mut dir = PathBuf::from(path);
+
+        if let Err(err) = check_app_dir_exists(&mut dir).await {
+            eprintln!("{RED}{err}{WHITE}");
+        } else {
+            local_dir = Some(dir);
+        }
+    } else {
+        eprintln!("{RED}Could not find %appdata%/local{WHITE}");
+    }
+
+    // settings decide if colors are used so they are resolved before anything is logged
+    let (mut settings, mut warnings) = Settings::load(local_dir.as_deref(), overrides);
+    if let Some(ref dir) = game_dir {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.clone());
+        if let Err(err) = settings.set(GAME_DIR_KEY, &dir.to_string_lossy()) {
+            warnings.push(format!("Ignoring --game-dir, {err}"));
+        }
+    }
+    let config = &settings.effective;
+    init_color(config.display.color);
+    warnings.extend(init_language(
+        local_dir.as_deref(),
+        &config.display.language,
+    ));
+    warnings.extend(init_geo_key(local_dir.as_deref()));
+    if let Some(ref dir) = local_dir {
+        init_subscriber(dir, &config.log).unwrap_or_else(|err| eprintln!("{RED}{err}{WHITE}"));
+        info!(name: LOG_ONLY, "App startup");
+        perf::load(dir);
+        load_placement(dir, &config.window);
+    } else {
+        #[cfg(debug_assertions)]
+        init_subscriber(std::path::Path::new(""), &config.log).unwrap();
+    }
+    for warning in warnings {
+        warn!("{warning}");
+    }
+    if let Err(err) = network.configure(&config.network) {
+        warn!("{err}");
+    }
 
     #[cfg(not(debug_assertions))]
     let game = {
-        let game_exe_path = match_wire::contains_required_files(&exe_dir).map_err(String::from)?;
+        use match_wire::{
+            commands::filter::FAVORITES,
+            utils::{
+                game_dir::{find_game, DirSource},
+                players2::{inspect, Players2Issue},
+            },
+        };
+
+        let (game_exe_path, source) = find_game(config.launch.game_dir.as_deref())
+            .await
+            .map_err(|err| DisplayError(&err).to_string())?;
+        if matches!(source, DirSource::Steam | DirSource::Installed) {
+            info!(
+                "Found the game in {}, use `--game-dir <PATH>` to pick another directory",
+                game_exe_path.parent().expect("has parent").display()
+            );
+        }
+        let issues = inspect(game_exe_path.parent().expect("has parent"));
+        for issue in issues.iter() {
+            warn!("{issue}");
+        }
+        if issues.iter().any(Players2Issue::repairable) {
+            warn!("Use command 'favorites repair' to fix {FAVORITES}");
+        }
         let (version, hash) = match_wire::exe_details(&game_exe_path);
         GameDetails::new(game_exe_path, version, hash)
     };
 
     #[cfg(debug_assertions)]
-    let game = GameDetails::default(&exe_dir);
+    let game = GameDetails::default(
+        &config
+            .launch
+            .game_dir
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default(),
+    );
+
+    if let (Some(_), Some(dir)) = (&game_dir, &local_dir) {
+        if let Err(err) = settings.write(dir) {
+            error!("Failed to save {GAME_DIR_KEY}, {err}");
+        }
+    }
 
-    let version_task = tokio::task::spawn(get_latest_version());
-    let hmw_hash_task = tokio::task::spawn(get_latest_hmw_hash());
+    remove_replaced_exe();
+
+    if offline {
+        info!("Started with --offline, no web requests are sent and the cache is not updated");
+    }
+    let version_task = (!offline).then(|| {
+        let http = Arc::clone(&http);
+        let channel = config.update.channel;
+        tokio::task::spawn(async move { get_latest_version(&*http, channel).await })
+    });
+    let hmw_hash_task = (!offline).then(|| {
+        let http = Arc::clone(&http);
+        tokio::task::spawn(async move { get_latest_hmw_hash(&*http).await })
+    });
 
     let splash_task = tokio::task::spawn(splash_screen());
 
-    let launch_task = tokio::task::spawn({
+    let launch_task = config.launch.auto_launch.then(|| {
         let game_exe_path = game.path.clone();
-        async move {
+        tokio::task::spawn(async move {
             // delay h2m doesn't block splash screen
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            launch_h2m_pseudo(&game_exe_path)
-        }
+            launch_h2m_pseudo(&game_exe_path, &[])
+        })
     });
 
-    let mut local_dir = None;
-    let mut connection_history = None;
+    let mut book = None;
     let mut region_cache = None;
-    if let Some(path) = std::env::var_os(LOCAL_DATA) {
-        let mut dir = PathBuf::from(path);
-
-        if let Err(err) = check_app_dir_exists(&mut dir) {
-            eprintln!("{RED}{err}{WHITE}");
-        } else {
-            init_subscriber(&dir).unwrap_or_else(|err| eprintln!("{RED}{err}{WHITE}"));
-            info!(name: LOG_ONLY, "App startup");
-            local_dir = Some(dir);
-            match read_cache(local_dir.as_ref().unwrap()).await {
-                Ok(cache) => {
-                    return Ok(StartupData {
-                        cache,
-                        local_dir,
-                        game,
-                        splash_task,
-                        launch_task,
-                        version_task,
-                        hmw_hash_task,
-                    })
-                }
-                Err(err) => {
-                    warn!("{err}");
-                    connection_history = err.connection_history;
-                    region_cache = err.region_cache;
-                }
-            }
-        }
-    } else {
-        eprintln!("{RED}Could not find %appdata%/local{WHITE}");
-
-        #[cfg(debug_assertions)]
-        init_subscriber(std::path::Path::new("")).unwrap();
-    }
-
-    let cache_file = build_cache(connection_history.as_deref(), region_cache.as_ref())
-        .await
-        .unwrap_or_else(|(err, backup)| {
-            error!("{err}");
-            backup
-        });
-
+    let mut country_cache = None;
+    let mut snapshot = None;
+    let mut populations = None;
+    let mut reliability = None;
     if let Some(ref dir) = local_dir {
-        match std::fs::File::create(dir.join(CACHED_DATA)) {
-            Ok(file) => {
-                if let Err(err) = serde_json::to_writer_pretty(file, &cache_file) {
-                    error!("{err}")
-                }
+        match read_cache(dir, offline).await {
+            Ok(cache) => {
+                return Ok(StartupData {
+                    cache,
+                    rebuild_cache: false,
+                    settings,
+                    local_dir,
+                    game,
+                    splash_task,
+                    launch_task,
+                    version_task,
+                    hmw_hash_task,
+                    http,
+                })
+            }
+            Err(err) => {
+                warn!("{err}");
+                book = err.book;
+                region_cache = err.region_cache;
+                country_cache = err.country_cache;
+                snapshot = err.snapshot;
+                populations = err.populations;
+                reliability = err.reliability;
             }
-            Err(err) => error!("{err}"),
         }
     }
+
+    // the master servers can take a while to answer, the cache is rebuilt once the prompt is up. Offline
+    // the recovered cache is used as it is
     Ok(StartupData {
-        cache: Cache::from(cache_file),
+        cache: Cache::from_backups(
+            book,
+            region_cache,
+            country_cache,
+            snapshot,
+            populations,
+            reliability,
+        ),
+        rebuild_cache: !offline,
+        settings,
         local_dir,
         game,
         splash_task,
         launch_task,
         version_task,
         hmw_hash_task,
+        http,
     })
 }
@@ -1,63 +1,158 @@
 use crate::{
-    cli::Source,
+    cli::{IpFamily, Source},
     commands::{
-        filter::{hmw_servers, iw4_servers, queue_info_requests, Server, Sourced},
-        handler::CommandContext,
-        launch_h2m::HostName,
-        reconnect::HISTORY_MAX,
+        filter::{hmw_servers, iw4_servers, queue_info_requests, Location, Server, Sourced},
+        handler::{CommandContext, Message},
     },
-    does_dir_contain, new_io_error,
+    error::Error,
     utils::{
+        display::DisplayError,
+        events::AppEvent,
+        getstatus::measure_ping,
+        http::{Http, Validator},
         input::style::{GREEN, WHITE},
-        json_data::{CacheFile, ServerCache},
+        json_data::{CacheFile, HostData, HostMove, MasterSnapshot, PingSample, ServerCache},
+        lenient::skipped_report,
+        population::PopulationHistory,
+        progress::Progress,
+        reliability::ReliabilityBook,
+        server_book::{PlayerSample, ServerBook, FORGET_AFTER},
     },
-    Operation, OperationResult, CACHED_DATA, LOG_ONLY,
+    CACHED_DATA, LOG_ONLY,
 };
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     io,
     net::{IpAddr, SocketAddr},
     path::Path,
+    sync::Arc,
     time::{Duration, SystemTime},
 };
 
-use tracing::{error, info, instrument, trace};
+use tokio::{
+    sync::{mpsc::Sender, Mutex},
+    task::JoinSet,
+};
+use tracing::{error, info, instrument, trace, warn};
+
+/// Pings older than this are measured again before they are shown
+pub const PING_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Moves kept for `diff`, older ones are dropped first
+pub const HOST_MOVES_MAX: usize = 50;
 
 pub struct Cache {
-    /// Key: host name with cod color codes
-    pub host_to_connect: HashMap<String, SocketAddr>,
+    pub book: ServerBook,
     pub ip_to_region: HashMap<IpAddr, [char; 2]>,
-    pub connection_history: Vec<HostName>,
+    /// Only used by regions narrowed down to countries, see [`crate::utils::regions`]
+    pub ip_to_country: HashMap<IpAddr, [char; 2]>,
     pub iw4m: HashMap<IpAddr, Vec<u16>>,
     pub hmw: HashMap<IpAddr, Vec<u16>>,
+    pub pings: HashMap<SocketAddr, PingSample>,
+    /// Addresses that host names listed in place of an IP last resolved to
+    pub host_ips: HashMap<Arc<str>, IpAddr>,
+    /// Host names that resolved to a new address since the last `diff`, oldest first
+    pub host_moves: Vec<HostMove>,
+    /// Arguments of the last `filter` command
+    pub last_filter: Option<String>,
+    /// Master server lists as of the last `diff`
+    pub snapshot: Option<MasterSnapshot>,
+    pub populations: PopulationHistory,
+    pub reliability: ReliabilityBook,
+    /// Not saved to the cache file
+    pub master_lists: MasterLists,
+    /// Servers listed by the last `search`, in the order they were numbered. Not saved to the cache file
+    pub last_search: Vec<SocketAddr>,
+    /// Location of this device's public address, looked up by the first `filter --near-me` of a session
+    /// and not saved since the address can change
+    pub own_location: Option<Location>,
     pub created: SystemTime,
 }
 
+/// Master server lists received this session along with their validators. The validator is sent with the
+/// next request, so a list that did not change is neither downloaded nor parsed again
+#[derive(Default)]
+pub struct MasterLists {
+    pub iw4m: SavedList<HostData>,
+    pub hmw: SavedList<String>,
+}
+
+/// A master server list with the validator it was received with
+pub type SavedList<T> = Option<(Validator, Vec<T>)>;
+
 impl From<CacheFile> for Cache {
     fn from(value: CacheFile) -> Self {
         Cache {
-            host_to_connect: value.cache.host_names,
+            book: value.book,
             ip_to_region: value.cache.regions,
-            connection_history: value.connection_history,
+            ip_to_country: value.cache.countries,
             iw4m: value.cache.iw4m,
             hmw: value.cache.hmw,
+            pings: value.cache.pings,
+            host_ips: value.cache.resolved,
+            host_moves: value.cache.moves,
+            last_filter: value.last_filter,
+            snapshot: value.snapshot,
+            populations: value.populations,
+            reliability: value.reliability,
+            master_lists: MasterLists::default(),
+            last_search: Vec::new(),
+            own_location: None,
             created: value.created,
         }
     }
 }
 
+impl Default for Cache {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+
 impl Cache {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Cache {
-            host_to_connect: HashMap::new(),
+            book: ServerBook::default(),
             ip_to_region: HashMap::new(),
-            connection_history: Vec::new(),
+            ip_to_country: HashMap::new(),
             iw4m: HashMap::new(),
             hmw: HashMap::new(),
+            pings: HashMap::new(),
+            host_ips: HashMap::new(),
+            host_moves: Vec::new(),
+            last_filter: None,
+            snapshot: None,
+            populations: PopulationHistory::default(),
+            reliability: ReliabilityBook::default(),
+            master_lists: MasterLists::default(),
+            last_search: Vec::new(),
+            own_location: None,
             created: SystemTime::now(),
         }
     }
 
+    /// Cache holding only what could be recovered from an unreadable cache file, used until a
+    /// rebuild started in the background replaces it
+    pub fn from_backups(
+        book: Option<ServerBook>,
+        regions: Option<HashMap<IpAddr, [char; 2]>>,
+        countries: Option<HashMap<IpAddr, [char; 2]>>,
+        snapshot: Option<MasterSnapshot>,
+        populations: Option<PopulationHistory>,
+        reliability: Option<ReliabilityBook>,
+    ) -> Self {
+        Cache {
+            book: book.unwrap_or_default(),
+            ip_to_region: regions.unwrap_or_default(),
+            ip_to_country: countries.unwrap_or_default(),
+            snapshot,
+            populations: populations.unwrap_or_default(),
+            reliability: reliability.unwrap_or_default(),
+            ..Cache::new()
+        }
+    }
+
     pub fn insert_ports(&mut self, ip: IpAddr, ports: &[u16], source: Source) {
         let map = match source {
             Source::HmwMaster => &mut self.hmw,
@@ -77,8 +172,11 @@ impl Cache {
     pub fn update_cache_with(&mut self, server: &Server, region: Option<[char; 2]>) {
         let socket_addr = server.source.socket_addr();
         if let Some(ref info) = server.info {
-            self.host_to_connect
-                .insert(info.host_name.clone(), socket_addr);
+            let now = SystemTime::now();
+            self.book
+                .record(socket_addr, Arc::clone(&info.host_name), now);
+            self.book
+                .record_players(socket_addr, PlayerSample::new(info, now));
         }
         if let Some(region) = region {
             self.ip_to_region.insert(socket_addr.ip(), region);
@@ -88,10 +186,61 @@ impl Cache {
         }
     }
 
+    /// `None` if `server` was never measured or the last sample is older than [`PING_MAX_AGE`]
+    pub fn fresh_ping(&self, server: SocketAddr) -> Option<Duration> {
+        self.pings
+            .get(&server)
+            .filter(|sample| {
+                sample
+                    .measured
+                    .elapsed()
+                    .is_ok_and(|age| age < PING_MAX_AGE)
+            })
+            .map(|sample| Duration::from_millis(sample.ms as u64))
+    }
+
+    pub fn record_ping(&mut self, server: SocketAddr, ping: Duration) {
+        let sample = PingSample {
+            ms: ping.as_millis().try_into().unwrap_or(u32::MAX),
+            measured: SystemTime::now(),
+        };
+        self.pings.insert(server, sample);
+    }
+
+    /// Remembers the addresses host names listed by the master servers resolved to. A host that moved
+    /// has the region of its new address dropped, so it is looked up again rather than taken from
+    /// whoever used the address before, and the move is kept for the next `diff`
+    pub fn record_resolved(&mut self, resolved: impl IntoIterator<Item = (Arc<str>, IpAddr)>) {
+        for (host, ip) in resolved {
+            let Some(from) = self.host_ips.insert(Arc::clone(&host), ip) else {
+                continue;
+            };
+            if from == ip {
+                continue;
+            }
+            info!(name: LOG_ONLY, "{host} moved from {from} to {ip}, its region will be looked up again");
+            self.ip_to_region.remove(&ip);
+            self.ip_to_country.remove(&ip);
+            if self.host_moves.len() >= HOST_MOVES_MAX {
+                self.host_moves.remove(0);
+            }
+            self.host_moves.push(HostMove {
+                host,
+                from,
+                to: ip,
+                at: SystemTime::now(),
+            });
+        }
+    }
+
     pub fn push(&mut self, server: Server, region: Option<[char; 2]>) {
         let socket_addr = server.source.socket_addr();
         if let Some(info) = server.info {
-            self.host_to_connect.insert(info.host_name, socket_addr);
+            let now = SystemTime::now();
+            self.populations.record(socket_addr, info.clients, now);
+            let sample = PlayerSample::new(&info, now);
+            self.book.record(socket_addr, info.host_name, now);
+            self.book.record_players(socket_addr, sample);
         }
         if let Some(region) = region {
             self.ip_to_region.insert(socket_addr.ip(), region);
@@ -104,58 +253,200 @@ impl Cache {
 
 impl CacheFile {
     fn from_backups(
-        connection_history: Option<Vec<HostName>>,
+        book: Option<ServerBook>,
         regions: Option<HashMap<IpAddr, [char; 2]>>,
+        populations: Option<PopulationHistory>,
+        reliability: Option<ReliabilityBook>,
     ) -> Self {
         CacheFile {
             version: env!("CARGO_PKG_VERSION").to_string(),
             created: std::time::SystemTime::now(),
-            connection_history: connection_history.unwrap_or_default(),
+            book: book.unwrap_or_default(),
+            connection_history: Vec::new(),
             cache: ServerCache {
                 iw4m: HashMap::new(),
                 hmw: HashMap::new(),
                 regions: regions.unwrap_or_default(),
+                countries: HashMap::new(),
                 host_names: HashMap::new(),
+                pings: HashMap::new(),
+                resolved: HashMap::new(),
+                moves: Vec::new(),
             },
+            last_filter: None,
+            snapshot: None,
+            populations: populations.unwrap_or_default(),
+            reliability: reliability.unwrap_or_default(),
         }
     }
+
+    /// Fills the server book of a cache file written by an older version from its host names and
+    /// name history
+    fn migrate_book(&mut self) {
+        if !self.book.is_empty() || self.cache.host_names.is_empty() {
+            return;
+        }
+        self.book = ServerBook::from_legacy(
+            std::mem::take(&mut self.cache.host_names),
+            &std::mem::take(&mut self.connection_history),
+            self.created,
+        );
+    }
 }
 
+/// Measures every server in `servers` without a fresh ping, or all of them if `force` is set. Servers
+/// that do not reply keep their previous sample
+pub async fn refresh_pings(cache: &Mutex<Cache>, servers: &[SocketAddr], force: bool) {
+    let stale = {
+        let cache = cache.lock().await;
+        servers
+            .iter()
+            .copied()
+            .filter(|&server| force || cache.fresh_ping(server).is_none())
+            .collect::<Vec<_>>()
+    };
+    let mut tasks = JoinSet::new();
+    for server in stale {
+        tasks.spawn(async move { (server, measure_ping(server, PING_TIMEOUT).await) });
+    }
+    let mut measured = Vec::with_capacity(tasks.len());
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((server, Ok(ping))) => measured.push((server, ping)),
+            Ok((_, Err(err))) => trace!("{err}"),
+            Err(err) => error!(name: LOG_ONLY, "{err}"),
+        }
+    }
+    let mut cache = cache.lock().await;
+    for (server, ping) in measured {
+        cache.record_ping(server, ping);
+    }
+}
+
+/// Sends `msg` to be printed above the prompt if a `msg_sender` is given, otherwise prints it directly
+async fn report(msg_sender: Option<&Sender<Message>>, msg: Message) {
+    let Some(sender) = msg_sender else {
+        match msg {
+            Message::Str(msg) => println!("{msg}"),
+            Message::Info(msg) => info!("{msg}"),
+            Message::Warn(msg) => warn!("{msg}"),
+            Message::Err(msg) => error!("{msg}"),
+            Message::Progress(_) | Message::Screen(_) | Message::Trim(_) => (),
+        }
+        return;
+    };
+    let _ = sender.send(msg).await;
+}
+
+/// What a cache update changed, shown once the new cache is swapped in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSummary {
+    /// Servers in the new cache
+    pub servers: usize,
+    /// Servers in the replaced cache
+    pub previous: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// Servers in the new cache whose region is known
+    pub with_region: usize,
+    /// Servers that did not answer their `getInfo` request
+    pub no_answer: usize,
+    pub elapsed: Duration,
+}
+
+impl CacheSummary {
+    /// Counts the servers of `new` and which of them were added or removed since `old`
+    pub fn compare(&mut self, old: &Cache, new: &ServerCache) {
+        let old = server_addrs(&old.iw4m, &old.hmw);
+        let new_addrs = server_addrs(&new.iw4m, &new.hmw);
+        self.servers = new_addrs.len();
+        self.previous = old.len();
+        self.added = new_addrs.difference(&old).count();
+        self.removed = old.difference(&new_addrs).count();
+        self.with_region = new_addrs
+            .iter()
+            .filter(|addr| new.regions.contains_key(&addr.ip()))
+            .count();
+    }
+}
+
+fn server_addrs(
+    iw4m: &HashMap<IpAddr, Vec<u16>>,
+    hmw: &HashMap<IpAddr, Vec<u16>>,
+) -> HashSet<SocketAddr> {
+    iw4m.iter()
+        .chain(hmw)
+        .flat_map(|(&ip, ports)| ports.iter().map(move |&port| SocketAddr::new(ip, port)))
+        .collect()
+}
+
+/// `msg_sender` should be provided when the cache is built while the prompt is displayed. The summary
+/// only counts the servers that did not answer, see [`CacheSummary::compare`] for the rest
 #[instrument(level = "trace", skip_all)]
 pub async fn build_cache(
-    connection_history: Option<&[HostName]>,
+    http: &Http,
+    book: Option<ServerBook>,
     regions: Option<&HashMap<IpAddr, [char; 2]>>,
-) -> Result<CacheFile, (&'static str, CacheFile)> {
-    println!("{GREEN}Updating cache...{WHITE}");
-
-    let mut servers = iw4_servers(None).await.unwrap_or_else(|err| {
-        error!("{err}");
-        Vec::new()
-    });
-    match hmw_servers(None).await {
+    populations: Option<PopulationHistory>,
+    reliability: Option<ReliabilityBook>,
+    msg_sender: Option<&Sender<Message>>,
+) -> Result<(CacheFile, CacheSummary), (&'static str, CacheFile)> {
+    report(
+        msg_sender,
+        Message::Str(format!("{GREEN}Updating cache...{WHITE}")),
+    )
+    .await;
+
+    let mut complete_listing = true;
+    let mut servers = match iw4_servers(&**http, None, IpFamily::default()).await {
+        Ok(iw4) => iw4,
+        Err(err) => {
+            complete_listing = false;
+            report(msg_sender, Message::Err(DisplayError(&err).to_string())).await;
+            Vec::new()
+        }
+    };
+    match hmw_servers(&**http, None, IpFamily::default()).await {
         Ok(ref mut hmw) => servers.append(hmw),
-        Err(err) => error!("{err}"),
+        Err(err) => {
+            complete_listing = false;
+            report(msg_sender, Message::Err(DisplayError(&err).to_string())).await
+        }
     };
 
     if servers.is_empty() {
         return Err((
             "Could not connect to either master server source",
-            CacheFile::from_backups(connection_history.map(|v| v.to_vec()), regions.cloned()),
+            CacheFile::from_backups(book, regions.cloned(), populations, reliability),
         ));
     }
+    if let Some(skipped) = skipped_report() {
+        report(msg_sender, Message::Warn(skipped)).await;
+    }
+
+    let mut reliability = reliability.unwrap_or_default();
+    if complete_listing {
+        reliability.record_listing(&servers.iter().map(Sourced::socket_addr).collect());
+    }
 
     let mut cache = Cache::new();
+    cache.book = book.unwrap_or_default();
+    cache.populations = populations.unwrap_or_default();
+    cache.populations.forget_stale(SystemTime::now());
     let mut tasks = Vec::new();
+    let mut summary = CacheSummary::default();
 
-    let client = reqwest::Client::builder()
-        .timeout(tokio::time::Duration::from_secs(3))
-        .build()
-        .unwrap();
+    queue_info_requests(servers, &mut tasks, false, http).await;
 
-    queue_info_requests(servers, &mut tasks, false, &client).await;
+    let mut progress =
+        msg_sender.map(|sender| Progress::new("servers cached", tasks.len(), sender));
 
     for task in tasks {
-        match task.await {
+        let result = task.await;
+        if let Some(ref mut progress) = progress {
+            progress.inc();
+        }
+        match result {
             Ok(result) => match result {
                 Ok(server) => {
                     let region = regions
@@ -164,6 +455,7 @@ pub async fn build_cache(
                     cache.push(server, region)
                 }
                 Err(mut err) => {
+                    summary.no_answer += 1;
                     error!(name: LOG_ONLY, "{}", err.with_socket_addr().with_source());
                     let source = err.meta.to_valid_source();
                     if let Sourced::Iw4(data) = err.meta {
@@ -171,9 +463,10 @@ pub async fn build_cache(
                             if let Some(source) = source {
                                 cache.insert_ports(ip, &[data.server.port], source);
                             }
-                            cache.host_to_connect.insert(
-                                data.server.host_name,
+                            cache.book.record(
                                 SocketAddr::new(ip, data.server.port),
+                                data.server.host_name,
+                                SystemTime::now(),
                             );
                         }
                     }
@@ -183,39 +476,65 @@ pub async fn build_cache(
         }
     }
 
-    Ok(CacheFile {
+    if let Some(cutoff) = SystemTime::now().checked_sub(FORGET_AFTER) {
+        cache.book.forget_before(cutoff);
+    }
+
+    let cache_file = CacheFile {
         version: env!("CARGO_PKG_VERSION").to_string(),
         created: std::time::SystemTime::now(),
-        connection_history: connection_history.map(|v| v.to_vec()).unwrap_or_default(),
+        book: cache.book,
+        connection_history: Vec::new(),
         cache: ServerCache {
             iw4m: cache.iw4m,
             hmw: cache.hmw,
             regions: cache.ip_to_region,
-            host_names: cache.host_to_connect,
+            countries: HashMap::new(),
+            host_names: HashMap::new(),
+            pings: cache.pings,
+            resolved: HashMap::new(),
+            moves: Vec::new(),
         },
-    })
+        last_filter: None,
+        snapshot: None,
+        populations: cache.populations,
+        reliability,
+    };
+    Ok((cache_file, summary))
 }
 
 pub struct ReadCacheErr {
     pub err: String,
-    pub connection_history: Option<Vec<HostName>>,
+    pub book: Option<ServerBook>,
     pub region_cache: Option<HashMap<IpAddr, [char; 2]>>,
+    pub country_cache: Option<HashMap<IpAddr, [char; 2]>>,
+    pub snapshot: Option<MasterSnapshot>,
+    pub populations: Option<PopulationHistory>,
+    pub reliability: Option<ReliabilityBook>,
 }
 
 impl ReadCacheErr {
     fn new(err: String) -> Self {
         ReadCacheErr {
             err,
-            connection_history: None,
+            book: None,
             region_cache: None,
+            country_cache: None,
+            snapshot: None,
+            populations: None,
+            reliability: None,
         }
     }
 
-    fn with_old(err: String, history: Vec<HostName>, region: HashMap<IpAddr, [char; 2]>) -> Self {
+    fn with_old(err: String, data: CacheFile) -> Self {
         ReadCacheErr {
             err,
-            connection_history: Some(history),
-            region_cache: Some(region),
+            book: Some(data.book),
+            region_cache: Some(data.cache.regions),
+            country_cache: Some(data.cache.countries),
+            snapshot: data.snapshot,
+            populations: Some(data.populations),
+            reliability: Some(data.reliability),
         }
     }
 }
@@ -224,8 +543,12 @@ impl From<io::Error> for ReadCacheErr {
     fn from(value: io::Error) -> Self {
         ReadCacheErr {
             err: format!("{value}, Starting new cache file"),
-            connection_history: None,
+            book: None,
             region_cache: None,
+            country_cache: None,
+            snapshot: None,
+            populations: None,
+            reliability: None,
         }
     }
 }
@@ -234,55 +557,59 @@ impl From<serde_json::Error> for ReadCacheErr {
     fn from(value: serde_json::Error) -> Self {
         ReadCacheErr {
             err: format!("{value}, Starting new cache file"),
-            connection_history: None,
+            book: None,
             region_cache: None,
+            country_cache: None,
+            snapshot: None,
+            populations: None,
+            reliability: None,
         }
     }
 }
 
+/// A cache older than a day is rejected unless `accept_stale` is set, e.g. when it can not be rebuilt
 #[instrument(level = "trace", skip_all)]
-pub async fn read_cache(local_env_dir: &Path) -> Result<Cache, ReadCacheErr> {
-    match does_dir_contain(local_env_dir, Operation::All, &[CACHED_DATA]) {
-        Ok(OperationResult::Bool(true)) => {
-            let file = std::fs::File::open(local_env_dir.join(CACHED_DATA))?;
-            let reader = io::BufReader::new(file);
-            let data = serde_json::from_reader::<_, CacheFile>(reader)?;
-            let curr_time = std::time::SystemTime::now();
-            match curr_time.duration_since(data.created) {
-                Ok(time) if time > Duration::new(60 * 60 * 24, 0) => {
-                    return Err(ReadCacheErr::with_old(
-                        "cache is too old".to_string(),
-                        data.connection_history,
-                        data.cache.regions,
-                    ))
-                }
-                Err(err) => {
-                    return Err(ReadCacheErr::with_old(
-                        err.to_string(),
-                        data.connection_history,
-                        data.cache.regions,
-                    ))
-                }
-                _ => (),
-            }
-            trace!("Cache read from file");
-            Ok(Cache::from(data))
+pub async fn read_cache(local_env_dir: &Path, accept_stale: bool) -> Result<Cache, ReadCacheErr> {
+    let bytes = match tokio::fs::read(local_env_dir.join(CACHED_DATA)).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(ReadCacheErr::new(format!("{CACHED_DATA} not found")))
         }
-        Ok(OperationResult::Bool(false)) => {
-            Err(ReadCacheErr::new(format!("{CACHED_DATA} not found")))
+        Err(err) => return Err(err.into()),
+    };
+    let mut data = serde_json::from_slice::<CacheFile>(&bytes)?;
+    data.migrate_book();
+    let curr_time = std::time::SystemTime::now();
+    match curr_time.duration_since(data.created) {
+        Ok(time) if time > Duration::new(60 * 60 * 24, 0) && !accept_stale => {
+            return Err(ReadCacheErr::with_old("cache is too old".to_string(), data))
         }
-        Err(err) => Err(err.into()),
-        _ => unreachable!(),
+        Err(err) => return Err(ReadCacheErr::with_old(err.to_string(), data)),
+        _ => (),
     }
+    trace!("Cache read from file");
+    Ok(Cache::from(data))
+}
+
+/// Writes `data` to the cache file in `local_dir`, the write runs off of the runtime threads so a slow
+/// disk does not hold up the REPL
+pub async fn write_cache_file(local_dir: &Path, data: &CacheFile) -> Result<(), Error> {
+    let cache_path = local_dir.join(CACHED_DATA);
+    let bytes = serde_json::to_vec_pretty(data)
+        .map_err(|err| Error::io("write", &cache_path, err.into()))?;
+    tokio::fs::write(&cache_path, bytes)
+        .await
+        .map_err(|err| Error::io("write", cache_path, err))
 }
 
 #[instrument(level = "trace", skip_all)]
-pub async fn write_cache<'a>(context: &CommandContext) -> io::Result<()> {
+pub async fn write_cache<'a>(context: &CommandContext) -> Result<(), Error> {
     let local_env_dir = context.local_dir();
     let Some(local_path) = local_env_dir else {
-        return new_io_error!(io::ErrorKind::Other, "No valid location to save cache to");
+        return Err(Error::Cache(Cow::Borrowed(
+            "No valid location to save cache to",
+        )));
     };
-    let file = std::fs::File::create(local_path.join(CACHED_DATA))?;
     let data = {
         let cache_lock = context.cache();
         let cache = cache_lock.lock().await;
@@ -293,16 +620,22 @@ pub async fn write_cache<'a>(context: &CommandContext) -> io::Result<()> {
                 iw4m: cache.iw4m.clone(),
                 hmw: cache.hmw.clone(),
                 regions: cache.ip_to_region.clone(),
-                host_names: cache.host_to_connect.clone(),
-            },
-            connection_history: if cache.connection_history.len() > HISTORY_MAX {
-                cache.connection_history[cache.connection_history.len() - HISTORY_MAX..].to_vec()
-            } else {
-                cache.connection_history.clone()
+                countries: cache.ip_to_country.clone(),
+                host_names: HashMap::new(),
+                pings: cache.pings.clone(),
+                resolved: cache.host_ips.clone(),
+                moves: cache.host_moves.clone(),
             },
+            book: cache.book.clone(),
+            connection_history: Vec::new(),
+            last_filter: cache.last_filter.clone(),
+            snapshot: cache.snapshot.clone(),
+            populations: cache.populations.clone(),
+            reliability: cache.reliability.clone(),
         }
     };
-    serde_json::to_writer_pretty(file, &data).map_err(io::Error::other)?;
+    write_cache_file(local_path, &data).await?;
+    context.events().publish(AppEvent::CacheUpdated);
     info!(name: LOG_ONLY, "Cache saved locally");
     Ok(())
 }
@@ -0,0 +1,528 @@
+use crate::{
+    cli::{BrowserOrder, Channel, Filters, IpFamily, Region},
+    utils::{
+        input::prompt::DEFAULT_PROMPT_TEMPLATE, lang::DEFAULT_LANGUAGE, regions::RegionSettings,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Env vars starting with this override settings, e.g. `H2M_FAVORITES_FILTER_LIMIT=50` sets `filter.limit`
+pub const ENV_PREFIX: &str = "H2M_FAVORITES_";
+
+/// Used with `settings <KEY> default` to remove a key so its default value is used
+pub const DEFAULT_VALUE: &str = "default";
+
+/// `launch.refresh-on-launch` value that runs a filter from `filter.presets`, e.g. `preset:eu_evening`
+pub const REFRESH_PRESET: &str = "preset:";
+/// `launch.refresh-on-launch` value that runs the last `filter` command again
+pub const REFRESH_LAST: &str = "last";
+
+/// Persistent settings read from `config.toml` in the local data directory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub filter: FilterConfig,
+    pub launch: LaunchConfig,
+    pub display: DisplayConfig,
+    pub log: LogConfig,
+    pub update: UpdateConfig,
+    pub network: NetworkConfig,
+    pub window: WindowConfig,
+    pub ping_monitor: PingMonitorConfig,
+    /// Continent and country codes of custom regions, keyed by name. The built in `na`, `eu` and `apac`
+    /// can be redefined too, e.g. `apac = ["AS", "OC"]`
+    pub region: RegionSettings,
+}
+
+/// Defaults for `filter` options that were not given on the command line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FilterConfig {
+    /// Maximum number of servers added to favorites.json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_min: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_size_max: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Vec<Region>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_max: Option<u8>,
+    /// Seconds a filter may take before it stops with what it has, 0 has no deadline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    pub include_unresponsive: bool,
+    /// Maximum number of location lookups in flight at once, 0 is unlimited
+    pub lookup_concurrency: usize,
+    /// Address type used for servers reachable over both IPv4 and IPv6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefer: Option<IpFamily>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser_order: Option<BrowserOrder>,
+    /// favourites.json files to write, relative paths start at the game directory. When empty only
+    /// players2/favourites.json is written
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub favorites_path: Vec<PathBuf>,
+    /// `filter` arguments saved under a name, e.g. `{ eu_evening = "-r eu -p 4" }`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LaunchConfig {
+    /// Start H2M when the app starts
+    pub auto_launch: bool,
+    pub auto_relaunch: bool,
+    pub focus_on_connect: bool,
+    /// Directory holding h1_mp64_ship.exe, found automatically when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_dir: Option<PathBuf>,
+    /// Filter run before `launch` starts the game, [`REFRESH_PRESET`] followed by a preset name or
+    /// [`REFRESH_LAST`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_on_launch: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DisplayConfig {
+    pub color: bool,
+    pub prompt: String,
+    /// Desktop notifications for updates, friends coming online and game crashes
+    pub notifications: bool,
+    /// Display names for maps, keyed by internal name, these take priority over the built in names
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub map_names: BTreeMap<String, String>,
+    /// Display names for game types, keyed by internal name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub mode_names: BTreeMap<String, String>,
+    /// Translation read from `lang/<CODE>.json` in the local environment directory
+    pub language: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct UpdateConfig {
+    /// Release channel used by the version check and `update`
+    pub channel: Channel,
+}
+
+/// Applied to every web request by the shared client
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NetworkConfig {
+    /// Seconds before a request is abandoned, 0 waits indefinitely
+    pub timeout: u64,
+    /// Extra attempts for requests that could not connect, timed out or got a server error
+    pub retries: u8,
+    /// Every request is sent through this proxy, e.g. `http://10.0.0.2:8080` or `socks5://127.0.0.1:1080`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+/// The console window MatchWire runs in, see [`crate::utils::window`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WindowConfig {
+    /// Show the server being played on in the window title
+    pub title_server: bool,
+    /// Tray icon to show the console, reconnect or quit, minimizing the console hides it to the tray
+    pub tray: bool,
+    /// Start hidden to the tray, the tray icon is shown even if `tray` is off
+    pub start_in_tray: bool,
+    /// Restore the size and position the window had when the last run closed
+    pub remember_placement: bool,
+}
+
+/// Probes of the server being played on, see [`crate::utils::ping_monitor`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PingMonitorConfig {
+    pub enabled: bool,
+    /// Seconds between probes
+    pub interval: u64,
+    /// Pings above this many milliseconds are spikes
+    pub spike_ms: u32,
+    /// Share of recent probes that may go unanswered before a warning is shown
+    pub loss_percent: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LogConfig {
+    /// How often a new log file is started
+    pub rotation: LogRotation,
+    /// Number of rotated log files kept, 0 keeps every file
+    pub retention: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            rotation: LogRotation::Daily,
+            retention: 7,
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            timeout: 20,
+            retries: 2,
+            proxy: None,
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            title_server: true,
+            tray: false,
+            start_in_tray: false,
+            remember_placement: true,
+        }
+    }
+}
+
+impl Default for PingMonitorConfig {
+    fn default() -> Self {
+        PingMonitorConfig {
+            enabled: false,
+            interval: 5,
+            spike_ms: 150,
+            loss_percent: 20,
+        }
+    }
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        LaunchConfig {
+            auto_launch: true,
+            auto_relaunch: false,
+            focus_on_connect: false,
+            game_dir: None,
+            refresh_on_launch: None,
+        }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            color: true,
+            prompt: String::from(DEFAULT_PROMPT_TEMPLATE),
+            notifications: false,
+            map_names: BTreeMap::new(),
+            mode_names: BTreeMap::new(),
+            language: String::from(DEFAULT_LANGUAGE),
+        }
+    }
+}
+
+impl FilterConfig {
+    /// Fills in options the user left unset
+    pub fn apply(&self, args: &mut Filters) {
+        args.limit = args.limit.or(self.limit);
+        args.player_min = args.player_min.or(self.player_min);
+        args.team_size_max = args.team_size_max.or(self.team_size_max);
+        args.retry_max = args.retry_max.or(self.retry_max);
+        args.deadline = args.deadline.or(self.deadline);
+        if args.region.is_none() {
+            args.region.clone_from(&self.region);
+        }
+        args.include_unresponsive |= self.include_unresponsive;
+        args.prefer = args.prefer.or(self.prefer);
+        args.browser_order = args.browser_order.or(self.browser_order);
+    }
+}
+
+impl Config {
+    /// `filter` arguments of `launch.refresh-on-launch`, `None` when it is unset. `last_filter` is what
+    /// [`REFRESH_LAST`] runs
+    pub fn launch_refresh(&self, last_filter: Option<&str>) -> Option<Result<String, String>> {
+        let refresh = self.launch.refresh_on_launch.as_deref()?.trim();
+        let args = if let Some(name) = refresh.strip_prefix(REFRESH_PRESET) {
+            self.filter
+                .presets
+                .get(name.trim())
+                .cloned()
+                .ok_or_else(|| format!("No preset named '{}' in filter.presets", name.trim()))
+        } else if refresh == REFRESH_LAST {
+            last_filter
+                .map(String::from)
+                .ok_or_else(|| String::from("No `filter` has been run yet"))
+        } else {
+            Err(format!(
+                "Expected '{REFRESH_PRESET}<NAME>' or '{REFRESH_LAST}' for launch.refresh-on-launch, \
+                found: '{refresh}'"
+            ))
+        };
+        Some(args)
+    }
+
+    #[inline]
+    pub fn path(local_dir: &Path) -> PathBuf {
+        local_dir.join(CONFIG_FILE)
+    }
+
+    pub fn write(&self, local_dir: &Path) -> io::Result<()> {
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(Config::path(local_dir), contents)
+    }
+
+    /// Looks up a dotted key, e.g. `display.prompt`
+    pub fn get(&self, key: &str) -> Result<Option<toml::Value>, String> {
+        let table = toml::Value::try_from(self).map_err(|err| err.to_string())?;
+        let (section, name) = split_key(key)?;
+        match table.get(section) {
+            Some(section) => Ok(section.get(name).cloned()),
+            None => Err(format!("Unknown section: '{section}'")),
+        }
+    }
+
+    /// Every key that currently has a value, in file order
+    pub fn entries(&self) -> Vec<(String, toml::Value)> {
+        let Ok(toml::Value::Table(table)) = toml::Value::try_from(self) else {
+            return Vec::new();
+        };
+        table
+            .into_iter()
+            .filter_map(|(section, values)| match values {
+                toml::Value::Table(values) => Some((section, values)),
+                _ => None,
+            })
+            .flat_map(|(section, values)| {
+                values
+                    .into_iter()
+                    .map(move |(name, value)| (format!("{section}.{name}"), value))
+            })
+            .collect()
+    }
+
+    /// Returns a copy of the config with `key` set to `value`, `value` is parsed as a TOML value and
+    /// falls back to a string. `DEFAULT_VALUE` removes the key
+    pub fn with_value(&self, key: &str, value: &str) -> Result<Config, String> {
+        let mut table = toml::Value::try_from(self).map_err(|err| err.to_string())?;
+        let (section, name) = split_key(key)?;
+        let section = table
+            .get_mut(section)
+            .and_then(toml::Value::as_table_mut)
+            .ok_or_else(|| format!("Unknown section: '{section}'"))?;
+        if value == DEFAULT_VALUE {
+            section.remove(name);
+        } else {
+            section.insert(String::from(name), parse_value(value));
+        }
+        table
+            .try_into()
+            .map_err(|err: toml::de::Error| err.message().to_string())
+    }
+}
+
+fn split_key(key: &str) -> Result<(&str, &str), String> {
+    key.split_once('.')
+        .ok_or_else(|| format!("Expected a key in the form 'section.name', found: '{key}'"))
+}
+
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(String::from(value)))
+}
+
+/// Where the effective value of a setting came from, later variants take priority
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SettingSource {
+    Default,
+    File,
+    Env(String),
+    Cli,
+}
+
+pub struct Override {
+    pub key: String,
+    pub value: String,
+    pub source: SettingSource,
+}
+
+/// Settings resolved in layers: defaults < config.toml < `H2M_FAVORITES_*` env vars < CLI flags
+#[derive(Default)]
+pub struct Settings {
+    /// values saved in config.toml, this is what `settings <KEY> <VALUE>` changes
+    file: Config,
+    /// keys present in config.toml
+    file_keys: HashSet<String>,
+    overrides: Vec<Override>,
+    /// `file` with `overrides` applied
+    pub effective: Config,
+}
+
+impl Settings {
+    /// Reads config.toml from `local_dir` (creating it with default values if missing), then applies env
+    /// var and `cli` overrides. Problems are returned as warnings, the layer at fault is skipped
+    pub fn load(local_dir: Option<&Path>, cli: Vec<(String, String)>) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut settings = Settings::default();
+        if let Some(dir) = local_dir {
+            match read_file(dir) {
+                Ok((file, file_keys)) => {
+                    settings.file = file;
+                    settings.file_keys = file_keys;
+                }
+                Err(err) => warnings.push(format!("{err}, using default settings")),
+            }
+        }
+        settings.overrides = env_overrides()
+            .chain(cli.into_iter().map(|(key, value)| Override {
+                key,
+                value,
+                source: SettingSource::Cli,
+            }))
+            .collect();
+        warnings.append(&mut settings.resolve());
+        (settings, warnings)
+    }
+
+    /// Rebuilds `effective`, overrides that fail to apply are dropped
+    fn resolve(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut effective = self.file.clone();
+        self.overrides.retain(
+            |entry| match effective.with_value(&entry.key, &entry.value) {
+                Ok(config) => {
+                    effective = config;
+                    true
+                }
+                Err(err) => {
+                    warnings.push(format!(
+                        "Ignoring {} override of '{}', {err}",
+                        entry.source, entry.key
+                    ));
+                    false
+                }
+            },
+        );
+        self.effective = effective;
+        warnings
+    }
+
+    /// Re-reads config.toml, overrides given at startup are kept
+    pub fn reload(&mut self, local_dir: &Path) -> Result<Vec<String>, String> {
+        let (file, file_keys) = read_file(local_dir).map_err(|err| err.to_string())?;
+        self.file = file;
+        self.file_keys = file_keys;
+        Ok(self.resolve())
+    }
+
+    /// Changes `key` in the config file layer, the effective value only changes if nothing overrides it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.file = self.file.with_value(key, value)?;
+        if value == DEFAULT_VALUE {
+            self.file_keys.remove(key);
+        } else {
+            self.file_keys.insert(String::from(key));
+        }
+        self.resolve();
+        Ok(())
+    }
+
+    #[inline]
+    pub fn write(&self, local_dir: &Path) -> io::Result<()> {
+        self.file.write(local_dir)
+    }
+
+    pub fn source(&self, key: &str) -> SettingSource {
+        if let Some(entry) = self.overrides.iter().rev().find(|entry| entry.key == key) {
+            return entry.source.clone();
+        }
+        if self.file_keys.contains(key) {
+            return SettingSource::File;
+        }
+        SettingSource::Default
+    }
+
+    /// Every effective value along with where it came from
+    pub fn sources(&self) -> Vec<(String, toml::Value, SettingSource)> {
+        self.effective
+            .entries()
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self.source(&key);
+                (key, value, source)
+            })
+            .collect()
+    }
+}
+
+fn read_file(local_dir: &Path) -> io::Result<(Config, HashSet<String>)> {
+    let path = Config::path(local_dir);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Config::default().write(local_dir)?;
+            return Ok((Config::default(), HashSet::new()));
+        }
+        Err(err) => return Err(err),
+    };
+    let parse_err = |err: toml::de::Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Failed to parse {}, {}", path.display(), err.message()),
+        )
+    };
+    let table = toml::from_str::<toml::Table>(&contents).map_err(parse_err)?;
+    let keys = table
+        .iter()
+        .filter_map(|(section, values)| Some((section, values.as_table()?)))
+        .flat_map(|(section, values)| values.keys().map(move |name| format!("{section}.{name}")))
+        .collect();
+    let config = toml::Value::Table(table).try_into().map_err(parse_err)?;
+    Ok((config, keys))
+}
+
+/// `H2M_FAVORITES_FILTER_LIMIT` -> `filter.limit`, `H2M_FAVORITES_LAUNCH_AUTO_LAUNCH` -> `launch.auto-launch`
+pub fn env_key(var: &str) -> Option<String> {
+    let var = var.strip_prefix(ENV_PREFIX)?;
+    let (section, name) = match var.strip_prefix("PING_MONITOR_") {
+        Some(name) => ("PING_MONITOR", name),
+        None => var.split_once('_')?,
+    };
+    Some(format!(
+        "{}.{}",
+        section.to_lowercase().replace('_', "-"),
+        name.to_lowercase().replace('_', "-")
+    ))
+}
+
+fn env_overrides() -> impl Iterator<Item = Override> {
+    let mut vars = std::env::vars()
+        .filter_map(|(var, value)| Some((env_key(&var)?, value, var)))
+        .collect::<Vec<_>>();
+    vars.sort_unstable();
+    vars.into_iter().map(|(key, value, var)| Override {
+        key,
+        value,
+        source: SettingSource::Env(var),
+    })
+}
@@ -1,15 +1,51 @@
 use crate::{
+    cli::{Channel, LogTarget, Region, Setting, Source, Toggle, Verbosity},
     commands::{
-        filter::{Sourced, UnresponsiveCounter},
+        dashboard::Dashboard,
+        diff::{SnapshotDiff, POPULATION_SWING},
+        doctor::Diagnostic,
+        favorites::FavoritesCheck,
+        filter::{
+            GeoFailures, RegionGroup, Sourced, UnresponsiveCounter, FAVORITES, FAVORITES_LOC,
+        },
+        friends::{Friends, JOIN_KEYS},
         handler::{AppDetails, GameDetails},
-        launch_h2m::LaunchError,
+        journal::PlaySession,
+        logs::{LogFile, LogPage},
+        reconnect::QueuedCommand,
+        search::SearchResult,
+        server_info::{ServerDetails, KEY_DVARS},
+        session::{LagSide, SessionStats},
+        status::{GameStatus, StartupPanel, StatusHeader},
+        tasks::RunningTask,
+        trim::TrimRequest,
+        whois::ServerRecord,
     },
+    error::Error,
+    strip_color_codes, utc_date_time,
     utils::{
-        caching::ReadCacheErr,
-        input::style::{GREEN, RED, WHITE, YELLOW},
+        caching::{CacheSummary, ReadCacheErr},
+        config::SettingSource,
+        geo_key::{KeySource, KeyUsage, BUNDLED_DAILY_LOOKUPS, GEO_KEY_ENV},
+        input::{
+            prompt::PROMPT_PLACEHOLDERS,
+            style::{AnsiColor, BLUE, GREEN, GREY, MAGENTA, RED, WHITE, YELLOW},
+        },
+        names::Names,
+        perf::PerfStats,
+        players2::{Players2Issue, Repaired, BACKUP_EXT},
+        profile::Profile,
+        regions::RegionCode,
+        reliability::ReliabilityBook,
+        server_book::ServerBook,
+        suggest::Suggestion,
     },
 };
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    net::SocketAddr,
+    time::{Duration, UNIX_EPOCH},
+};
 
 const SOURCE_HMW: &str = "HMW master server";
 const SOURCE_HMW_CACHED: &str = "Cached HMW server";
@@ -28,7 +64,7 @@ impl Display for ConnectionHelp {
 }
 
 /// `(count, COLOR)`
-pub struct DisplayServerCount(pub usize, pub &'static str);
+pub struct DisplayServerCount(pub usize, pub AnsiColor);
 
 impl Display for DisplayServerCount {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -78,6 +114,28 @@ impl Display for SingularPlural {
     }
 }
 
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// (label, done, total)
+pub struct DisplayProgress(pub &'static str, pub usize, pub usize);
+
+impl Display for DisplayProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let filled = (self.1 * PROGRESS_BAR_WIDTH)
+            .checked_div(self.2)
+            .unwrap_or(PROGRESS_BAR_WIDTH);
+        write!(
+            f,
+            "[{}{}] {}/{} {}",
+            "#".repeat(filled),
+            "-".repeat(PROGRESS_BAR_WIDTH - filled),
+            self.1,
+            self.2,
+            self.0
+        )
+    }
+}
+
 /// `history.len()`
 pub struct DisplayHistoryErr(pub usize);
 
@@ -167,13 +225,255 @@ impl Display for UnresponsiveCounter {
     }
 }
 
-impl Display for LaunchError {
+/// One line for every failed location lookup of a `filter`, the causes are listed most frequent first.
+/// `.1` is whether `--keep-unknown-region` was given
+pub struct DisplayGeoFailures<'a>(pub &'a GeoFailures, pub bool);
+
+impl Display for DisplayGeoFailures<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let display = match self {
-            LaunchError::Running(msg) => *msg,
-            LaunchError::SpawnErr(err) => &err.to_string_lossy(),
+        let failures = self.0;
+        let total = failures.total();
+        write!(
+            f,
+            "{RED}Failed to resolve location for {total} server {}",
+            SingularPlural(total, "hoster", "hosters")
+        )?;
+        let mut causes = [
+            (failures.timeout, "timed out"),
+            (failures.rate_limited, "rate limited (429)"),
+            (failures.unreadable, "unreadable replies"),
+            (failures.rejected, "refused by the API"),
+            (failures.cancelled, "cancelled"),
+            (failures.other, "other errors"),
+        ];
+        causes.sort_by_key(|&(count, _)| std::cmp::Reverse(count));
+        let mut sep = ": ";
+        for (count, cause) in causes.into_iter().filter(|&(count, _)| count > 0) {
+            write!(f, "{sep}{count} {cause}")?;
+            sep = ", ";
+        }
+        write!(f, "{WHITE}")?;
+        if self.1 {
+            write!(
+                f,
+                "\n{GREY}Their servers were kept since their region is unknown{WHITE}"
+            )
+        } else {
+            write!(
+                f,
+                "\n{GREY}Their servers were left out, add `--keep-unknown-region` to keep them{WHITE}"
+            )
+        }
+    }
+}
+
+fn continent_name(code: [char; 2]) -> &'static str {
+    match code {
+        ['N', 'A'] => "North America",
+        ['S', 'A'] => "South America",
+        ['E', 'U'] => "Europe",
+        ['A', 'S'] => "Asia",
+        ['O', 'C'] => "Oceania",
+        ['A', 'F'] => "Africa",
+        ['A', 'N'] => "Antarctica",
+        _ => "",
+    }
+}
+
+/// Matches of `filter --preview` under their region and community with subtotals, servers with a
+/// reliability score are followed by it. (groups, total, limit, reliability)
+pub struct DisplayPreview<'a>(
+    pub &'a [RegionGroup<'a>],
+    pub usize,
+    pub usize,
+    pub &'a ReliabilityBook,
+);
+
+impl Display for DisplayPreview<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let &DisplayPreview(groups, total, limit, reliability) = self;
+        for group in groups {
+            match group.region {
+                Some(code @ [a, b]) => write!(f, "{BLUE}{a}{b}{WHITE} {}", continent_name(code))?,
+                None => write!(f, "{GREY}Unknown region{WHITE}")?,
+            }
+            writeln!(f, ", {}", DisplayServerCount(group.server_count(), GREEN))?;
+            for host in group.hosts.iter() {
+                writeln!(
+                    f,
+                    "  {YELLOW}{}{WHITE}, {}",
+                    host.host,
+                    DisplayServerCount(host.servers.len(), GREEN)
+                )?;
+                for server in host.servers.iter() {
+                    let addr = server.source.socket_addr();
+                    match server.info {
+                        Some(ref info) => write!(
+                            f,
+                            "    {:>2}/{:<2} {} {GREY}{addr}{WHITE}",
+                            info.clients,
+                            info.max_clients,
+                            strip_color_codes(&info.host_name)
+                        )?,
+                        None => write!(f, "      -   {GREY}{addr}{WHITE}")?,
+                    }
+                    match reliability.score(addr) {
+                        Some(score) => writeln!(f, " {}", DisplayReliability(score))?,
+                        None => writeln!(f)?,
+                    }
+                }
+            }
+        }
+        write!(f, "{} match", DisplayServerCount(total, GREEN))?;
+        if total > limit {
+            write!(
+                f,
+                ", only the {limit} with the most players would be written"
+            )?;
+        }
+        write!(
+            f,
+            "\n{GREY}{FAVORITES} was not changed, run the same filter without `--preview` to write it{WHITE}"
+        )
+    }
+}
+
+/// Percent of master server lists a server was in, colored by how dependable that is
+pub struct DisplayReliability(pub u8);
+
+impl Display for DisplayReliability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let color = match self.0 {
+            90.. => GREEN,
+            60..=89 => YELLOW,
+            _ => RED,
         };
-        write!(f, "{display}")
+        write!(f, "{color}{}% listed{WHITE}", self.0)
+    }
+}
+
+/// Rows shown of each section of `diff` before the rest are only counted
+const DIFF_ROWS: usize = 20;
+
+/// Changes to the master lists found by `diff`, named from the server book where possible
+pub struct DisplayMasterDiff<'a>(pub &'a SnapshotDiff, pub &'a ServerBook);
+
+impl DisplayMasterDiff<'_> {
+    fn name(&self, server: SocketAddr) -> String {
+        self.1
+            .host_name(server)
+            .map(|name| strip_color_codes(name))
+            .unwrap_or_default()
+    }
+
+    fn more(f: &mut std::fmt::Formatter<'_>, total: usize) -> std::fmt::Result {
+        if total > DIFF_ROWS {
+            writeln!(f, "  {GREY}... and {} more{WHITE}", total - DIFF_ROWS)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DisplayMasterDiff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let diff = self.0;
+        write!(
+            f,
+            "Compared {} listed now with ",
+            DisplayServerCount(diff.listed, GREEN)
+        )?;
+        match diff.since.elapsed() {
+            Ok(age) => writeln!(f, "the snapshot from {} ago", DisplayDuration(age))?,
+            Err(_) => writeln!(f, "the last snapshot")?,
+        }
+        if diff.added.is_empty()
+            && diff.removed.is_empty()
+            && diff.swings.is_empty()
+            && diff.moved.is_empty()
+        {
+            return write!(f, "{GREY}Nothing changed{WHITE}");
+        }
+        if !diff.added.is_empty() {
+            writeln!(f, "{GREEN}{}{WHITE} new:", diff.added.len())?;
+            for &server in diff.added.iter().take(DIFF_ROWS) {
+                writeln!(f, "  {GREEN}+{WHITE} {server:<21} {}", self.name(server))?;
+            }
+            Self::more(f, diff.added.len())?;
+        }
+        if !diff.removed.is_empty() {
+            writeln!(f, "{RED}{}{WHITE} no longer listed:", diff.removed.len())?;
+            for &server in diff.removed.iter().take(DIFF_ROWS) {
+                writeln!(f, "  {RED}-{WHITE} {server:<21} {}", self.name(server))?;
+            }
+            Self::more(f, diff.removed.len())?;
+        }
+        if !diff.swings.is_empty() {
+            writeln!(
+                f,
+                "{YELLOW}{}{WHITE} changed by {} or more players:",
+                diff.swings.len(),
+                POPULATION_SWING
+            )?;
+            for &(server, before, now) in diff.swings.iter().take(DIFF_ROWS) {
+                let color = if now > before { GREEN } else { RED };
+                writeln!(
+                    f,
+                    "  {before:>2} -> {color}{now:<2}{WHITE} {server:<21} {}",
+                    self.name(server)
+                )?;
+            }
+            Self::more(f, diff.swings.len())?;
+        }
+        if !diff.moved.is_empty() {
+            writeln!(
+                f,
+                "{YELLOW}{}{WHITE} resolved to a new address:",
+                DisplayCountOf(diff.moved.len(), "host name", "host names")
+            )?;
+            for moved in diff.moved.iter().rev().take(DIFF_ROWS) {
+                writeln!(
+                    f,
+                    "  {:>15} -> {YELLOW}{:<15}{WHITE} {}",
+                    moved.from, moved.to, moved.host
+                )?;
+            }
+            Self::more(f, diff.moved.len())?;
+        }
+        write!(f, "{GREY}Saved as the snapshot for the next `diff`{WHITE}")
+    }
+}
+
+/// A misspelled command with the one that was probably meant and how to use it
+pub struct DisplaySuggestion<'a>(pub &'a Suggestion);
+
+impl Display for DisplaySuggestion<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Suggestion {
+            typed,
+            command,
+            example,
+        } = self.0;
+        write!(
+            f,
+            "Unknown command '{typed}', did you mean {GREEN}{command}{WHITE}?"
+        )?;
+        match example {
+            Some(example) => write!(f, "\n{GREY}e.g. {example}{WHITE}"),
+            None => write!(f, "\n{GREY}See `{command} --help` for how to use it{WHITE}"),
+        }
+    }
+}
+
+/// The error followed by what the user can try next
+pub struct DisplayError<'a>(pub &'a Error);
+
+impl Display for DisplayError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)?;
+        if let Some(hint) = self.0.hint() {
+            write!(f, "\n{GREY}{hint}{WHITE}")?;
+        }
+        Ok(())
     }
 }
 
@@ -183,6 +483,34 @@ impl Display for ReadCacheErr {
     }
 }
 
+impl Display for CacheSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{GREEN}Cache updated{WHITE} in {}, {}",
+            DisplayElapsed(self.elapsed),
+            DisplayCountOf(self.servers, "server", "servers")
+        )?;
+        // a cache replaced at startup is usually empty, every server would count as added
+        if self.previous > 0 {
+            write!(
+                f,
+                " ({GREEN}+{}{WHITE} {RED}-{}{WHITE})",
+                self.added, self.removed
+            )?;
+        }
+        write!(f, ", regions known for {}", self.with_region)?;
+        if self.no_answer > 0 {
+            write!(
+                f,
+                ", {YELLOW}{}{WHITE} did not answer 'getInfo'",
+                self.no_answer
+            )?;
+        }
+        Ok(())
+    }
+}
+
 pub struct HmwUpdateHelp;
 
 impl Display for HmwUpdateHelp {
@@ -202,7 +530,7 @@ impl Display for GameDetails {
                 .to_string_lossy()
         )?;
         let color = match (&self.hash_curr, &self.hash_latest) {
-            (Some(curr), Some(latest)) => {
+            (Some(curr), Some(latest)) if self.tracks_hmw_manifest() => {
                 if curr == latest {
                     GREEN
                 } else {
@@ -232,12 +560,12 @@ impl Display for GameDetails {
 
 impl Display for AppDetails {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let color = if let Some(ref latest) = self.ver_latest {
-            if self.ver_curr == latest {
-                GREEN
-            } else {
-                YELLOW
-            }
+        let color = if self.unsupported() {
+            RED
+        } else if self.update_available() {
+            YELLOW
+        } else if self.ver_latest.is_some() {
+            GREEN
         } else {
             WHITE
         };
@@ -247,11 +575,1206 @@ impl Display for AppDetails {
             env!("CARGO_PKG_NAME"),
             self.ver_curr
         )?;
+        if self.channel != Channel::Stable {
+            write!(f, " {GREY}({} channel){WHITE}", self.channel)?;
+        }
+        if color == RED {
+            write!(f, "\n{RED}This version is no longer supported{WHITE}")?;
+        }
+        let update_available = self.update_available();
         if let Some(ref msg) = self.update_msg {
-            if color == YELLOW {
+            if update_available {
                 write!(f, "\n{msg}")?;
             }
         }
+        if update_available {
+            if self.changelog.is_some() {
+                write!(
+                    f,
+                    "\nUse command '{YELLOW}changelog{WHITE}' to see what changed"
+                )?;
+            }
+            write!(
+                f,
+                "\nUse command '{YELLOW}update{WHITE}' to install the latest version"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `(version, plain text release notes)`
+pub struct DisplayChangelog<'a>(pub &'a str, pub &'a str);
+
+impl Display for DisplayChangelog<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let title = format!("{} v{}", env!("CARGO_PKG_NAME"), self.0);
+        writeln!(f, "{BLUE}{title}{WHITE}")?;
+        writeln!(f, "{}", "-".repeat(title.chars().count()))?;
+        write!(f, "{}", self.1)
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::NA => write!(f, "na"),
+            Region::EU => write!(f, "eu"),
+            Region::Apac => write!(f, "apac"),
+            Region::Custom(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl Display for RegionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b] = self.continent;
+        write!(f, "{a}{b}")?;
+        if let Some([a, b]) = self.country {
+            write!(f, ":{a}{b}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Channel::Stable => write!(f, "stable"),
+            Channel::Beta => write!(f, "beta"),
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display = match self {
+            Setting::AutoRelaunch => "auto-relaunch",
+            Setting::FocusOnConnect => "focus-on-connect",
+            Setting::Notifications => "notifications",
+        };
+        write!(f, "{display}")
+    }
+}
+
+impl Display for Toggle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display = match self {
+            Toggle::On => "enabled",
+            Toggle::Off => "disabled",
+        };
+        write!(f, "{display}")
+    }
+}
+
+/// Formats as the two largest non zero units, e.g. `2h 5m` or `40s`
+pub struct DisplayDuration(pub Duration);
+
+impl Display for DisplayDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+        if days > 0 {
+            write!(f, "{days}d {hours}h")
+        } else if hours > 0 {
+            write!(f, "{hours}h {mins}m")
+        } else if mins > 0 {
+            write!(f, "{mins}m {}s", secs % 60)
+        } else {
+            write!(f, "{secs}s")
+        }
+    }
+}
+
+/// Formats a byte count with the largest fitting unit, e.g. `1.4 MB`
+pub struct DisplayBytes(pub u64);
+
+impl Display for DisplayBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{size:.1} {}", UNITS[unit])
+    }
+}
+
+pub struct DisplayLogFiles<'a>(pub &'a [LogFile]);
+
+impl Display for DisplayLogFiles<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|file| file.name().chars().count())
+            .max()
+            .unwrap_or_default();
+        for file in self.0 {
+            let size = DisplayBytes(file.len).to_string();
+            write!(f, "  {:<width$}  {size:>9}", file.name())?;
+            match file.modified.elapsed() {
+                Ok(age) => writeln!(f, "  {GREY}{} ago{WHITE}", DisplayDuration(age))?,
+                Err(_) => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct DisplayLogPage<'a>(pub LogPage<'a>);
+
+impl Display for DisplayLogPage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in self.0.entries {
+            let color = match entry.level {
+                Some(tracing::Level::ERROR) => RED,
+                Some(tracing::Level::WARN) => YELLOW,
+                Some(tracing::Level::INFO) => GREEN,
+                Some(tracing::Level::DEBUG) => BLUE,
+                Some(tracing::Level::TRACE) => MAGENTA,
+                None => WHITE,
+            };
+            for line in &entry.lines {
+                writeln!(f, "{color}{line}{WHITE}")?;
+            }
+        }
+        let (page, page_count) = (self.0.page, self.0.page_count);
+        write!(f, "{GREY}Page {page} of {page_count}")?;
+        if page < page_count {
+            write!(f, ", use --page {} to view older entries", page + 1)?;
+        }
+        writeln!(f, "{WHITE}")
+    }
+}
+
+impl Display for Verbosity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verbosity::Trace => write!(f, "trace"),
+            Verbosity::Debug => write!(f, "debug"),
+            Verbosity::Info => write!(f, "info"),
+            Verbosity::Warn => write!(f, "warn"),
+            Verbosity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl Display for LogTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogTarget::File => write!(f, "File"),
+            LogTarget::Terminal => write!(f, "Terminal"),
+            LogTarget::All => write!(f, "File and terminal"),
+        }
+    }
+}
+
+/// Current `(file, terminal)` log levels
+pub struct DisplayLogLevels(pub (Option<Verbosity>, Option<Verbosity>));
+
+impl Display for DisplayLogLevels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (file, terminal) = self.0;
+        for (i, (name, level)) in [("file", file), ("terminal", terminal)]
+            .into_iter()
+            .enumerate()
+        {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            match level {
+                Some(level) => write!(f, "{name:<10}{YELLOW}{level}{WHITE}")?,
+                None => write!(f, "{name:<10}{GREY}not in use{WHITE}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct DisplayPromptPlaceholders;
+
+impl Display for DisplayPromptPlaceholders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Placeholders:")?;
+        for (placeholder, desc) in PROMPT_PLACEHOLDERS {
+            write!(f, "\n  {YELLOW}{placeholder:<15}{WHITE}{desc}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SettingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingSource::Default => write!(f, "default"),
+            SettingSource::File => write!(f, "config.toml"),
+            SettingSource::Env(var) => write!(f, "env {var}"),
+            SettingSource::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+pub struct DisplaySettingSources<'a>(pub &'a [(String, toml::Value, SettingSource)]);
+
+impl Display for DisplaySettingSources<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .0
+            .iter()
+            .map(|(key, value, source)| (format!("{key} = {value}"), source))
+            .collect::<Vec<_>>();
+        let width = entries
+            .iter()
+            .map(|(entry, _)| entry.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (entry, source) in entries {
+            let color = match source {
+                SettingSource::Default => GREY,
+                SettingSource::File => WHITE,
+                SettingSource::Env(_) | SettingSource::Cli => YELLOW,
+            };
+            writeln!(f, "{entry:<width$}  {color}{source}{WHITE}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `(command, description)` listed on the startup panel
+const COMMON_COMMANDS: [(&str, &str); 5] = [
+    (
+        "filter",
+        "Write servers matching your filters to favorites.json",
+    ),
+    ("reconnect", "Rejoin the last server you played on"),
+    ("launch", "Start H2M-mod if it is not already running"),
+    ("console", "View and send commands to the game console"),
+    ("status", "Display game, connection and cache status"),
+];
+
+impl Display for StartupPanel<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.app)?;
+        if self.game.version.is_some() || self.game.hash_curr.is_some() {
+            writeln!(f, "{}", self.game)?;
+        }
+        writeln!(f)?;
+        let header = &self.header;
+        writeln!(
+            f,
+            "H2M-mod:  {}",
+            DisplayGameState(header.running, header.attached, header.log_attached)
+        )?;
+        write!(
+            f,
+            "Cache:    {}",
+            DisplayCountOf(self.cached_servers, "server", "servers")
+        )?;
+        match header.cache_age {
+            Some(age) => writeln!(f, ", updated {} ago", DisplayDuration(age))?,
+            None => writeln!(f)?,
+        }
+        writeln!(f)?;
+        writeln!(f, "Common commands:")?;
+        for (command, desc) in COMMON_COMMANDS {
+            writeln!(f, "  {YELLOW}{command:<11}{WHITE}{desc}")?;
+        }
+        write!(
+            f,
+            "Use `{YELLOW}help{WHITE}` to list every command, or `{YELLOW}<COMMAND> --help{WHITE}` for its options"
+        )
+    }
+}
+
+/// `(running, attached, log_attached)`
+struct DisplayGameState(bool, bool, bool);
+
+impl Display for DisplayGameState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.0, self.1) {
+            (true, true) => write!(f, "{GREEN}running{WHITE}, console attached"),
+            (true, false) if self.2 => write!(f, "{YELLOW}running{WHITE}, following console log"),
+            (true, false) => write!(f, "{YELLOW}running{WHITE}, console not attached"),
+            (false, _) => write!(f, "{RED}not running{WHITE}"),
+        }
+    }
+}
+
+/// `(dashboard, names from the display settings)`
+pub struct DisplayDashboard<'a>(pub &'a Dashboard, pub Names<'a>);
+
+impl Display for DisplayDashboard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (dashboard, names) = (self.0, self.1);
+        // title, blank line, column names and the row left for the "more" line
+        const RESERVED_ROWS: usize = 4;
+        // width of every column before the server name
+        const FIXED_WIDTH: usize = 66;
+
+        let responded = dashboard
+            .rows
+            .iter()
+            .filter(|row| row.info.is_some())
+            .count();
+        write!(
+            f,
+            "{GREEN}Dashboard{WHITE} {GREY}|{WHITE} {} on {responded}/{} {GREY}|{WHITE} \
+            Refreshed every {} in {:.1}s {GREY}|{WHITE} Press any key to exit",
+            DisplayCountOf(dashboard.player_total(), "player", "players"),
+            DisplayCountOf(dashboard.rows.len(), "server", "servers"),
+            DisplayDuration(dashboard.interval),
+            dashboard.elapsed.as_secs_f32(),
+        )?;
+        if dashboard.skipped > 0 {
+            write!(
+                f,
+                " {GREY}|{WHITE} {YELLOW}{}{WHITE} not shown",
+                DisplayCountOf(dashboard.skipped, "favourite", "favourites")
+            )?;
+        }
+        writeln!(f, "\n")?;
+        writeln!(
+            f,
+            "{GREY}{:>7} {:<4} {:>4} {:>5}  {:<18} {:<20} Server{WHITE}",
+            "Players", "", "Bots", "Ping", "Mode", "Map"
+        )?;
+
+        let name_width = (dashboard.term_size.0 as usize)
+            .saturating_sub(FIXED_WIDTH)
+            .max(12);
+        let visible = (dashboard.term_size.1 as usize).saturating_sub(RESERVED_ROWS);
+        for row in dashboard.rows.iter().take(visible) {
+            let Some(ref info) = row.info else {
+                writeln!(
+                    f,
+                    "{GREY}{:>7} {:<4} {:>4} {:>5}  {:<18} {:<20} {} did not respond{WHITE}",
+                    "-", "", "", "", "", "", row.socket_addr
+                )?;
+                continue;
+            };
+            let (color, change) = match row.player_change {
+                0 => (WHITE, String::new()),
+                change if change > 0 => (GREEN, format!("+{change}")),
+                change => (RED, change.to_string()),
+            };
+            let bots = if info.bots > 0 {
+                info.bots.to_string()
+            } else {
+                String::new()
+            };
+            let ping = row
+                .ping
+                .map(|ping| ping.as_millis().to_string())
+                .unwrap_or_default();
+            let map_color = if row.map_changed { YELLOW } else { WHITE };
+            let name = strip_color_codes(&info.host_name)
+                .chars()
+                .take(name_width)
+                .collect::<String>();
+            writeln!(
+                f,
+                "{color}{:>7} {change:<4}{WHITE} {bots:>4} {ping:>5}  {:<18.18} {map_color}{:<20.20}{WHITE} {name}",
+                format!("{}/{}", info.clients, info.max_clients),
+                names.mode(&info.game_type),
+                names.map(&info.map_name),
+            )?;
+        }
+        if dashboard.rows.len() > visible {
+            write!(
+                f,
+                "{GREY}...and {} more{WHITE}",
+                dashboard.rows.len() - visible
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Friends {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let online = self.online();
+        write!(
+            f,
+            "{GREEN}Friends{WHITE} {GREY}|{WHITE} {}/{} online {GREY}|{WHITE} ",
+            online.len(),
+            self.names().len()
+        )?;
+        match self.last_scan() {
+            Some(scan) => writeln!(f, "Scanned {} ago", DisplayDuration(scan.elapsed()))?,
+            None => writeln!(f, "{YELLOW}First scan of cached servers is running{WHITE}")?,
+        }
+        let name_width = self
+            .names()
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0);
+        for (i, (name, seen)) in online.iter().enumerate() {
+            let key = if i < JOIN_KEYS {
+                format!("{}.", i + 1)
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "{key:>3} {GREEN}{name:<name_width$}{WHITE}  {} {GREY}{}{WHITE}",
+                strip_color_codes(&seen.host_name),
+                seen.server
+            )?;
+        }
+        for name in self
+            .names()
+            .iter()
+            .filter(|name| !online.iter().any(|(online, _)| online == name))
+        {
+            writeln!(f, "    {GREY}{name:<name_width$}  offline{WHITE}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `(details, names from the display settings)`
+pub struct DisplayServerDetails<'a>(pub &'a ServerDetails, pub Names<'a>);
+
+impl Display for DisplayServerDetails<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (details, names) = (self.0, self.1);
+        let dvars = &details.status.dvars;
+        let dvar = |key: &str| dvars.get(key).map(String::as_str).unwrap_or_default();
+        let bots = details
+            .status
+            .players
+            .iter()
+            .filter(|player| player.ping == 0)
+            .count();
+        writeln!(
+            f,
+            "{} {GREY}|{WHITE} {} {GREY}|{WHITE} {}ms",
+            strip_color_codes(dvar("sv_hostname")),
+            details.server,
+            details.latency.as_millis()
+        )?;
+        writeln!(
+            f,
+            "Map:      {} {GREY}({}){WHITE}",
+            names.map(dvar("mapname")),
+            dvar("mapname")
+        )?;
+        writeln!(
+            f,
+            "Mode:     {} {GREY}({}){WHITE}",
+            names.mode(dvar("g_gametype")),
+            dvar("g_gametype")
+        )?;
+        write!(
+            f,
+            "Players:  {}/{}",
+            details.status.players.len(),
+            dvars
+                .get("sv_maxclients")
+                .map(String::as_str)
+                .unwrap_or("?")
+        )?;
+        if bots > 0 {
+            write!(f, " {GREY}({}){WHITE}", DisplayCountOf(bots, "bot", "bots"))?;
+        }
+        writeln!(f)?;
+
+        if details.all_dvars {
+            let mut sorted = dvars.iter().collect::<Vec<_>>();
+            sorted.sort_unstable_by_key(|&(key, _)| key.to_lowercase());
+            let width = sorted.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+            writeln!(f)?;
+            for (key, value) in sorted {
+                writeln!(f, "{GREY}{key:<width$}{WHITE}  {value}")?;
+            }
+        } else {
+            for (key, label) in KEY_DVARS {
+                if let Some(value) = dvars.get(key) {
+                    writeln!(f, "{:<10}{value}", format!("{label}:"))?;
+                }
+            }
+        }
+
+        if details.status.players.is_empty() {
+            return writeln!(f, "\n{GREY}No players online{WHITE}");
+        }
+        writeln!(f, "\n{GREY}{:>6} {:>5}  Name{WHITE}", "Score", "Ping")?;
+        for player in details.status.players.iter() {
+            let ping = if player.ping == 0 {
+                String::from("bot")
+            } else {
+                player.ping.to_string()
+            };
+            writeln!(
+                f,
+                "{:>6} {ping:>5}  {}",
+                player.score,
+                strip_color_codes(&player.name)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Servers a name matched, numbered so one can be picked, and how many more matched past those
+pub struct ServerChoices<'a>(pub &'a [(SocketAddr, String)], pub usize);
+
+impl Display for ServerChoices<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Several servers match, press a number to pick one, any other key to cancel"
+        )?;
+        for (i, (server, host_name)) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "  {GREEN}{}{WHITE}. {host_name} {GREY}{server}{WHITE}",
+                i + 1
+            )?;
+        }
+        if self.1 > 0 {
+            writeln!(f, "  {GREY}{} more match, be more specific{WHITE}", self.1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ServerRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.aliases.last() {
+            Some(host_name) => writeln!(f, "{host_name} {GREY}|{WHITE} {}", self.server)?,
+            None => writeln!(f, "{GREY}Unnamed{WHITE} {GREY}|{WHITE} {}", self.server)?,
+        }
+        if self.aliases.len() > 1 {
+            let older = &self.aliases[..self.aliases.len() - 1];
+            writeln!(f, "Aliases:  {}", older.join(", "))?;
+        }
+        if let Some([a, b]) = self.region {
+            writeln!(f, "Region:   {a}{b}")?;
+        }
+        if let Some(seen) = self.last_seen {
+            match seen.elapsed() {
+                Ok(age) => writeln!(f, "Seen:     {} ago", DisplayDuration(age))?,
+                Err(_) => writeln!(f, "Seen:     just now")?,
+            }
+        }
+        if let Some(ping) = self.ping {
+            writeln!(f, "Ping:     {}ms", ping.as_millis())?;
+        }
+        if let Some(players) = self.players {
+            write!(f, "Players:  {}/{}", players.clients, players.max_clients)?;
+            match players.at.elapsed() {
+                Ok(age) => writeln!(f, ", {} ago", DisplayDuration(age))?,
+                Err(_) => writeln!(f, ", just now")?,
+            }
+        }
+        if !self.listed_by.is_empty() {
+            let sources = self
+                .listed_by
+                .iter()
+                .map(|source| match source {
+                    Source::HmwMaster => SOURCE_HMW,
+                    Source::Iw4Master => SOURCE_IW4,
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "Listed:   {}", sources.join(", "))?;
+        }
+        if let Some(num) = self.history {
+            writeln!(f, "History:  {num}, join with `reconnect -c {num}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for StatusHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "H2M-mod: {} {GREY}|{WHITE} Cache age: ",
+            DisplayGameState(self.running, self.attached, self.log_attached)
+        )?;
+        match self.cache_age {
+            Some(age) => write!(f, "{}", DisplayDuration(age)),
+            None => write!(f, "{GREY}unknown{WHITE}"),
+        }
+    }
+}
+
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "H2M-mod:     {}",
+            DisplayGameState(self.running, self.attached, self.log_attached)
+        )?;
+        if let Some(session) = self.session {
+            writeln!(f, "Session:     {}", DisplayDuration(session))?;
+        }
+        if self.queued > 0 {
+            writeln!(
+                f,
+                "Queued:      {YELLOW}{}{WHITE}, waiting for H2M to finish loading",
+                DisplayCountOf(self.queued, "command", "commands")
+            )?;
+        }
+        match self.server {
+            Some(ref server) => {
+                writeln!(f, "Last joined: {}", server.host_name)?;
+                match server.socket_addr {
+                    Some(addr) => {
+                        write!(f, "  Address:   {addr}")?;
+                        if let Some([a, b]) = server.region {
+                            write!(f, " ({a}{b})")?;
+                        }
+                        writeln!(f)?;
+                    }
+                    None => writeln!(f, "  Address:   {GREY}not found in cache{WHITE}")?,
+                }
+                match server.info {
+                    Some(ref info) => {
+                        write!(f, "  Players:   {}/{}", info.clients, info.max_clients)?;
+                        if info.bots > 0 {
+                            write!(
+                                f,
+                                " ({})",
+                                DisplayCountOf(info.bots as usize, "bot", "bots")
+                            )?;
+                        }
+                        writeln!(f)?;
+                    }
+                    None if server.socket_addr.is_some() => {
+                        writeln!(f, "  Players:   {GREY}server did not respond{WHITE}")?
+                    }
+                    None => (),
+                }
+            }
+            None => writeln!(f, "Last joined: {GREY}no servers in history{WHITE}")?,
+        }
+        match self.cache_age {
+            Some(age) => write!(f, "Cache age:   {}", DisplayDuration(age)),
+            None => write!(f, "Cache age:   {GREY}unknown{WHITE}"),
+        }
+    }
+}
+
+impl Display for QueuedCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueuedCommand::Connect(ip_port) => write!(f, "connect {ip_port}"),
+        }
+    }
+}
+
+impl Display for Players2Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Players2Issue::NotWritable(err) => write!(f, "{FAVORITES_LOC} can not be written to, {err}"),
+            Players2Issue::ReadOnly => write!(f, "{FAVORITES} is read-only, changes to it are never saved"),
+            Players2Issue::Placeholder => write!(
+                f,
+                "{FAVORITES} is only stored in the cloud (OneDrive), the game can not read it until it is downloaded"
+            ),
+            Players2Issue::Corrupt(err) => write!(f, "{FAVORITES} is damaged, {err}"),
+        }
+    }
+}
+
+impl Display for Repaired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fixed.is_empty() && self.remaining.is_empty() {
+            return writeln!(f, "{GREEN}{FAVORITES_LOC} has no problems to repair{WHITE}");
+        }
+        for issue in self.fixed.iter() {
+            writeln!(f, "{GREEN}fixed{WHITE}  {issue}")?;
+        }
+        if let Some(count) = self.salvaged {
+            writeln!(
+                f,
+                "{GREY}Rebuilt {FAVORITES} with {}, the damaged file was kept as {}{WHITE}",
+                DisplayCountOf(count, "server", "servers"),
+                FAVORITES.replace("json", BACKUP_EXT)
+            )?;
+        }
+        for issue in self.remaining.iter() {
+            writeln!(f, "{RED}left{WHITE}   {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FavoritesCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checked {}: {GREEN}{} answered{WHITE}, ",
+            DisplayCountOf(self.checked, "server", "servers"),
+            self.checked - self.dead.len()
+        )?;
+        let dead_color = if self.dead.is_empty() { WHITE } else { RED };
+        write!(
+            f,
+            "{dead_color}{} dead{WHITE}, {} renamed, {} moved region",
+            self.dead.len(),
+            self.renamed.len(),
+            self.moved.len()
+        )?;
+        for (server, last_name) in self.dead.iter() {
+            write!(
+                f,
+                "\n  {RED}dead{WHITE}     {server:<21}  {GREY}{}{WHITE}",
+                last_name.as_deref().unwrap_or("never cached")
+            )?;
+        }
+        for (server, old, new) in self.renamed.iter() {
+            write!(
+                f,
+                "\n  {YELLOW}renamed{WHITE}  {server:<21}  {GREY}{old}{WHITE} -> {new}"
+            )?;
+        }
+        for (server, old, new) in self.moved.iter() {
+            write!(
+                f,
+                "\n  {YELLOW}region{WHITE}   {server:<21}  {GREY}{}{WHITE} -> {}",
+                old.iter().collect::<String>(),
+                new.iter().collect::<String>()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `(stats, names from the display settings)`
+pub struct DisplaySessionStats<'a>(pub &'a SessionStats, pub Names<'a>);
+
+impl Display for DisplaySessionStats<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (stats, names) = (self.0, self.1);
+        writeln!(f, "Session length: {}", DisplayDuration(stats.duration))?;
+        if stats.maps.is_empty() {
+            writeln!(f, "Maps played:    {GREY}none recorded{WHITE}")?;
+        } else {
+            writeln!(
+                f,
+                "Maps played:    {} ({})",
+                stats.maps.len(),
+                stats
+                    .maps
+                    .iter()
+                    .map(|map| names.map(map))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        write!(f, "Kills recorded: {}", stats.kills)?;
+        let mut write_counts = |title: &str, counts: &[(String, usize)]| -> std::fmt::Result {
+            if counts.is_empty() {
+                return Ok(());
+            }
+            write!(f, "\n{title}:")?;
+            for (name, count) in counts {
+                write!(f, "\n  {name}{GREY}:{WHITE} {GREEN}{count}{WHITE}")?;
+            }
+            Ok(())
+        };
+        write_counts("Top players", &stats.top_players)?;
+        write_counts("Top weapons", &stats.top_weapons)?;
+        write_counts("Round wins", &stats.round_wins)?;
+
+        let connection = &stats.connection;
+        if connection.sent == 0 {
+            return Ok(());
+        }
+        write!(f, "\nConnection:     ")?;
+        match connection.average_ms {
+            Some(average) => write!(
+                f,
+                "{average} ms average, {} ms worst",
+                connection.worst_ms.unwrap_or(average)
+            )?,
+            None => write!(f, "{RED}no replies{WHITE}")?,
+        }
+        let lost_color = if connection.lost > 0 { YELLOW } else { GREEN };
+        write!(
+            f,
+            ", {lost_color}{}%{WHITE} of {} probes lost",
+            connection.lost * 100 / connection.sent,
+            connection.sent
+        )?;
+        if connection.lag.is_empty() {
+            return Ok(());
+        }
+        write!(f, "\nLag:")?;
+        for (time, server, side) in connection.lag.iter() {
+            let (_, (hour, min, _)) = utc_date_time(UNIX_EPOCH + Duration::from_secs(*time));
+            let side = match side {
+                LagSide::Server => "the server",
+                LagSide::Local => "your connection",
+                LagSide::Unknown => "unknown cause",
+            };
+            write!(
+                f,
+                "\n  {hour:02}:{min:02} UTC  {server}  {GREY}{side}{WHITE}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `(session, names from the display settings)`
+pub struct DisplayPlaySession<'a>(pub &'a PlaySession, pub Names<'a>);
+
+impl Display for DisplayPlaySession<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (session, names) = (self.0, self.1);
+        let utc = |secs: u64| utc_date_time(UNIX_EPOCH + Duration::from_secs(secs));
+        let ((year, month, day), (hour, min, _)) = utc(session.started);
+        write!(
+            f,
+            "{GREEN}{year:04}-{month:02}-{day:02} {hour:02}:{min:02}{WHITE} UTC {GREY}|{WHITE} {} {GREY}|{WHITE} {}",
+            DisplayDuration(session.duration()),
+            DisplayCountOf(session.visits.len(), "server", "servers"),
+        )?;
+        let server_width = session
+            .visits
+            .iter()
+            .map(|visit| visit.server.chars().count())
+            .max()
+            .unwrap_or(0);
+        for visit in session.visits.iter() {
+            let (_, (hour, min, _)) = utc(visit.joined);
+            write!(
+                f,
+                "\n  {hour:02}:{min:02}  {:<server_width$}  {:>7}  {GREY}{}{WHITE}",
+                visit.server,
+                DisplayDuration(visit.duration()).to_string(),
+                visit
+                    .maps
+                    .iter()
+                    .map(|map| names.map(map))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        if let Some(exit_code) = session.exit_code {
+            write!(f, "\n  {RED}Crashed with exit code: {exit_code:#X}{WHITE}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Player: {}",
+            self.name.as_deref().unwrap_or("name not set")
+        )?;
+        for (dvar, value) in self.dvars.iter().filter(|(dvar, _)| *dvar != "name") {
+            writeln!(f, "  {dvar}{GREY}:{WHITE} {value}")?;
+        }
+        write!(f, "{GREY}Read from: {}{WHITE}", self.path.display())
+    }
+}
+
+pub struct DisplayDiagnostics<'a>(pub &'a [Diagnostic]);
+
+impl Display for DisplayDiagnostics<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or(0);
+        let mut passed = 0;
+        for check in self.0 {
+            match check.result {
+                Ok(ref detail) => {
+                    passed += 1;
+                    writeln!(
+                        f,
+                        "{GREEN}PASS{WHITE} {:<width$}  {GREY}{detail}{WHITE}",
+                        check.name
+                    )?;
+                }
+                Err(ref failure) => {
+                    writeln!(
+                        f,
+                        "{RED}FAIL{WHITE} {:<width$}  {}",
+                        check.name, failure.err
+                    )?;
+                    writeln!(f, "     {:<width$}  {YELLOW}{}{WHITE}", "", failure.hint)?;
+                }
+            }
+        }
+        let color = if passed == self.0.len() {
+            GREEN
+        } else {
+            YELLOW
+        };
+        write!(
+            f,
+            "{color}{passed} of {} checks passed{WHITE}",
+            self.0.len()
+        )
+    }
+}
+
+/// Formats short durations down to the millisecond, e.g. `340ms` or `8.4s`
+pub struct DisplayElapsed(pub Duration);
+
+impl Display for DisplayElapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 < Duration::from_secs(1) {
+            write!(f, "{}ms", self.0.as_millis())
+        } else if self.0 < Duration::from_secs(60) {
+            write!(f, "{:.1}s", self.0.as_secs_f64())
+        } else {
+            write!(f, "{}", DisplayDuration(self.0))
+        }
+    }
+}
+
+/// Web requests past this are left out, slowest first
+const PERF_ENDPOINTS_MAX: usize = 8;
+
+/// `(stats, filter.lookup-concurrency)`
+pub struct DisplayPerf<'a>(pub &'a PerfStats, pub usize);
+
+impl Display for DisplayPerf<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stats = self.0;
+        if let Some(since) = stats.since {
+            let ((year, month, day), _) = utc_date_time(since);
+            writeln!(
+                f,
+                "{GREY}Recorded since {year:04}-{month:02}-{day:02}{WHITE}"
+            )?;
+        }
+
+        let mut commands = stats.commands.iter().collect::<Vec<_>>();
+        commands.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.average()));
+        if !commands.is_empty() {
+            writeln!(
+                f,
+                "\n{:<16} {:>6} {:>9} {:>9}",
+                "Command", "Runs", "Average", "Slowest"
+            )?;
+        }
+        for (name, timing) in commands {
+            writeln!(
+                f,
+                "{YELLOW}{name:<16}{WHITE} {:>6} {:>9} {:>9}",
+                timing.count,
+                DisplayElapsed(timing.average()).to_string(),
+                DisplayElapsed(timing.max()).to_string(),
+            )?;
+        }
+
+        let geo = &stats.geo;
+        if let Some(hit_rate) = geo.hit_rate() {
+            writeln!(
+                f,
+                "\nLocation lookups: {GREEN}{}{WHITE} cached, {} requested, {} failed {GREY}({:.0}% from cache){WHITE}",
+                geo.cached,
+                geo.looked_up,
+                geo.failed,
+                hit_rate * 100.0
+            )?;
+            if geo.failed > 0 {
+                let concurrency = match self.1 {
+                    0 => String::from("unlimited"),
+                    limit => limit.to_string(),
+                };
+                writeln!(
+                    f,
+                    "{YELLOW}A lower `filter.lookup-concurrency` (currently {concurrency}) can avoid failed lookups{WHITE}"
+                )?;
+            }
+        }
+
+        let mut endpoints = stats.endpoints.iter().collect::<Vec<_>>();
+        endpoints.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.average()));
+        if !endpoints.is_empty() {
+            writeln!(
+                f,
+                "\n{:<32} {:>8} {:>6} {:>9} {:>9}",
+                "Slowest web requests", "Requests", "Failed", "Average", "Slowest"
+            )?;
+        }
+        for (endpoint, timing) in endpoints.into_iter().take(PERF_ENDPOINTS_MAX) {
+            let failed_color = if timing.failed > 0 { RED } else { WHITE };
+            writeln!(
+                f,
+                "{endpoint:<32} {:>8} {failed_color}{:>6}{WHITE} {:>9} {:>9}",
+                timing.count,
+                timing.failed,
+                DisplayElapsed(timing.average()).to_string(),
+                DisplayElapsed(timing.max()).to_string(),
+            )?;
+        }
+        write!(
+            f,
+            "{GREY}Use `{YELLOW}perf reset{GREY}` to start over{WHITE}"
+        )
+    }
+}
+
+pub struct DisplayGeoKey(pub KeySource, pub KeyUsage);
+
+impl Display for DisplayGeoKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (source, usage) = (self.0, self.1);
+        match source {
+            KeySource::Bundled => writeln!(
+                f,
+                "Location lookups use the key bundled with MatchWire: {} of {BUNDLED_DAILY_LOOKUPS} today",
+                usage.lookups
+            )?,
+            KeySource::Saved => writeln!(
+                f,
+                "Location lookups use your saved key: {} today",
+                usage.lookups
+            )?,
+            KeySource::Env => writeln!(
+                f,
+                "Location lookups use the key in {YELLOW}{GEO_KEY_ENV}{WHITE}: {} today",
+                usage.lookups
+            )?,
+        }
+        if usage.rate_limited > 0 {
+            writeln!(
+                f,
+                "{YELLOW}{} lookups were rate limited today{WHITE}",
+                usage.rate_limited
+            )?;
+        }
+        match source {
+            KeySource::Bundled => write!(
+                f,
+                "{GREY}Every user shares the bundled key, add a free key of your own from findip.net with `{YELLOW}geo-key set <KEY>{GREY}`{WHITE}"
+            ),
+            KeySource::Saved => write!(
+                f,
+                "{GREY}Use `{YELLOW}geo-key clear{GREY}` to go back to the bundled key{WHITE}"
+            ),
+            KeySource::Env => write!(
+                f,
+                "{GREY}A saved key is only used while {GEO_KEY_ENV} is unset{WHITE}"
+            ),
+        }
+    }
+}
+
+pub struct DisplayTrim<'a>(pub &'a TrimRequest);
+
+impl Display for DisplayTrim<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let request = self.0;
+        let total = request.kept_above + request.candidates.len() + request.left_out_below;
+        write!(
+            f,
+            "{} match but only {} fit in {FAVORITES}",
+            DisplayServerCount(total, GREEN),
+            request.limit
+        )?;
+        if request.kept_above > 0 {
+            write!(
+                f,
+                ", the {} with the most players are kept",
+                request.kept_above
+            )?;
+        }
+        writeln!(f)?;
+        for (i, server) in request.candidates.iter().enumerate() {
+            let (mark, color) = if server.keep {
+                ("keep", GREEN)
+            } else {
+                ("drop", GREY)
+            };
+            writeln!(
+                f,
+                "{:>3}. {color}{mark}{WHITE} {:>2}/{:<2} {} {GREY}{}{WHITE}",
+                i + 1,
+                server.players,
+                server.max_players,
+                server.name,
+                server.addr
+            )?;
+        }
+        if request.left_out_below > 0 {
+            writeln!(
+                f,
+                "{GREY}{} with fewer players {} left out{WHITE}",
+                DisplayCountOf(request.left_out_below, "more server", "more servers"),
+                SingularPlural(request.left_out_below, "is", "are")
+            )?;
+        }
+        write!(
+            f,
+            "{GREY}Enter numbers like `3 5` or `2-4` to switch them between keep and drop{WHITE}"
+        )
+    }
+}
+
+/// Numbered rows of `search` results, the numbers are what `add` picks by
+pub struct DisplaySearch<'a>(pub &'a [SearchResult]);
+
+impl Display for DisplaySearch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, result) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:>3}. ", i + 1)?;
+            match result.players {
+                Some(players) => write!(f, "{:>2}/{:<2} ", players.clients, players.max_clients)?,
+                None => write!(f, "{GREY}--/--{WHITE} ")?,
+            }
+            match result.region {
+                Some([a, b]) => write!(f, "{a}{b} ")?,
+                None => write!(f, "{GREY}??{WHITE} ")?,
+            }
+            match result.name {
+                Some(ref name) => write!(f, "{name} ")?,
+                None => write!(f, "{GREY}Unnamed{WHITE} ")?,
+            }
+            write!(f, "{GREY}{}{WHITE}", result.server)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rows of `tasks`, the ids are what `tasks cancel` takes
+pub struct DisplayTasks<'a>(pub &'a [RunningTask]);
+
+impl Display for DisplayTasks<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|task| task.name.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, task) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:>3}. {:<width$}  {GREY}running for {}{WHITE}",
+                task.id,
+                task.name,
+                DisplayDuration(task.started.elapsed())
+            )?;
+        }
         Ok(())
     }
 }
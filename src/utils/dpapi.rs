@@ -0,0 +1,68 @@
+#[cfg(feature = "windows-console")]
+use std::ptr::{null, null_mut};
+#[cfg(feature = "windows-console")]
+use winapi::um::{
+    dpapi::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN},
+    winbase::LocalFree,
+    wincrypt::DATA_BLOB,
+};
+
+#[cfg(not(feature = "windows-console"))]
+pub use crate::utils::unsupported::{protect_secret, unprotect_secret};
+
+/// Encrypts `secret` so only the current Windows user can read it back
+#[cfg(feature = "windows-console")]
+pub fn protect_secret(secret: &[u8]) -> Result<Vec<u8>, String> {
+    dpapi_call(secret, |input, output| unsafe {
+        CryptProtectData(
+            input,
+            null(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            output,
+        )
+    })
+}
+
+#[cfg(feature = "windows-console")]
+pub fn unprotect_secret(protected: &[u8]) -> Result<Vec<u8>, String> {
+    dpapi_call(protected, |input, output| unsafe {
+        CryptUnprotectData(
+            input,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            output,
+        )
+    })
+}
+
+#[cfg(feature = "windows-console")]
+fn dpapi_call(
+    data: &[u8],
+    call: impl FnOnce(*mut DATA_BLOB, *mut DATA_BLOB) -> i32,
+) -> Result<Vec<u8>, String> {
+    let mut input = DATA_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = DATA_BLOB {
+        cbData: 0,
+        pbData: null_mut(),
+    };
+    if call(&mut input, &mut output) == 0 {
+        return Err(format!(
+            "DPAPI call failed, {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    // SAFETY: on success DPAPI allocates `output` with `LocalAlloc`, it is copied then freed
+    let bytes =
+        unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+    unsafe { LocalFree(output.pbData.cast()) };
+    Ok(bytes)
+}
@@ -0,0 +1,84 @@
+use crate::utils::{game_state::GameState, toast::show_toast};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::{broadcast, watch};
+use tracing::trace;
+
+/// Events a slow receiver can fall behind by before it misses the oldest ones
+const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub enum AppEvent {
+    /// H2M console output read in one go, newline separated when several lines were read
+    ConsoleLine(Arc<str>),
+    GameStateChanged(GameState),
+    /// The cache file was written
+    CacheUpdated,
+    /// A background task ran to completion
+    TaskFinished(&'static str),
+    /// Shown as a desktop notification when notifications are enabled
+    Notification {
+        title: String,
+        body: String,
+    },
+}
+
+/// Broadcast of things that happened somewhere in the app, producers publish without knowing who listens
+#[derive(Clone)]
+pub struct EventBus(broadcast::Sender<AppEvent>);
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus(broadcast::Sender::new(EVENT_CAPACITY))
+    }
+}
+
+impl EventBus {
+    /// Events published while nobody is subscribed are dropped
+    #[inline]
+    pub fn publish(&self, event: AppEvent) {
+        let _ = self.0.send(event);
+    }
+
+    #[inline]
+    pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
+        self.0.subscribe()
+    }
+
+    #[inline]
+    pub fn notify(&self, title: impl Into<String>, body: impl Into<String>) {
+        self.publish(AppEvent::Notification {
+            title: title.into(),
+            body: body.into(),
+        });
+    }
+}
+
+/// Publishes every change of the game state until the sender is dropped
+pub async fn forward_game_state(mut game_state: watch::Receiver<GameState>, bus: EventBus) {
+    while game_state.changed().await.is_ok() {
+        let state = game_state.borrow_and_update().clone();
+        bus.publish(AppEvent::GameStateChanged(state));
+    }
+}
+
+/// Shows [`AppEvent::Notification`]s as desktop notifications while `enabled` is set
+pub async fn notification_loop(
+    mut events: broadcast::Receiver<AppEvent>,
+    enabled: Arc<AtomicBool>,
+) {
+    loop {
+        match events.recv().await {
+            Ok(AppEvent::Notification { title, body }) if enabled.load(Ordering::SeqCst) => {
+                show_toast(&title, &body)
+            }
+            Ok(_) => (),
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                trace!("Notification loop missed {missed} events")
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use crate::{contains_required_files, error::Error, REQUIRED_FILES};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+/// Setting that pins the game directory, `--game-dir` saves to it
+pub const GAME_DIR_KEY: &str = "launch.game-dir";
+const MWR_STEAM_DIR: &str = "Call of Duty Modern Warfare Remastered";
+const LIBRARY_FOLDERS: &str = "libraryfolders.vdf";
+#[cfg(not(target_os = "linux"))]
+const DEFAULT_STEAM: &str = r"C:\Program Files (x86)\Steam";
+#[cfg(target_os = "linux")]
+const DEFAULT_STEAM: [&str; 2] = [".steam/steam", ".local/share/Steam"];
+
+/// Where a game directory was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirSource {
+    Setting,
+    Current,
+    Steam,
+    Installed,
+}
+
+/// Library paths listed in Steam's `libraryfolders.vdf`. Handles both the current layout, where each
+/// numbered block has a `"path"` key, and the older one where the numbered keys hold the path
+pub fn library_folders(vdf: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut depth = 0_usize;
+    let mut key = None;
+    let mut chars = vdf.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                depth += 1;
+                key = None;
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                key = None;
+            }
+            '"' => {
+                let token = read_quoted(&mut chars);
+                let Some(name) = key.take() else {
+                    key = Some(token);
+                    continue;
+                };
+                let is_path = match depth {
+                    1 => name.parse::<u32>().is_ok(),
+                    2 => name.eq_ignore_ascii_case("path"),
+                    _ => false,
+                };
+                if is_path && !token.is_empty() {
+                    paths.push(PathBuf::from(token));
+                }
+            }
+            _ => (),
+        }
+    }
+    paths
+}
+
+/// Reads up to the closing quote, vdf escapes backslashes and quotes with a backslash
+fn read_quoted(chars: &mut std::str::Chars) -> String {
+    let mut token = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => token.extend(chars.next()),
+            _ => token.push(c),
+        }
+    }
+    token
+}
+
+fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = registry::steam_roots();
+    #[cfg(not(target_os = "linux"))]
+    roots.push(PathBuf::from(DEFAULT_STEAM));
+    #[cfg(target_os = "linux")]
+    if let Some(home) = std::env::var_os("HOME") {
+        roots.extend(DEFAULT_STEAM.map(|dir| Path::new(&home).join(dir)));
+    }
+    roots
+}
+
+/// Where the Steam game installed under `folder` would be in every Steam library on this machine
+pub fn steam_game_dirs(folder: &str) -> Vec<PathBuf> {
+    let mut libraries = Vec::new();
+    for root in steam_roots() {
+        let listed = std::fs::read_to_string(root.join("steamapps").join(LIBRARY_FOLDERS))
+            .map(|vdf| library_folders(&vdf))
+            .unwrap_or_default();
+        libraries.push(root);
+        libraries.extend(listed);
+    }
+    libraries
+        .into_iter()
+        .map(|library| library.join("steamapps").join("common").join(folder))
+        .collect()
+}
+
+/// Directories that may hold the game, most likely first, without repeats
+fn candidates() -> Vec<(PathBuf, DirSource)> {
+    let mut found = Vec::<(PathBuf, DirSource)>::new();
+    let current = std::env::current_dir().ok();
+    let discovered = current
+        .into_iter()
+        .map(|dir| (dir, DirSource::Current))
+        .chain(
+            steam_game_dirs(MWR_STEAM_DIR)
+                .into_iter()
+                .map(|dir| (dir, DirSource::Steam)),
+        )
+        .chain(
+            registry::install_locations()
+                .into_iter()
+                .map(|dir| (dir, DirSource::Installed)),
+        );
+    for (dir, source) in discovered {
+        if !found.iter().any(|(seen, _)| same_dir(seen, &dir)) {
+            found.push((dir, source));
+        }
+    }
+    found
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+async fn is_file(path: &Path) -> bool {
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+/// Path of the game client to launch and where its directory was found. `configured` is the only
+/// directory checked when given, otherwise the first discovered directory with a supported client
+/// is used. Directories with the game but no client are only reported if nothing better is found
+pub async fn find_game(configured: Option<&Path>) -> Result<(PathBuf, DirSource), Error> {
+    if let Some(dir) = configured {
+        if !is_file(&dir.join(REQUIRED_FILES[0])).await {
+            return Err(Error::GameDir(Cow::Owned(format!(
+                "{} was not found in {GAME_DIR_KEY} '{}'",
+                REQUIRED_FILES[0],
+                dir.display()
+            ))));
+        }
+        return contains_required_files(dir)
+            .await
+            .map(|path| (path, DirSource::Setting));
+    }
+    let mut missing_client = None;
+    for (dir, source) in candidates() {
+        if !is_file(&dir.join(REQUIRED_FILES[0])).await {
+            continue;
+        }
+        match contains_required_files(&dir).await {
+            Ok(path) => return Ok((path, source)),
+            Err(err) => {
+                missing_client.get_or_insert(err);
+            }
+        }
+    }
+    Err(missing_client.unwrap_or(Error::GameDir(Cow::Borrowed(
+        "h1_mp64_ship.exe was not found in the current directory, a Steam library or the installed programs",
+    ))))
+}
+
+#[cfg(feature = "windows-console")]
+mod registry {
+    use std::path::PathBuf;
+    use winapi::{
+        shared::{
+            minwindef::{DWORD, HKEY},
+            winerror::ERROR_SUCCESS,
+        },
+        um::{
+            winnt::{KEY_READ, KEY_WOW64_32KEY, KEY_WOW64_64KEY},
+            winreg::{
+                RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY_CURRENT_USER,
+                HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ,
+            },
+        },
+    };
+
+    /// Steam app id of Call of Duty: Modern Warfare Remastered
+    const MWR_APP_ID: &str = "393080";
+    const UNINSTALL: &str = r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall";
+    const MWR_NAME: &str = "Modern Warfare Remastered";
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// Registry key opened for reading, closed on drop
+    struct Key(HKEY);
+
+    impl Key {
+        fn open(root: HKEY, path: &str, view: DWORD) -> Option<Self> {
+            let mut key: HKEY = std::ptr::null_mut();
+            let status =
+                unsafe { RegOpenKeyExW(root, wide(path).as_ptr(), 0, KEY_READ | view, &mut key) };
+            (status == ERROR_SUCCESS as i32).then_some(Key(key))
+        }
+
+        fn string(&self, name: &str) -> Option<String> {
+            let name = wide(name);
+            let mut size: DWORD = 0;
+            unsafe {
+                if RegGetValueW(
+                    self.0,
+                    std::ptr::null(),
+                    name.as_ptr(),
+                    RRF_RT_REG_SZ,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    &mut size,
+                ) != ERROR_SUCCESS as i32
+                {
+                    return None;
+                }
+                let mut buffer = vec![0_u16; size as usize / 2];
+                if RegGetValueW(
+                    self.0,
+                    std::ptr::null(),
+                    name.as_ptr(),
+                    RRF_RT_REG_SZ,
+                    std::ptr::null_mut(),
+                    buffer.as_mut_ptr() as *mut _,
+                    &mut size,
+                ) != ERROR_SUCCESS as i32
+                {
+                    return None;
+                }
+                let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+                Some(String::from_utf16_lossy(&buffer[..len])).filter(|value| !value.is_empty())
+            }
+        }
+
+        fn subkeys(&self) -> Vec<String> {
+            let mut names = Vec::new();
+            let mut buffer = [0_u16; 256];
+            for i in 0.. {
+                let mut len = buffer.len() as DWORD;
+                let status = unsafe {
+                    RegEnumKeyExW(
+                        self.0,
+                        i,
+                        buffer.as_mut_ptr(),
+                        &mut len,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    )
+                };
+                if status != ERROR_SUCCESS as i32 {
+                    break;
+                }
+                names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+            }
+            names
+        }
+    }
+
+    impl Drop for Key {
+        fn drop(&mut self) {
+            unsafe {
+                RegCloseKey(self.0);
+            }
+        }
+    }
+
+    /// Steam install directory of the current user, then the machine wide one
+    pub(super) fn steam_roots() -> Vec<PathBuf> {
+        [
+            (HKEY_CURRENT_USER, r"Software\Valve\Steam", "SteamPath", 0),
+            (
+                HKEY_LOCAL_MACHINE,
+                r"SOFTWARE\Valve\Steam",
+                "InstallPath",
+                KEY_WOW64_32KEY,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(root, path, value, view)| Key::open(root, path, view)?.string(value))
+        .map(PathBuf::from)
+        .collect()
+    }
+
+    /// Install locations of uninstall entries for MWR, from both registry views and the current user
+    pub(super) fn install_locations() -> Vec<PathBuf> {
+        let steam_entry = format!("Steam App {MWR_APP_ID}");
+        let mut found = Vec::new();
+        for (root, view) in [
+            (HKEY_LOCAL_MACHINE, KEY_WOW64_64KEY),
+            (HKEY_LOCAL_MACHINE, KEY_WOW64_32KEY),
+            (HKEY_CURRENT_USER, 0),
+        ] {
+            let Some(uninstall) = Key::open(root, UNINSTALL, view) else {
+                continue;
+            };
+            for name in uninstall.subkeys() {
+                let Some(entry) = Key::open(root, &format!(r"{UNINSTALL}\{name}"), view) else {
+                    continue;
+                };
+                let is_mwr = name == steam_entry
+                    || entry
+                        .string("DisplayName")
+                        .is_some_and(|display| display.contains(MWR_NAME));
+                if let Some(location) = entry.string("InstallLocation").filter(|_| is_mwr) {
+                    // some installers save the location with surrounding quotes
+                    found.push(PathBuf::from(location.trim_matches('"')));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// The registry only exists on Windows, builds without the `windows-console` feature rely on the
+/// default Steam locations
+#[cfg(not(feature = "windows-console"))]
+mod registry {
+    use std::path::PathBuf;
+
+    pub(super) fn steam_roots() -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    pub(super) fn install_locations() -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Console output that signals H2M is loading and would drop or choke on injected commands
+pub const BUSY_MARKERS: [&str; 3] = ["Loading fastfile", "Connecting to server", "Joining "];
+/// Console output printed when the game leaves a server
+const DISCONNECT_MARKERS: [&str; 2] = ["Disconnected from server", "EXE_DISCONNECTED"];
+/// Game log lines echoed to the console when a map starts and ends
+const INIT_GAME: &str = "InitGame:";
+const SHUTDOWN_GAME: &str = "ShutdownGame:";
+
+/// What the game is doing, derived from H2M's process and console output
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum GameState {
+    /// H2M is not running, or is running without being launched or followed by us
+    #[default]
+    Idle,
+    /// H2M was started and has not finished loading into the main menu
+    Launching,
+    InMenu,
+    /// Connecting to, or changing maps on, `server`
+    Loading {
+        server: Arc<str>,
+    },
+    InGame {
+        server: Arc<str>,
+    },
+    Crashed {
+        exit_code: u32,
+    },
+}
+
+impl GameState {
+    /// Name or address of the server being joined or played on
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            GameState::Loading { server } | GameState::InGame { server } => Some(server),
+            _ => None,
+        }
+    }
+
+    /// Commands sent to the console while the game is launching or loading can be dropped
+    pub fn accepts_commands(&self) -> bool {
+        matches!(self, GameState::InMenu | GameState::InGame { .. })
+    }
+
+    /// State after the console printed `line`, which is expected to be stripped of ansi sequences.
+    /// `None` if the line does not change the state
+    pub fn after_line(&self, line: &str) -> Option<GameState> {
+        match self {
+            GameState::Idle | GameState::Crashed { .. } => None,
+            _ if DISCONNECT_MARKERS
+                .iter()
+                .any(|marker| line.contains(marker)) =>
+            {
+                Some(GameState::InMenu)
+            }
+            GameState::Loading { server } if line.contains(INIT_GAME) => Some(GameState::InGame {
+                server: Arc::clone(server),
+            }),
+            GameState::InGame { server } if line.contains(SHUTDOWN_GAME) => {
+                Some(GameState::Loading {
+                    server: Arc::clone(server),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// State after a batch of console output, `busy` is set if any line had one of [`BUSY_MARKERS`].
+    /// A quiet batch means the menu or map finished loading
+    pub fn after_batch(&self, busy: bool) -> Option<GameState> {
+        match self {
+            GameState::Launching if !busy => Some(GameState::InMenu),
+            GameState::Loading { server } if !busy => Some(GameState::InGame {
+                server: Arc::clone(server),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces the state with the result of `transition`, receivers are only notified when the state
+/// actually changes
+pub fn transition(
+    sender: &watch::Sender<GameState>,
+    transition: impl FnOnce(&GameState) -> Option<GameState>,
+) {
+    sender.send_if_modified(|state| match transition(state) {
+        Some(next) if next != *state => {
+            *state = next;
+            true
+        }
+        _ => false,
+    });
+}
+
+#[inline]
+pub fn set_state(sender: &watch::Sender<GameState>, next: GameState) {
+    transition(sender, |_| Some(next));
+}
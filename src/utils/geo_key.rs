@@ -0,0 +1,271 @@
+use crate::{
+    location_api_key::FIND_IP_NET_PRIVATE_KEY,
+    utils::dpapi::{protect_secret, unprotect_secret},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Env var holding a findip.net key, it is used for the session and never saved
+pub const GEO_KEY_ENV: &str = "MATCH_WIRE_GEO_KEY";
+/// Lookups a day made with the bundled key, after that regions of new servers stay unknown until the
+/// next day (UTC) or until a key of your own is added
+pub const BUNDLED_DAILY_LOOKUPS: u32 = 500;
+
+const GEO_KEY_FILE: &str = "geo_key.json";
+const TOKEN_QUERY: &str = "/?token=";
+
+/// A key of your own is used before the bundled key, whose quota every user shares
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    Bundled,
+    /// Saved with `geo-key set`
+    Saved,
+    /// Read from [`GEO_KEY_ENV`]
+    Env,
+}
+
+/// Lookups made with a key on `day`, days are counted since the unix epoch
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct KeyUsage {
+    pub day: u64,
+    pub lookups: u32,
+    /// Lookups the API answered 429
+    pub rate_limited: u32,
+}
+
+impl KeyUsage {
+    /// Starts counting over when `today` is a new day
+    fn today(&mut self, today: u64) -> &mut Self {
+        if self.day != today {
+            *self = KeyUsage {
+                day: today,
+                ..KeyUsage::default()
+            };
+        }
+        self
+    }
+}
+
+/// Contents of [`GEO_KEY_FILE`]
+#[derive(Default, Serialize, Deserialize)]
+struct GeoKeyFile {
+    /// base64 of the DPAPI protected key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    protected: Option<String>,
+    #[serde(default)]
+    usage: BTreeMap<KeySource, KeyUsage>,
+}
+
+struct GeoKeys {
+    own: Option<(String, KeySource)>,
+    protected: Option<String>,
+    usage: BTreeMap<KeySource, KeyUsage>,
+}
+
+static KEYS: Mutex<GeoKeys> = Mutex::new(GeoKeys {
+    own: None,
+    protected: None,
+    usage: BTreeMap::new(),
+});
+
+fn keys() -> MutexGuard<'static, GeoKeys> {
+    KEYS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86400)
+}
+
+/// Token of the bundled key, without the query it is appended with
+fn bundled_token() -> &'static str {
+    FIND_IP_NET_PRIVATE_KEY
+        .strip_prefix(TOKEN_QUERY)
+        .unwrap_or(FIND_IP_NET_PRIVATE_KEY)
+}
+
+/// findip.net keys are plain tokens, anything that would change the request url is refused
+pub fn validate_key(key: &str) -> Result<&str, String> {
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(String::from(
+            "Expected the token findip.net shows on its dashboard, letters and digits only",
+        ));
+    }
+    Ok(key)
+}
+
+/// Reads the saved key and today's usage, a key in [`GEO_KEY_ENV`] takes priority over the saved one.
+/// Returns what could not be read
+pub fn init_geo_key(local_dir: Option<&Path>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let file = local_dir
+        .and_then(|dir| std::fs::read(dir.join(GEO_KEY_FILE)).ok())
+        .and_then(
+            |contents| match serde_json::from_slice::<GeoKeyFile>(&contents) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    problems.push(format!("Ignoring {GEO_KEY_FILE}, {err}"));
+                    None
+                }
+            },
+        )
+        .unwrap_or_default();
+    let saved = file.protected.as_deref().and_then(|encoded| {
+        let key = STANDARD
+            .decode(encoded)
+            .map_err(|err| err.to_string())
+            .and_then(|protected| unprotect_secret(&protected))
+            .and_then(|key| String::from_utf8(key).map_err(|err| err.to_string()));
+        match key {
+            Ok(key) => Some(key),
+            Err(err) => {
+                problems.push(format!(
+                    "Could not read the saved findip.net key, {err}. Save it again with `geo-key set`"
+                ));
+                None
+            }
+        }
+    });
+    let env = std::env::var(GEO_KEY_ENV)
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .and_then(|key| match validate_key(&key) {
+            Ok(key) => Some(key.to_string()),
+            Err(err) => {
+                problems.push(format!("Ignoring {GEO_KEY_ENV}, {err}"));
+                None
+            }
+        });
+    let mut keys = keys();
+    keys.own = env
+        .map(|key| (key, KeySource::Env))
+        .or(saved.map(|key| (key, KeySource::Saved)));
+    keys.protected = file.protected;
+    keys.usage = file.usage;
+    problems
+}
+
+/// Key a lookup is made with
+pub struct GeoKey {
+    pub source: KeySource,
+    /// Appended to the lookup url
+    pub query: Cow<'static, str>,
+}
+
+/// Counts a lookup against the key it is made with, `None` once the bundled key was used
+/// [`BUNDLED_DAILY_LOOKUPS`] times today and there is no key of your own
+pub fn take_lookup() -> Option<GeoKey> {
+    let mut keys = keys();
+    let today = today();
+    let key = match keys.own {
+        Some((ref key, source)) => GeoKey {
+            source,
+            query: Cow::Owned(format!("{TOKEN_QUERY}{key}")),
+        },
+        None => GeoKey {
+            source: KeySource::Bundled,
+            query: Cow::Borrowed(FIND_IP_NET_PRIVATE_KEY),
+        },
+    };
+    let usage = keys.usage.entry(key.source).or_default().today(today);
+    if key.source == KeySource::Bundled && usage.lookups >= BUNDLED_DAILY_LOOKUPS {
+        return None;
+    }
+    usage.lookups += 1;
+    Some(key)
+}
+
+pub fn record_rate_limited(source: KeySource) {
+    keys()
+        .usage
+        .entry(source)
+        .or_default()
+        .today(today())
+        .rate_limited += 1;
+}
+
+/// Key used for the next lookup and how much it was used today
+pub fn key_status() -> (KeySource, KeyUsage) {
+    let mut keys = keys();
+    let source = keys
+        .own
+        .as_ref()
+        .map_or(KeySource::Bundled, |&(_, source)| source);
+    let usage = *keys.usage.entry(source).or_default().today(today());
+    (source, usage)
+}
+
+pub fn save_geo_keys(local_dir: &Path) -> io::Result<()> {
+    let keys = keys();
+    let file = GeoKeyFile {
+        protected: keys.protected.clone(),
+        usage: keys.usage.clone(),
+    };
+    let contents = serde_json::to_vec_pretty(&file)?;
+    std::fs::write(local_dir.join(GEO_KEY_FILE), contents)
+}
+
+/// Encrypts and saves `key`, it is used right away unless [`GEO_KEY_ENV`] is set
+pub fn set_key(local_dir: &Path, key: &str) -> Result<(), String> {
+    let key = validate_key(key)?;
+    let protected = protect_secret(key.as_bytes())?;
+    {
+        let mut keys = keys();
+        keys.protected = Some(STANDARD.encode(protected));
+        if keys
+            .own
+            .as_ref()
+            .is_none_or(|&(_, source)| source != KeySource::Env)
+        {
+            keys.own = Some((key.to_string(), KeySource::Saved));
+        }
+    }
+    save_geo_keys(local_dir).map_err(|err| format!("Could not save {GEO_KEY_FILE}, {err}"))
+}
+
+/// Removes the saved key, returns `false` if none was saved
+pub fn clear_key(local_dir: &Path) -> Result<bool, String> {
+    {
+        let mut keys = keys();
+        if keys.protected.take().is_none() {
+            return Ok(false);
+        }
+        if keys
+            .own
+            .as_ref()
+            .is_some_and(|&(_, source)| source == KeySource::Saved)
+        {
+            keys.own = None;
+        }
+    }
+    save_geo_keys(local_dir)
+        .map(|_| true)
+        .map_err(|err| format!("Could not save {GEO_KEY_FILE}, {err}"))
+}
+
+/// Replaces every known key in `text`, used on logs before they are shared
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let keys = keys();
+    let own = keys.own.as_ref().map(|(key, _)| key.as_str());
+    let mut text = Cow::Borrowed(text);
+    for key in std::iter::once(bundled_token())
+        .chain(own)
+        .filter(|key| !key.is_empty())
+    {
+        if text.contains(key) {
+            text = Cow::Owned(text.replace(key, "***"));
+        }
+    }
+    text
+}
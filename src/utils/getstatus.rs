@@ -0,0 +1,111 @@
+use crate::utils::http::is_offline;
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+use tokio::net::UdpSocket;
+
+/// Prefix of every connectionless packet
+pub const OOB_HEADER: [u8; 4] = [0xff; 4];
+const STATUS_REQUEST: &[u8] = b"getstatus";
+const STATUS_HEADER: &[u8] = b"statusResponse";
+/// Largest UDP payload over IPv4
+pub const MAX_PACKET: usize = 65_507;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerStatus {
+    pub score: i32,
+    /// Bots report a ping of 0
+    pub ping: u16,
+    /// As sent by the server, color codes included
+    pub name: String,
+}
+
+/// Reply to a `getstatus` query, unlike `getInfo` it lists every player by name
+#[derive(Debug, Default)]
+pub struct ServerStatus {
+    /// Server info dvars, e.g. `sv_hostname`, `mapname` and `g_gametype`
+    pub dvars: HashMap<String, String>,
+    pub players: Vec<PlayerStatus>,
+}
+
+/// Binds a socket on the unspecified address of the same family as `server` and connects it
+pub async fn connected_socket(server: SocketAddr) -> std::io::Result<UdpSocket> {
+    let bind = match server {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    Ok(socket)
+}
+
+/// `None` if `packet` is not a `statusResponse`
+pub fn parse_status_response(packet: &[u8]) -> Option<ServerStatus> {
+    let body = packet
+        .strip_prefix(&OOB_HEADER)?
+        .strip_prefix(STATUS_HEADER)?;
+    let body = String::from_utf8_lossy(body);
+    let mut lines = body.lines().skip_while(|line| line.is_empty());
+
+    let mut dvars = HashMap::new();
+    let mut info = lines.next().unwrap_or_default().split('\\');
+    if info.next().is_some_and(|leading| !leading.is_empty()) {
+        return None;
+    }
+    while let (Some(key), Some(value)) = (info.next(), info.next()) {
+        dvars.insert(key.to_string(), value.to_string());
+    }
+
+    let players = lines.filter_map(parse_player_line).collect();
+    Some(ServerStatus { dvars, players })
+}
+
+/// Player lines are `<score> <ping> "<name>"`
+fn parse_player_line(line: &str) -> Option<PlayerStatus> {
+    let (numbers, name) = line.split_once('"')?;
+    let mut numbers = numbers.split_whitespace();
+    Some(PlayerStatus {
+        score: numbers.next()?.parse().ok()?,
+        ping: numbers.next()?.parse().ok()?,
+        name: name.strip_suffix('"').unwrap_or(name).to_string(),
+    })
+}
+
+pub async fn get_status(server: SocketAddr, timeout: Duration) -> Result<ServerStatus, String> {
+    if is_offline() {
+        return Err(format!("{server} was not queried, started with --offline"));
+    }
+    let socket = connected_socket(server)
+        .await
+        .map_err(|err| format!("Could not reach {server}, {err}"))?;
+    let mut request = Vec::with_capacity(OOB_HEADER.len() + STATUS_REQUEST.len());
+    request.extend_from_slice(&OOB_HEADER);
+    request.extend_from_slice(STATUS_REQUEST);
+    socket
+        .send(&request)
+        .await
+        .map_err(|err| format!("Could not send to {server}, {err}"))?;
+
+    let mut buf = vec![0; MAX_PACKET];
+    let wait_for_status = async {
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            if let Some(status) = parse_status_response(&buf[..len]) {
+                return Ok::<_, std::io::Error>(status);
+            }
+        }
+    };
+    match tokio::time::timeout(timeout, wait_for_status).await {
+        Ok(Ok(status)) => Ok(status),
+        Ok(Err(err)) => Err(format!("Could not read the reply from {server}, {err}")),
+        Err(_) => Err(format!("{server} did not reply to getstatus")),
+    }
+}
+
+/// Round trip of a `getstatus` request
+pub async fn measure_ping(server: SocketAddr, timeout: Duration) -> Result<Duration, String> {
+    let start = Instant::now();
+    get_status(server, timeout).await.map(|_| start.elapsed())
+}
@@ -0,0 +1,425 @@
+use crate::{
+    error::FetchError,
+    utils::{config::NetworkConfig, perf},
+    LOG_ONLY,
+};
+use reqwest::{
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(8);
+/// Longest wait for the next chunk of a response, requests may still set an overall timeout
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+const RETRY_TIME_SCALE: Duration = Duration::from_millis(500);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+pub type FetchFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<HttpResponse, FetchError>> + Send + 'a>>;
+
+/// Shared handle to the active [`HttpFetch`] implementation
+pub type Http = Arc<dyn HttpFetch>;
+
+/// Outbound HTTP requests, served from fixture files by [`FixtureFetch`] or saved as fixtures by
+/// [`RecordFetch`] to replay a run later
+pub trait HttpFetch: Send + Sync {
+    /// Sends a GET request to `url`, a response with an error status is still returned as `Ok`
+    ///
+    /// `timeout` replaces the configured network timeout for callers that retry on their own, such
+    /// requests are only attempted once
+    fn get<'a>(&'a self, url: &'a str, timeout: Option<Duration>) -> FetchFuture<'a>;
+
+    /// Sends a GET request to `url` that only asks for the body if it changed since `validator` was
+    /// received, an unchanged resource is answered with [`StatusCode::NOT_MODIFIED`] and no body.
+    /// Implementations without conditional requests always send the body
+    fn get_if_changed<'a>(&'a self, url: &'a str, _validator: &'a Validator) -> FetchFuture<'a> {
+        self.get(url, None)
+    }
+}
+
+/// `ETag` and `Last-Modified` headers of a response, sent back with [`HttpFetch::get_if_changed`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validator {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validator {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Validator {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+    pub validator: Validator,
+}
+
+impl HttpResponse {
+    #[inline]
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED
+    }
+
+    pub fn error_for_status(self) -> Result<Self, FetchError> {
+        if self.status.is_success() {
+            Ok(self)
+        } else {
+            Err(FetchError::Status(self.status))
+        }
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, FetchError> {
+        serde_json::from_slice(&self.body).map_err(FetchError::from)
+    }
+}
+
+/// Sends requests over the network with a [`NetworkClient`], the default implementation
+pub struct ReqwestFetch {
+    network: NetworkClient,
+}
+
+impl ReqwestFetch {
+    pub fn new(network: NetworkClient) -> Self {
+        ReqwestFetch { network }
+    }
+}
+
+struct SharedClient {
+    config: NetworkConfig,
+    /// Built from `config` on first use
+    client: OnceLock<reqwest::Client>,
+}
+
+impl SharedClient {
+    fn client(&self) -> &reqwest::Client {
+        self.client.get_or_init(|| {
+            build_client(&self.config)
+                .or_else(|_| {
+                    build_client(&NetworkConfig {
+                        proxy: None,
+                        ..self.config.clone()
+                    })
+                })
+                .expect("TLS backend is available")
+        })
+    }
+}
+
+fn build_client(config: &NetworkConfig) -> Result<reqwest::Client, reqwest::Error> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .gzip(true)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE);
+    if config.timeout > 0 {
+        builder = builder.timeout(Duration::from_secs(config.timeout));
+    }
+    if let Some(ref proxy) = config.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    builder.build()
+}
+
+/// The client network requests are sent with, built from the `network` settings. Clones share the
+/// client, so a change made with [`NetworkClient::configure`] reaches every holder. Idle connections are
+/// kept in its pool so repeated requests to a host, like a batch of location lookups, skip the TLS
+/// handshake
+#[derive(Clone)]
+pub struct NetworkClient {
+    shared: Arc<RwLock<Arc<SharedClient>>>,
+}
+
+impl Default for NetworkClient {
+    fn default() -> Self {
+        NetworkClient::new(&NetworkConfig::default())
+    }
+}
+
+impl NetworkClient {
+    /// An invalid proxy is left out, see [`NetworkClient::configure`] to have it reported
+    pub fn new(config: &NetworkConfig) -> Self {
+        NetworkClient {
+            shared: Arc::new(RwLock::new(Arc::new(SharedClient {
+                config: config.clone(),
+                client: OnceLock::new(),
+            }))),
+        }
+    }
+
+    /// Rebuilds the client with `config`, requests sent after this use it. An unchanged `config` keeps
+    /// the current client. An invalid proxy is returned as `Err` and the client is built without it
+    pub fn configure(&self, config: &NetworkConfig) -> Result<(), String> {
+        let mut shared = self.shared.write().unwrap_or_else(|err| err.into_inner());
+        if shared.config == *config {
+            return Ok(());
+        }
+        *shared = Arc::new(SharedClient {
+            config: config.clone(),
+            client: OnceLock::new(),
+        });
+        match config.proxy {
+            Some(ref proxy) => reqwest::Proxy::all(proxy)
+                .map(|_| ())
+                .map_err(|err| format!("Ignoring network.proxy, {err}")),
+            None => Ok(()),
+        }
+    }
+
+    fn shared(&self) -> Arc<SharedClient> {
+        Arc::clone(&self.shared.read().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    /// The client as it is now, built on first use
+    pub fn client(&self) -> reqwest::Client {
+        self.shared().client().clone()
+    }
+}
+
+/// Failures worth another attempt, the host could not be reached in time or had a temporary problem
+fn is_transient(res: &Result<reqwest::Response, reqwest::Error>) -> bool {
+    match res {
+        Ok(response) => response.status().is_server_error(),
+        Err(err) => err.is_connect() || err.is_timeout(),
+    }
+}
+
+impl ReqwestFetch {
+    async fn send(
+        &self,
+        url: &str,
+        timeout: Option<Duration>,
+        validator: Option<&Validator>,
+    ) -> Result<HttpResponse, FetchError> {
+        let shared = self.network.shared();
+        let (client, retries) = (shared.client(), shared.config.retries);
+        let started = Instant::now();
+        let attempts = if timeout.is_some() {
+            1
+        } else {
+            retries.saturating_add(1)
+        };
+        let mut attempt = 1;
+        let response = loop {
+            let mut request = client.get(url);
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            if let Some(validator) = validator {
+                if let Some(ref etag) = validator.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(ref last_modified) = validator.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            let res = request.send().await;
+            if attempt >= attempts || !is_transient(&res) {
+                let ok = res.as_ref().is_ok_and(|response| {
+                    response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED
+                });
+                perf::record_request(url, started.elapsed(), ok);
+                break res?;
+            }
+            tokio::time::sleep(RETRY_TIME_SCALE * attempt as u32).await;
+            attempt += 1;
+        };
+        let status = response.status();
+        let validator = Validator::from_headers(response.headers());
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse {
+            status,
+            body,
+            validator,
+        })
+    }
+}
+
+impl HttpFetch for ReqwestFetch {
+    fn get<'a>(&'a self, url: &'a str, timeout: Option<Duration>) -> FetchFuture<'a> {
+        Box::pin(self.send(url, timeout, None))
+    }
+
+    fn get_if_changed<'a>(&'a self, url: &'a str, validator: &'a Validator) -> FetchFuture<'a> {
+        Box::pin(self.send(url, None, Some(validator)))
+    }
+}
+
+/// Answers every request with the contents of a file in `dir`, see [`fixture_name`] for how urls map
+/// to file names. Missing fixtures fail the request like an unreachable host would
+pub struct FixtureFetch {
+    dir: PathBuf,
+}
+
+impl FixtureFetch {
+    pub fn new(dir: PathBuf) -> Self {
+        FixtureFetch { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl HttpFetch for FixtureFetch {
+    fn get<'a>(&'a self, url: &'a str, _timeout: Option<Duration>) -> FetchFuture<'a> {
+        Box::pin(async move {
+            let path = self.dir.join(fixture_name(url));
+            match std::fs::read(&path) {
+                Ok(body) => Ok(HttpResponse {
+                    status: StatusCode::OK,
+                    body,
+                    validator: Validator::default(),
+                }),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    Err(FetchError::MissingFixture(path))
+                }
+                Err(source) => Err(FetchError::Fixture { path, source }),
+            }
+        })
+    }
+}
+
+/// Fails every request without sending it, used with `--offline`
+pub struct OfflineFetch;
+
+impl HttpFetch for OfflineFetch {
+    fn get<'a>(&'a self, _url: &'a str, _timeout: Option<Duration>) -> FetchFuture<'a> {
+        Box::pin(async { Err(FetchError::Offline) })
+    }
+}
+
+/// [`OfflineFetch`], also stops `getstatus` queries for the rest of the run, see [`is_offline`]
+pub fn offline_http() -> Http {
+    OFFLINE.store(true, Ordering::Relaxed);
+    Arc::new(OfflineFetch)
+}
+
+/// Whether the app was started with `--offline`, requests that do not go through [`HttpFetch`] check
+/// this before they are sent
+#[inline]
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Passes every request on to `inner` and saves successful responses to `dir` under their
+/// [`fixture_name`], so the run can be repeated later with [`FixtureFetch`]. Responses with an error
+/// status are not saved, replaying them fails like an unreachable host
+pub struct RecordFetch {
+    dir: PathBuf,
+    inner: Http,
+}
+
+impl RecordFetch {
+    pub fn new(dir: PathBuf, inner: Http) -> Self {
+        RecordFetch { dir, inner }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    async fn save(&self, url: &str, response: &HttpResponse) {
+        if !response.status.is_success() {
+            return;
+        }
+        let path = self.dir.join(fixture_name(url));
+        let saved = match tokio::fs::create_dir_all(&self.dir).await {
+            Ok(()) => tokio::fs::write(&path, &response.body).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = saved {
+            error!(name: LOG_ONLY, "Failed to record {}, {err}", path.display());
+        }
+    }
+}
+
+impl HttpFetch for RecordFetch {
+    fn get<'a>(&'a self, url: &'a str, timeout: Option<Duration>) -> FetchFuture<'a> {
+        Box::pin(async move {
+            let response = self.inner.get(url, timeout).await?;
+            self.save(url, &response).await;
+            Ok(response)
+        })
+    }
+
+    /// An unchanged resource is not saved again, the fixture from its first response is kept
+    fn get_if_changed<'a>(&'a self, url: &'a str, validator: &'a Validator) -> FetchFuture<'a> {
+        Box::pin(async move {
+            let response = self.inner.get_if_changed(url, validator).await?;
+            self.save(url, &response).await;
+            Ok(response)
+        })
+    }
+}
+
+/// File name a fixture for `url` is stored under. The scheme, query and fragment are dropped so api
+/// keys never end up in file names, e.g. `http://1.2.3.4:27016/getInfo` -> `1.2.3.4_27016_getInfo.json`
+pub fn fixture_name(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let mut name = url
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string();
+    name.push_str(".json");
+    name
+}
+
+/// The network backed implementation sending requests with `network`, or fixtures from `fixtures` when
+/// given. Network responses are also saved to `record` when given
+pub fn new_http(
+    fixtures: Option<PathBuf>,
+    record: Option<PathBuf>,
+    network: NetworkClient,
+) -> Http {
+    match (fixtures, record) {
+        (Some(dir), _) => Arc::new(FixtureFetch::new(dir)),
+        (None, Some(dir)) => Arc::new(RecordFetch::new(dir, Arc::new(ReqwestFetch::new(network)))),
+        (None, None) => Arc::new(ReqwestFetch::new(network)),
+    }
+}
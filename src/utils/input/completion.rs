@@ -377,6 +377,8 @@ impl From<&'static CommandScheme> for Completion {
             rec_map,
             rec_list,
             value_sets,
+            dynamic_recs: HashMap::new(),
+            interned: HashSet::new(),
             indexer: Indexer::default(),
         }
     }
@@ -394,6 +396,10 @@ pub struct Completion {
     rec_list: Vec<&'static RecData>,
     rec_map: HashMap<&'static str, usize>,
     value_sets: HashMap<usize, HashSet<&'static str>>,
+    /// recomendations for `RecKind::UserDefined` nodes that are only known at runtime, keyed the same as `value_sets`
+    dynamic_recs: HashMap<usize, Vec<&'static str>>,
+    /// every runtime recomendation is leaked once and reused, the set only grows by entries never seen before
+    interned: HashSet<&'static str>,
 }
 
 struct Indexer {
@@ -615,6 +621,36 @@ impl SliceData {
 }
 
 impl Completion {
+    /// Replaces the runtime recomendations for user defined node `name`, does nothing if `name` is not a
+    /// known `RecKind::UserDefined` node
+    pub fn set_dynamic_recs<I: IntoIterator<Item = String>>(&mut self, name: &str, recs: I) {
+        let Some(&i) = self.rec_map.get(name) else {
+            return;
+        };
+        if !matches!(self.rec_list[i].kind, RecKind::UserDefined(_)) {
+            return;
+        }
+        let recs = recs
+            .into_iter()
+            .map(|rec| match self.interned.get(rec.as_str()) {
+                Some(&interned) => interned,
+                None => {
+                    let leaked: &'static str = Box::leak(rec.into_boxed_str());
+                    self.interned.insert(leaked);
+                    leaked
+                }
+            })
+            .collect();
+        self.dynamic_recs.insert(i, recs);
+    }
+
+    /// Returns the parent of the command, argument or alias `name` if it is part of the scheme
+    pub fn parent_of(&self, name: &str) -> Option<&'static str> {
+        self.rec_map
+            .get(name)
+            .and_then(|&i| self.rec_list[i].parent)
+    }
+
     fn rec_data_from_unchecked(&self, recomendation_i: &i8) -> &RecData {
         if !self.indexer.multiple {
             return self.rec_list[self.indexer.list.0];
@@ -1110,6 +1146,12 @@ impl LineReader<'_> {
         if self.curr_token().is_empty() {
             if let Some(recs) = rec_data_1.recs {
                 self.completion.recomendations = recs[..rec_data_1.unique_rec_end()].to_vec();
+            } else if let Some(dynamic) = self
+                .completion
+                .dynamic_recs
+                .get(&self.completion.indexer.list.0)
+            {
+                self.completion.recomendations = dynamic.clone();
             } else {
                 self.completion.recomendations.clear();
             }
@@ -1139,10 +1181,20 @@ impl LineReader<'_> {
             .then(|| rec_data_2.recs.map(|recs| recs.iter()))
             .flatten();
 
+        let dynamic_1 = (!self.curr_token().starts_with('-'))
+            .then(|| {
+                self.completion
+                    .dynamic_recs
+                    .get(&self.completion.indexer.list.0)
+                    .map(|recs| recs.iter())
+            })
+            .flatten();
+
         let add_help = self.completion.add_help().then_some([HELP_STR].iter());
 
         let mut recomendations = std::iter::empty()
             .chain(rec_1.unwrap_or_default())
+            .chain(dynamic_1.unwrap_or_default())
             .chain(rec_2.unwrap_or_default())
             .chain(add_help.unwrap_or_default())
             .filter(|rec| rec.contains(&input_lower))
@@ -1282,9 +1334,11 @@ impl LineReader<'_> {
 
             match kind {
                 RecKind::Argument => format_line(true),
-                RecKind::Value(_) | RecKind::Command => format_line(false),
+                RecKind::Value(_) | RecKind::UserDefined(_) | RecKind::Command => {
+                    format_line(false)
+                }
                 RecKind::Help => format_line(self.completion.curr_command().is_some()),
-                RecKind::UserDefined(_) | RecKind::Null => unreachable!("by guard clause"),
+                RecKind::Null => unreachable!("by guard clause"),
             }
         };
 
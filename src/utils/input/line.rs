@@ -1,5 +1,8 @@
 use crate::{
-    commands::handler::{end_forward, CommandContext, Message},
+    commands::{
+        handler::{end_forward, CommandContext, Message},
+        status::StatusHeader,
+    },
     strip_ansi_sequences,
     utils::input::{
         completion::{CommandScheme, Completion, Direction},
@@ -10,7 +13,10 @@ use crossterm::{
     cursor,
     event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     style::Stylize,
-    terminal::{self, Clear, ClearType::FromCursorDown},
+    terminal::{
+        self, Clear,
+        ClearType::{All, FromCursorDown, Purge},
+    },
     QueueableCommand,
 };
 use std::{
@@ -21,7 +27,6 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicUsize, Ordering},
 };
-use tracing::{error, info, warn};
 
 pub type InputEventHook = dyn Fn(&mut LineReader, Event) -> io::Result<(EventLoop, bool)>;
 pub type LineCallback = dyn Fn(&mut LineReader) -> io::Result<()>;
@@ -34,12 +39,28 @@ pub type AsyncCtxCallback =
 pub struct LineReader<'a> {
     pub completion: Completion,
     pub line: LineData,
+    /// prompt displayed while no input hook or search has replaced it
+    home_prompt: String,
     history: History,
+    search: Option<HistorySearch>,
     term: &'a mut Stdout,
     /// (columns, rows)
     term_size: (u16, u16),
     uneventful: bool,
     cursor_at_start: bool,
+    /// row the terminal cursor was left on by `render` when it is not at the end of the line
+    cursor_row: Option<u16>,
+    /// text removed by the last kill command, inserted back with 'ctrl + y'
+    kill_buf: String,
+    /// set when 'ctrl + c' was pressed on an empty line, a second press quits
+    quit_armed: bool,
+    /// latest progress update of a background task, drawn on its own row above the prompt
+    progress: Option<String>,
+    progress_rendered: bool,
+    /// set while a full screen view is drawn in the alternate screen, the prompt is not rendered
+    screen: bool,
+    /// messages that arrived while `screen` was shown, printed once the prompt is back
+    held: Vec<Message>,
     command_entered: bool,
     input_hooks: VecDeque<InputHook>,
 }
@@ -110,6 +131,8 @@ pub struct LineData {
     prompt_len: u16,
     input: String,
     len: u16,
+    /// number of chars in `input` before the cursor
+    cursor: u16,
     comp_enabled: bool,
     err: bool,
 }
@@ -167,6 +190,80 @@ impl LineData {
     pub fn completion_enabled(&self) -> bool {
         self.comp_enabled
     }
+
+    #[inline]
+    pub fn cursor(&self) -> u16 {
+        self.cursor
+    }
+
+    /// moves the cursor to char index `to`, clamped to the end of the input
+    #[inline]
+    pub fn set_cursor(&mut self, to: u16) {
+        self.cursor = to.min(self.len);
+    }
+
+    /// replaces the input and places the cursor at the end
+    pub fn set_input(&mut self, line: String) {
+        self.len = line.chars().count() as u16;
+        self.cursor = self.len;
+        self.input = line;
+    }
+
+    /// byte index into `input` of the char at `char_i`
+    pub fn byte_index(&self, char_i: u16) -> usize {
+        self.input
+            .char_indices()
+            .nth(char_i as usize)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+
+    /// char index of the start of the word before the cursor
+    pub fn prev_word_start(&self) -> u16 {
+        let chars = self.input.chars().collect::<Vec<_>>();
+        let mut i = self.cursor as usize;
+        while i > 0 && chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !chars[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        i as u16
+    }
+
+    /// char index of the end of the word after the cursor
+    pub fn next_word_end(&self) -> u16 {
+        let chars = self.input.chars().collect::<Vec<_>>();
+        let mut i = self.cursor as usize;
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        i as u16
+    }
+
+    /// removes and returns the chars in `start..end`, the cursor is left at `start`
+    pub fn kill(&mut self, start: u16, end: u16) -> String {
+        let end = end.min(self.len);
+        if start >= end {
+            return String::new();
+        }
+        let (byte_start, byte_end) = (self.byte_index(start), self.byte_index(end));
+        let killed = self.input.drain(byte_start..byte_end).collect();
+        self.len -= end - start;
+        self.cursor = start;
+        killed
+    }
+
+    /// inserts `text` at the cursor and moves the cursor past it
+    pub fn yank(&mut self, text: &str) {
+        let i = self.byte_index(self.cursor);
+        let yank_len = text.chars().count() as u16;
+        self.input.insert_str(i, text);
+        self.len = self.len.saturating_add(yank_len);
+        self.cursor = self.cursor.saturating_add(yank_len);
+    }
 }
 
 #[derive(Default)]
@@ -176,10 +273,57 @@ pub struct History {
     curr_index: usize,
 }
 
-// MARK: TODO
-// Add support for a movable cursor
-// currently `CompletionState` only supports char events at line end
-// `CompletionState` will have to be carefully mannaged if cursor is moveable
+impl History {
+    #[inline]
+    pub fn push(&mut self, entry: String) {
+        self.prev_entries.push(entry);
+    }
+
+    /// index of the newest entry before index `before` that contains `query`, with `wrap` the search
+    /// continues from the newest entry once the oldest match is passed
+    pub fn rfind(&self, query: &str, before: usize, wrap: bool) -> Option<usize> {
+        let matches = |entry: &String| entry.contains(query);
+        let before = before.min(self.prev_entries.len());
+        match self.prev_entries[..before].iter().rposition(matches) {
+            None if wrap => self.prev_entries.iter().rposition(matches),
+            found => found,
+        }
+    }
+}
+
+/// state of an in progress reverse history search, started with 'ctrl + r'
+struct HistorySearch {
+    query: String,
+    /// index into `History.prev_entries` of the current match
+    found: Option<usize>,
+    /// prompt and line to restore once the search ends
+    prompt: String,
+    line: String,
+}
+
+// MARK: IMPROVE
+// `CompletionState` only tracks char events at line end, edits made with the cursor moved
+// back into the line rebuild the completion state from scratch with `refresh_completion`
+
+pub fn is_ctrl_c(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('c'),
+            kind: KeyEventKind::Press,
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        })
+    )
+}
+
+/// Clears the visible terminal and its scrollback, leaving the cursor in the top left corner
+pub fn clear_terminal(term: &mut impl Write) -> io::Result<()> {
+    term.queue(Clear(All))?
+        .queue(Clear(Purge))?
+        .queue(cursor::MoveTo(0, 0))?
+        .flush()
+}
 
 pub enum EventLoop {
     Continue,
@@ -195,13 +339,23 @@ impl<'a> LineReader<'a> {
         term: &'a mut Stdout,
         name_ctx: &'static CommandScheme,
     ) -> io::Result<Self> {
+        let line = LineData::new(prompt);
         let new = LineReader {
-            line: LineData::new(prompt),
+            home_prompt: line.prompt.clone(),
+            line,
             history: History::default(),
+            search: None,
             term,
             term_size: terminal::size().unwrap(),
             uneventful: false,
             cursor_at_start: false,
+            cursor_row: None,
+            kill_buf: String::new(),
+            quit_armed: false,
+            progress: None,
+            progress_rendered: false,
+            screen: false,
+            held: Vec::new(),
             command_entered: true,
             completion: Completion::from(name_ctx),
             input_hooks: VecDeque::new(),
@@ -249,7 +403,7 @@ impl<'a> LineReader<'a> {
     }
 
     pub fn conditionally_remove_hook(&mut self, ctx: &mut CommandContext, uid: usize) {
-        self.set_prompt(LineData::default_prompt());
+        self.restore_prompt();
         self.set_completion(true);
         if let Some(callback) = self.next_input_hook() {
             if callback.uid() == uid {
@@ -260,12 +414,79 @@ impl<'a> LineReader<'a> {
     }
 
     pub fn print_background_msg(&mut self, msg: Message) -> io::Result<()> {
+        match msg {
+            Message::Screen(Some(frame)) => return self.draw_screen(&frame),
+            Message::Screen(None) => return self.leave_screen(),
+            msg if self.screen => {
+                self.held.push(msg);
+                return Ok(());
+            }
+            _ => (),
+        }
         self.move_to_beginning(self.line_len())?;
         match msg {
-            Message::Str(msg) => println!("{msg}"),
-            Message::Info(msg) => info!("{msg}"),
-            Message::Warn(msg) => warn!("{msg}"),
-            Message::Err(msg) => error!("{msg}"),
+            Message::Progress(progress) => self.progress = progress,
+            msg => msg.print(),
+        }
+        Ok(())
+    }
+
+    /// Shows `frame` in the alternate screen in place of the prompt
+    fn draw_screen(&mut self, frame: &str) -> io::Result<()> {
+        if !self.screen {
+            self.term
+                .queue(terminal::EnterAlternateScreen)?
+                .queue(cursor::Hide)?;
+            self.screen = true;
+        }
+        self.term.queue(cursor::MoveTo(0, 0))?.queue(Clear(All))?;
+        for line in frame.lines() {
+            write!(self.term, "{line}")?;
+            self.term.queue(cursor::MoveToNextLine(1))?;
+        }
+        self.term.flush()
+    }
+
+    /// Returns to the prompt and prints the messages held while the full screen view was shown
+    fn leave_screen(&mut self) -> io::Result<()> {
+        if !std::mem::take(&mut self.screen) {
+            return Ok(());
+        }
+        self.term
+            .queue(terminal::LeaveAlternateScreen)?
+            .queue(cursor::Show)?;
+        self.move_to_beginning(self.line_len())?;
+        for msg in std::mem::take(&mut self.held) {
+            match msg {
+                Message::Progress(progress) => self.progress = progress,
+                msg => msg.print(),
+            }
+        }
+        Ok(())
+    }
+
+    /// clears the terminal, the line is redrawn in the top left corner on the next render
+    pub fn clear_screen(&mut self) -> io::Result<()> {
+        clear_terminal(self.term)?;
+        self.cursor_at_start = true;
+        self.cursor_row = None;
+        self.progress_rendered = false;
+        Ok(())
+    }
+
+    pub fn clear_progress(&mut self) -> io::Result<()> {
+        if self.progress.take().is_some() {
+            self.move_to_beginning(self.line_len())?;
+        }
+        Ok(())
+    }
+
+    /// redraws the line without the progress row so it does not end up in the scrollback
+    fn hide_progress(&mut self) -> io::Result<()> {
+        if self.progress_rendered {
+            self.move_to_beginning(self.line_len())?;
+            write!(self.term, "{}", self.line)?;
+            self.cursor_at_start = false;
         }
         Ok(())
     }
@@ -288,11 +509,42 @@ impl<'a> LineReader<'a> {
             .expect("only called after `self.enter_command()`")
     }
 
+    /// Switches back to the home prompt, used when leaving an input hook
+    pub fn restore_prompt(&mut self) {
+        self.set_prompt(self.home_prompt.clone());
+    }
+
+    /// Updates the home prompt, it is only redrawn if nothing else currently owns the prompt.
+    /// Returns `false` if the prompt was already up to date
+    pub fn set_home_prompt(&mut self, prompt: String) -> io::Result<bool> {
+        if prompt == self.home_prompt {
+            return Ok(false);
+        }
+        if let Some(ref mut search) = self.search {
+            if search.prompt == self.home_prompt {
+                search.prompt = prompt.clone();
+            }
+        } else if self.line.prompt == self.home_prompt {
+            if !self.cursor_at_start {
+                self.move_to_beginning(self.line_len())?;
+            }
+            self.set_prompt(prompt.clone());
+        }
+        self.home_prompt = prompt;
+        Ok(true)
+    }
+
     pub fn set_prompt(&mut self, prompt: String) {
         self.line.prompt_len = LineData::prompt_len(&prompt);
         self.line.prompt = prompt;
     }
 
+    /// the line is left as is on the next loop, for events that did not change anything on screen
+    #[inline]
+    pub fn skip_render(&mut self) {
+        self.uneventful = true
+    }
+
     #[inline]
     pub fn uneventful(&mut self) -> bool {
         std::mem::take(&mut self.uneventful)
@@ -321,7 +573,17 @@ impl<'a> LineReader<'a> {
     }
 
     pub fn move_to_beginning(&mut self, from: u16) -> io::Result<()> {
-        let line_height = self.line_height(from);
+        // the line is still on the main screen, it is cleared once the full screen view is left
+        if self.screen {
+            return Ok(());
+        }
+        let mut line_height = self
+            .cursor_row
+            .take()
+            .unwrap_or_else(|| self.line_height(from));
+        if std::mem::take(&mut self.progress_rendered) {
+            line_height += 1;
+        }
         if line_height != 0 {
             self.term.queue(cursor::MoveUp(line_height))?;
         }
@@ -344,45 +606,173 @@ impl<'a> LineReader<'a> {
     }
 
     pub fn render(&mut self) -> io::Result<()> {
+        if self.screen {
+            return Ok(());
+        }
         let line_len = self.line_len();
         if !self.cursor_at_start {
             self.move_to_beginning(line_len.saturating_sub(1))?;
         }
 
+        if let Some(ref progress) = self.progress {
+            let max_width = self.term_size.0.saturating_sub(1) as usize;
+            let progress = progress.chars().take(max_width).collect::<String>();
+            writeln!(self.term, "{}", progress.dark_grey())?;
+            self.term.queue(cursor::MoveToColumn(0))?;
+            self.progress_rendered = true;
+        }
+
         write!(self.term, "{}", self.line)?;
 
         self.move_to_line_end(line_len)?;
+        if self.line.cursor < self.line.len {
+            let cursor_pos = self.line.prompt_len.saturating_add(self.line.cursor);
+            let cursor_row = self.line_height(cursor_pos);
+            let rows_up = self.line_height(line_len) - cursor_row;
+            if rows_up != 0 {
+                self.term.queue(cursor::MoveUp(rows_up))?;
+            }
+            self.term
+                .queue(cursor::MoveToColumn(self.line_remainder(cursor_pos)))?;
+            self.cursor_row = Some(cursor_row);
+        }
         self.term.flush()
     }
 
-    pub fn insert_char(&mut self, c: char) {
-        self.line.input.push(c);
-        self.line.len = self.line.len.saturating_add(1);
+    /// moves the terminal cursor back to the end of the rendered line, if `render` left it within the line
+    fn restore_cursor_to_end(&mut self) -> io::Result<()> {
+        if let Some(cursor_row) = self.cursor_row.take() {
+            let line_len = self.line_len();
+            let rows_down = self.line_height(line_len) - cursor_row;
+            if rows_down != 0 {
+                self.term.queue(cursor::MoveDown(rows_down))?;
+            }
+            self.term
+                .queue(cursor::MoveToColumn(self.line_remainder(line_len)))?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn cursor_at_end(&self) -> bool {
+        self.line.cursor == self.line.len
+    }
+
+    fn refresh_completion(&mut self) {
+        self.reset_completion();
         if self.line.comp_enabled {
             self.update_completeion();
         }
     }
 
+    pub fn insert_char(&mut self, c: char) {
+        if self.cursor_at_end() {
+            self.line.input.push(c);
+            self.line.len = self.line.len.saturating_add(1);
+            self.line.cursor = self.line.len;
+            if self.line.comp_enabled {
+                self.update_completeion();
+            }
+            return;
+        }
+        let i = self.line.byte_index(self.line.cursor);
+        self.line.input.insert(i, c);
+        self.line.len = self.line.len.saturating_add(1);
+        self.line.cursor += 1;
+        self.refresh_completion();
+    }
+
     pub fn remove_char(&mut self) -> io::Result<()> {
-        self.line.input.pop();
+        if self.line.cursor == 0 {
+            return Ok(());
+        }
         self.move_to_beginning(self.line_len())?;
+        if self.cursor_at_end() {
+            self.line.input.pop();
+            self.line.len = self.line.len.saturating_sub(1);
+            self.line.cursor = self.line.len;
+            if self.line.comp_enabled {
+                self.update_completeion();
+            }
+            return Ok(());
+        }
+        self.line.cursor -= 1;
+        self.line
+            .input
+            .remove(self.line.byte_index(self.line.cursor));
         self.line.len = self.line.len.saturating_sub(1);
-        if self.line.comp_enabled {
-            self.update_completeion();
+        self.refresh_completion();
+        Ok(())
+    }
+
+    /// removes the char under the cursor
+    fn delete_char(&mut self) -> io::Result<()> {
+        if self.cursor_at_end() {
+            return Ok(());
         }
+        self.move_to_beginning(self.line_len())?;
+        self.line
+            .input
+            .remove(self.line.byte_index(self.line.cursor));
+        self.line.len = self.line.len.saturating_sub(1);
+        self.refresh_completion();
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, to: u16) -> io::Result<()> {
+        self.move_to_beginning(self.line_len())?;
+        self.line.set_cursor(to);
+        Ok(())
+    }
+
+    /// removes the chars in `start..end` and stores them in the kill buffer
+    fn kill(&mut self, start: u16, end: u16) -> io::Result<()> {
+        if start >= end {
+            return Ok(());
+        }
+        self.move_to_beginning(self.line_len())?;
+        self.kill_buf = self.line.kill(start, end);
+        self.refresh_completion();
+        Ok(())
+    }
+
+    fn yank(&mut self) -> io::Result<()> {
+        if self.kill_buf.is_empty() {
+            return Ok(());
+        }
+        self.move_to_beginning(self.line_len())?;
+        self.line.yank(&self.kill_buf);
+        self.refresh_completion();
         Ok(())
     }
 
     pub fn new_line(&mut self) -> io::Result<()> {
+        self.hide_progress()?;
+        self.restore_cursor_to_end()?;
         writeln!(self.term)?;
         self.clear_line()
     }
 
     pub fn ctrl_c_line(&mut self) -> io::Result<()> {
+        self.hide_progress()?;
+        self.restore_cursor_to_end()?;
         writeln!(self.term, "{}", "^C".red())?;
         self.clear_line()
     }
 
+    fn arm_quit(&mut self) -> io::Result<()> {
+        self.hide_progress()?;
+        self.restore_cursor_to_end()?;
+        writeln!(
+            self.term,
+            "{} {}",
+            "^C".red(),
+            "press 'ctrl + c' again to quit".dark_grey()
+        )?;
+        self.quit_armed = true;
+        self.clear_line()
+    }
+
     fn clear_line(&mut self) -> io::Result<()> {
         self.reset_line_data();
         self.move_to_beginning(self.line_len())?;
@@ -394,20 +784,19 @@ impl<'a> LineReader<'a> {
     fn reset_line_data(&mut self) {
         self.line.input.clear();
         self.line.len = 0;
+        self.line.cursor = 0;
         self.line.err = false;
     }
 
     pub fn change_line(&mut self, line: String) -> io::Result<()> {
         self.move_to_beginning(self.line_len())?;
-        self.line.len = line.chars().count() as u16;
-        self.line.input = line;
+        self.line.set_input(line);
         Ok(())
     }
 
     fn enter_command(&mut self) -> io::Result<()> {
-        self.history
-            .prev_entries
-            .push(std::mem::take(&mut self.line.input));
+        self.hide_progress()?;
+        self.history.push(std::mem::take(&mut self.line.input));
         self.reset_history_idx();
         self.new_line()?;
         self.term.queue(cursor::Hide)?.flush()?;
@@ -448,6 +837,129 @@ impl<'a> LineReader<'a> {
         self.change_line(new_line)
     }
 
+    fn start_search(&mut self) -> io::Result<()> {
+        if self.history.prev_entries.is_empty() {
+            return Ok(());
+        }
+        self.search = Some(HistorySearch {
+            query: String::new(),
+            found: None,
+            prompt: self.line.prompt.clone(),
+            line: self.line.input.clone(),
+        });
+        self.update_search(self.history.prev_entries.len(), false)
+    }
+
+    /// searches backwards for the query starting just before index `from` and redraws the search line,
+    /// `wrap` continues from the newest entry when nothing older matches
+    fn update_search(&mut self, from: usize, wrap: bool) -> io::Result<()> {
+        let search = self.search.as_mut().expect("only called while searching");
+        let mut failed = false;
+        if search.query.is_empty() {
+            search.found = None;
+        } else {
+            match self.history.rfind(&search.query, from, wrap) {
+                Some(i) => search.found = Some(i),
+                None => failed = true,
+            }
+        }
+        let prompt = format!(
+            "({}reverse-i-search)`{}'",
+            if failed { "failed " } else { "" },
+            search.query
+        );
+        let line = search
+            .found
+            .map_or_else(String::new, |i| self.history.prev_entries[i].clone());
+
+        self.move_to_beginning(self.line_len())?;
+        self.set_prompt(prompt);
+        self.line.set_input(line);
+        self.line.err = failed;
+        Ok(())
+    }
+
+    /// leaves search mode, `accept` keeps the matched entry as the current line
+    fn end_search(&mut self, accept: bool) -> io::Result<()> {
+        let search = self.search.take().expect("only called while searching");
+        self.move_to_beginning(self.line_len())?;
+        self.set_prompt(search.prompt);
+        if !accept {
+            self.line.set_input(search.line);
+        }
+        self.line.err = false;
+        self.reset_history_idx();
+        self.reset_completion();
+        if self.line.comp_enabled {
+            self.update_completeion();
+        }
+        Ok(())
+    }
+
+    fn process_search_event(&mut self, event: Event) -> io::Result<EventLoop> {
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        else {
+            if let Event::Resize(x, y) = event {
+                self.term_size = (x, y);
+            }
+            return Ok(EventLoop::Continue);
+        };
+        let search = self.search.as_mut().expect("only called while searching");
+        match code {
+            KeyCode::Char('r') if modifiers == KeyModifiers::CONTROL => {
+                let from = search.found.unwrap_or(self.history.prev_entries.len());
+                self.update_search(from, true)?;
+            }
+            KeyCode::Char('c') if modifiers == KeyModifiers::CONTROL => self.end_search(false)?,
+            KeyCode::Esc => self.end_search(false)?,
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                search.query.push(c);
+                let from = search
+                    .found
+                    .map_or(self.history.prev_entries.len(), |i| i + 1);
+                self.update_search(from, false)?;
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.update_search(self.history.prev_entries.len(), false)?;
+            }
+            _ => {
+                // any other key accepts the match and is then handled as usual, e.g. 'enter' runs the command
+                self.end_search(true)?;
+                return self.process_input_event(event);
+            }
+        }
+        Ok(EventLoop::Continue)
+    }
+
+    /// readline style cursor movement and kill/yank bindings, returns `true` if the key was handled
+    fn try_edit_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> io::Result<bool> {
+        const NONE: KeyModifiers = KeyModifiers::NONE;
+        const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+        match (code, modifiers) {
+            (KeyCode::Left, NONE) => self.move_cursor(self.line.cursor.saturating_sub(1))?,
+            (KeyCode::Right, NONE) => self.move_cursor(self.line.cursor.saturating_add(1))?,
+            (KeyCode::Left, CTRL) => self.move_cursor(self.line.prev_word_start())?,
+            (KeyCode::Right, CTRL) => self.move_cursor(self.line.next_word_end())?,
+            (KeyCode::Home, _) | (KeyCode::Char('a'), CTRL) => self.move_cursor(0)?,
+            (KeyCode::End, _) | (KeyCode::Char('e'), CTRL) => self.move_cursor(self.line.len)?,
+            (KeyCode::Delete, NONE) => self.delete_char()?,
+            (KeyCode::Char('w'), CTRL) => {
+                self.kill(self.line.prev_word_start(), self.line.cursor)?
+            }
+            (KeyCode::Char('u'), CTRL) => self.kill(0, self.line.cursor)?,
+            (KeyCode::Char('k'), CTRL) => self.kill(self.line.cursor, self.line.len)?,
+            (KeyCode::Char('y'), CTRL) => self.yank()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
     pub fn process_input_event(&mut self, event: Event) -> io::Result<EventLoop> {
         if !self.input_hooks.is_empty() {
             if let Event::Key(KeyEvent {
@@ -464,7 +976,45 @@ impl<'a> LineReader<'a> {
                 return Ok(event_loop);
             }
         }
+        if self.search.is_some() {
+            return self.process_search_event(event);
+        }
+        let quit_armed = std::mem::take(&mut self.quit_armed);
+        if let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        {
+            if self.try_edit_key(code, modifiers)? {
+                return Ok(EventLoop::Continue);
+            }
+        }
         match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('r'),
+                kind: KeyEventKind::Press,
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => {
+                self.start_search()?;
+                Ok(EventLoop::Continue)
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('l'),
+                kind: KeyEventKind::Press,
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            }) => {
+                self.clear_screen()?;
+                Ok(EventLoop::AsyncCallback(Box::new(|context| {
+                    Box::pin(async move {
+                        println!("{}", StatusHeader::read(context).await);
+                        Ok(())
+                    })
+                })))
+            }
             Event::Key(KeyEvent {
                 code: KeyCode::Char('c'),
                 kind: KeyEventKind::Press,
@@ -472,6 +1022,10 @@ impl<'a> LineReader<'a> {
                 ..
             }) => {
                 if self.line.input.is_empty() {
+                    if !quit_armed {
+                        self.arm_quit()?;
+                        return Ok(EventLoop::Continue);
+                    }
                     self.line.input.push_str("quit");
                     self.enter_command()?;
                     return Ok(EventLoop::TryProcessCommand);
@@ -484,6 +1038,9 @@ impl<'a> LineReader<'a> {
                 kind: KeyEventKind::Press,
                 ..
             }) => {
+                if !self.cursor_at_end() {
+                    self.move_cursor(self.line.len)?;
+                }
                 self.try_completion(Direction::Next)?;
                 Ok(EventLoop::Continue)
             }
@@ -492,6 +1049,9 @@ impl<'a> LineReader<'a> {
                 kind: KeyEventKind::Press,
                 ..
             }) => {
+                if !self.cursor_at_end() {
+                    self.move_cursor(self.line.len)?;
+                }
                 self.try_completion(Direction::Previous)?;
                 Ok(EventLoop::Continue)
             }
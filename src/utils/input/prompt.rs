@@ -0,0 +1,86 @@
+use crate::utils::{
+    game_state::GameState,
+    input::{
+        line::LineData,
+        style::{GREEN, GREY, RED, WHITE, YELLOW},
+    },
+};
+
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "{game_status} {app}";
+
+/// Placeholders recognized in a prompt template
+pub const PROMPT_PLACEHOLDERS: [(&str, &str); 4] = [
+    ("{app}", "name of this app"),
+    (
+        "{game_status}",
+        "dot colored by the state of the connection to H2M",
+    ),
+    (
+        "{game_state}",
+        "what H2M is doing, e.g. `loading` or `in game`",
+    ),
+    ("{server}", "name of the server being joined or played on"),
+];
+
+/// Longer server names are cut short so the prompt leaves room for input
+const SERVER_NAME_MAX: usize = 24;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsoleState {
+    /// pseudo terminal to H2M is open
+    Attached,
+    /// only reading the H2M console log file
+    LogAttached,
+    Detached,
+}
+
+impl ConsoleState {
+    pub fn new(attached: bool, log_attached: bool) -> Self {
+        match (attached, log_attached) {
+            (true, _) => ConsoleState::Attached,
+            (false, true) => ConsoleState::LogAttached,
+            (false, false) => ConsoleState::Detached,
+        }
+    }
+
+    /// each state also has its own glyph so it can be told apart when colors are disabled
+    fn dot(self) -> String {
+        match self {
+            ConsoleState::Attached => format!("{GREEN}●{WHITE}"),
+            ConsoleState::LogAttached => format!("{YELLOW}◐{WHITE}"),
+            ConsoleState::Detached => format!("{RED}○{WHITE}"),
+        }
+    }
+}
+
+fn state_label(state: &GameState) -> String {
+    match state {
+        GameState::Idle => String::new(),
+        GameState::Launching => format!("{GREY}launching{WHITE}"),
+        GameState::InMenu => format!("{GREY}in menu{WHITE}"),
+        GameState::Loading { .. } => format!("{YELLOW}loading{WHITE}"),
+        GameState::InGame { .. } => format!("{GREEN}in game{WHITE}"),
+        GameState::Crashed { .. } => format!("{RED}crashed{WHITE}"),
+    }
+}
+
+/// Fills in the placeholders of `template`, empty placeholders are dropped along with the extra
+/// whitespace they leave
+pub fn render_prompt(template: &str, console: ConsoleState, game: &GameState) -> String {
+    let server = match game.server() {
+        Some(name) => {
+            let mut short = name.chars().take(SERVER_NAME_MAX).collect::<String>();
+            if name.chars().nth(SERVER_NAME_MAX).is_some() {
+                short.push('…');
+            }
+            format!("{GREY}{short}{WHITE}")
+        }
+        None => String::new(),
+    };
+    let prompt = template
+        .replace("{app}", &LineData::default_prompt())
+        .replace("{game_status}", &console.dot())
+        .replace("{game_state}", &state_label(game))
+        .replace("{server}", &server);
+    prompt.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -1,15 +1,58 @@
-use crate::utils::input::line::LineData;
+use crate::{strip_ansi_sequences, utils::input::line::LineData};
 use crossterm::style::{Color, Stylize};
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 pub const PROMPT_END: &str = "> ";
-pub const RED: &str = "\x1b[31m";
-pub const YELLOW: &str = "\x1b[38;5;220m";
-pub const GREEN: &str = "\x1b[92m";
-pub const BLUE: &str = "\x1b[38;5;38m";
-pub const MAGENTA: &str = "\x1b[35m";
-pub const GREY: &str = "\x1b[38;5;238m";
-pub const WHITE: &str = "\x1b[0m";
+
+pub const RED: AnsiColor = AnsiColor("\x1b[31m");
+pub const YELLOW: AnsiColor = AnsiColor("\x1b[38;5;220m");
+pub const GREEN: AnsiColor = AnsiColor("\x1b[92m");
+pub const BLUE: AnsiColor = AnsiColor("\x1b[38;5;38m");
+pub const MAGENTA: AnsiColor = AnsiColor("\x1b[35m");
+pub const GREY: AnsiColor = AnsiColor("\x1b[38;5;238m");
+pub const WHITE: AnsiColor = AnsiColor("\x1b[0m");
+
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Escape sequence that is only written out while colored output is enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnsiColor(&'static str);
+
+impl AnsiColor {
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        if color_enabled() {
+            self.0
+        } else {
+            ""
+        }
+    }
+}
+
+impl Display for AnsiColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[inline]
+pub fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Decides if output should be colored, should be called before anything is printed. Colors stay off
+/// if `requested` is false, the `NO_COLOR` env var is non-empty, `TERM=dumb`, or stdout is not a terminal
+pub fn init_color(requested: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty());
+    let dumb_term = std::env::var_os("TERM").is_some_and(|term| term == "dumb");
+    let enabled = requested && !no_color_env && !dumb_term && std::io::stdout().is_terminal();
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+    crossterm::style::force_color_output(enabled);
+}
 
 enum TextColor {
     Yellow,
@@ -20,12 +63,13 @@ enum TextColor {
 
 impl TextColor {
     fn to_str(&self) -> &'static str {
-        match self {
+        let color = match self {
             TextColor::Yellow => YELLOW,
             TextColor::Blue => BLUE,
             TextColor::Grey => GREY,
             TextColor::White => WHITE,
-        }
+        };
+        color.as_str()
     }
 }
 
@@ -84,6 +128,10 @@ impl FormatState {
 
 impl Display for LineData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !color_enabled() {
+            let prompt = strip_ansi_sequences(self.prompt());
+            return write!(f, "{prompt}{PROMPT_END}{}", self.input());
+        }
         write!(
             f,
             "{WHITE}{}{}{}",
@@ -2,39 +2,72 @@ use std::{
     collections::HashMap,
     net::{IpAddr, SocketAddr},
     str::FromStr,
+    sync::Arc,
 };
 
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::commands::launch_h2m::HostName;
+use crate::{
+    cli::Channel,
+    commands::launch_h2m::HostName,
+    utils::{
+        lenient::{lenient_list, ListEntry},
+        population::PopulationHistory,
+        reliability::ReliabilityBook,
+        server_book::ServerBook,
+    },
+};
 
-#[derive(Deserialize, Debug)]
+/// An IW4MAdmin instance listed by the iw4 master. Fields MatchWire does not use are optional so a change
+/// to them does not make the instance unreadable, see [`crate::utils::lenient`]
+#[derive(Deserialize, Debug, Clone)]
 pub struct HostData {
+    #[serde(deserialize_with = "lenient_list")]
     pub servers: Vec<ServerInfo>,
+    #[serde(default)]
     pub uptime: u32,
+    #[serde(default)]
     pub id: String,
+    #[serde(default)]
     pub last_heartbeat: u64,
     pub ip_address: String,
     pub webfront_url: String,
+    #[serde(default)]
     pub version: String,
 }
 
-#[derive(Deserialize, Debug)]
+impl ListEntry for HostData {
+    const KIND: &'static str = "iw4m instances";
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct ServerInfo {
     pub ip: String,
     #[serde(rename = "clientnum")]
     pub clients: u8,
-    #[serde(rename = "gametype")]
+    #[serde(rename = "gametype", default)]
     pub game_type: String,
+    #[serde(default)]
     pub id: i64,
     #[serde(rename = "maxclientnum")]
     pub max_clients: u8,
     pub port: u16,
+    #[serde(default)]
     pub map: String,
+    #[serde(default)]
     pub version: String,
     pub game: String,
     #[serde(rename = "hostname")]
-    pub host_name: String,
+    pub host_name: Arc<str>,
+}
+
+impl ListEntry for ServerInfo {
+    const KIND: &'static str = "iw4m servers";
+}
+
+/// The hmw master lists servers as `IP:PORT` strings
+impl ListEntry for String {
+    const KIND: &'static str = "hmw servers";
 }
 
 #[derive(Deserialize, Debug)]
@@ -54,7 +87,10 @@ pub struct GetInfo {
     #[serde(rename = "gametype")]
     pub game_type: String,
     #[serde(rename = "hostname")]
-    pub host_name: String,
+    pub host_name: Arc<str>,
+    /// Missing from older server versions
+    #[serde(rename = "mapname", default)]
+    pub map_name: String,
 }
 
 fn from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
@@ -70,16 +106,29 @@ where
 #[derive(Deserialize, Debug)]
 pub struct ServerLocation {
     pub continent: Option<Continent>,
+    pub country: Option<Country>,
     #[serde(rename = "Message")]
     pub message: Option<String>,
 }
 
+/// Reply of the public address echo used by `filter --near-me`
+#[derive(Deserialize, Debug)]
+pub struct PublicIp {
+    pub ip: IpAddr,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Continent {
     #[serde(deserialize_with = "deserialize_country_code")]
     pub code: [char; 2],
 }
 
+#[derive(Deserialize, Debug)]
+pub struct Country {
+    #[serde(rename = "iso_code", deserialize_with = "deserialize_country_code")]
+    pub code: [char; 2],
+}
+
 fn deserialize_country_code<'de, D>(deserializer: D) -> Result<[char; 2], D::Error>
 where
     D: Deserializer<'de>,
@@ -92,18 +141,93 @@ where
     })
 }
 
+/// Top level fields describe the stable channel
 #[derive(Deserialize, Debug)]
 pub struct Version {
     pub latest: String,
     pub message: String,
+    /// Versions older than this no longer work with the services MatchWire relies on
+    #[serde(default)]
+    pub min_supported: Option<String>,
+    /// SHA-256 of the released executable as hex, `update` refuses a download that does not match
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub channels: HashMap<Channel, ChannelVersion>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChannelVersion {
+    pub latest: String,
+    pub message: String,
+    #[serde(default)]
+    pub min_supported: Option<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Version {
+    /// Details of `channel`, falling back to stable if the channel is not listed
+    pub fn into_channel(mut self, channel: Channel) -> ChannelVersion {
+        self.channels.remove(&channel).unwrap_or(ChannelVersion {
+            latest: self.latest,
+            message: self.message,
+            min_supported: self.min_supported,
+            sha256: self.sha256,
+        })
+    }
+}
+
+/// Subset of a GitHub release returned by the releases API
+#[derive(Deserialize, Debug)]
+pub struct Release {
+    /// Release notes in GitHub flavored markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+    /// e.g. `sha256:<hex>`, not present on older releases
+    #[serde(default)]
+    pub digest: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct CacheFile {
     pub version: String,
     pub created: std::time::SystemTime,
+    /// Missing from cache files written before the server book
+    #[serde(default)]
+    pub book: ServerBook,
+    /// Joined host names written by versions before the server book, only read to fill `book`
+    #[serde(default, skip_serializing)]
     pub connection_history: Vec<HostName>,
     pub cache: ServerCache,
+    /// Arguments of the last `filter` command, used to backfill favourites
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_filter: Option<String>,
+    /// Master server lists as of the last `diff`, kept when the cache is rebuilt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<MasterSnapshot>,
+    /// Player counts by hour of the day, kept when the cache is rebuilt
+    #[serde(default, skip_serializing_if = "PopulationHistory::is_empty")]
+    pub populations: PopulationHistory,
+    /// How often servers were listed by the master servers, kept when the cache is rebuilt
+    #[serde(default, skip_serializing_if = "ReliabilityBook::is_empty")]
+    pub reliability: ReliabilityBook,
+}
+
+/// Servers listed by the master servers and their player counts
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MasterSnapshot {
+    pub taken: std::time::SystemTime,
+    /// `None` when the server did not answer and its master server does not list players
+    pub servers: HashMap<SocketAddr, Option<u8>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -115,7 +239,41 @@ pub struct ServerCache {
         serialize_with = "serialize_country_code_map"
     )]
     pub regions: HashMap<IpAddr, [char; 2]>,
-    pub host_names: HashMap<String, SocketAddr>,
+    /// Missing from cache files written before countries were looked up
+    #[serde(
+        default,
+        deserialize_with = "deserialize_country_code_map",
+        serialize_with = "serialize_country_code_map"
+    )]
+    pub countries: HashMap<IpAddr, [char; 2]>,
+    /// Host name to address map written by versions before the server book
+    #[serde(default, skip_serializing)]
+    pub host_names: HashMap<Arc<str>, SocketAddr>,
+    /// Missing from cache files written before pings were measured
+    #[serde(default)]
+    pub pings: HashMap<SocketAddr, PingSample>,
+    /// Addresses that host names listed in place of an IP last resolved to
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resolved: HashMap<Arc<str>, IpAddr>,
+    /// Host names that resolved to a new address since the last `diff`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moves: Vec<HostMove>,
+}
+
+/// A host name listed by a master server that resolved to a different address than the time before
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HostMove {
+    pub host: Arc<str>,
+    pub from: IpAddr,
+    pub to: IpAddr,
+    pub at: std::time::SystemTime,
+}
+
+/// A `getstatus` round trip and when it was measured
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct PingSample {
+    pub ms: u32,
+    pub measured: std::time::SystemTime,
 }
 
 fn deserialize_country_code_map<'de, D>(
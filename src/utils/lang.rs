@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Folder in the local environment directory translations are read from
+pub const LANG_DIR: &str = "lang";
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Sorted by key. Words in braces are filled in by [`tr`] and must be kept by translations
+const ENGLISH: [(&str, &str); 27] = [
+    ("error.game_running", "H2M is already running"),
+    ("error.geo_api", "Location lookup failed for {ip}, {reason}"),
+    ("error.io", "Failed to {action} {path}, {source}"),
+    (
+        "error.master_api",
+        "Could not get the {master} master server list, {source}",
+    ),
+    ("error.network", "{what} request failed, {source}"),
+    ("error.pty", "Pseudoconsole error, {message}"),
+    (
+        "error.unsupported",
+        "{feature} is not available in this build",
+    ),
+    ("error.wine", "Could not start H2M through Wine, {message}"),
+    ("fetch.deadline", "no answer before the filter deadline"),
+    ("fetch.decode", "unexpected response, {source}"),
+    ("fetch.fixture", "Failed to read fixture {path}, {source}"),
+    ("fetch.missing_fixture", "no fixture found at {path}"),
+    ("fetch.offline", "not sent, started with --offline"),
+    ("fetch.status", "server responded with {status}"),
+    ("fetch.timed_out", "no answer within {secs}s"),
+    (
+        "hint.cache",
+        "Use command 'cache reset' to rebuild the cache",
+    ),
+    (
+        "hint.game_client",
+        "H2M mod files are available to download for free through the Horizon MW launcher \
+        https://discord.com/invite/HorizonMW",
+    ),
+    (
+        "hint.game_dir",
+        "Move {app}.exe into your 'Call of Duty Modern Warfare Remastered' directory, or start it \
+        with `--game-dir <PATH>`",
+    ),
+    (
+        "hint.geo_api",
+        "Region filters need the geolocation API, the daily lookup limit may have been reached",
+    ),
+    (
+        "hint.io",
+        "Make sure the file is not open in another program and the folder is not read-only",
+    ),
+    (
+        "hint.master_api",
+        "The master server may be down, cached servers are used when available",
+    ),
+    (
+        "hint.network",
+        "Check your internet connection, use command 'doctor' if the problem persists",
+    ),
+    (
+        "hint.pty",
+        "Use command 'doctor' to check if pseudoconsoles work on this system",
+    ),
+    (
+        "hint.unsupported",
+        "Controlling the game needs a Windows build with the 'windows-console' feature enabled",
+    ),
+    (
+        "hint.wine",
+        "Install Wine, or set the WINE environment variable to the wine binary bundled with Proton",
+    ),
+    (
+        "lang.missing",
+        "No translation found at {path}, using English",
+    ),
+    ("lang.problem", "{path}: {problem}"),
+];
+
+static LOADED: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+#[inline]
+pub fn translation_path(local_dir: &Path, code: &str) -> PathBuf {
+    local_dir.join(LANG_DIR).join(format!("{code}.json"))
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .binary_search_by_key(&key, |&(key, _)| key)
+        .ok()
+        .map(|i| ENGLISH[i].1)
+}
+
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut found = text
+        .split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .collect::<Vec<_>>();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+/// Keys that are not English keys and texts that drop or add a word in braces, each as a message
+pub fn check_translation(texts: &BTreeMap<String, String>) -> Vec<String> {
+    texts
+        .iter()
+        .filter_map(|(key, text)| match english(key) {
+            None => Some(format!("unknown key '{key}'")),
+            Some(english) if placeholders(english) != placeholders(text) => Some(format!(
+                "'{key}' must use {{{}}}",
+                placeholders(english).join("}, {")
+            )),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+/// Switches to the translation `code`, English is used for every key when it can not be read. Returns
+/// what was wrong with the file, keys with problems are left English
+pub fn init_language(local_dir: Option<&Path>, code: &str) -> Vec<String> {
+    let mut loaded = LOADED.write().unwrap_or_else(|err| err.into_inner());
+    loaded.clear();
+    if code.eq_ignore_ascii_case(DEFAULT_LANGUAGE) {
+        return Vec::new();
+    }
+    let Some(local_dir) = local_dir else {
+        return Vec::new();
+    };
+    let path = translation_path(local_dir, code);
+    let texts = match std::fs::read_to_string(&path) {
+        Ok(json) => match serde_json::from_str::<BTreeMap<String, String>>(&json) {
+            Ok(texts) => texts,
+            Err(err) => return vec![problem(&path, &err)],
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let path = path.display();
+            return vec![tr_english("lang.missing", &[("path", &path)])];
+        }
+        Err(err) => return vec![problem(&path, &err)],
+    };
+    let problems = check_translation(&texts);
+    loaded.extend(texts.into_iter().filter(|(key, text)| {
+        english(key).is_some_and(|english| placeholders(english) == placeholders(text))
+    }));
+    problems.iter().map(|msg| problem(&path, msg)).collect()
+}
+
+fn problem(path: &Path, problem: &dyn Display) -> String {
+    tr_english(
+        "lang.problem",
+        &[("path", &path.display()), ("problem", problem)],
+    )
+}
+
+/// Text of `key` in the current language with each `{name}` replaced by its value in `args`
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let loaded = LOADED.read().unwrap_or_else(|err| err.into_inner());
+    match loaded.get(key) {
+        Some(text) => fill(text, args),
+        None => tr_english(key, args),
+    }
+}
+
+/// Text of `key` without arguments, see [`tr`]
+#[inline]
+pub fn text(key: &str) -> String {
+    tr(key, &[])
+}
+
+fn tr_english(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    fill(english(key).unwrap_or(key), args)
+}
+
+fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let (_, value) = args.iter().find(|(name, _)| *name == &rest[1..end])?;
+            Some((end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                filled.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,126 @@
+use crate::LOG_ONLY;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, warn};
+
+/// Written to the local data directory with `--debug-parse`
+pub const DEBUG_PARSE_FILE: &str = "debug_parse.jsonl";
+
+/// An entry of a master server list
+pub trait ListEntry: DeserializeOwned {
+    /// What the entries are, in plural, used when reporting the ones that were skipped
+    const KIND: &'static str;
+}
+
+struct Skipped {
+    counts: BTreeMap<&'static str, usize>,
+    dump: Option<PathBuf>,
+}
+
+static SKIPPED: Mutex<Skipped> = Mutex::new(Skipped {
+    counts: BTreeMap::new(),
+    dump: None,
+});
+
+fn skipped() -> MutexGuard<'static, Skipped> {
+    SKIPPED.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Appends every entry that could not be read to [`DEBUG_PARSE_FILE`] in `local_dir` from now on
+pub fn enable_debug_parse(local_dir: &Path) {
+    skipped().dump = Some(local_dir.join(DEBUG_PARSE_FILE));
+}
+
+#[derive(Serialize)]
+struct DumpLine<'a> {
+    time: u64,
+    kind: &'static str,
+    error: String,
+    entry: &'a Value,
+}
+
+fn dump(path: &Path, line: &DumpLine) -> io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    serde_json::to_writer(&mut file, line)?;
+    writeln!(file)
+}
+
+/// Reads each of `values` as a `T`, counting and skipping the ones that do not fit
+pub fn keep_valid<T: ListEntry>(values: Vec<Value>) -> Vec<T> {
+    let mut entries = Vec::with_capacity(values.len());
+    for value in values {
+        match T::deserialize(&value) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => {
+                warn!(name: LOG_ONLY, "Skipped one of the {} that could not be read, {err}", T::KIND);
+                let mut skipped = skipped();
+                *skipped.counts.entry(T::KIND).or_default() += 1;
+                if let Some(path) = skipped.dump.clone() {
+                    let line = DumpLine {
+                        time: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |time| time.as_secs()),
+                        kind: T::KIND,
+                        error: err.to_string(),
+                        entry: &value,
+                    };
+                    if let Err(err) = dump(&path, &line) {
+                        error!("Could not write {DEBUG_PARSE_FILE}, {err}");
+                        skipped.dump = None;
+                    }
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Parses a json list, see [`keep_valid`]. Only fails when `body` is not a json list
+pub fn parse_entries<T: ListEntry>(body: &[u8]) -> serde_json::Result<Vec<T>> {
+    serde_json::from_slice(body).map(keep_valid)
+}
+
+/// `deserialize_with` of lists nested inside an entry, see [`keep_valid`]
+pub fn lenient_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ListEntry,
+{
+    Vec::<Value>::deserialize(deserializer).map(keep_valid)
+}
+
+/// Entries skipped by kind since this was last called
+pub fn take_skipped() -> BTreeMap<&'static str, usize> {
+    std::mem::take(&mut skipped().counts)
+}
+
+/// Line telling how many entries were skipped since this was last called, `None` if none were
+pub fn skipped_report() -> Option<String> {
+    let counts = take_skipped();
+    if counts.is_empty() {
+        return None;
+    }
+    let counts = counts
+        .iter()
+        .map(|(kind, count)| format!("{kind}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let hint = if skipped().dump.is_some() {
+        format!("they were saved to {DEBUG_PARSE_FILE}")
+    } else {
+        String::from("start MatchWire with --debug-parse to save them for a bug report")
+    };
+    Some(format!(
+        "Skipped entries the master servers sent in an unexpected format ({counts}), {hint}"
+    ))
+}
@@ -0,0 +1,126 @@
+/// Strips markdown syntax, keeping the text readable in a terminal. Headings are kept as their own
+/// line, list markers become `•`, links keep their text followed by the url and code fences are dropped
+pub fn to_plain_text(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut in_comment = false;
+    let mut blank_run = 0;
+    for line in markdown.lines() {
+        let mut line = line.trim_end();
+        if in_comment {
+            match line.find("-->") {
+                Some(end) => {
+                    in_comment = false;
+                    line = &line[end + 3..];
+                }
+                None => continue,
+            }
+        }
+        if let Some(start) = line.find("<!--") {
+            if !line[start..].contains("-->") {
+                in_comment = true;
+            }
+            line = &line[..start];
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            continue;
+        }
+        let plain = plain_line(line);
+        if plain.trim().is_empty() {
+            blank_run += 1;
+            if blank_run > 1 || out.is_empty() {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(&plain);
+        out.push('\n');
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn plain_line(line: &str) -> String {
+    let indent = line.len() - line.trim_start().len();
+    let trimmed = line.trim_start();
+    let (prefix, rest) = if let Some(heading) = trimmed
+        .strip_prefix('#')
+        .map(|heading| heading.trim_start_matches('#'))
+        .filter(|heading| heading.is_empty() || heading.starts_with(' '))
+    {
+        (String::new(), heading.trim_start())
+    } else if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+    {
+        (format!("{}• ", " ".repeat(indent)), item)
+    } else if let Some(quote) = trimmed.strip_prefix('>') {
+        (String::from("  "), quote.trim_start())
+    } else if trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | '=')) {
+        return String::new();
+    } else {
+        (" ".repeat(indent), trimmed)
+    };
+    prefix + &plain_inline(rest)
+}
+
+/// Removes emphasis and code markers, turns `[text](url)` into `text (url)` and drops images
+fn plain_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '*' | '`' => continue,
+            '_' if is_emphasis_underscore(text, i) => continue,
+            '!' if text[i + 1..].starts_with('[') => {
+                if let Some((_, _, len)) = parse_link(&text[i + 1..]) {
+                    skip(&mut chars, len);
+                    continue;
+                }
+                out.push(c);
+            }
+            '[' => match parse_link(&text[i..]) {
+                Some((label, url, len)) => {
+                    out.push_str(&plain_inline(label));
+                    if !url.is_empty() && url != label {
+                        out.push_str(&format!(" ({url})"));
+                    }
+                    skip(&mut chars, len - 1);
+                }
+                None => out.push(c),
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `_` only marks emphasis at the edge of a word, `snake_case` is left alone
+fn is_emphasis_underscore(text: &str, i: usize) -> bool {
+    let before = text[..i].chars().next_back();
+    let after = text[i + 1..].chars().next();
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    !(is_word(before) && is_word(after))
+}
+
+/// Parses `[label](url)` at the start of `text`, returning the label, url and byte length
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[1..label_end];
+    let url_start = label_end + 2;
+    let url_len = text[url_start..].find(')')?;
+    Some((
+        label,
+        &text[url_start..url_start + url_len],
+        url_start + url_len + 1,
+    ))
+}
+
+fn skip(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>, bytes: usize) {
+    let Some(&(start, _)) = chars.peek() else {
+        return;
+    };
+    let end = start + bytes;
+    while chars.next_if(|&(i, _)| i < end).is_some() {}
+}
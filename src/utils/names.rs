@@ -0,0 +1,131 @@
+use crate::utils::config::DisplayConfig;
+use std::collections::BTreeMap;
+
+const MAP_NAMES: [(&str, &str); 46] = [
+    ("mp_abandon", "Carnival"),
+    ("mp_afghan", "Afghan"),
+    ("mp_backlot", "Backlot"),
+    ("mp_bloc", "Bloc"),
+    ("mp_bog", "Bog"),
+    ("mp_bog_summer", "Beach Bog"),
+    ("mp_boneyard", "Scrapyard"),
+    ("mp_brecourt", "Wasteland"),
+    ("mp_broadcast", "Broadcast"),
+    ("mp_carentan", "Chinatown"),
+    ("mp_cargoship", "Wet Work"),
+    ("mp_checkpoint", "Karachi"),
+    ("mp_citystreets", "District"),
+    ("mp_compact", "Salvage"),
+    ("mp_complex", "Bailout"),
+    ("mp_convoy", "Ambush"),
+    ("mp_countdown", "Countdown"),
+    ("mp_crash", "Crash"),
+    ("mp_crash_snow", "Winter Crash"),
+    ("mp_creek", "Creek"),
+    ("mp_crossfire", "Crossfire"),
+    ("mp_derail", "Derail"),
+    ("mp_estate", "Estate"),
+    ("mp_farm", "Downpour"),
+    ("mp_farm_spring", "Day Break"),
+    ("mp_favela", "Favela"),
+    ("mp_fuel2", "Fuel"),
+    ("mp_highrise", "Highrise"),
+    ("mp_invasion", "Invasion"),
+    ("mp_killhouse", "Killhouse"),
+    ("mp_nightshift", "Skidrow"),
+    ("mp_overgrown", "Overgrown"),
+    ("mp_pipeline", "Pipeline"),
+    ("mp_quarry", "Quarry"),
+    ("mp_rundown", "Rundown"),
+    ("mp_rust", "Rust"),
+    ("mp_shipment", "Shipment"),
+    ("mp_showdown", "Showdown"),
+    ("mp_storm", "Storm"),
+    ("mp_strike", "Strike"),
+    ("mp_subbase", "Sub Base"),
+    ("mp_terminal", "Terminal"),
+    ("mp_trailerpark", "Trailer Park"),
+    ("mp_underpass", "Underpass"),
+    ("mp_vacant", "Vacant"),
+    ("mp_vlobby_room", "Lobby"),
+];
+
+const MODE_NAMES: [(&str, &str); 16] = [
+    ("arena", "Arena"),
+    ("conf", "Kill Confirmed"),
+    ("ctf", "Capture the Flag"),
+    ("dd", "Demolition"),
+    ("dm", "Free-for-all"),
+    ("dom", "Domination"),
+    ("gtnw", "Global Thermonuclear War"),
+    ("gun", "Gun Game"),
+    ("hp", "Hardpoint"),
+    ("infect", "Infected"),
+    ("koth", "Headquarters"),
+    ("oneflag", "One Flag CTF"),
+    ("sab", "Sabotage"),
+    ("sd", "Search and Destroy"),
+    ("vip", "VIP"),
+    ("war", "Team Deathmatch"),
+];
+
+/// Map and game type names from the `display` settings, looked up before the built in names. Keys are
+/// matched ignoring case
+#[derive(Clone, Copy)]
+pub struct Names<'a> {
+    maps: &'a BTreeMap<String, String>,
+    modes: &'a BTreeMap<String, String>,
+}
+
+impl<'a> From<&'a DisplayConfig> for Names<'a> {
+    fn from(config: &'a DisplayConfig) -> Self {
+        Names {
+            maps: &config.map_names,
+            modes: &config.mode_names,
+        }
+    }
+}
+
+fn lookup(
+    raw: &str,
+    overrides: &BTreeMap<String, String>,
+    builtin: &[(&str, &str)],
+) -> Option<String> {
+    let raw = raw.to_lowercase();
+    overrides
+        .iter()
+        .find(|(key, _)| key.to_lowercase() == raw)
+        .map(|(_, name)| name.clone())
+        .or_else(|| {
+            builtin
+                .binary_search_by_key(&raw.as_str(), |&(key, _)| key)
+                .ok()
+                .map(|i| String::from(builtin[i].1))
+        })
+}
+
+impl Names<'_> {
+    /// Unknown maps drop the `mp_` prefix and have each word capitalized, `mp_my_map` becomes `My Map`
+    pub fn map(&self, raw: &str) -> String {
+        lookup(raw, self.maps, &MAP_NAMES).unwrap_or_else(|| {
+            let trimmed = raw.strip_prefix("mp_").unwrap_or(raw);
+            trimmed
+                .split('_')
+                .filter(|word| !word.is_empty())
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+    }
+
+    /// Unknown game types are returned unchanged
+    pub fn mode(&self, raw: &str) -> String {
+        lookup(raw, self.modes, &MODE_NAMES).unwrap_or_else(|| String::from(raw))
+    }
+}
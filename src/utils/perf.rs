@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+const PERF_FILE: &str = "perf.json";
+
+static STATS: Mutex<PerfStats> = Mutex::new(PerfStats::new());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct Timing {
+    pub count: u64,
+    /// Runs that ended in an error, only tracked for web requests
+    pub failed: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Timing {
+    fn add(&mut self, elapsed: Duration, ok: bool) {
+        let ms = elapsed.as_millis() as u64;
+        self.count += 1;
+        self.failed += u64::from(!ok);
+        self.total_ms = self.total_ms.saturating_add(ms);
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn average(&self) -> Duration {
+        Duration::from_millis(self.total_ms / self.count.max(1))
+    }
+
+    #[inline]
+    pub fn max(&self) -> Duration {
+        Duration::from_millis(self.max_ms)
+    }
+}
+
+/// Servers whose region was needed by a `filter` with `--region`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct GeoStats {
+    pub cached: u64,
+    pub looked_up: u64,
+    pub failed: u64,
+}
+
+impl GeoStats {
+    /// Share of regions that were already cached, `None` before any were needed
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.cached + self.looked_up;
+        (total > 0).then(|| self.cached as f64 / total as f64)
+    }
+}
+
+/// How long commands and web requests take, only ever saved to perf.json in the local data directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PerfStats {
+    /// When the first timing was recorded
+    pub since: Option<SystemTime>,
+    pub commands: BTreeMap<String, Timing>,
+    /// Keyed by [`endpoint_key`]
+    pub endpoints: BTreeMap<String, Timing>,
+    pub geo: GeoStats,
+}
+
+impl PerfStats {
+    const fn new() -> Self {
+        PerfStats {
+            since: None,
+            commands: BTreeMap::new(),
+            endpoints: BTreeMap::new(),
+            geo: GeoStats {
+                cached: 0,
+                looked_up: 0,
+                failed: 0,
+            },
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none()
+    }
+}
+
+/// A panic while recording leaves the numbers usable, so a poisoned lock is taken as is
+fn stats() -> MutexGuard<'static, PerfStats> {
+    STATS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn with_stats(f: impl FnOnce(&mut PerfStats)) {
+    let mut stats = stats();
+    stats.since.get_or_insert_with(SystemTime::now);
+    f(&mut stats)
+}
+
+/// Groups urls that only differ by the server or address asked about, e.g.
+/// `http://1.2.3.4:27016/getInfo` -> `{server}/getInfo`. The query is dropped so api keys are never
+/// stored
+pub fn endpoint_key(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    url.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            if segment.parse::<SocketAddr>().is_ok() {
+                "{server}"
+            } else if segment.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+                "{ip}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn record_command(name: &str, elapsed: Duration) {
+    with_stats(|stats| {
+        stats
+            .commands
+            .entry(name.to_string())
+            .or_default()
+            .add(elapsed, true)
+    })
+}
+
+pub fn record_request(url: &str, elapsed: Duration, ok: bool) {
+    with_stats(|stats| {
+        stats
+            .endpoints
+            .entry(endpoint_key(url))
+            .or_default()
+            .add(elapsed, ok)
+    })
+}
+
+pub fn record_geo(cached: usize, looked_up: usize, failed: usize) {
+    with_stats(|stats| {
+        stats.geo.cached += cached as u64;
+        stats.geo.looked_up += looked_up as u64;
+        stats.geo.failed += failed as u64;
+    })
+}
+
+#[inline]
+pub fn snapshot() -> PerfStats {
+    stats().clone()
+}
+
+pub fn reset() {
+    *stats() = PerfStats::new();
+}
+
+/// Continues from the timings saved by the last run, an unreadable file starts over
+pub fn load(local_dir: &Path) {
+    let Some(saved) = std::fs::read(local_dir.join(PERF_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<PerfStats>(&contents).ok())
+    else {
+        return;
+    };
+    *stats() = saved;
+}
+
+pub fn save(local_dir: &Path) -> io::Result<()> {
+    let file = std::fs::File::create(local_dir.join(PERF_FILE))?;
+    serde_json::to_writer(file, &snapshot())?;
+    Ok(())
+}
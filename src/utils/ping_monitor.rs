@@ -0,0 +1,207 @@
+use crate::{
+    commands::{
+        handler::Message,
+        services::{CacheService, ConfigService},
+        session::{LagSide, MatchEvent, SessionLog},
+    },
+    strip_color_codes,
+    utils::{
+        config::PingMonitorConfig, game_state::GameState, getstatus::measure_ping, http::is_offline,
+    },
+};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::sync::{mpsc::Sender, watch, Mutex};
+
+/// Probes summed up into one session log entry
+pub const SUMMARY_PROBES: usize = 12;
+/// Probes the loss is judged on, no loss is reported before this many were sent
+pub const RECENT_PROBES: usize = 5;
+/// Replies arriving later than this count as lost
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Health {
+    Steady,
+    /// The last two replies took longer than `ping-monitor.spike-ms`, holds the slower one
+    Spiking(Duration),
+    /// Percent of the last [`RECENT_PROBES`] probes that were not answered
+    Losing(u8),
+}
+
+/// Judges the latest `probes`, `None` being a probe without a reply
+pub fn assess(probes: &[Option<Duration>], spike: Duration, loss_percent: u8) -> Health {
+    let recent = &probes[probes.len().saturating_sub(RECENT_PROBES)..];
+    let lost = recent.iter().filter(|probe| probe.is_none()).count();
+    if recent.len() == RECENT_PROBES
+        && lost > 0
+        && lost * 100 >= recent.len() * loss_percent as usize
+    {
+        return Health::Losing((lost * 100 / recent.len()) as u8);
+    }
+    match probes {
+        [.., Some(a), Some(b)] if *a > spike && *b > spike => Health::Spiking(*a.max(b)),
+        _ => Health::Steady,
+    }
+}
+
+/// Probes since the last summary
+struct Stretch {
+    server: SocketAddr,
+    name: String,
+    probes: Vec<Option<Duration>>,
+    lag: Option<LagSide>,
+}
+
+impl Stretch {
+    fn summary(&self) -> MatchEvent {
+        let answered = self.probes.iter().flatten().collect::<Vec<_>>();
+        let millis = |ping: &Duration| ping.as_millis() as u32;
+        MatchEvent::Connection {
+            server: self.name.clone(),
+            sent: self.probes.len() as u16,
+            lost: (self.probes.len() - answered.len()) as u16,
+            average_ms: (!answered.is_empty()).then(|| {
+                answered.iter().map(|ping| millis(ping)).sum::<u32>() / answered.len() as u32
+            }),
+            worst_ms: answered.iter().map(|ping| millis(ping)).max(),
+            lag: self.lag,
+        }
+    }
+}
+
+/// Probes the server joined before `server`, if it answers about as usual the lag is the server's
+async fn lag_side(cache: &CacheService, server: SocketAddr, spike: Duration) -> LagSide {
+    let other = cache
+        .lock()
+        .await
+        .book
+        .history()
+        .iter()
+        .rev()
+        .find(|&&joined| joined != server)
+        .copied();
+    let Some(other) = other else {
+        return LagSide::Unknown;
+    };
+    match measure_ping(other, PROBE_TIMEOUT).await {
+        Ok(ping) if ping <= spike => LagSide::Server,
+        Ok(_) | Err(_) => LagSide::Local,
+    }
+}
+
+async fn record(session_log: &Mutex<SessionLog>, stretch: &mut Option<Stretch>) {
+    if let Some(stretch) = stretch.take().filter(|stretch| !stretch.probes.is_empty()) {
+        session_log.lock().await.record(stretch.summary());
+    }
+}
+
+/// Probes the server being played on while in game, every [`SUMMARY_PROBES`] probes are summed up in
+/// the session log. The `ping-monitor` settings are read again before every probe
+pub async fn ping_monitor_loop(
+    mut game_state: watch::Receiver<GameState>,
+    cache: CacheService,
+    config: ConfigService,
+    session_log: Arc<Mutex<SessionLog>>,
+    msg_sender: Arc<Sender<Message>>,
+) {
+    let mut stretch: Option<Stretch> = None;
+    // outlives the summaries so loss is judged on the latest probes
+    let mut recent = Vec::with_capacity(RECENT_PROBES + 1);
+    let mut health = Health::Steady;
+    // where the lag comes from while the connection is not steady
+    let mut lag = None;
+    loop {
+        let settings =
+            Some(config.current().ping_monitor.clone()).filter(|settings| settings.enabled);
+        let interval = Duration::from_secs(
+            settings
+                .as_ref()
+                .map_or(PingMonitorConfig::default().interval, |settings| {
+                    settings.interval
+                })
+                .max(1),
+        );
+        let name = match *game_state.borrow_and_update() {
+            GameState::InGame { ref server } => Some(strip_color_codes(server)),
+            _ => None,
+        };
+        let server = cache.lock().await.book.history().last().copied();
+
+        let (Some(settings), Some(name), Some(server), false) =
+            (settings, name, server, is_offline())
+        else {
+            record(&session_log, &mut stretch).await;
+            recent.clear();
+            (health, lag) = (Health::Steady, None);
+            tokio::select! {
+                changed = game_state.changed() => if changed.is_err() { return },
+                _ = tokio::time::sleep(interval) => (),
+            }
+            continue;
+        };
+
+        if stretch
+            .as_ref()
+            .is_some_and(|stretch| stretch.server != server)
+        {
+            record(&session_log, &mut stretch).await;
+            recent.clear();
+            (health, lag) = (Health::Steady, None);
+        }
+        let current = stretch.get_or_insert_with(|| Stretch {
+            server,
+            name: name.clone(),
+            probes: Vec::with_capacity(SUMMARY_PROBES),
+            lag: None,
+        });
+        let probe = measure_ping(server, PROBE_TIMEOUT).await.ok();
+        current.probes.push(probe);
+        recent.push(probe);
+        recent.drain(..recent.len().saturating_sub(RECENT_PROBES));
+
+        let spike = Duration::from_millis(u64::from(settings.spike_ms));
+        let next = assess(&recent, spike, settings.loss_percent);
+        let problem = match next {
+            Health::Steady => None,
+            Health::Spiking(ping) => {
+                Some(format!("Ping to {name} spiked to {} ms", ping.as_millis()))
+            }
+            Health::Losing(percent) => {
+                Some(format!("{percent}% of pings to {name} went unanswered"))
+            }
+        };
+        match problem {
+            Some(problem) if health == Health::Steady => {
+                let side = lag_side(&cache, server, spike).await;
+                lag = Some(side);
+                let cause = match side {
+                    LagSide::Server => "the server is lagging, another server answered normally",
+                    LagSide::Local => "likely your connection, another server was slow too",
+                    LagSide::Unknown => "no other server was joined to compare with",
+                };
+                let _ = msg_sender
+                    .send(Message::Warn(format!("{problem}, {cause}")))
+                    .await;
+            }
+            None if health != Health::Steady => {
+                lag = None;
+                let _ = msg_sender
+                    .send(Message::Info(format!(
+                        "Connection to {name} is steady again"
+                    )))
+                    .await;
+            }
+            _ => (),
+        }
+        health = next;
+        current.lag = current.lag.or(lag);
+        if current.probes.len() >= SUMMARY_PROBES {
+            record(&session_log, &mut stretch).await;
+        }
+
+        tokio::select! {
+            changed = game_state.changed() => if changed.is_err() { return },
+            _ = tokio::time::sleep(interval) => (),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    commands::{
+        favorites::write_favorites,
+        filter::{FAVORITES, FAVORITES_LOC},
+    },
+    error::Error,
+};
+#[cfg(feature = "windows-console")]
+use std::os::windows::fs::MetadataExt;
+use std::{
+    collections::HashSet,
+    fs::{Metadata, OpenOptions},
+    io,
+    net::SocketAddr,
+    path::Path,
+};
+#[cfg(feature = "windows-console")]
+use winapi::um::winnt::{
+    FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS, FILE_ATTRIBUTE_RECALL_ON_OPEN,
+};
+
+/// Extension added to a damaged favourites.json before it is rebuilt
+pub const BACKUP_EXT: &str = "json.bak";
+
+/// Problems with `players2` that keep favourites.json from being read or written
+#[derive(Debug)]
+pub enum Players2Issue {
+    /// `players2` is missing or a file can not be created in it
+    NotWritable(io::Error),
+    ReadOnly,
+    /// favourites.json only exists in cloud storage, e.g. OneDrive "files on demand"
+    Placeholder,
+    /// favourites.json is not a json list, e.g. the write was cut off. Holds the parse error
+    Corrupt(String),
+}
+
+impl Players2Issue {
+    /// Whether `favorites repair` can fix it
+    #[inline]
+    pub fn repairable(&self) -> bool {
+        !matches!(self, Players2Issue::NotWritable(_))
+    }
+}
+
+/// Opens a scratch file in `dir` for writing and removes it again
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    let test_file = dir.join(concat!(".", env!("CARGO_PKG_NAME"), "_write_test"));
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&test_file)?;
+    std::fs::remove_file(test_file)
+}
+
+#[cfg(feature = "windows-console")]
+fn is_placeholder(meta: &Metadata) -> bool {
+    meta.file_attributes()
+        & (FILE_ATTRIBUTE_OFFLINE
+            | FILE_ATTRIBUTE_RECALL_ON_OPEN
+            | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS)
+        != 0
+}
+
+#[cfg(not(feature = "windows-console"))]
+fn is_placeholder(_meta: &Metadata) -> bool {
+    false
+}
+
+/// Checks `players2` in `game_dir` and the favourites.json in it, a missing favourites.json is fine as
+/// the game creates it. A placeholder is not read, reading it would start the download
+pub fn inspect(game_dir: &Path) -> Vec<Players2Issue> {
+    let players2 = game_dir.join(FAVORITES_LOC);
+    let mut issues = Vec::new();
+    if let Err(err) = check_writable(&players2) {
+        issues.push(Players2Issue::NotWritable(err));
+    }
+    let path = players2.join(FAVORITES);
+    let Ok(meta) = std::fs::metadata(&path) else {
+        return issues;
+    };
+    if is_placeholder(&meta) {
+        issues.push(Players2Issue::Placeholder);
+        return issues;
+    }
+    if meta.permissions().readonly() {
+        issues.push(Players2Issue::ReadOnly);
+    }
+    let parsed = std::fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            serde_json::from_slice::<Vec<String>>(&contents).map_err(|err| err.to_string())
+        });
+    if let Err(err) = parsed {
+        issues.push(Players2Issue::Corrupt(err));
+    }
+    issues
+}
+
+/// Servers that can still be read from a damaged favourites.json. Only complete quoted entries are
+/// kept, an entry cut off mid write could still parse with the wrong port
+pub fn salvage_entries(contents: &[u8]) -> Vec<SocketAddr> {
+    let text = String::from_utf8_lossy(contents);
+    let mut pieces = text.split('"').collect::<Vec<_>>();
+    if pieces.len() % 2 == 0 {
+        pieces.pop();
+    }
+    let mut seen = HashSet::new();
+    pieces
+        .into_iter()
+        .skip(1)
+        .step_by(2)
+        .filter_map(|entry| entry.trim().parse().ok())
+        .filter(|server| seen.insert(*server))
+        .collect()
+}
+
+pub struct Repaired {
+    pub fixed: Vec<Players2Issue>,
+    /// Servers kept when a damaged favourites.json was rebuilt
+    pub salvaged: Option<usize>,
+    /// Issues left after repairing, downloading a placeholder can reveal a damaged file
+    pub remaining: Vec<Players2Issue>,
+}
+
+/// Creates `players2` if it is missing, downloads a placeholder, clears the read-only flag and rebuilds
+/// a damaged favourites.json from the servers that can still be read. The damaged file is kept with
+/// [`BACKUP_EXT`]
+pub async fn repair(game_dir: &Path) -> Result<Repaired, Error> {
+    let players2 = game_dir.join(FAVORITES_LOC);
+    tokio::fs::create_dir_all(&players2)
+        .await
+        .map_err(|err| Error::io("create", &players2, err))?;
+    let path = players2.join(FAVORITES);
+    let mut fixed = Vec::new();
+    let mut salvaged = None;
+    for issue in inspect(game_dir) {
+        match issue {
+            Players2Issue::NotWritable(_) => continue,
+            Players2Issue::Placeholder => {
+                tokio::fs::read(&path)
+                    .await
+                    .map_err(|err| Error::io("download", &path, err))?;
+            }
+            Players2Issue::ReadOnly => {
+                let mut permissions = tokio::fs::metadata(&path)
+                    .await
+                    .map_err(|err| Error::io("read", &path, err))?
+                    .permissions();
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    permissions.set_mode(permissions.mode() | 0o200);
+                }
+                // only clears the read-only attribute on Windows
+                #[cfg(not(unix))]
+                #[allow(clippy::permissions_set_readonly_false)]
+                permissions.set_readonly(false);
+                tokio::fs::set_permissions(&path, permissions)
+                    .await
+                    .map_err(|err| Error::io("write", &path, err))?;
+            }
+            Players2Issue::Corrupt(_) => {
+                let contents = tokio::fs::read(&path)
+                    .await
+                    .map_err(|err| Error::io("read", &path, err))?;
+                let servers = salvage_entries(&contents);
+                let backup = path.with_extension(BACKUP_EXT);
+                tokio::fs::rename(&path, &backup)
+                    .await
+                    .map_err(|err| Error::io("move", &backup, err))?;
+                write_favorites(std::slice::from_ref(&path), &servers).await?;
+                salvaged = Some(servers.len());
+            }
+        }
+        fixed.push(issue);
+    }
+    Ok(Repaired {
+        fixed,
+        salvaged,
+        remaining: inspect(game_dir),
+    })
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const HOURS: usize = 24;
+/// Samples an hourly average is weighted by at most, so a server that changed its schedule catches up
+/// within a few weeks
+const SAMPLE_WEIGHT_MAX: u8 = 14;
+/// Servers that were not sampled for this many days are dropped
+const FORGET_AFTER_DAYS: u64 = 30;
+
+/// Hour of the day `at` falls in, in UTC
+pub fn hour_of_day(at: SystemTime) -> usize {
+    let secs = at
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    (secs / 3600 % HOURS as u64) as usize
+}
+
+fn day_of(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / (3600 * HOURS as u64))
+}
+
+/// Average player count of a server in each hour of the day
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct HourlyPlayers {
+    /// Index 0 is 00:00-00:59 UTC
+    players: [f32; HOURS],
+    samples: [u8; HOURS],
+    /// Days since the unix epoch of the last sample
+    last_day: u64,
+}
+
+impl HourlyPlayers {
+    /// `None` when the server was never sampled in `hour`
+    #[inline]
+    pub fn at_hour(&self, hour: usize) -> Option<f32> {
+        (self.samples[hour] > 0).then_some(self.players[hour])
+    }
+
+    fn record(&mut self, hour: usize, players: u8, day: u64) {
+        let samples = self.samples[hour].min(SAMPLE_WEIGHT_MAX - 1) + 1;
+        self.players[hour] += (f32::from(players) - self.players[hour]) / f32::from(samples);
+        self.samples[hour] = samples;
+        self.last_day = self.last_day.max(day);
+    }
+}
+
+/// Hourly player counts of every sampled server
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct PopulationHistory(HashMap<SocketAddr, HourlyPlayers>);
+
+impl PopulationHistory {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds `players` to the average of the hour `at` falls in
+    pub fn record(&mut self, server: SocketAddr, players: u8, at: SystemTime) {
+        self.0
+            .entry(server)
+            .or_default()
+            .record(hour_of_day(at), players, day_of(at));
+    }
+
+    /// Players `server` usually has in the hour `at` falls in, `None` when it was never sampled then
+    pub fn usual_players(&self, server: SocketAddr, at: SystemTime) -> Option<f32> {
+        self.0.get(&server)?.at_hour(hour_of_day(at))
+    }
+
+    /// Drops servers that were last sampled over [`FORGET_AFTER_DAYS`] before `now`
+    pub fn forget_stale(&mut self, now: SystemTime) {
+        let cutoff = day_of(now).saturating_sub(FORGET_AFTER_DAYS);
+        self.0.retain(|_, hourly| hourly.last_day >= cutoff);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::strip_color_codes;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Config files H2M saves player settings to, relative to the game directory
+const CONFIG_CANDIDATES: [&str; 3] = [
+    "players2/user/config_mp.cfg",
+    "players2/config_mp.cfg",
+    "h2m-mod/players2/config_mp.cfg",
+];
+
+/// Dvars shown by the `profile` command
+pub const KEY_DVARS: [&str; 6] = [
+    "name",
+    "sensitivity",
+    "cg_fov",
+    "com_maxfps",
+    "r_mode",
+    "snd_volume",
+];
+
+pub struct Profile {
+    pub path: PathBuf,
+    /// Player name with cod color codes removed
+    pub name: Option<String>,
+    /// Key dvars in the order of `KEY_DVARS`, if they were found
+    pub dvars: Vec<(&'static str, String)>,
+}
+
+impl Profile {
+    /// Reads the most recently saved config found in `game_dir`
+    pub fn read(game_dir: &Path) -> io::Result<Self> {
+        let path = CONFIG_CANDIDATES
+            .iter()
+            .map(|candidate| game_dir.join(candidate))
+            .filter_map(|path| {
+                let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+                Some((path, modified))
+            })
+            .max_by_key(|&(_, modified)| modified)
+            .map(|(path, _)| path)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No player config found in players2",
+                )
+            })?;
+        let bytes = std::fs::read(&path)?;
+        let dvars = parse_config(&String::from_utf8_lossy(&bytes));
+        Ok(Profile {
+            name: dvars
+                .iter()
+                .rev()
+                .find(|(dvar, _)| dvar.eq_ignore_ascii_case("name"))
+                .map(|(_, name)| strip_color_codes(name))
+                .filter(|name| !name.trim().is_empty()),
+            dvars: KEY_DVARS
+                .iter()
+                .filter_map(|&key| {
+                    dvars
+                        .iter()
+                        .rev()
+                        .find(|(dvar, _)| dvar.eq_ignore_ascii_case(key))
+                        .map(|(_, value)| (key, value.clone()))
+                })
+                .collect(),
+            path,
+        })
+    }
+}
+
+/// Parses `set`/`seta` lines into `(dvar, value)` pairs in file order, later entries override earlier ones
+///
+/// Handles a leading BOM, `//` comments, CRLF line endings, quoted or bare values and
+/// unterminated quotes, other commands (`bind`, `exec`, ...) are skipped
+pub fn parse_config(contents: &str) -> Vec<(String, String)> {
+    contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let command = parts.next()?;
+            if !matches!(command.to_ascii_lowercase().as_str(), "set" | "seta") {
+                return None;
+            }
+            let rest = parts.next()?.trim_start();
+            let (dvar, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let value = value.trim();
+            let value = match value.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                None => value.split("//").next().unwrap_or_default().trim_end(),
+            };
+            Some((dvar.to_string(), value.to_string()))
+        })
+        .collect()
+}
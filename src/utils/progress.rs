@@ -0,0 +1,123 @@
+use crate::{
+    commands::handler::Message,
+    utils::{
+        display::DisplayProgress,
+        input::style::{GREY, WHITE},
+    },
+};
+use crossterm::{
+    cursor::MoveToColumn,
+    terminal::{Clear, ClearType},
+    QueueableCommand,
+};
+use std::{
+    future::Future,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Reports the progress of a counted operation, the line reader draws the latest update above the prompt.
+/// Updates are throttled and dropped if the message channel is full, the progress line is removed once
+/// `Progress` is dropped
+pub struct Progress<'a> {
+    label: &'static str,
+    done: usize,
+    total: usize,
+    last_update: Option<Instant>,
+    msg_sender: &'a Sender<Message>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(label: &'static str, total: usize, msg_sender: &'a Sender<Message>) -> Self {
+        let mut progress = Progress {
+            label,
+            done: 0,
+            total,
+            last_update: None,
+            msg_sender,
+        };
+        progress.update();
+        progress
+    }
+
+    pub fn inc(&mut self) {
+        self.done = self.done.saturating_add(1).min(self.total);
+        if self.done == self.total
+            || !matches!(self.last_update, Some(last) if last.elapsed() < UPDATE_INTERVAL)
+        {
+            self.update();
+        }
+    }
+
+    fn update(&mut self) {
+        let line = DisplayProgress(self.label, self.done, self.total).to_string();
+        let _ = self.msg_sender.try_send(Message::Progress(Some(line)));
+        self.last_update = Some(Instant::now());
+    }
+}
+
+impl Drop for Progress<'_> {
+    fn drop(&mut self) {
+        let _ = self.msg_sender.try_send(Message::Progress(None));
+    }
+}
+
+/// Awaits `task` with a spinner drawn on the current line, for work a command waits on before it can
+/// go on. Messages `task` sends to the sender of `messages` are printed above the spinner and the
+/// latest progress line is shown next to it
+pub async fn spin_while<T>(
+    label: &str,
+    mut messages: Receiver<Message>,
+    task: impl Future<Output = T>,
+) -> T {
+    tokio::pin!(task);
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    let mut frames = SPINNER_FRAMES.iter().cycle();
+    let mut progress = None;
+    let output = loop {
+        tokio::select! {
+            output = &mut task => break output,
+            Some(msg) = messages.recv() => match msg {
+                Message::Progress(line) => progress = line,
+                Message::Screen(_) | Message::Trim(_) => (),
+                msg => {
+                    let _ = clear_line();
+                    msg.print();
+                }
+            },
+            _ = interval.tick() => {
+                let frame = frames.next().expect("cycle never ends");
+                let _ = draw_spinner(*frame, label, progress.as_deref());
+            }
+        }
+    };
+    let _ = clear_line();
+    while let Ok(msg) = messages.try_recv() {
+        msg.print();
+    }
+    output
+}
+
+fn draw_spinner(frame: char, label: &str, progress: Option<&str>) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout
+        .queue(MoveToColumn(0))?
+        .queue(Clear(ClearType::CurrentLine))?;
+    write!(stdout, "{frame} {label}")?;
+    if let Some(progress) = progress {
+        write!(stdout, " {GREY}{progress}{WHITE}")?;
+    }
+    stdout.flush()
+}
+
+fn clear_line() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout
+        .queue(MoveToColumn(0))?
+        .queue(Clear(ClearType::CurrentLine))?;
+    stdout.flush()
+}
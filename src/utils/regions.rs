@@ -0,0 +1,123 @@
+use crate::cli::Region;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, str::FromStr};
+
+const NA: [RegionCode; 1] = [RegionCode::continent(['N', 'A'])];
+const EU: [RegionCode; 1] = [RegionCode::continent(['E', 'U'])];
+const APAC: [RegionCode; 3] = [
+    RegionCode::continent(['A', 'S']),
+    RegionCode::continent(['O', 'C']),
+    RegionCode::continent(['A', 'F']),
+];
+
+/// A continent code, optionally narrowed down to a country code, e.g. `AS` or `AS:SA`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RegionCode {
+    pub continent: [char; 2],
+    pub country: Option<[char; 2]>,
+}
+
+impl RegionCode {
+    pub const fn continent(continent: [char; 2]) -> Self {
+        RegionCode {
+            continent,
+            country: None,
+        }
+    }
+
+    /// `country` is only compared when this code names one, `None` never matches a country code
+    pub fn matches(&self, continent: [char; 2], country: Option<[char; 2]>) -> bool {
+        self.continent == continent && self.country.is_none_or(|code| country == Some(code))
+    }
+}
+
+fn parse_code(code: &str) -> Result<[char; 2], String> {
+    let chars = code.trim().chars().collect::<Vec<_>>();
+    match chars[..] {
+        [a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+            Ok([a.to_ascii_uppercase(), b.to_ascii_uppercase()])
+        }
+        _ => Err(format!("expected a 2 letter code, found: '{code}'")),
+    }
+}
+
+impl FromStr for RegionCode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (continent, country) = match s.split_once(':') {
+            Some((continent, country)) => (continent, Some(parse_code(country)?)),
+            None => (s, None),
+        };
+        Ok(RegionCode {
+            continent: parse_code(continent)?,
+            country,
+        })
+    }
+}
+
+impl TryFrom<String> for RegionCode {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RegionCode> for String {
+    fn from(value: RegionCode) -> Self {
+        value.to_string()
+    }
+}
+
+/// Codes of the regions named in the `region` settings, built in regions missing from it keep their
+/// default codes
+pub type RegionSettings = BTreeMap<Region, Vec<RegionCode>>;
+
+/// Names of the regions defined in `groups`, built in regions are left out
+pub fn custom_regions(groups: &RegionSettings) -> Vec<Region> {
+    groups
+        .keys()
+        .filter(|region| matches!(region, Region::Custom(_)))
+        .cloned()
+        .collect()
+}
+
+impl Region {
+    /// Built in regions in the order they are listed
+    pub const BUILT_IN: [Region; 3] = [Region::NA, Region::EU, Region::Apac];
+
+    fn codes<'a>(&self, groups: &'a RegionSettings) -> &'a [RegionCode] {
+        if let Some(codes) = groups.get(self) {
+            return codes;
+        }
+        match self {
+            Region::NA => &NA,
+            Region::EU => &EU,
+            Region::Apac => &APAC,
+            Region::Custom(_) => &[],
+        }
+    }
+
+    /// Built in regions are always defined, custom regions once they are named in the `region` settings
+    pub fn is_defined(&self, groups: &RegionSettings) -> bool {
+        !matches!(self, Region::Custom(_)) || groups.contains_key(self)
+    }
+
+    pub fn matches(
+        &self,
+        groups: &RegionSettings,
+        continent: [char; 2],
+        country: Option<[char; 2]>,
+    ) -> bool {
+        self.codes(groups)
+            .iter()
+            .any(|code| code.matches(continent, country))
+    }
+
+    /// Whether servers on `continent` can only be placed in this region once their country is known
+    pub fn needs_country(&self, groups: &RegionSettings, continent: [char; 2]) -> bool {
+        self.codes(groups)
+            .iter()
+            .any(|code| code.continent == continent && code.country.is_some())
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+};
+
+/// Checks a score is based on at most, older checks are halved away so a server that got more dependable
+/// is not held back by its past
+const CHECK_WINDOW: u16 = 40;
+/// No score is given before a server was checked this many times
+const CHECKS_MIN: u16 = 3;
+/// Servers missing this many checks in a row are dropped
+const FORGET_AFTER_MISSES: u16 = 30;
+
+/// Times a server was found and missed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Availability {
+    seen: u16,
+    missed: u16,
+    missed_in_row: u16,
+}
+
+impl Availability {
+    fn record(&mut self, seen: bool) {
+        if self.seen + self.missed >= CHECK_WINDOW {
+            self.seen /= 2;
+            self.missed /= 2;
+        }
+        if seen {
+            self.seen += 1;
+            self.missed_in_row = 0;
+        } else {
+            self.missed += 1;
+            self.missed_in_row = self.missed_in_row.saturating_add(1);
+        }
+    }
+
+    /// Percent of checks the server was found in, `None` until it was checked [`CHECKS_MIN`] times
+    pub fn score(&self) -> Option<u8> {
+        let checks = self.seen + self.missed;
+        (checks >= CHECKS_MIN).then(|| (u32::from(self.seen) * 100 / u32::from(checks)) as u8)
+    }
+}
+
+/// How often each server was found or missed by `filter` and `dashboard`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct ReliabilityBook(HashMap<SocketAddr, Availability>);
+
+impl ReliabilityBook {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn record(&mut self, server: SocketAddr, seen: bool) {
+        self.0.entry(server).or_default().record(seen);
+    }
+
+    /// Counts every server in `listed` as seen and every other known server as missed. Only call this
+    /// with complete master server lists, a partial list would count the rest as gone
+    pub fn record_listing(&mut self, listed: &HashSet<SocketAddr>) {
+        for (server, availability) in self.0.iter_mut() {
+            availability.record(listed.contains(server));
+        }
+        for &server in listed {
+            self.0.entry(server).or_insert_with(|| {
+                let mut availability = Availability::default();
+                availability.record(true);
+                availability
+            });
+        }
+        self.0
+            .retain(|_, availability| availability.missed_in_row < FORGET_AFTER_MISSES);
+    }
+
+    /// See [`Availability::score`]
+    #[inline]
+    pub fn score(&self, server: SocketAddr) -> Option<u8> {
+        self.0.get(&server)?.score()
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{
+    commands::{launch_h2m::HostName, reconnect::HISTORY_MAX},
+    parse_hostname,
+    utils::json_data::GetInfo,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// Shorter queries only match names containing them, otherwise too many servers would match
+const FUZZY_MIN: usize = 4;
+/// Older names past this are forgotten
+pub const ALIASES_MAX: usize = 4;
+/// Servers that were neither listed nor joined for this long are dropped when the cache is rebuilt
+pub const FORGET_AFTER: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BookEntry {
+    /// Host names with cod color codes, the current one last
+    pub aliases: Vec<Arc<str>>,
+    pub last_seen: SystemTime,
+    /// Missing from entries written before player counts were kept
+    #[serde(default)]
+    pub players: Option<PlayerSample>,
+}
+
+/// Players a server had the last time it answered a `getInfo` request
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerSample {
+    pub clients: u8,
+    pub max_clients: u8,
+    pub at: SystemTime,
+}
+
+impl PlayerSample {
+    #[inline]
+    pub fn new(info: &GetInfo, at: SystemTime) -> Self {
+        PlayerSample {
+            clients: info.clients,
+            max_clients: info.max_clients,
+            at,
+        }
+    }
+}
+
+impl BookEntry {
+    #[inline]
+    pub fn host_name(&self) -> Option<&Arc<str>> {
+        self.aliases.last()
+    }
+
+    /// Best match of `query` on any name, a match on the current name ranks above the same match on
+    /// an older one
+    fn score(&self, query: &NameQuery) -> Option<(Match, bool)> {
+        let current = self.aliases.len().checked_sub(1)?;
+        self.aliases
+            .iter()
+            .enumerate()
+            .filter_map(|(i, alias)| Some((query.score(alias)?, i == current)))
+            .max()
+    }
+}
+
+/// How closely a query matched a name, weakest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    /// The query's letters appear in order
+    Fuzzy,
+    /// Every word of the query appears
+    Words,
+    Contains,
+    Exact,
+}
+
+/// Letters and digits of `name` in lowercase with color codes removed, so `^1[EU] Best|Rust` and
+/// `eu best rust` compare equal
+fn compact(name: &str) -> String {
+    parse_hostname(name)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+struct NameQuery {
+    compact: String,
+    words: Vec<String>,
+}
+
+impl NameQuery {
+    fn new(query: &str) -> Self {
+        let words = query
+            .split_whitespace()
+            .map(compact)
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        NameQuery {
+            compact: words.concat(),
+            words,
+        }
+    }
+
+    fn score(&self, name: &str) -> Option<Match> {
+        if self.compact.is_empty() {
+            return None;
+        }
+        let name = compact(name);
+        if name == self.compact {
+            Some(Match::Exact)
+        } else if name.contains(&self.compact) {
+            Some(Match::Contains)
+        } else if self.words.iter().all(|word| name.contains(word.as_str())) {
+            Some(Match::Words)
+        } else if self.compact.chars().count() >= FUZZY_MIN && {
+            let mut chars = name.chars();
+            self.compact.chars().all(|c| chars.any(|n| n == c))
+        } {
+            Some(Match::Fuzzy)
+        } else {
+            None
+        }
+    }
+}
+
+/// Every server we know of by address, with the host names it was listed or joined under
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ServerBook {
+    servers: HashMap<SocketAddr, BookEntry>,
+    /// Joined servers, most recent last
+    history: Vec<SocketAddr>,
+}
+
+impl ServerBook {
+    /// Book from the name to address map and name history written by older versions
+    pub fn from_legacy(
+        host_names: HashMap<Arc<str>, SocketAddr>,
+        history: &[HostName],
+        seen: SystemTime,
+    ) -> Self {
+        let mut book = ServerBook::default();
+        for (host_name, socket_addr) in host_names {
+            book.record(socket_addr, host_name, seen);
+        }
+        for host_name in history {
+            if let Some(socket_addr) = book.address_of(&host_name.raw) {
+                book.push_history(socket_addr);
+            }
+        }
+        book
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    #[inline]
+    pub fn servers(&self) -> &HashMap<SocketAddr, BookEntry> {
+        &self.servers
+    }
+
+    #[inline]
+    pub fn get(&self, socket_addr: SocketAddr) -> Option<&BookEntry> {
+        self.servers.get(&socket_addr)
+    }
+
+    #[inline]
+    pub fn host_name(&self, socket_addr: SocketAddr) -> Option<&Arc<str>> {
+        self.servers
+            .get(&socket_addr)
+            .and_then(BookEntry::host_name)
+    }
+
+    /// Records that `socket_addr` was seen named `host_name` at `seen`. Returns `true` if the name is
+    /// new to this address or it was renamed back to an older one
+    pub fn record(
+        &mut self,
+        socket_addr: SocketAddr,
+        host_name: Arc<str>,
+        seen: SystemTime,
+    ) -> bool {
+        let entry = self
+            .servers
+            .entry(socket_addr)
+            .or_insert_with(|| BookEntry {
+                aliases: Vec::new(),
+                last_seen: seen,
+                players: None,
+            });
+        entry.last_seen = entry.last_seen.max(seen);
+        match entry.aliases.iter().position(|alias| *alias == host_name) {
+            Some(i) if i == entry.aliases.len() - 1 => false,
+            Some(i) => {
+                let alias = entry.aliases.remove(i);
+                entry.aliases.push(alias);
+                true
+            }
+            None => {
+                entry.aliases.push(host_name);
+                if entry.aliases.len() > ALIASES_MAX {
+                    entry.aliases.remove(0);
+                }
+                true
+            }
+        }
+    }
+
+    /// Keeps `sample` as the player count of `socket_addr` unless a newer one is known, servers that are
+    /// not in the book are skipped
+    pub fn record_players(&mut self, socket_addr: SocketAddr, sample: PlayerSample) {
+        if let Some(entry) = self.servers.get_mut(&socket_addr) {
+            if entry.players.is_none_or(|known| known.at <= sample.at) {
+                entry.players = Some(sample);
+            }
+        }
+    }
+
+    /// Most recently seen server currently named exactly `host_name`, color codes included
+    pub fn address_of(&self, host_name: &str) -> Option<SocketAddr> {
+        self.servers
+            .iter()
+            .filter(|(_, entry)| entry.host_name().is_some_and(|name| **name == *host_name))
+            .max_by_key(|(_, entry)| entry.last_seen)
+            .map(|(&socket_addr, _)| socket_addr)
+    }
+
+    /// Every server with a name matching `query`, or an address starting with it, best match first
+    pub fn search(&self, query: &str) -> Vec<SocketAddr> {
+        let name_query = NameQuery::new(query);
+        let mut found = self
+            .servers
+            .iter()
+            .filter_map(|(socket_addr, entry)| {
+                let by_addr = socket_addr.to_string().starts_with(query);
+                let score = entry.score(&name_query);
+                (by_addr || score.is_some()).then_some((by_addr, score, socket_addr, entry))
+            })
+            .collect::<Vec<_>>();
+        found.sort_unstable_by(|a, b| {
+            (b.0, b.1, b.3.last_seen)
+                .cmp(&(a.0, a.1, a.3.last_seen))
+                .then(a.2.cmp(b.2))
+        });
+        found
+            .into_iter()
+            .map(|(_, _, &socket_addr, _)| socket_addr)
+            .collect()
+    }
+
+    /// Servers sharing the best match for `query`, most recently seen first. Names are compared on
+    /// their letters and digits only, falling back to matching every word and then the query's
+    /// letters in order
+    pub fn candidates(&self, query: &str) -> Vec<SocketAddr> {
+        let query = NameQuery::new(query);
+        let mut found = self
+            .servers
+            .iter()
+            .filter_map(|(&socket_addr, entry)| Some((entry.score(&query)?, socket_addr, entry)))
+            .collect::<Vec<_>>();
+        let Some(best) = found.iter().map(|&(score, ..)| score).max() else {
+            return Vec::new();
+        };
+        found.retain(|&(score, ..)| score == best);
+        found.sort_unstable_by(|(_, a_addr, a), (_, b_addr, b)| {
+            b.last_seen.cmp(&a.last_seen).then(a_addr.cmp(b_addr))
+        });
+        found
+            .into_iter()
+            .map(|(_, socket_addr, _)| socket_addr)
+            .collect()
+    }
+
+    /// Joined servers, most recent last
+    #[inline]
+    pub fn history(&self) -> &[SocketAddr] {
+        &self.history
+    }
+
+    /// Moves `socket_addr` to the end of the history, returns `false` if it already was the last entry.
+    /// Only the last [`HISTORY_MAX`] servers joined are kept
+    pub fn push_history(&mut self, socket_addr: SocketAddr) -> bool {
+        if self.history.last() == Some(&socket_addr) {
+            return false;
+        }
+        self.history.retain(|&joined| joined != socket_addr);
+        self.history.push(socket_addr);
+        self.history
+            .drain(..self.history.len().saturating_sub(HISTORY_MAX));
+        true
+    }
+
+    /// Numbered history entry as displayed by `reconnect --history`, 1 being the last server joined
+    #[inline]
+    pub fn history_entry(&self, num: usize) -> Option<SocketAddr> {
+        num.checked_sub(1)
+            .and_then(|i| self.history.iter().rev().nth(i))
+            .copied()
+    }
+
+    /// Takes the history of `live` for a book that was rebuilt while servers could still be joined,
+    /// along with the entries of joined servers the rebuilt book is missing
+    pub fn adopt_history(&mut self, live: &ServerBook) {
+        for socket_addr in live.history.iter() {
+            if let Some(entry) = live.servers.get(socket_addr) {
+                self.servers
+                    .entry(*socket_addr)
+                    .or_insert_with(|| entry.clone());
+            }
+        }
+        self.history = live.history.clone();
+    }
+
+    /// Drops servers last seen before `cutoff` that are not in the history
+    pub fn forget_before(&mut self, cutoff: SystemTime) {
+        let history = &self.history;
+        self.servers.retain(|socket_addr, entry| {
+            entry.last_seen >= cutoff || history.contains(socket_addr)
+        });
+    }
+}
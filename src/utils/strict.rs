@@ -0,0 +1,115 @@
+use crate::strip_ansi_sequences;
+use serde::Serialize;
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static GEO_FAILURES_ALLOWED: AtomicUsize = AtomicUsize::new(0);
+static STATE: Mutex<State> = Mutex::new(State {
+    line: None,
+    problems: Vec::new(),
+});
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    Error,
+    Warning,
+    /// More servers than `--max-geo-failures` could not be placed in a region
+    GeoLookups,
+    /// Matching servers were left out of favourites.json by the limit
+    Truncated,
+    /// The filter deadline was reached, servers still being checked were skipped
+    TimedOut,
+    /// One of several favourites.json files could not be written
+    WriteFailed,
+}
+
+/// Written to stderr as one line of json
+#[derive(Serialize, Debug, Clone)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// Script line that was running, `None` during startup
+    pub line: Option<usize>,
+    pub command: Option<String>,
+    pub message: String,
+}
+
+struct State {
+    line: Option<(usize, String)>,
+    problems: Vec<Problem>,
+}
+
+/// A panic while recording leaves what was kept usable, so a poisoned lock is taken as is
+fn state() -> MutexGuard<'static, State> {
+    STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keeps every problem reported from now on, nothing is kept before this is called
+pub fn enable(geo_failures_allowed: usize) {
+    GEO_FAILURES_ALLOWED.store(geo_failures_allowed, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Problems reported from now on are tied to line `num` of the script
+pub fn set_line(num: usize, command: &str) {
+    if enabled() {
+        state().line = Some((num, command.to_string()));
+    }
+}
+
+pub fn report(kind: ProblemKind, message: &str) {
+    if !enabled() {
+        return;
+    }
+    let mut state = state();
+    let (line, command) = state
+        .line
+        .clone()
+        .map_or((None, None), |(num, command)| (Some(num), Some(command)));
+    state.problems.push(Problem {
+        kind,
+        line,
+        command,
+        message: strip_ansi_sequences(message).into_owned(),
+    });
+}
+
+/// Only reported once `failed` is more than `--max-geo-failures`
+pub fn report_geo_failures(failed: usize) {
+    if failed > GEO_FAILURES_ALLOWED.load(Ordering::Relaxed) {
+        report(
+            ProblemKind::GeoLookups,
+            &format!("Failed to resolve location for {failed} servers"),
+        );
+    }
+}
+
+#[inline]
+pub fn problems() -> Vec<Problem> {
+    state().problems.clone()
+}
+
+/// Writes every problem to stderr and returns the exit code of the run, `1` if there were any
+pub fn finish() -> i32 {
+    let problems = problems();
+    let mut stderr = std::io::stderr().lock();
+    for problem in problems.iter() {
+        if let Ok(json) = serde_json::to_string(problem) {
+            let _ = writeln!(stderr, "{json}");
+        }
+    }
+    i32::from(!problems.is_empty())
+}
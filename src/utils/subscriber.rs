@@ -1,4 +1,164 @@
-use tracing_subscriber::{filter::EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+use crate::{
+    cli::{LogTarget, Verbosity},
+    utils::{
+        config::LogConfig,
+        input::style::color_enabled,
+        strict::{self, ProblemKind},
+    },
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, OnceLock,
+};
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter},
+    fmt,
+    layer::{Context, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+};
+
+static ERRORS_LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts error events so callers can tell a command failed without it having to return a result.
+/// With `--strict` warnings and errors shown in the terminal are also kept
+struct ErrorCounter;
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for ErrorCounter {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let kind = match *meta.level() {
+            tracing::Level::ERROR => {
+                ERRORS_LOGGED.fetch_add(1, Ordering::Relaxed);
+                ProblemKind::Error
+            }
+            tracing::Level::WARN => ProblemKind::Warning,
+            _ => return,
+        };
+        if strict::enabled() && meta.name() != crate::LOG_ONLY {
+            let mut message = MessageField::default();
+            event.record(&mut message);
+            strict::report(kind, &message.0);
+        }
+    }
+}
+
+/// Text of an event's `message` field
+#[derive(Default)]
+struct MessageField(String);
+
+impl tracing::field::Visit for MessageField {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+/// Total number of error events logged since startup
+#[inline]
+pub fn errors_logged() -> usize {
+    ERRORS_LOGGED.load(Ordering::Relaxed)
+}
+
+impl From<Verbosity> for LevelFilter {
+    fn from(value: Verbosity) -> Self {
+        match value {
+            Verbosity::Trace => LevelFilter::TRACE,
+            Verbosity::Debug => LevelFilter::DEBUG,
+            Verbosity::Info => LevelFilter::INFO,
+            Verbosity::Warn => LevelFilter::WARN,
+            Verbosity::Error => LevelFilter::ERROR,
+        }
+    }
+}
+
+type ReloadFn = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Swaps the `EnvFilter` of one layer while the subscriber is running
+struct LevelHandle {
+    level: Mutex<Verbosity>,
+    directives: fn(Verbosity) -> String,
+    reload: ReloadFn,
+}
+
+impl LevelHandle {
+    fn new<S: 'static>(
+        level: Verbosity,
+        directives: fn(Verbosity) -> String,
+        handle: reload::Handle<EnvFilter, S>,
+    ) -> Self {
+        LevelHandle {
+            level: Mutex::new(level),
+            directives,
+            reload: Box::new(move |filter| handle.reload(filter)),
+        }
+    }
+
+    fn set(&self, level: Verbosity) -> Result<(), String> {
+        (self.reload)(EnvFilter::new((self.directives)(level))).map_err(|err| err.to_string())?;
+        *self.level.lock().unwrap() = level;
+        Ok(())
+    }
+
+    fn get(&self) -> Verbosity {
+        *self.level.lock().unwrap()
+    }
+}
+
+static FILE_LEVEL: OnceLock<LevelHandle> = OnceLock::new();
+static TERMINAL_LEVEL: OnceLock<LevelHandle> = OnceLock::new();
+
+/// Current `(file, terminal)` log levels, `None` if that output is not in use
+pub fn log_levels() -> (Option<Verbosity>, Option<Verbosity>) {
+    (
+        FILE_LEVEL.get().map(LevelHandle::get),
+        TERMINAL_LEVEL.get().map(LevelHandle::get),
+    )
+}
+
+/// Changes the log level of `target` without rebuilding the subscriber
+pub fn set_log_level(target: LogTarget, level: Verbosity) -> Result<(), String> {
+    let handles = match target {
+        LogTarget::File => vec![("file", &FILE_LEVEL)],
+        LogTarget::Terminal => vec![("terminal", &TERMINAL_LEVEL)],
+        LogTarget::All => vec![("file", &FILE_LEVEL), ("terminal", &TERMINAL_LEVEL)],
+    };
+    let mut set_any = false;
+    for (name, handle) in handles {
+        match handle.get() {
+            Some(handle) => {
+                handle.set(level)?;
+                set_any = true;
+            }
+            None if target != LogTarget::All => return Err(format!("No {name} log is in use")),
+            None => (),
+        }
+    }
+    if !set_any {
+        return Err(String::from("Logging has not been initialized"));
+    }
+    Ok(())
+}
+
+#[cfg(not(debug_assertions))]
+fn file_directives(level: Verbosity) -> String {
+    format!(
+        "{}={},reqwest=warn",
+        env!("CARGO_PKG_NAME"),
+        LevelFilter::from(level)
+    )
+}
+
+fn terminal_directives(level: Verbosity) -> String {
+    format!("{}={}", env!("CARGO_PKG_NAME"), LevelFilter::from(level))
+}
 
 #[cfg(not(debug_assertions))]
 use tracing::{Event, Level, Subscriber};
@@ -94,15 +254,27 @@ where
     }
 }
 
+/// Extension shared by every log file written to the local environment directory
+pub const LOG_EXTENSION: &str = "log";
+
 #[cfg(not(debug_assertions))]
-pub fn init_subscriber(local_env_dir: &std::path::Path) -> std::io::Result<()> {
+pub fn init_subscriber(local_env_dir: &std::path::Path, config: &LogConfig) -> std::io::Result<()> {
+    use crate::utils::config::LogRotation;
+    use tracing_appender::rolling::{RollingFileAppender, Rotation};
     use tracing_subscriber::{filter::DynFilterFn, Layer};
-    let name = env!("CARGO_PKG_NAME");
-    let log_name = format!("{name}.log");
 
-    let file_appender = tracing_appender::rolling::RollingFileAppender::builder()
-        .filename_prefix(log_name)
-        .max_log_files(5)
+    let mut builder = RollingFileAppender::builder()
+        .filename_prefix(env!("CARGO_PKG_NAME"))
+        .filename_suffix(LOG_EXTENSION)
+        .rotation(match config.rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        });
+    if config.retention != 0 {
+        builder = builder.max_log_files(config.retention);
+    }
+    let file_appender = builder
         .build(local_env_dir)
         .map_err(std::io::Error::other)?;
 
@@ -111,8 +283,10 @@ pub fn init_subscriber(local_env_dir: &std::path::Path) -> std::io::Result<()> {
             fmt::format().with_target(false).with_ansi(false),
         ))
         .fmt_fields(PrettyFields::new())
-        .with_writer(file_appender)
-        .with_filter(EnvFilter::new(format!("{name}=info,reqwest=warn")));
+        .with_writer(file_appender);
+    let (file_filter, file_handle) =
+        reload::Layer::new(EnvFilter::new(file_directives(Verbosity::Info)));
+    let log_layer = log_layer.with_filter(file_filter);
 
     let exclude_log_only = DynFilterFn::new(|metadata, _| metadata.name() != crate::LOG_ONLY);
 
@@ -123,30 +297,60 @@ pub fn init_subscriber(local_env_dir: &std::path::Path) -> std::io::Result<()> {
                 .without_time()
                 .with_level(false),
         ))
-        .with_writer(std::io::stdout)
-        .with_filter(EnvFilter::new(format!("{name}=info")))
+        .with_ansi(color_enabled())
+        .with_writer(std::io::stdout);
+    let (terminal_filter, terminal_handle) =
+        reload::Layer::new(EnvFilter::new(terminal_directives(Verbosity::Info)));
+    let stdout_layer = stdout_layer
+        .with_filter(terminal_filter)
         .with_filter(exclude_log_only);
 
     tracing_subscriber::registry()
         .with(log_layer)
         .with(stdout_layer)
+        .with(ErrorCounter)
         .init();
 
+    let _ = FILE_LEVEL.set(LevelHandle::new(
+        Verbosity::Info,
+        file_directives,
+        file_handle,
+    ));
+    let _ = TERMINAL_LEVEL.set(LevelHandle::new(
+        Verbosity::Info,
+        terminal_directives,
+        terminal_handle,
+    ));
     Ok(())
 }
 
 #[cfg(debug_assertions)]
-pub fn init_subscriber(_local_env_dir: &std::path::Path) -> std::io::Result<()> {
-    use tracing_subscriber::{filter::LevelFilter, Layer};
+pub fn init_subscriber(
+    _local_env_dir: &std::path::Path,
+    _config: &LogConfig,
+) -> std::io::Result<()> {
+    use tracing_subscriber::Layer;
 
+    let (terminal_filter, terminal_handle) = reload::Layer::new(
+        EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy(),
+    );
     tracing_subscriber::registry()
         .with(
-            fmt::layer().with_target(false).pretty().with_filter(
-                EnvFilter::builder()
-                    .with_default_directive(LevelFilter::INFO.into())
-                    .from_env_lossy(),
-            ),
+            fmt::layer()
+                .with_target(false)
+                .pretty()
+                .with_ansi(color_enabled())
+                .with_filter(terminal_filter),
         )
+        .with(ErrorCounter)
         .init();
+
+    let _ = TERMINAL_LEVEL.set(LevelHandle::new(
+        Verbosity::Info,
+        terminal_directives,
+        terminal_handle,
+    ));
     Ok(())
 }
@@ -0,0 +1,109 @@
+use crate::cli::UserCommand;
+use clap::CommandFactory;
+
+/// One line example of each command that takes arguments, sorted by command name
+const EXAMPLES: [(&str, &str); 28] = [
+    ("add", "add 1,4 7-9"),
+    ("alerts", "alerts add ranked"),
+    ("cache", "cache update"),
+    ("changelog", "changelog 0.8.0"),
+    ("config", "config edit"),
+    ("dashboard", "dashboard --interval 30"),
+    ("favorites", "favorites export-code"),
+    ("filter", "filter --region eu --player-min 4"),
+    ("friend", "friend add Soap"),
+    ("geo-key", "geo-key set <KEY>"),
+    ("import", "import --from iw4x"),
+    ("info", "info 1"),
+    ("journal", "journal --today"),
+    ("launch", "launch -- +set fs_game mods/example"),
+    ("log-level", "log-level debug --target file"),
+    ("logs", "logs view"),
+    ("open", "open logs"),
+    ("perf", "perf reset"),
+    ("rcon", "rcon 1 map_rotate"),
+    ("reconnect", "reconnect --history"),
+    ("run", "run commands.txt"),
+    ("search", "search rust"),
+    ("session", "session stats"),
+    ("set", "set notifications on"),
+    ("settings", "settings filter.limit 50"),
+    ("tasks", "tasks cancel 2"),
+    ("whois", "whois 1"),
+    ("window", "window tray"),
+];
+
+pub struct Suggestion {
+    pub typed: String,
+    /// Full command that was probably meant, e.g. `favorites import` for `favorites imprt`
+    pub command: String,
+    pub example: Option<&'static str>,
+}
+
+/// Levenshtein distance between `a` and `b` ignoring case
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<_>>();
+    let b = b.to_lowercase().chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, a_char) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(a_char != b_char);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// Example of `command` if one is listed, the example of a command also covers its first argument
+pub fn example(command: &str) -> Option<&'static str> {
+    let name = command.split(' ').next()?;
+    EXAMPLES
+        .binary_search_by_key(&name, |&(name, _)| name)
+        .ok()
+        .map(|i| EXAMPLES[i].1)
+        .filter(|example| {
+            example
+                .strip_prefix(command)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+}
+
+/// Walks `tokens` through the nested commands until one is not a command name or alias, then finds the
+/// closest visible command at that level. `None` when every command matched or nothing is close enough
+pub fn suggest(tokens: &[String]) -> Option<Suggestion> {
+    let mut cmd = UserCommand::command();
+    let mut path = Vec::new();
+    for token in tokens {
+        if token.starts_with('-') || !cmd.has_subcommands() {
+            return None;
+        }
+        if let Some(found) = cmd.find_subcommand(token) {
+            path.push(found.get_name().to_string());
+            cmd = found.clone();
+            continue;
+        }
+        let max_distance = (token.chars().count() / 2).clamp(1, 3);
+        let (_, closest) = cmd
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set())
+            .flat_map(|sub| {
+                std::iter::once(sub.get_name())
+                    .chain(sub.get_all_aliases())
+                    .map(move |name| (edit_distance(token, name), sub.get_name()))
+            })
+            .filter(|&(distance, _)| distance <= max_distance)
+            .min()?;
+        path.push(closest.to_string());
+        let command = path.join(" ");
+        let example = example(&command);
+        return Some(Suggestion {
+            typed: token.clone(),
+            command,
+            example,
+        });
+    }
+    None
+}
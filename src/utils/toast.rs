@@ -0,0 +1,86 @@
+use crate::LOG_ONLY;
+use std::{io, process::Child};
+use tracing::warn;
+
+#[cfg(feature = "windows-console")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "windows-console")]
+use std::os::windows::process::CommandExt;
+
+/// PowerShell's own AppUserModelID, toasts need a registered one and every Windows install has it
+#[cfg(feature = "windows-console")]
+const POWERSHELL_APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+#[cfg(feature = "windows-console")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+#[cfg(feature = "windows-console")]
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(feature = "windows-console")]
+fn spawn_toast(title: &str, body: &str) -> io::Result<Child> {
+    let xml = format!(
+        "<toast><visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text></binding>\
+        </visual></toast>",
+        escape_xml(title),
+        escape_xml(body)
+    );
+    // single quotes are the only character special inside a PowerShell literal string
+    let script = format!(
+        "$null = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime];\
+        $null = [Windows.Data.Xml.Dom.XmlDocument, Windows.Data.Xml.Dom.XmlDocument, ContentType = WindowsRuntime];\
+        $xml = New-Object Windows.Data.Xml.Dom.XmlDocument;\
+        $xml.LoadXml('{}');\
+        [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('{POWERSHELL_APP_ID}').Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        xml.replace('\'', "''")
+    );
+    let encoded = STANDARD.encode(
+        script
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>(),
+    );
+    std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-EncodedCommand", &encoded])
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+}
+
+#[cfg(all(not(feature = "windows-console"), target_os = "linux"))]
+fn spawn_toast(title: &str, body: &str) -> io::Result<Child> {
+    std::process::Command::new("notify-send")
+        .args(["--app-name", env!("CARGO_PKG_NAME"), title, body])
+        .spawn()
+}
+
+#[cfg(all(not(feature = "windows-console"), not(target_os = "linux")))]
+fn spawn_toast(_title: &str, _body: &str) -> io::Result<Child> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        crate::error::Error::Unsupported("Desktop notifications").to_string(),
+    ))
+}
+
+/// Shows a notification without waiting for it, failures are only logged to file. Callers check
+/// `display.notifications` first
+pub fn show_toast(title: &str, body: &str) {
+    match spawn_toast(title, body) {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(err) => warn!(name: LOG_ONLY, "Could not show notification, {err}"),
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(not(target_os = "linux"))]
+use crate::commands::handler::CommandContext;
+use crate::{error::Error, utils::window::Placement};
+#[cfg(not(target_os = "linux"))]
+use std::ffi::OsString;
+use std::path::Path;
+
+#[cfg(not(target_os = "linux"))]
+const GAME_CONTROL: &str = "Launching and controlling H2M";
+
+/// A pseudoconsole can never be created without the `windows-console` feature, so this type has no
+/// values. Its methods mirror the ones used from `winptyrs::PTY`
+#[cfg(not(target_os = "linux"))]
+#[allow(clippy::upper_case_acronyms)]
+pub enum PTY {}
+
+#[cfg(not(target_os = "linux"))]
+impl PTY {
+    pub fn read(&self, _length: u32, _blocking: bool) -> Result<OsString, OsString> {
+        match *self {}
+    }
+    pub fn write(&self, _buf: OsString) -> Result<u32, OsString> {
+        match *self {}
+    }
+    pub fn is_alive(&self) -> Result<bool, OsString> {
+        match *self {}
+    }
+    pub fn get_exitstatus(&self) -> Result<Option<u32>, OsString> {
+        match *self {}
+    }
+    pub fn get_pid(&self) -> u32 {
+        match *self {}
+    }
+}
+
+/// Never resolves, there is no console window to be closed
+pub struct CtrlClose;
+
+impl CtrlClose {
+    pub async fn recv(&mut self) -> Option<()> {
+        std::future::pending().await
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pty_spawn_check() -> Result<(), Error> {
+    Err(Error::Unsupported("Pseudoconsole support"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn launch_h2m_pseudo(_game_path: &Path, _args: &[String]) -> Result<PTY, Error> {
+    Err(Error::Unsupported(GAME_CONTROL))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn initalize_listener(_context: &mut CommandContext) -> Result<(), String> {
+    Err(Error::Unsupported(GAME_CONTROL).to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn h2m_running() -> bool {
+    false
+}
+
+pub fn focus_h2m_window() -> Result<(), &'static str> {
+    Err("Focusing the H2M window is not available in this build")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn terminate_process(_pid: u32) -> Result<(), String> {
+    Err(Error::Unsupported("Terminating processes").to_string())
+}
+
+pub fn get_exe_version(_path: &Path) -> Option<f64> {
+    None
+}
+
+pub fn protect_secret(_secret: &[u8]) -> Result<Vec<u8>, String> {
+    Err(Error::Unsupported("Encrypting saved passwords and keys").to_string())
+}
+
+pub fn unprotect_secret(_protected: &[u8]) -> Result<Vec<u8>, String> {
+    Err(Error::Unsupported("Reading saved passwords and keys").to_string())
+}
+
+pub fn console_placement() -> Option<Placement> {
+    None
+}
+
+/// There is no console window to move, so every placement counts as restored
+pub fn restore_placement(_placement: &Placement) -> bool {
+    true
+}
+
+pub fn show_tray() -> Result<(), String> {
+    Err(Error::Unsupported("The tray icon").to_string())
+}
+
+pub fn remove_tray() {}
+
+pub fn hide_console() -> Result<(), String> {
+    Err(Error::Unsupported("Hiding the console to the tray").to_string())
+}
@@ -0,0 +1,500 @@
+use crate::{
+    commands::services::ConfigService,
+    strip_color_codes,
+    utils::{config::WindowConfig, game_state::GameState},
+    LOG_ONLY,
+};
+use crossterm::{execute, terminal::SetTitle};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+use tokio::sync::{mpsc, watch};
+use tracing::debug;
+
+#[cfg(feature = "windows-console")]
+use std::{ffi::OsStr, os::windows::ffi::OsStrExt, sync::atomic::AtomicUsize, time::Duration};
+#[cfg(feature = "windows-console")]
+use winapi::{
+    shared::{
+        minwindef::{DWORD, LPARAM, LRESULT, UINT, WPARAM},
+        windef::{HICON, HWINEVENTHOOK, HWND, POINT, RECT},
+    },
+    um::{
+        libloaderapi::GetModuleHandleW,
+        shellapi::{
+            Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+        },
+        wincon::GetConsoleWindow,
+        winuser::{
+            AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu,
+            DispatchMessageW, GetCursorPos, GetMessageW, GetWindowRect, IsIconic, IsWindowVisible,
+            LoadIconW, MonitorFromRect, PostMessageW, RegisterClassW, SetForegroundWindow,
+            SetWinEventHook, SetWindowPos, ShowWindow, TrackPopupMenu, TranslateMessage,
+            EVENT_SYSTEM_MINIMIZESTART, IDI_APPLICATION, MAKEINTRESOURCEW, MF_STRING,
+            MONITOR_DEFAULTTONULL, MSG, SWP_NOACTIVATE, SWP_NOZORDER, SW_HIDE, SW_RESTORE, SW_SHOW,
+            TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON, WINEVENT_OUTOFCONTEXT, WM_APP,
+            WM_LBUTTONUP, WM_NULL, WM_RBUTTONUP, WNDCLASSW,
+        },
+    },
+};
+
+#[cfg(not(feature = "windows-console"))]
+pub use crate::utils::unsupported::{
+    console_placement, hide_console, remove_tray, restore_placement, show_tray,
+};
+
+const WINDOW_FILE: &str = "window.json";
+
+/// Commands the tray menu runs as if they were entered at the prompt
+pub const TRAY_RECONNECT: &str = "reconnect";
+pub const TRAY_QUIT: &str = "quit";
+
+/// Set by `window forget` so the placement is not saved again on exit
+static FORGOTTEN: AtomicBool = AtomicBool::new(false);
+static TRAY_COMMANDS: OnceLock<mpsc::Sender<&'static str>> = OnceLock::new();
+
+/// Position and size of the console window in screen coordinates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Shows or removes the tray icon to match `window.tray`
+pub fn apply_tray(config: &WindowConfig) {
+    if TRAY_COMMANDS.get().is_none() {
+        return;
+    }
+    if config.tray || config.start_in_tray {
+        if let Err(err) = show_tray() {
+            debug!(name: LOG_ONLY, "Could not show the tray icon, {err}");
+        }
+    } else {
+        remove_tray();
+    }
+}
+
+/// Where the tray menu sends the commands it runs, set once before the window settings are applied
+pub fn init_tray_commands(sender: mpsc::Sender<&'static str>) {
+    let _ = TRAY_COMMANDS.set(sender);
+}
+
+#[cfg(feature = "windows-console")]
+fn send_tray_command(command: &'static str) {
+    if let Some(sender) = TRAY_COMMANDS.get() {
+        let _ = sender.try_send(command);
+    }
+}
+
+/// `match_wire`, followed by the server being played on when `window.title-server` is enabled
+pub fn window_title(server: Option<&str>, config: &WindowConfig) -> String {
+    match server.filter(|_| config.title_server) {
+        Some(server) => format!(
+            "{} - {}",
+            env!("CARGO_PKG_NAME"),
+            strip_color_codes(server).trim()
+        ),
+        None => String::from(env!("CARGO_PKG_NAME")),
+    }
+}
+
+/// Keeps the window title in step with the game state
+pub async fn title_loop(mut game_state: watch::Receiver<GameState>, config: ConfigService) {
+    let mut title = String::new();
+    loop {
+        let settings = config.current();
+        let next = window_title(game_state.borrow_and_update().server(), &settings.window);
+        if next != title {
+            if let Err(err) = execute!(io::stdout(), SetTitle(&next)) {
+                debug!(name: LOG_ONLY, "Could not set the window title, {err}");
+            }
+            title = next;
+        }
+        if game_state.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Moves the console window to where it was when the last run closed, if that is still on a monitor
+pub fn load_placement(local_dir: &Path, config: &WindowConfig) {
+    if !config.remember_placement {
+        return;
+    }
+    let Some(placement) = std::fs::read(local_dir.join(WINDOW_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<Placement>(&contents).ok())
+    else {
+        return;
+    };
+    if !restore_placement(&placement) {
+        debug!(name: LOG_ONLY, "Saved window placement is off screen, it was not restored");
+    }
+}
+
+/// Saves the current position and size of the console window, a hidden or minimized window keeps the
+/// placement saved before it
+pub fn save_placement(local_dir: &Path, config: &WindowConfig) -> io::Result<()> {
+    if !config.remember_placement || FORGOTTEN.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let Some(placement) = console_placement() else {
+        return Ok(());
+    };
+    let file = std::fs::File::create(local_dir.join(WINDOW_FILE))?;
+    serde_json::to_writer(file, &placement)?;
+    Ok(())
+}
+
+/// Removes the saved placement, the next run opens with the default size and position
+pub fn forget_placement(local_dir: &Path) -> io::Result<()> {
+    FORGOTTEN.store(true, Ordering::SeqCst);
+    match std::fs::remove_file(local_dir.join(WINDOW_FILE)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "windows-console")]
+const TRAY_CALLBACK: UINT = WM_APP + 1;
+#[cfg(feature = "windows-console")]
+const TRAY_ID: UINT = 1;
+#[cfg(feature = "windows-console")]
+const TRAY_CLASS: &str = "MatchWireTray";
+/// Resource id winresource gives the icon set in build.rs
+#[cfg(feature = "windows-console")]
+const APP_ICON_ID: u16 = 1;
+#[cfg(feature = "windows-console")]
+const TRAY_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(feature = "windows-console")]
+const MENU_SHOW: i32 = 1;
+#[cfg(feature = "windows-console")]
+const MENU_RECONNECT: i32 = 2;
+#[cfg(feature = "windows-console")]
+const MENU_QUIT: i32 = 3;
+
+/// Hidden window receiving the tray icon's messages, 0 until the tray thread created it
+#[cfg(feature = "windows-console")]
+static TRAY_WINDOW: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "windows-console")]
+static ICON_SHOWN: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "windows-console")]
+fn wide(text: &str) -> Vec<u16> {
+    OsStr::new(text)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+#[cfg(feature = "windows-console")]
+fn console_window() -> Option<HWND> {
+    let hwnd = unsafe { GetConsoleWindow() };
+    (!hwnd.is_null()).then_some(hwnd)
+}
+
+#[cfg(feature = "windows-console")]
+pub fn console_placement() -> Option<Placement> {
+    let hwnd = console_window()?;
+    unsafe {
+        if IsIconic(hwnd) != 0 || IsWindowVisible(hwnd) == 0 {
+            return None;
+        }
+        let mut rect: RECT = std::mem::zeroed();
+        if GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+        Some(Placement {
+            x: rect.left,
+            y: rect.top,
+            width: rect.right - rect.left,
+            height: rect.bottom - rect.top,
+        })
+    }
+}
+
+/// Returns `false` when `placement` is not on any connected monitor
+#[cfg(feature = "windows-console")]
+pub fn restore_placement(placement: &Placement) -> bool {
+    let Some(hwnd) = console_window() else {
+        return true;
+    };
+    let rect = RECT {
+        left: placement.x,
+        top: placement.y,
+        right: placement.x + placement.width,
+        bottom: placement.y + placement.height,
+    };
+    unsafe {
+        if MonitorFromRect(&rect, MONITOR_DEFAULTTONULL).is_null() {
+            return false;
+        }
+        SetWindowPos(
+            hwnd,
+            std::ptr::null_mut(),
+            placement.x,
+            placement.y,
+            placement.width,
+            placement.height,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+    true
+}
+
+#[cfg(feature = "windows-console")]
+fn show_console() {
+    let Some(hwnd) = console_window() else {
+        return;
+    };
+    unsafe {
+        ShowWindow(
+            hwnd,
+            if IsIconic(hwnd) != 0 {
+                SW_RESTORE
+            } else {
+                SW_SHOW
+            },
+        );
+        SetForegroundWindow(hwnd);
+    }
+}
+
+/// Hides the console until it is shown again from the tray icon, which is added if it is not shown
+#[cfg(feature = "windows-console")]
+pub fn hide_console() -> Result<(), String> {
+    show_tray()?;
+    let hwnd = console_window().ok_or("MatchWire is not running in a console window")?;
+    unsafe { ShowWindow(hwnd, SW_HIDE) };
+    Ok(())
+}
+
+#[cfg(feature = "windows-console")]
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let mut data: NOTIFYICONDATAW = unsafe { std::mem::zeroed() };
+    data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as DWORD;
+    data.hWnd = hwnd;
+    data.uID = TRAY_ID;
+    data
+}
+
+#[cfg(feature = "windows-console")]
+fn app_icon() -> HICON {
+    unsafe {
+        let icon = LoadIconW(
+            GetModuleHandleW(std::ptr::null()),
+            MAKEINTRESOURCEW(APP_ICON_ID),
+        );
+        if icon.is_null() {
+            LoadIconW(std::ptr::null_mut(), IDI_APPLICATION)
+        } else {
+            icon
+        }
+    }
+}
+
+/// Adds the tray icon, the thread running its menu is started the first time
+#[cfg(feature = "windows-console")]
+pub fn show_tray() -> Result<(), String> {
+    if ICON_SHOWN.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let hwnd = tray_window()?;
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+    data.uCallbackMessage = TRAY_CALLBACK;
+    data.hIcon = app_icon();
+    for (tip, c) in data.szTip.iter_mut().zip(wide(env!("CARGO_PKG_NAME"))) {
+        *tip = c;
+    }
+    if unsafe { Shell_NotifyIconW(NIM_ADD, &mut data) } == 0 {
+        return Err(format!(
+            "Could not add the tray icon, {}",
+            io::Error::last_os_error()
+        ));
+    }
+    ICON_SHOWN.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Removes the tray icon, a console hidden to the tray is shown first so it can not get lost
+#[cfg(feature = "windows-console")]
+pub fn remove_tray() {
+    if !ICON_SHOWN.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if console_window().is_some_and(|hwnd| unsafe { IsWindowVisible(hwnd) } == 0) {
+        show_console();
+    }
+    let mut data = icon_data(TRAY_WINDOW.load(Ordering::SeqCst) as HWND);
+    unsafe { Shell_NotifyIconW(NIM_DELETE, &mut data) };
+}
+
+#[cfg(feature = "windows-console")]
+fn tray_window() -> Result<HWND, String> {
+    let hwnd = TRAY_WINDOW.load(Ordering::SeqCst);
+    if hwnd != 0 {
+        return Ok(hwnd as HWND);
+    }
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::Builder::new()
+        .name(String::from("tray"))
+        .spawn(move || {
+            // `HWND` is not `Send`, the handle crosses over as an address
+            let created = unsafe { create_tray_window() }.map(|hwnd| hwnd as usize);
+            let running = created.is_ok();
+            let _ = ready_tx.send(created);
+            if running {
+                unsafe { message_loop() };
+            }
+        })
+        .map_err(|err| format!("Could not start the tray thread, {err}"))?;
+    let hwnd = ready_rx
+        .recv_timeout(TRAY_START_TIMEOUT)
+        .map_err(|_| String::from("The tray thread did not start"))??;
+    TRAY_WINDOW.store(hwnd, Ordering::SeqCst);
+    Ok(hwnd as HWND)
+}
+
+/// Creates the hidden window the tray icon reports to, and hides the console to the tray whenever it
+/// is minimized while the icon is shown
+#[cfg(feature = "windows-console")]
+unsafe fn create_tray_window() -> Result<HWND, String> {
+    let instance = GetModuleHandleW(std::ptr::null());
+    let class = wide(TRAY_CLASS);
+    let window_class = WNDCLASSW {
+        lpfnWndProc: Some(tray_proc),
+        hInstance: instance,
+        lpszClassName: class.as_ptr(),
+        ..std::mem::zeroed()
+    };
+    if RegisterClassW(&window_class) == 0 {
+        return Err(format!(
+            "Could not register the tray window, {}",
+            io::Error::last_os_error()
+        ));
+    }
+    let hwnd = CreateWindowExW(
+        0,
+        class.as_ptr(),
+        class.as_ptr(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        instance,
+        std::ptr::null_mut(),
+    );
+    if hwnd.is_null() {
+        return Err(format!(
+            "Could not create the tray window, {}",
+            io::Error::last_os_error()
+        ));
+    }
+    SetWinEventHook(
+        EVENT_SYSTEM_MINIMIZESTART,
+        EVENT_SYSTEM_MINIMIZESTART,
+        std::ptr::null_mut(),
+        Some(minimize_hook),
+        0,
+        0,
+        WINEVENT_OUTOFCONTEXT,
+    );
+    Ok(hwnd)
+}
+
+#[cfg(feature = "windows-console")]
+unsafe fn message_loop() {
+    let mut msg: MSG = std::mem::zeroed();
+    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
+}
+
+#[cfg(feature = "windows-console")]
+unsafe extern "system" fn minimize_hook(
+    _hook: HWINEVENTHOOK,
+    _event: DWORD,
+    hwnd: HWND,
+    _object: i32,
+    _child: i32,
+    _thread: DWORD,
+    _time: DWORD,
+) {
+    if ICON_SHOWN.load(Ordering::SeqCst) && console_window() == Some(hwnd) {
+        ShowWindow(hwnd, SW_HIDE);
+    }
+}
+
+#[cfg(feature = "windows-console")]
+unsafe extern "system" fn tray_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg != TRAY_CALLBACK {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+    match lparam as UINT {
+        WM_LBUTTONUP => show_console(),
+        WM_RBUTTONUP => match tray_menu(hwnd) {
+            MENU_SHOW => show_console(),
+            MENU_RECONNECT => send_tray_command(TRAY_RECONNECT),
+            MENU_QUIT => {
+                // quitting may ask for confirmation at the prompt
+                show_console();
+                send_tray_command(TRAY_QUIT);
+            }
+            _ => (),
+        },
+        _ => (),
+    }
+    0
+}
+
+/// Shows the tray menu at the cursor, returns the id of the chosen item or 0
+#[cfg(feature = "windows-console")]
+unsafe fn tray_menu(hwnd: HWND) -> i32 {
+    let menu = CreatePopupMenu();
+    if menu.is_null() {
+        return 0;
+    }
+    for (id, label) in [
+        (MENU_SHOW, "Show console"),
+        (MENU_RECONNECT, "Reconnect"),
+        (MENU_QUIT, "Quit"),
+    ] {
+        let label = wide(label);
+        AppendMenuW(menu, MF_STRING, id as usize, label.as_ptr());
+    }
+    let mut cursor: POINT = std::mem::zeroed();
+    GetCursorPos(&mut cursor);
+    // the menu only closes when clicking elsewhere if its owner is in the foreground
+    SetForegroundWindow(hwnd);
+    let chosen = TrackPopupMenu(
+        menu,
+        TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON,
+        cursor.x,
+        cursor.y,
+        0,
+        hwnd,
+        std::ptr::null(),
+    );
+    PostMessageW(hwnd, WM_NULL, 0, 0);
+    DestroyMenu(menu);
+    chosen
+}
@@ -0,0 +1,228 @@
+use crate::{
+    commands::{
+        handler::{CommandContext, Message},
+        launch_h2m::ConsoleLines,
+        reconnect::send_queued,
+    },
+    error::Error,
+    utils::game_state::{set_state, GameState},
+    GAME_EXECUTABLES,
+};
+use std::{
+    ffi::OsString,
+    io::{BufRead, BufReader, Write},
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::Mutex,
+    time::Duration,
+};
+
+const WINE_ENV: &str = "WINE";
+const DEFAULT_WINE: &str = "wine";
+/// Output stops once the game settles, a quiet stretch this long means it finished loading
+const QUIET_INTERVAL: Duration = Duration::from_secs(3);
+
+fn wine() -> OsString {
+    std::env::var_os(WINE_ENV)
+        .filter(|wine| !wine.is_empty())
+        .unwrap_or_else(|| OsString::from(DEFAULT_WINE))
+}
+
+fn io_err(err: std::io::Error) -> OsString {
+    OsString::from(err.to_string())
+}
+
+/// The game running under Wine, named after `winptyrs::PTY` so the code that sends commands to the
+/// game is shared between platforms
+#[allow(clippy::upper_case_acronyms)]
+pub struct PTY {
+    pid: u32,
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    stdout: Mutex<Option<ChildStdout>>,
+}
+
+impl PTY {
+    pub fn write(&self, buf: OsString) -> Result<u32, OsString> {
+        let mut stdin = self
+            .stdin
+            .lock()
+            .map_err(|_| OsString::from("stdin lock poisoned"))?;
+        stdin
+            .write_all(buf.as_bytes())
+            .and_then(|_| stdin.flush())
+            .map_err(io_err)?;
+        Ok(buf.len() as u32)
+    }
+
+    pub fn is_alive(&self) -> Result<bool, OsString> {
+        Ok(self.get_exitstatus()?.is_none())
+    }
+
+    /// `Ok(None)` while the game is running, a game killed by a signal exits with `u32::MAX`
+    pub fn get_exitstatus(&self) -> Result<Option<u32>, OsString> {
+        let mut child = self
+            .child
+            .lock()
+            .map_err(|_| OsString::from("child lock poisoned"))?;
+        let status = child.try_wait().map_err(io_err)?;
+        Ok(status.map(|status| status.code().map_or(u32::MAX, |code| code as u32)))
+    }
+
+    pub fn get_pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// The game's stdout can only be read by one listener, later calls return `None`
+    fn take_stdout(&self) -> Option<ChildStdout> {
+        self.stdout.lock().ok()?.take()
+    }
+}
+
+/// Checks that the Wine runner can be started
+pub fn pty_spawn_check() -> Result<(), Error> {
+    let wine = wine();
+    let output = Command::new(&wine)
+        .arg("--version")
+        .output()
+        .map_err(|err| Error::Wine(format!("{}, {err}", wine.to_string_lossy())))?;
+    if !output.status.success() {
+        return Err(Error::Wine(format!(
+            "{} --version exited with {}",
+            wine.to_string_lossy(),
+            output.status
+        )));
+    }
+    Ok(())
+}
+
+/// Starts the game with the runner in `WINE`, or `wine` when unset. Proton is used by pointing `WINE` at
+/// the wine binary bundled with it
+pub fn launch_h2m_pseudo(game_path: &Path, args: &[String]) -> Result<PTY, Error> {
+    if h2m_running() {
+        return Err(Error::GameRunning);
+    }
+
+    let wine = wine();
+    let mut child = Command::new(&wine)
+        .arg(game_path)
+        .args(args)
+        .current_dir(game_path.parent().unwrap_or(Path::new(".")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| Error::Wine(format!("{}, {err}", wine.to_string_lossy())))?;
+
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take();
+    Ok(PTY {
+        pid: child.id(),
+        child: Mutex::new(child),
+        stdin: Mutex::new(stdin),
+        stdout: Mutex::new(stdout),
+    })
+}
+
+/// Reads the game's console output from the Wine pipe until the game exits
+pub async fn initalize_listener(context: &mut CommandContext) -> Result<(), String> {
+    context.check_h2m_connection().await?;
+
+    let pty = context
+        .pty_handle()
+        .expect("`check_h2m_connection` was `Ok`");
+    let stdout = pty
+        .read()
+        .await
+        .take_stdout()
+        .ok_or_else(|| String::from("H2M console output is already being read"))?;
+
+    let console_lines = ConsoleLines::new(context);
+    let msg_sender_arc = context.msg_sender();
+
+    let (line_tx, mut line_rx) = tokio::sync::mpsc::channel::<String>(256);
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line_tx.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        loop {
+            match tokio::time::timeout(QUIET_INTERVAL, line_rx.recv()).await {
+                Ok(Some(line)) => {
+                    let mut lines = vec![line];
+                    while let Ok(line) = line_rx.try_recv() {
+                        lines.push(line);
+                    }
+                    console_lines.push(lines.iter().map(String::as_str)).await;
+                }
+                Ok(None) => break,
+                Err(_) => console_lines.quiet(),
+            }
+            let game = console_lines.game();
+            if game.state().borrow().accepts_commands() {
+                send_queued(game, &*pty.read().await, &msg_sender_arc).await;
+            }
+        }
+
+        // the pipe closes once the game exits
+        let game = console_lines.game();
+        game.set_connected(false);
+        game.end_session().await;
+        let msg = match pty.read().await.get_exitstatus() {
+            Ok(Some(code)) if code != 0 => {
+                set_state(game.state(), GameState::Crashed { exit_code: code });
+                Message::Err(format!("H2M-mod exited with code: {code:#X}"))
+            }
+            _ => {
+                set_state(game.state(), GameState::Idle);
+                Message::Warn(String::from("No longer reading H2M console ouput"))
+            }
+        };
+        let _ = msg_sender_arc.send(msg).await;
+    });
+    Ok(())
+}
+
+/// Looks for a process whose command line names one of the game executables, Wine keeps the
+/// Windows path of the executable as the first argument
+pub fn h2m_running() -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    let own_pid = std::process::id().to_string();
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            name != own_pid.as_str() && name.as_bytes().iter().all(u8::is_ascii_digit)
+        })
+        .filter_map(|entry| std::fs::read(entry.path().join("cmdline")).ok())
+        .any(|cmdline| cmdline.split(|&byte| byte == 0).any(is_game_executable))
+}
+
+fn is_game_executable(arg: &[u8]) -> bool {
+    let arg = String::from_utf8_lossy(arg).to_ascii_lowercase();
+    let file_name = arg.rsplit(['/', '\\']).next().unwrap_or_default();
+    GAME_EXECUTABLES.iter().any(|&(exe, _)| file_name == exe)
+}
+
+pub fn terminate_process(pid: u32) -> Result<(), String> {
+    let status = Command::new("kill")
+        .args(["-KILL", &pid.to_string()])
+        .status()
+        .map_err(|err| format!("Failed to run kill, {err}"))?;
+    if !status.success() {
+        return Err(format!(
+            "Failed to terminate process {pid}, kill exited with {status}"
+        ));
+    }
+    Ok(())
+}
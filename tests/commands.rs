@@ -0,0 +1,478 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::OsString,
+        net::SocketAddr,
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use clap::{CommandFactory, Parser};
+    use match_wire::{
+        cli::{
+            parse_age, parse_grep_pattern, AppArgs, Command, IpFamily, Region, Source, TasksCmd,
+            UserCommand,
+        },
+        commands::{
+            filter::DEFAULT_DEADLINE_SECS,
+            handler::{
+                editor_command, redact_passwords, try_execute_command, CommandContextBuilder,
+                GameDetails,
+            },
+            launch_h2m::{ConsoleHistory, CONSOLE_HISTORY_MAX},
+            query::FilterQuery,
+            search::{append_picked, Appended},
+            services::{CacheService, GameControl},
+            tasks::TaskList,
+            trim::{parse_picks, trim_window, TRIM_MARGIN},
+        },
+        error::Error,
+        utils::{
+            caching::Cache,
+            game_state::GameState,
+            http::{FixtureFetch, Http},
+            input::{
+                completion::{CommandScheme, Completion, ROOT},
+                prompt::DEFAULT_PROMPT_TEMPLATE,
+            },
+            suggest::{edit_distance, example, suggest},
+        },
+    };
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn console_history_search() {
+        assert_eq!(parse_age("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_age("45S"), Ok(Duration::from_secs(45)));
+        assert!(parse_age("10").is_err());
+        assert!(parse_age("5w").is_err());
+        assert!(parse_age("0m").is_err());
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut history = ConsoleHistory::default();
+        for i in 0..CONSOLE_HISTORY_MAX + 5 {
+            history.push_at(start + Duration::from_secs(i as u64), format!("line {i}"));
+        }
+        // read before the newest line, kept in order
+        history.push_at(start, String::from("\x1b[31mERROR: late\x1b[0m"));
+        assert_eq!(history.len(), CONSOLE_HISTORY_MAX);
+        assert_eq!(history.iter().next().unwrap(), "line 6");
+
+        let recent = start + Duration::from_secs(CONSOLE_HISTORY_MAX as u64 + 2);
+        let lines = history.search(Some(recent), None).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "line 10002",
+                "line 10003",
+                "line 10004",
+                "\x1b[31mERROR: late\x1b[0m"
+            ]
+        );
+
+        let pattern = parse_grep_pattern("error:").unwrap();
+        assert_eq!(history.search(None, Some(&pattern)).count(), 1);
+        assert_eq!(history.search(Some(recent), Some(&pattern)).count(), 1);
+        let later = recent + Duration::from_secs(60);
+        assert_eq!(history.search(Some(later), None).count(), 0);
+    }
+
+    #[test]
+    fn did_you_mean() {
+        let tokens = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(edit_distance("fliter", "Filter"), 2);
+        assert_eq!(
+            UserCommand::try_parse_from(["", "fliter"])
+                .unwrap_err()
+                .kind(),
+            clap::error::ErrorKind::InvalidSubcommand
+        );
+
+        let suggestion = suggest(&tokens("fliter --region eu")).unwrap();
+        assert_eq!(
+            (suggestion.typed.as_str(), suggestion.command.as_str()),
+            ("fliter", "filter")
+        );
+        assert_eq!(
+            suggestion.example,
+            Some("filter --region eu --player-min 4")
+        );
+
+        let suggestion = suggest(&tokens("favorites imprt")).unwrap();
+        assert_eq!(suggestion.command, "favorites import");
+        assert_eq!(suggestion.example, None);
+
+        assert_eq!(suggest(&tokens("QUIT")).unwrap().command, "quit");
+        assert!(suggest(&tokens("xyzzy")).is_none());
+        assert!(suggest(&tokens("filter --regoin eu")).is_none());
+
+        for command in UserCommand::command().get_subcommands() {
+            if let Some(example) = example(command.get_name()) {
+                let mut args = vec![""];
+                args.extend(example.split(' '));
+                assert!(UserCommand::try_parse_from(args).is_ok(), "{example}");
+            }
+        }
+    }
+
+    #[test]
+    fn completion_matches_cli() {
+        static SCHEME: CommandScheme = CommandScheme::init();
+        let completion = Completion::from(&SCHEME);
+
+        for command in UserCommand::command().get_subcommands() {
+            let name = command.get_name();
+            assert_eq!(completion.parent_of(name), Some(ROOT), "command: {name}");
+            for long in command.get_arguments().filter_map(|arg| arg.get_long()) {
+                assert_eq!(completion.parent_of(long), Some(name), "{name} --{long}");
+            }
+        }
+    }
+
+    #[test]
+    fn filter_args_round_trip() {
+        let parse = |args: &[&str]| {
+            let mut command = vec!["", "filter"];
+            command.extend_from_slice(args);
+            match UserCommand::try_parse_from(command).unwrap().command {
+                Command::Filter { args } => args.unwrap_or_default(),
+                _ => unreachable!(),
+            }
+        };
+        let filters = parse(&[
+            "-r",
+            "eu",
+            "na",
+            "-p",
+            "4",
+            "-i",
+            "one long term",
+            "snipe",
+            "--without-bots",
+            "--prefer",
+            "v6",
+            "--deadline",
+            "30",
+            "--interactive",
+        ]);
+        assert!(filters.interactive);
+
+        let args = filters.to_args();
+        assert!(!args.iter().any(|arg| arg == "--interactive"));
+        let round_trip = parse(&args.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(round_trip.region, Some(vec![Region::EU, Region::NA]));
+        assert_eq!(round_trip.player_min, Some(4));
+        assert_eq!(
+            round_trip.includes.as_deref(),
+            Some(&[String::from("one long term"), String::from("snipe")][..])
+        );
+        assert!(round_trip.without_bots && !round_trip.with_bots);
+        assert_eq!(round_trip.prefer, Some(IpFamily::Ipv6));
+        assert_eq!(round_trip.limit, None);
+        assert_eq!(
+            FilterQuery::from(&round_trip).deadline,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            FilterQuery::from(&parse(&[])).deadline,
+            Some(Duration::from_secs(DEFAULT_DEADLINE_SECS))
+        );
+        assert_eq!(
+            FilterQuery::from(&parse(&["--deadline", "0"])).deadline,
+            None
+        );
+    }
+
+    #[test]
+    fn near_me_args() {
+        let query = |args: &[&str]| {
+            let mut command = vec!["", "filter"];
+            command.extend_from_slice(args);
+            UserCommand::try_parse_from(command).map(|parsed| match parsed.command {
+                Command::Filter { args } => args.unwrap_or_default(),
+                _ => unreachable!(),
+            })
+        };
+
+        let near = query(&["--near-me"]).unwrap();
+        assert!(near.to_args().contains(&String::from("--near-me")));
+        let near = FilterQuery::from(&near);
+        assert!(near.near_me && near.measure_pings);
+
+        let busiest = FilterQuery::from(&query(&["--near-me", "--no-ping"]).unwrap());
+        assert!(busiest.near_me && !busiest.measure_pings);
+
+        assert!(query(&["--no-ping"]).is_err());
+        assert!(query(&["--near-me", "--active-now"]).is_err());
+    }
+
+    #[test]
+    fn strict_needs_script() {
+        assert!(AppArgs::try_parse_from(["match_wire", "--strict"]).is_err());
+        assert!(AppArgs::try_parse_from([
+            "match_wire",
+            "--run-script",
+            "a.txt",
+            "--max-geo-failures",
+            "3"
+        ])
+        .is_err());
+
+        let args = AppArgs::try_parse_from([
+            "match_wire",
+            "--run-script",
+            "a.txt",
+            "--strict",
+            "--max-geo-failures",
+            "3",
+        ])
+        .unwrap();
+        assert!(args.strict);
+        assert_eq!(args.max_geo_failures, 3);
+    }
+
+    #[tokio::test]
+    async fn filter_query_builder() {
+        let http: Http = Arc::new(FixtureFetch::new(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/offline"),
+        ));
+        let found = FilterQuery::builder()
+            .min_players(4)
+            .include("FIXTURE")
+            .retry_max(0)
+            .execute(&http)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, Source::HmwMaster);
+        assert_eq!(found[0].info.as_ref().unwrap().clients, 6);
+
+        let found = FilterQuery::builder()
+            .include_unresponsive(true)
+            .exclude("domination")
+            .retry_max(0)
+            .execute(&http)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, Source::Iw4Master);
+        assert_eq!(found[0].info.as_ref().unwrap().clients, 4);
+
+        let found = FilterQuery::builder()
+            .include_unresponsive(true)
+            .retry_max(0)
+            .limit(1)
+            .execute(&http)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].source, Source::Iw4Master);
+    }
+
+    #[test]
+    fn context_services() {
+        let builder = || {
+            let (msg_sender, _) = tokio::sync::mpsc::channel(1);
+            CommandContextBuilder::new()
+                .msg_sender(msg_sender)
+                .game_details(GameDetails::default(&PathBuf::from("game")))
+        };
+
+        let shared = CacheService::new(Cache::new());
+        let context = builder().cache_service(shared.clone()).build().unwrap();
+        context.cache().mark_changed();
+        assert!(shared.take_changed());
+        assert!(!context.cache().is_changed());
+        assert!(!context.game_control().connected());
+        assert_eq!(*context.game_control().state().borrow(), GameState::Idle);
+
+        let conflicts = [
+            builder().cache(Cache::new()).cache_service(shared.clone()),
+            builder()
+                .cache(Cache::new())
+                .game_control(GameControl::new(false, false, false))
+                .launch_res(Ok(Err(Error::GameRunning))),
+            builder(),
+        ];
+        for builder in conflicts {
+            assert!(builder.build().is_err());
+        }
+    }
+
+    #[test]
+    fn trim_picks() {
+        assert_eq!(parse_picks("3 1,3 5-6", 8), Ok(vec![2, 0, 4, 5]));
+        assert_eq!(parse_picks("  ", 8), Ok(Vec::new()));
+        for input in ["0", "9", "6-4", "a", "2-", "-3"] {
+            assert!(parse_picks(input, 8).is_err(), "{input:?}");
+        }
+
+        assert_eq!(trim_window(300, 100), 100 - TRIM_MARGIN..100 + TRIM_MARGIN);
+        assert_eq!(trim_window(103, 100), 100 - TRIM_MARGIN..103);
+        assert_eq!(trim_window(12, 3), 0..3 + TRIM_MARGIN);
+
+        let cli = UserCommand::try_parse_from(["", "filter", "-l", "50", "-y"]).unwrap();
+        let Command::Filter { args: Some(args) } = cli.command else {
+            panic!("expected filter");
+        };
+        assert!(args.yes && args.to_args().contains(&String::from("--yes")));
+    }
+
+    #[test]
+    fn add_search_results() {
+        let addr = |last: u8| SocketAddr::from(([203, 0, 113, last], 27016));
+        let mut current = vec![addr(1), addr(2)];
+        let appended = append_picked(&mut current, [addr(3), addr(1), addr(3), addr(4)], None);
+        assert_eq!(current, [addr(1), addr(2), addr(3), addr(4)]);
+        assert_eq!(
+            appended,
+            Appended {
+                added: 2,
+                duplicates: 2,
+                over_cap: 0
+            }
+        );
+
+        let appended = append_picked(&mut current, [addr(5), addr(6), addr(2)], Some(5));
+        assert_eq!(current.len(), 5);
+        assert_eq!(
+            (appended.added, appended.duplicates, appended.over_cap),
+            (1, 1, 1)
+        );
+
+        let cli = UserCommand::try_parse_from(["", "add", "1,4", "7-9"]).unwrap();
+        let Command::Add { picks } = cli.command else {
+            panic!("expected add");
+        };
+        assert_eq!(parse_picks(&picks.join(" "), 9), Ok(vec![0, 3, 6, 7, 8]));
+        assert!(UserCommand::try_parse_from(["", "add"]).is_err());
+    }
+
+    #[test]
+    fn background_task_list() {
+        let mut tasks = TaskList::default();
+        let (filter, cache) = (CancellationToken::new(), CancellationToken::new());
+        let filter_id = tasks.insert("filter", filter.clone());
+        let cache_id = tasks.insert("cache", cache.clone());
+        assert_ne!(filter_id, cache_id);
+        assert!(tasks.is_running("filter"));
+
+        assert_eq!(tasks.cancel(filter_id), Some("filter"));
+        assert!(filter.is_cancelled() && !cache.is_cancelled());
+        assert!(!tasks.is_running("filter"));
+        assert_eq!(tasks.cancel(filter_id), None);
+
+        // ids are not handed out again once a task is gone
+        tasks.finish(cache_id);
+        let diff_id = tasks.insert("diff", CancellationToken::new());
+        assert!(diff_id > cache_id);
+        assert_eq!(tasks.running().len(), 1);
+        assert_eq!(tasks.cancel_all(), 1);
+        assert!(tasks.running().is_empty());
+
+        let cli = UserCommand::try_parse_from(["", "tasks", "cancel", "2"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Tasks {
+                command: Some(TasksCmd::Cancel { id: 2 })
+            }
+        ));
+        assert!(UserCommand::try_parse_from(["", "tasks", "cancel"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn prompt_survives_settings() {
+        let (msg_sender, _msg_rx) = tokio::sync::mpsc::channel(1);
+        let mut context = CommandContextBuilder::new()
+            .msg_sender(msg_sender)
+            .game_details(GameDetails::default(&PathBuf::from("game")))
+            .cache(Cache::new())
+            .build()
+            .unwrap();
+        let run = |line: &[&str]| line.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        try_execute_command(run(&["prompt", "{server} 42"]), &mut context).await;
+        assert_eq!(context.prompt_template(), "{server} 42");
+        try_execute_command(run(&["settings", "filter.limit", "50"]), &mut context).await;
+        assert_eq!(context.config().filter.limit, Some(50));
+        assert_eq!(context.prompt_template(), "{server} 42");
+
+        // a template that reads as a number is still a template
+        try_execute_command(run(&["prompt", "42"]), &mut context).await;
+        assert_eq!(context.prompt_template(), "42");
+        try_execute_command(run(&["prompt", "--reset"]), &mut context).await;
+        assert_eq!(context.prompt_template(), DEFAULT_PROMPT_TEMPLATE);
+    }
+
+    #[test]
+    fn editor_commands() {
+        let split = |editor: Option<&str>| {
+            let (program, args) = editor_command(editor.map(OsString::from)).unwrap();
+            (program.to_string_lossy().to_string(), args)
+        };
+        assert_eq!(split(None), (String::from("notepad"), vec![]));
+        assert_eq!(split(Some("  ")), (String::from("notepad"), vec![]));
+        assert_eq!(
+            split(Some("code --wait")),
+            (String::from("code"), vec![String::from("--wait")])
+        );
+        assert_eq!(
+            split(Some(
+                r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst"#
+            )),
+            (
+                String::from(r"C:\Program Files\Notepad++\notepad++.exe"),
+                vec![String::from("-multiInst")]
+            )
+        );
+        assert!(editor_command(Some(OsString::from("\"vim -n"))).is_err());
+    }
+
+    #[tokio::test]
+    async fn config_edit_reloads() {
+        let dir = std::env::temp_dir().join(format!("match_wire-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (msg_sender, _msg_rx) = tokio::sync::mpsc::channel(1);
+        let mut context = CommandContextBuilder::new()
+            .msg_sender(msg_sender)
+            .game_details(GameDetails::default(&PathBuf::from("game")))
+            .cache(Cache::new())
+            .local_dir(Some(dir.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(context.config().filter.limit, None);
+
+        // stands in for an editor that saves a change and closes
+        std::fs::write(dir.join("config.toml"), "[filter]\nlimit = 50\n").unwrap();
+        let editor = if cfg!(windows) {
+            "cmd /c rem"
+        } else {
+            "sh -c :"
+        };
+        std::env::set_var("EDITOR", editor);
+        let line = ["config", "edit"].map(String::from).to_vec();
+        try_execute_command(line, &mut context).await;
+        assert_eq!(context.config().filter.limit, Some(50));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn redacted_commands() {
+        let redact =
+            |line: &str| redact_passwords(&line.split(' ').map(String::from).collect::<Vec<_>>());
+        assert_eq!(redact("geo-key set abc123"), "geo-key set ***");
+        assert_eq!(redact("GeoKey set abc123"), "GeoKey set ***");
+        assert_eq!(redact("GEO-KEY SET abc123"), "GEO-KEY SET ***");
+        assert_eq!(redact("geo-key clear"), "geo-key clear");
+        assert_eq!(redact("settings set abc123"), "settings set abc123");
+        assert_eq!(
+            redact("rcon 1 --password hunter2 map_rotate"),
+            "rcon 1 --password *** map_rotate"
+        );
+        assert_eq!(
+            redact("Rcon 1 --password=hunter2 status"),
+            "Rcon 1 --password=*** status"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use match_wire::utils::input::line::{History, LineData};
+
+    fn line_at(input: &str, cursor: u16) -> LineData {
+        let mut line = LineData::default();
+        line.set_input(input.to_string());
+        line.set_cursor(cursor);
+        line
+    }
+
+    #[test]
+    fn byte_indices() {
+        let line = line_at("aé😀b", 0);
+        assert_eq!(line.byte_index(0), 0);
+        assert_eq!(line.byte_index(1), 1);
+        assert_eq!(line.byte_index(2), 3);
+        assert_eq!(line.byte_index(3), 7);
+        assert_eq!(line.byte_index(4), 8);
+        assert_eq!(line.byte_index(9), 8);
+    }
+
+    #[test]
+    fn word_boundaries() {
+        // "connect   café   😀x", chars: connect 0..7, spaces 7..10, café 10..14, spaces 14..17, 😀x 17..19
+        let input = "connect   café   😀x";
+
+        let line = line_at(input, 0);
+        assert_eq!(line.prev_word_start(), 0);
+        assert_eq!(line.next_word_end(), 7);
+
+        let line = line_at(input, 19);
+        assert_eq!(line.cursor(), 19);
+        assert_eq!(line.prev_word_start(), 17);
+        assert_eq!(line.next_word_end(), 19);
+
+        let line = line_at(input, 15);
+        assert_eq!(line.prev_word_start(), 10);
+        assert_eq!(line.next_word_end(), 19);
+
+        let line = line_at(input, 12);
+        assert_eq!(line.prev_word_start(), 10);
+        assert_eq!(line.next_word_end(), 14);
+
+        let line = line_at("   ", 2);
+        assert_eq!(line.prev_word_start(), 0);
+        assert_eq!(line.next_word_end(), 3);
+
+        assert_eq!(line_at("ab", 7).cursor(), 2);
+    }
+
+    #[test]
+    fn kill_and_yank() {
+        let mut line = line_at("filter  --region eü", 16);
+        let start = line.prev_word_start();
+        let killed = line.kill(start, line.cursor());
+        assert_eq!(killed, "--region");
+        assert_eq!(line.input(), "filter   eü");
+        assert_eq!(line.cursor(), 8);
+
+        line.set_cursor(6);
+        line.yank(&killed);
+        assert_eq!(line.input(), "filter--region   eü");
+        assert_eq!(line.cursor(), 14);
+
+        let mut line = line_at("añb ü", 0);
+        assert_eq!(line.kill(0, line.cursor()), "");
+        assert_eq!(line.kill(0, 2), "añ");
+        assert_eq!(line.input(), "b ü");
+
+        line.set_cursor(3);
+        assert_eq!(line.kill(line.cursor(), 3), "");
+        line.yank("ñ");
+        assert_eq!(line.input(), "b üñ");
+        assert_eq!(line.cursor(), 4);
+
+        line.set_cursor(0);
+        let killed = line.kill(0, 5);
+        assert_eq!(killed, "b üñ");
+        assert_eq!(line.input(), "");
+        assert_eq!(line.cursor(), 0);
+        line.yank(&killed);
+        line.set_cursor(2);
+        line.yank("  ");
+        assert_eq!(line.input(), "b   üñ");
+        assert_eq!(line.cursor(), 4);
+    }
+
+    #[test]
+    fn reverse_history_search() {
+        let mut history = History::default();
+        for entry in [
+            "filter --region eu",
+            "connect 1",
+            "filter --region na",
+            "reconnect",
+        ] {
+            history.push(entry.to_string());
+        }
+
+        assert_eq!(history.rfind("filter", 4, false), Some(2));
+        assert_eq!(history.rfind("filter", 2, false), Some(0));
+        assert_eq!(history.rfind("connect", 4, false), Some(3));
+        assert_eq!(history.rfind("connect", 3, false), Some(1));
+        assert_eq!(history.rfind("", 4, false), Some(3));
+        assert_eq!(history.rfind("filter", 10, false), Some(2));
+
+        assert_eq!(history.rfind("filter", 0, false), None);
+        assert_eq!(history.rfind("filter", 0, true), Some(2));
+        assert_eq!(history.rfind("--region na", 2, true), Some(2));
+
+        assert_eq!(history.rfind("apac", 4, false), None);
+        assert_eq!(history.rfind("apac", 4, true), None);
+        assert_eq!(History::default().rfind("filter", 0, true), None);
+    }
+}
@@ -0,0 +1,303 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use clap::Parser;
+    use match_wire::{
+        cli::{AppArgs, Channel, IpFamily},
+        commands::{
+            filter::{hmw_servers, iw4_servers, try_get_info, Request, Sourced},
+            handler::AppDetails,
+        },
+        compare_versions,
+        error::{Error, FetchError},
+        utils::{
+            caching::Cache,
+            http::{
+                fixture_name, FetchFuture, FixtureFetch, HttpFetch, HttpResponse, OfflineFetch,
+                RecordFetch, Validator,
+            },
+            json_data::{HostData, Version},
+            lenient::{parse_entries, take_skipped},
+            perf::endpoint_key,
+        },
+    };
+
+    #[test]
+    fn perf_endpoint_keys() {
+        assert_eq!(
+            endpoint_key("http://1.2.3.4:27016/getInfo"),
+            "{server}/getInfo"
+        );
+        assert_eq!(
+            endpoint_key("http://[2001:db8::1]:27017/getInfo"),
+            "{server}/getInfo"
+        );
+        assert_eq!(
+            endpoint_key("https://api.findip.net/8.8.8.8/?token=secret"),
+            "api.findip.net/{ip}"
+        );
+        assert_eq!(
+            endpoint_key("http://master.iw4.zip/instance"),
+            "master.iw4.zip/instance"
+        );
+    }
+
+    #[test]
+    fn version_ordering() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_versions("0.5.10", "0.5.9"), Ordering::Greater);
+        assert_eq!(compare_versions("v0.6.0", "0.6.0"), Ordering::Equal);
+        assert_eq!(compare_versions("0.6.0-beta.1", "0.6.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("0.6.0-beta.2", "0.6.0-beta.10"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("0.6.0-beta.1", "0.5.5"), Ordering::Greater);
+    }
+
+    #[test]
+    fn version_digest() {
+        let hash = "a".repeat(64);
+        let json = format!(
+            r#"{{"latest": "0.9.0", "message": "", "sha256": "{hash}",
+                "channels": {{"beta": {{"latest": "0.9.1-beta.1", "message": "", "sha256": "not hex"}}}}}}"#
+        );
+        let version = serde_json::from_str::<Version>(&json).unwrap();
+        let stable = AppDetails::new(version.into_channel(Channel::Stable), Channel::Stable);
+        assert_eq!(stable.exe_sha256.as_deref(), Some(hash.as_str()));
+
+        let version = serde_json::from_str::<Version>(&json).unwrap();
+        let beta = AppDetails::new(version.into_channel(Channel::Beta), Channel::Beta);
+        assert_eq!(beta.ver_latest.as_deref(), Some("0.9.1-beta.1"));
+        assert!(beta.exe_sha256.is_none());
+
+        let old = r#"{"latest": "0.9.0", "message": ""}"#;
+        assert!(serde_json::from_str::<Version>(old)
+            .unwrap()
+            .sha256
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn offline_fixtures() {
+        assert_eq!(
+            fixture_name("https://api.findip.net/1.1.1.1/?token=secret"),
+            "api.findip.net_1.1.1.1.json"
+        );
+
+        let http = FixtureFetch::new(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/offline"),
+        );
+        let iw4 = iw4_servers(&http, None, IpFamily::default()).await.unwrap();
+        let hmw = hmw_servers(&http, None, IpFamily::default()).await.unwrap();
+        assert_eq!(iw4.len(), 1);
+        assert_eq!(hmw.len(), 1);
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)), 27017);
+        assert_eq!(hmw[0].socket_addr(), addr);
+        let Ok(server) =
+            try_get_info(Request::New(hmw.into_iter().next().unwrap()), &http, None).await
+        else {
+            panic!("fixture should answer getInfo");
+        };
+        let info = server.info.unwrap();
+        assert_eq!((info.clients, info.bots), (6, 2));
+    }
+
+    /// Fixtures sent with an `ETag`, a request carrying it is answered as not modified
+    struct ConditionalFetch {
+        fixtures: FixtureFetch,
+        not_modified: std::sync::atomic::AtomicUsize,
+    }
+
+    impl HttpFetch for ConditionalFetch {
+        fn get<'a>(&'a self, url: &'a str, timeout: Option<Duration>) -> FetchFuture<'a> {
+            Box::pin(async move {
+                let mut response = self.fixtures.get(url, timeout).await?;
+                response.validator.etag = Some(String::from("\"v1\""));
+                Ok(response)
+            })
+        }
+
+        fn get_if_changed<'a>(&'a self, url: &'a str, validator: &'a Validator) -> FetchFuture<'a> {
+            if validator.etag.as_deref() != Some("\"v1\"") {
+                return self.get(url, None);
+            }
+            self.not_modified
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Box::pin(async {
+                Ok(HttpResponse {
+                    status: reqwest::StatusCode::NOT_MODIFIED,
+                    body: Vec::new(),
+                    validator: Validator::default(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn master_list_not_modified() {
+        let http = ConditionalFetch {
+            fixtures: FixtureFetch::new(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/offline"),
+            ),
+            not_modified: Default::default(),
+        };
+        let cache = tokio::sync::Mutex::new(Cache::new());
+        let iw4 = iw4_servers(&http, Some(&cache), IpFamily::default())
+            .await
+            .unwrap();
+        let hmw = hmw_servers(&http, Some(&cache), IpFamily::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            http.not_modified.load(std::sync::atomic::Ordering::Relaxed),
+            0
+        );
+        assert!(cache.lock().await.master_lists.iw4m.is_some());
+
+        let unchanged = iw4_servers(&http, Some(&cache), IpFamily::default())
+            .await
+            .unwrap();
+        assert_eq!(unchanged.len(), iw4.len());
+        assert!(matches!(unchanged[0], Sourced::Iw4(_)));
+        let unchanged = hmw_servers(&http, Some(&cache), IpFamily::default())
+            .await
+            .unwrap();
+        assert_eq!(unchanged[0].socket_addr(), hmw[0].socket_addr());
+        assert_eq!(
+            http.not_modified.load(std::sync::atomic::Ordering::Relaxed),
+            2
+        );
+
+        // without a cache nothing is saved to compare against
+        assert!(iw4_servers(&http, None, IpFamily::default()).await.is_ok());
+        assert_eq!(
+            http.not_modified.load(std::sync::atomic::Ordering::Relaxed),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_uses_cache() {
+        assert!(
+            AppArgs::try_parse_from(["match_wire", "--offline", "--replay", "fixtures"]).is_err()
+        );
+        assert!(
+            AppArgs::try_parse_from(["match_wire", "--offline"])
+                .unwrap()
+                .offline
+        );
+
+        let mut cache = Cache::new();
+        cache
+            .hmw
+            .insert(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), vec![27016, 27017]);
+        let cache = tokio::sync::Mutex::new(cache);
+        assert!(matches!(
+            OfflineFetch
+                .get("http://ms.s2mod.to/game-servers", None)
+                .await,
+            Err(FetchError::Offline)
+        ));
+        let hmw = hmw_servers(&OfflineFetch, Some(&cache), IpFamily::default())
+            .await
+            .unwrap();
+        assert_eq!(hmw.len(), 2);
+        assert!(hmw
+            .iter()
+            .all(|server| matches!(server, Sourced::HmwCached(_))));
+        assert!(matches!(
+            iw4_servers(&OfflineFetch, None, IpFamily::default()).await,
+            Err(Error::MasterApi {
+                source: FetchError::Offline,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn record_then_replay() {
+        let recorded =
+            std::env::temp_dir().join(format!("match_wire-record-{}", std::process::id()));
+        let http = RecordFetch::new(
+            recorded.clone(),
+            Arc::new(FixtureFetch::new(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/offline"),
+            )),
+        );
+        let live = hmw_servers(&http, None, IpFamily::default()).await.unwrap();
+        assert!(iw4_servers(&http, None, IpFamily::default()).await.is_ok());
+
+        let replay = FixtureFetch::new(recorded.clone());
+        let replayed = hmw_servers(&replay, None, IpFamily::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            live.iter().map(Sourced::socket_addr).collect::<Vec<_>>(),
+            replayed
+                .iter()
+                .map(Sourced::socket_addr)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            iw4_servers(&replay, None, IpFamily::default())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        // never requested while recording, so it was not saved
+        assert!(try_get_info(
+            Request::New(replayed.into_iter().next().unwrap()),
+            &replay,
+            None
+        )
+        .await
+        .is_err());
+        let _ = std::fs::remove_dir_all(recorded);
+    }
+
+    #[test]
+    fn lenient_master_lists() {
+        const IW4: &str = r#"[
+            {
+                "servers": [
+                    {"ip": "203.0.113.10", "clientnum": 4, "maxclientnum": 18, "port": 27016,
+                     "game": "H2M", "hostname": "Kept", "gamemode": "renamed"},
+                    {"ip": "203.0.113.10", "clientnum": 4, "maxclientnum": 18, "port": "27017",
+                     "game": "H2M", "hostname": "Port is a string"}
+                ],
+                "ip_address": "203.0.113.10",
+                "webfront_url": "http://203.0.113.10:1624",
+                "heartbeat": 1729000000
+            },
+            {"servers": [], "ip_address": "203.0.113.11"}
+        ]"#;
+        const HMW: &str = r#"["198.51.100.7:27017", 27016, {"ip": "198.51.100.8"}]"#;
+
+        let hosts = parse_entries::<HostData>(IW4.as_bytes()).unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].servers.len(), 1);
+        assert_eq!(&*hosts[0].servers[0].host_name, "Kept");
+        let hmw = parse_entries::<String>(HMW.as_bytes()).unwrap();
+        assert_eq!(hmw, ["198.51.100.7:27017"]);
+
+        let skipped = take_skipped();
+        for (kind, count) in [
+            ("iw4m instances", 1),
+            ("iw4m servers", 1),
+            ("hmw servers", 2),
+        ] {
+            assert!(skipped.get(kind).is_some_and(|&n| n >= count), "{kind}");
+        }
+        assert!(parse_entries::<String>(b"{\"servers\": []}").is_err());
+        assert!(AppArgs::try_parse_from(["", "--debug-parse"]).is_ok_and(|args| args.debug_parse));
+    }
+}
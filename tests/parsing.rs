@@ -1,9 +1,30 @@
 #[cfg(test)]
 mod tests {
-    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        path::PathBuf,
+        sync::Arc,
+    };
 
     use match_wire::{
-        commands::launch_h2m::HostName, strip_ansi_private_modes, strip_ansi_sequences,
+        cli::{parse_connect_target, ConnectTarget},
+        commands::{
+            chat::ChatMessage,
+            launch_h2m::{join_cmdline, take_complete_lines, HostName},
+            logs::parse_log_entries,
+            rcon::{parse_rcon_reply, rcon_packet},
+            script::parse_script,
+            session::MatchEvent,
+        },
+        strip_ansi_private_modes, strip_ansi_sequences,
+        utils::{
+            game_dir::library_folders,
+            game_state::GameState,
+            getstatus::{parse_status_response, PlayerStatus},
+            markdown,
+            players2::salvage_entries,
+            profile::parse_config,
+        },
     };
 
     #[test]
@@ -98,4 +119,345 @@ mod tests {
             assert_eq!(parsed, OUTPUT[i]);
         }
     }
+
+    #[test]
+    fn parse_match_events() {
+        const INPUT: [&str; 5] = [
+            "  3:12 K;0110000100000001;3;axis;Victim;0110000100000002;7;allies;Attacker 1;iw9_m4_mp;30;MOD_RIFLE_BULLET;torso_upper",
+            "W;allies;0110000100000002;Attacker 1",
+            "InitGame: \\g_gametype\\war\\mapname\\mp_rust\\sv_hostname\\Server",
+            "Loading fastfile mp_shirt_028_p_tr",
+            "K;too;few;fields",
+        ];
+
+        for (i, input) in INPUT.iter().enumerate() {
+            match (i, MatchEvent::parse(input)) {
+                (
+                    0,
+                    Some(MatchEvent::Kill {
+                        attacker,
+                        victim,
+                        weapon,
+                    }),
+                ) => {
+                    assert_eq!(attacker, "Attacker 1");
+                    assert_eq!(victim, "Victim");
+                    assert_eq!(weapon, "iw9_m4_mp");
+                }
+                (1, Some(MatchEvent::RoundWin { team })) => assert_eq!(team, "allies"),
+                (2, Some(MatchEvent::MapChange { map, game_type })) => {
+                    assert_eq!(map, "mp_rust");
+                    assert_eq!(game_type, "war");
+                }
+                (3 | 4, None) => (),
+                (i, event) => panic!("unexpected result for input {i}: {event:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_players2_config() {
+        const INPUT: &str = "\u{feff}// generated by h2m-mod\r\n\
+            seta name \"^1Ward^7Lord\"\r\n\
+            bind TAB \"+scores\"\r\n\
+            seta cg_fov 90 // comment\r\n\
+            set com_maxfps \"250\r\n\
+            seta name \"Second\"\r\n";
+
+        const OUTPUT: [(&str, &str); 4] = [
+            ("name", "^1Ward^7Lord"),
+            ("cg_fov", "90"),
+            ("com_maxfps", "250"),
+            ("name", "Second"),
+        ];
+
+        let parsed = parse_config(INPUT);
+        assert_eq!(parsed.len(), OUTPUT.len());
+        for (i, (dvar, value)) in parsed.iter().enumerate() {
+            assert_eq!(dvar, OUTPUT[i].0);
+            assert_eq!(value, OUTPUT[i].1);
+        }
+    }
+
+    #[test]
+    fn parse_connect_targets() {
+        const INPUT: [&str; 4] = ["3", "127.0.0.1:27016", "[US] Best Maps", "200"];
+
+        let output = [
+            Some(ConnectTarget::History(3)),
+            Some(ConnectTarget::SocketAddr(SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                27016,
+            ))),
+            Some(ConnectTarget::HostName(String::from("[us] best maps"))),
+            None,
+        ];
+
+        for (i, input) in INPUT.iter().enumerate() {
+            assert_eq!(parse_connect_target(input).ok(), output[i]);
+        }
+    }
+
+    #[test]
+    fn parse_script_lines() {
+        const INPUT: &str = "# refresh and join\n\ncache update\r\n  filter -r eu -l 50  \n#launch\nreconnect -c 2\n";
+
+        assert_eq!(
+            parse_script(INPUT),
+            ["cache update", "filter -r eu -l 50", "reconnect -c 2"]
+        );
+    }
+
+    #[test]
+    fn parse_app_log_entries() {
+        const INPUT: &str = "2024-10-20T18:02:11.532Z  INFO App startup\n\
+            2024-10-20T18:02:14.002Z  WARN Failed to resolve location\n\
+            \x20   caused by: timed out\n\
+            2024-10-20T18:02:15.120Z ERROR H2M-mod crashed with exit code: 0xC0000005\n";
+
+        let entries = parse_log_entries(INPUT);
+        let levels = entries.iter().map(|entry| entry.level).collect::<Vec<_>>();
+
+        assert_eq!(
+            levels,
+            [
+                Some(tracing::Level::INFO),
+                Some(tracing::Level::WARN),
+                Some(tracing::Level::ERROR)
+            ]
+        );
+        assert_eq!(entries[1].lines.len(), 2);
+    }
+
+    #[test]
+    fn release_notes_to_plain_text() {
+        const INPUT: &str = "<!-- generated -->\n\
+            ## What's Changed\n\
+            \n\
+            * **New** `changelog` command by @user in [#42](https://github.com/o/r/pull/42)\n\
+            - Fixed `game_dir` lookup\n\
+            \n\
+            \n\
+            ![preview](https://example.com/a.png)\n\
+            ```\n\
+            cache update\n\
+            ```";
+
+        assert_eq!(
+            markdown::to_plain_text(INPUT),
+            "What's Changed\n\
+            \n\
+            • New changelog command by @user in #42 (https://github.com/o/r/pull/42)\n\
+            • Fixed game_dir lookup\n\
+            \n\
+            cache update"
+        );
+    }
+
+    #[test]
+    fn rcon_packets() {
+        assert_eq!(
+            rcon_packet("hunter2", "map_rotate"),
+            b"\xff\xff\xff\xffrcon hunter2 map_rotate"
+        );
+        assert_eq!(
+            parse_rcon_reply(b"\xff\xff\xff\xffprint\nmap: mp_terminal_cls\n"),
+            Some(&b"map: mp_terminal_cls\n"[..])
+        );
+        assert_eq!(parse_rcon_reply(b"\xff\xff\xff\xffstatusResponse\n"), None);
+        assert_eq!(parse_rcon_reply(b"print\nmissing header"), None);
+    }
+
+    #[test]
+    fn getstatus_response() {
+        let status = parse_status_response(
+            b"\xff\xff\xff\xffstatusResponse\n\\sv_hostname\\^1Best ^7TDM\\mapname\\mp_terminal_cls\\g_gametype\\war\n\
+            12 48 \"^2Ward\"\n0 0 \"Bot Sam\"\n",
+        )
+        .unwrap();
+        assert_eq!(status.dvars["sv_hostname"], "^1Best ^7TDM");
+        assert_eq!(status.dvars["mapname"], "mp_terminal_cls");
+        assert_eq!(status.dvars["g_gametype"], "war");
+        assert_eq!(
+            status.players[0],
+            PlayerStatus {
+                score: 12,
+                ping: 48,
+                name: String::from("^2Ward"),
+            }
+        );
+        assert_eq!(status.players[1].name, "Bot Sam");
+        assert!(parse_status_response(b"\xff\xff\xff\xffinfoResponse\n").is_none());
+    }
+
+    #[test]
+    fn game_state_from_console() {
+        let server: Arc<str> = Arc::from("Best Shipment 24/7");
+        let loading = GameState::Loading {
+            server: Arc::clone(&server),
+        };
+        let in_game = GameState::InGame {
+            server: Arc::clone(&server),
+        };
+
+        assert_eq!(GameState::Launching.after_batch(true), None);
+        assert_eq!(
+            GameState::Launching.after_batch(false),
+            Some(GameState::InMenu)
+        );
+        assert_eq!(
+            loading.after_line("0:00 InitGame: \\g_gametype\\dom"),
+            Some(in_game.clone())
+        );
+        assert_eq!(
+            in_game.after_line("12:34 ShutdownGame:"),
+            Some(loading.clone())
+        );
+        assert_eq!(
+            in_game.after_line("Disconnected from server"),
+            Some(GameState::InMenu)
+        );
+        assert_eq!(GameState::Idle.after_line("Disconnected from server"), None);
+        assert_eq!(in_game.server(), Some("Best Shipment 24/7"));
+        assert!(!loading.accepts_commands());
+    }
+
+    #[test]
+    fn steam_library_folders() {
+        let current = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+		"apps"
+		{
+			"228980"		"1234"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\Games\\\"Steam\" Library"
+		"apps"
+		{
+			"393080"		"70000000000"
+		}
+	}
+}"#;
+        let legacy = r#"
+"LibraryFolders"
+{
+	"TimeNextStatsReport"		"1700000000"
+	"ContentStatsID"		"-123"
+	"1"		"E:\\SteamLibrary"
+}"#;
+
+        assert_eq!(
+            library_folders(current),
+            [
+                PathBuf::from(r"C:\Program Files (x86)\Steam"),
+                PathBuf::from(r#"D:\Games\"Steam" Library"#),
+            ]
+        );
+        assert_eq!(library_folders(legacy), [PathBuf::from(r"E:\SteamLibrary")]);
+        assert!(library_folders("").is_empty());
+    }
+
+    #[test]
+    fn salvage_damaged_favorites() {
+        let servers: [SocketAddr; 2] = [
+            "203.0.113.1:27016".parse().unwrap(),
+            "[2001:db8::1]:27017".parse().unwrap(),
+        ];
+
+        let truncated =
+            br#"["203.0.113.1:27016","[2001:db8::1]:27017","203.0.113.1:27016","203.0.113.9:270"#;
+        assert_eq!(salvage_entries(truncated), servers);
+
+        let padded = b"[\"203.0.113.1:27016\",\"[2001:db8::1]:27017\"\0\0\0\0";
+        assert_eq!(salvage_entries(padded), servers);
+        assert!(salvage_entries(b"\0\0\0\0").is_empty());
+    }
+
+    #[test]
+    fn split_log_reads() {
+        let line = "Kill;Soap;Price\n".as_bytes();
+        let mut partial = Vec::new();
+        // 'é' is two bytes, the read ends between them
+        partial.extend_from_slice(b"say;Caf\xc3");
+        assert_eq!(take_complete_lines(&mut partial), None);
+        partial.extend_from_slice(b"\xa9\n");
+        partial.extend_from_slice(&line[..4]);
+        assert_eq!(
+            take_complete_lines(&mut partial).as_deref(),
+            Some("say;Café\n")
+        );
+        assert_eq!(partial, &line[..4]);
+        partial.extend_from_slice(&line[4..]);
+        assert_eq!(
+            take_complete_lines(&mut partial).as_deref(),
+            Some("Kill;Soap;Price\n")
+        );
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn quote_cmdline() {
+        let cmdline = |args: &[&str]| {
+            join_cmdline(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            cmdline(&["+set", "fs_game", "mods/tdm"]),
+            "+set fs_game mods/tdm"
+        );
+        assert_eq!(cmdline(&["-name", ""]), r#"-name """#);
+        assert_eq!(
+            cmdline(&["+connect", "my server"]),
+            r#"+connect "my server""#
+        );
+        assert_eq!(cmdline(&["a\tb"]), "\"a\tb\"");
+        // backslashes are left alone unless a quote follows them
+        assert_eq!(cmdline(&[r"C:\Games\H2M"]), r"C:\Games\H2M");
+        assert_eq!(cmdline(&[r"C:\Games\H2M Mod\"]), r#""C:\Games\H2M Mod\\""#);
+        assert_eq!(cmdline(&[r#"say"hi"#]), r#""say\"hi""#);
+        assert_eq!(cmdline(&[r#"a\"b c"#]), r#""a\\\"b c""#);
+        assert_eq!(cmdline(&[r"a\\b c"]), r#""a\\b c""#);
+    }
+
+    #[test]
+    fn parse_chat_messages() {
+        let chat =
+            |line: &str| ChatMessage::parse(line).map(|chat| (chat.name, chat.message, chat.team));
+        let said = |name: &str, message: &str, team: bool| {
+            Some((name.to_string(), message.to_string(), team))
+        };
+        assert_eq!(
+            chat("say;0110000100000001;3;Soap;hello"),
+            said("Soap", "hello", false)
+        );
+        assert_eq!(
+            chat("  12:34 say;0110000100000001;3;Soap;hello  "),
+            said("Soap", "hello", false)
+        );
+        assert_eq!(
+            chat("1:02:03 sayteam;0110000100000002;5;Price;hold B"),
+            said("Price", "hold B", true)
+        );
+        // the message keeps any separators of its own
+        assert_eq!(
+            chat("say;0110000100000001;3;^1So^7ap;\u{15}^2gg; ^3ez"),
+            said("Soap", "gg; ez", false)
+        );
+        // not a timestamp, so the line does not start with a chat prefix
+        assert_eq!(chat("12:3a say;0110000100000001;3;Soap;hello"), None);
+        assert_eq!(chat("say;0110000100000001;3;Soap"), None);
+        assert_eq!(chat("say;0110000100000001"), None);
+        assert_eq!(chat("Say;0110000100000001;3;Soap;hello"), None);
+        assert_eq!(
+            chat("12:34 K;guid;0;axis;Soap;guid;1;allies;Price;iw5_m4_mp"),
+            None
+        );
+        assert_eq!(chat(""), None);
+    }
 }
@@ -0,0 +1,661 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use clap::Parser;
+    use match_wire::{
+        cli::{parse_import_source, BrowserOrder, ImportSource, IpFamily, UserCommand},
+        commands::{
+            diff::compare,
+            favorites::{
+                decode, encode_preset, encode_servers, write_targets, SharedList, WriteOutcome,
+            },
+            filter::{
+                dedup_servers, favorite_entry, preview_groups, retain_matching_usual,
+                sort_for_browser, Server, Sourced,
+            },
+            import::parse_import,
+            journal::{group_sessions, JournalEntry, Visit},
+            launch_h2m::HostName,
+            query::FilterQuery,
+            session::{LagSide, MatchEvent, SessionLog, SessionStats},
+        },
+        utils::{
+            caching::{Cache, CacheSummary, HOST_MOVES_MAX},
+            json_data::{GetInfo, HostMove, MasterSnapshot, ServerCache},
+            ping_monitor::{assess, Health, RECENT_PROBES},
+            population::PopulationHistory,
+            regions::RegionSettings,
+            reliability::ReliabilityBook,
+            server_book::{PlayerSample, ServerBook},
+        },
+    };
+
+    #[test]
+    fn cache_summary() {
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+        let mut old = Cache::new();
+        old.iw4m.insert(ip(1), vec![27016, 27017]);
+        old.hmw.insert(ip(2), vec![27016]);
+        let new = ServerCache {
+            iw4m: HashMap::from([(ip(1), vec![27016])]),
+            hmw: HashMap::from([(ip(1), vec![27016]), (ip(3), vec![27016, 27017])]),
+            regions: HashMap::from([(ip(3), ['E', 'U'])]),
+            countries: HashMap::new(),
+            host_names: HashMap::new(),
+            pings: HashMap::new(),
+            resolved: HashMap::new(),
+            moves: Vec::new(),
+        };
+
+        let mut summary = CacheSummary::default();
+        summary.compare(&old, &new);
+        assert_eq!(
+            (summary.servers, summary.previous, summary.with_region),
+            (3, 3, 2)
+        );
+        assert_eq!((summary.added, summary.removed), (2, 2));
+    }
+
+    #[test]
+    fn favorites_share_codes() {
+        let servers = vec![
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)), 27017),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 27016),
+        ];
+        let code = encode_servers(&servers);
+        assert!(code.starts_with("mw1s."));
+        assert_eq!(decode(&code), Ok(SharedList::Servers(servers.clone())));
+        assert_eq!(
+            decode(r#"[ "198.51.100.7:27017", "not a server", "[::1]:27016" ]"#),
+            Ok(SharedList::Servers(servers))
+        );
+
+        let preset = "-r na --includes \"best tdm\"";
+        assert_eq!(
+            decode(&encode_preset(preset)),
+            Ok(SharedList::Preset(String::from(preset)))
+        );
+
+        assert!(decode(&code[..code.len() - 2]).is_err());
+        assert!(decode("198.51.100.7:27017").is_err());
+    }
+
+    #[test]
+    fn journal_sessions() {
+        const INPUT: &str = r#"{"kind":"launch","time":100}
+{"kind":"visit","server":"Best Shipment 24/7","joined":130,"left":1930,"maps":["mp_shipment"]}
+{"kind":"exit","time":2000,"exit_code":3221225477}
+{"kind":"visit","server":"127.0.0.1:27016","joined":5000,"left":5600,"maps":[]}"#;
+
+        let entries = INPUT
+            .lines()
+            .map(|line| serde_json::from_str::<JournalEntry>(line).unwrap())
+            .collect::<Vec<_>>();
+        let sessions = group_sessions(entries);
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].started, 100);
+        assert_eq!(sessions[0].exit_code, Some(0xC0000005));
+        assert_eq!(sessions[0].duration().as_secs(), 1900);
+        assert_eq!(
+            sessions[0].visits,
+            [Visit {
+                server: String::from("Best Shipment 24/7"),
+                joined: 130,
+                left: 1930,
+                maps: vec![String::from("mp_shipment")],
+            }]
+        );
+        assert_eq!(sessions[1].started, 5000);
+        assert_eq!(sessions[1].ended, None);
+        assert_eq!(sessions[1].duration().as_secs(), 600);
+    }
+
+    #[test]
+    fn dedup_ipv4_and_ipv6() {
+        let server = |addr: &str, name: &str, clients: u8| Server {
+            source: Sourced::Hmw(addr.parse().unwrap()),
+            info: Some(GetInfo {
+                clients,
+                max_clients: 18,
+                private_clients: 0,
+                bots: 0,
+                game_name: String::from("H2M"),
+                game_type: String::from("war"),
+                host_name: Arc::from(name),
+                map_name: String::from("mp_rust"),
+            }),
+            community: None,
+        };
+        let mut servers = vec![
+            server("[2001:db8::1]:27016", "^1Best ^7Rust", 7),
+            server("203.0.113.4:27016", "Best Rust", 7),
+            server("203.0.113.4:27017", "Best Rust", 7),
+            server("198.51.100.2:27016", "H2M Server", 0),
+            server("198.51.100.3:27016", "H2M Server", 0),
+        ];
+
+        assert_eq!(dedup_servers(&mut servers, IpFamily::Ipv4), 1);
+        let kept = servers
+            .iter()
+            .map(|server| server.source.socket_addr().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kept,
+            [
+                "203.0.113.4:27016",
+                "203.0.113.4:27017",
+                "198.51.100.2:27016",
+                "198.51.100.3:27016"
+            ]
+        );
+
+        let mut servers = vec![
+            server("203.0.113.4:27016", "Best Rust", 7),
+            server("[2001:db8::1]:27016", "Best Rust", 7),
+        ];
+        assert_eq!(dedup_servers(&mut servers, IpFamily::Ipv6), 1);
+        assert_eq!(
+            favorite_entry(servers[0].source.socket_addr()),
+            "[2001:db8::1]:27016"
+        );
+        assert_eq!(
+            favorite_entry("[::ffff:203.0.113.4]:27016".parse().unwrap()),
+            "203.0.113.4:27016"
+        );
+    }
+
+    #[test]
+    fn browser_order() {
+        let server = |addr: &str, name: &str, clients: u8| Server {
+            source: Sourced::Hmw(addr.parse().unwrap()),
+            info: Some(GetInfo {
+                clients,
+                max_clients: 18,
+                private_clients: 0,
+                bots: 0,
+                game_name: String::from("H2M"),
+                game_type: String::from("war"),
+                host_name: Arc::from(name),
+                map_name: String::from("mp_rust"),
+            }),
+            community: None,
+        };
+        let servers = [
+            server("192.0.2.1:27016", "^1Zulu", 3),
+            server("198.51.100.2:27016", "^2alpha", 1),
+            server("203.0.113.4:27016", "Mike", 12),
+            server("203.0.113.5:27016", "bravo", 5),
+        ];
+        let regions = HashMap::from([
+            ("198.51.100.2".parse().unwrap(), ['N', 'A']),
+            ("203.0.113.4".parse().unwrap(), ['A', 'S']),
+            ("203.0.113.5".parse().unwrap(), ['E', 'U']),
+        ]);
+        let ordered = |order| {
+            let mut written = servers.iter().collect::<Vec<_>>();
+            sort_for_browser(&mut written, order, &regions, &RegionSettings::new());
+            written
+                .iter()
+                .map(|server| server.source.socket_addr().ip().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            ordered(BrowserOrder::Name),
+            ["198.51.100.2", "203.0.113.5", "203.0.113.4", "192.0.2.1"]
+        );
+        assert_eq!(
+            ordered(BrowserOrder::Players),
+            ["203.0.113.4", "203.0.113.5", "192.0.2.1", "198.51.100.2"]
+        );
+        assert_eq!(
+            ordered(BrowserOrder::Region),
+            ["198.51.100.2", "203.0.113.5", "203.0.113.4", "192.0.2.1"]
+        );
+    }
+
+    #[test]
+    fn preview_grouping() {
+        let server = |addr: &str, community: Option<&str>, clients: u8| Server {
+            source: Sourced::Hmw(addr.parse().unwrap()),
+            info: Some(GetInfo {
+                clients,
+                max_clients: 18,
+                private_clients: 0,
+                bots: 0,
+                game_name: String::from("H2M"),
+                game_type: String::from("war"),
+                host_name: Arc::from("Server"),
+                map_name: String::from("mp_rust"),
+            }),
+            community: community.map(Arc::from),
+        };
+        let servers = vec![
+            server("203.0.113.4:27016", Some("admin.example.com"), 2),
+            server("203.0.113.5:27016", Some("admin.example.com"), 9),
+            server("198.51.100.2:27016", None, 4),
+            server("198.51.100.2:27017", None, 0),
+            server("192.0.2.1:27016", None, 6),
+        ];
+        let regions = HashMap::from([
+            ("203.0.113.4".parse().unwrap(), ['E', 'U']),
+            ("203.0.113.5".parse().unwrap(), ['E', 'U']),
+            ("198.51.100.2".parse().unwrap(), ['N', 'A']),
+        ]);
+
+        let groups = preview_groups(&servers, &regions);
+        let summary = groups
+            .iter()
+            .map(|group| {
+                let hosts = group
+                    .hosts
+                    .iter()
+                    .map(|host| (host.host.as_str(), host.servers.len()))
+                    .collect::<Vec<_>>();
+                (group.region, hosts)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (Some(['E', 'U']), vec![("admin.example.com", 2)]),
+                (Some(['N', 'A']), vec![("198.51.100.2", 2)]),
+                (None, vec![("192.0.2.1", 1)]),
+            ]
+        );
+        let players = groups[0].hosts[0]
+            .servers
+            .iter()
+            .map(|server| server.info.as_ref().unwrap().clients)
+            .collect::<Vec<_>>();
+        assert_eq!(players, [9, 2]);
+    }
+
+    #[test]
+    fn population_by_hour() {
+        let evening = SystemTime::UNIX_EPOCH + Duration::from_secs(18 * 3600);
+        let midnight = SystemTime::UNIX_EPOCH + Duration::from_secs(24 * 3600);
+        let day = Duration::from_secs(24 * 3600);
+        let busy = SocketAddr::from(([203, 0, 113, 4], 27016));
+        let late = SocketAddr::from(([203, 0, 113, 5], 27016));
+
+        let mut history = PopulationHistory::default();
+        for players in [10, 14] {
+            history.record(busy, players, evening);
+        }
+        history.record(late, 16, midnight);
+        assert_eq!(history.usual_players(busy, evening + day * 3), Some(12.0));
+        assert_eq!(history.usual_players(busy, midnight), None);
+        assert_eq!(history.usual_players(late, midnight + day), Some(16.0));
+
+        let server = |addr: SocketAddr, clients: u8| Server {
+            source: Sourced::Hmw(addr),
+            info: Some(GetInfo {
+                clients,
+                max_clients: 18,
+                private_clients: 0,
+                bots: 0,
+                game_name: String::from("H2M"),
+                game_type: String::from("war"),
+                host_name: Arc::from("Server"),
+                map_name: String::from("mp_rust"),
+            }),
+            community: None,
+        };
+        let usual = [busy, late]
+            .into_iter()
+            .filter_map(|addr| Some((addr, history.usual_players(addr, evening)?)))
+            .collect::<HashMap<_, _>>();
+        let mut servers = vec![server(busy, 0), server(late, 0)];
+        let query = FilterQuery::builder()
+            .min_players(4)
+            .active_now(true)
+            .build();
+        retain_matching_usual(&mut servers, &query, &usual);
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].source.socket_addr(), busy);
+
+        history.forget_stale(midnight + day * 30);
+        assert!(history.usual_players(busy, evening).is_none());
+        assert!(history.usual_players(late, midnight).is_some());
+        history.forget_stale(midnight + day * 31);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn reliability_scores() {
+        let steady = SocketAddr::from(([203, 0, 113, 4], 27016));
+        let flaky = SocketAddr::from(([203, 0, 113, 5], 27016));
+        let gone = SocketAddr::from(([203, 0, 113, 6], 27016));
+
+        let mut book = ReliabilityBook::default();
+        book.record_listing(&HashSet::from([steady, flaky, gone]));
+        assert_eq!(book.score(steady), None);
+        for check in 0..3 {
+            let mut listed = HashSet::from([steady]);
+            if check == 0 {
+                listed.insert(flaky);
+            }
+            book.record_listing(&listed);
+        }
+        assert_eq!(book.score(steady), Some(100));
+        assert_eq!(book.score(flaky), Some(50));
+        assert_eq!(book.score(gone), Some(25));
+
+        for _ in 0..26 {
+            book.record_listing(&HashSet::from([steady, flaky]));
+        }
+        assert!(book.score(gone).is_some());
+        book.record_listing(&HashSet::from([steady, flaky]));
+        assert_eq!(book.score(gone), None);
+
+        // old checks are halved away
+        for _ in 0..40 {
+            book.record_listing(&HashSet::from([steady, flaky]));
+        }
+        assert!(book.score(flaky).is_some_and(|score| score > 95));
+    }
+
+    #[test]
+    fn snapshot_diff() {
+        let addr = |last: u8| SocketAddr::from(([203, 0, 113, last], 27016));
+        let snapshot = |servers: &[(u8, Option<u8>)]| MasterSnapshot {
+            taken: SystemTime::UNIX_EPOCH,
+            servers: servers
+                .iter()
+                .map(|&(last, players)| (addr(last), players))
+                .collect(),
+        };
+        let prev = snapshot(&[
+            (1, Some(2)),
+            (2, Some(18)),
+            (3, Some(4)),
+            (4, None),
+            (5, Some(0)),
+        ]);
+        let curr = snapshot(&[
+            (1, Some(12)),
+            (2, Some(3)),
+            (3, Some(8)),
+            (4, Some(17)),
+            (6, None),
+        ]);
+
+        let diff = compare(&prev, &curr);
+        assert_eq!(diff.listed, 5);
+        assert_eq!(diff.added, [addr(6)]);
+        assert_eq!(diff.removed, [addr(5)]);
+        assert_eq!(diff.swings, [(addr(2), 18, 3), (addr(1), 2, 12)]);
+    }
+
+    #[test]
+    fn host_moves() {
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(203, 0, 113, last));
+        let host = Arc::<str>::from("h2m.example.com");
+        let mut cache = Cache::new();
+        cache.ip_to_region.insert(ip(1), ['E', 'U']);
+        cache.ip_to_region.insert(ip(2), ['N', 'A']);
+
+        cache.record_resolved([(Arc::clone(&host), ip(1))]);
+        cache.record_resolved([(Arc::clone(&host), ip(1))]);
+        assert!(cache.host_moves.is_empty());
+
+        cache.record_resolved([(Arc::clone(&host), ip(2))]);
+        assert_eq!(cache.host_ips.get(&host), Some(&ip(2)));
+        assert!(matches!(
+            cache.host_moves.as_slice(),
+            [HostMove { from, to, .. }] if *from == ip(1) && *to == ip(2)
+        ));
+        assert!(
+            cache.ip_to_region.contains_key(&ip(1)) && !cache.ip_to_region.contains_key(&ip(2))
+        );
+
+        for last in 0..HOST_MOVES_MAX as u8 {
+            cache.record_resolved([(Arc::clone(&host), ip(last + 10))]);
+        }
+        assert_eq!(cache.host_moves.len(), HOST_MOVES_MAX);
+        assert_eq!(cache.host_moves[0].to, ip(10));
+    }
+
+    #[test]
+    fn server_book_aliases() {
+        let (rust, shipment) = (
+            "203.0.113.4:27016".parse().unwrap(),
+            "203.0.113.5:27016".parse().unwrap(),
+        );
+        let legacy = HashMap::from([
+            (Arc::from("^1Best ^7Rust"), rust),
+            (Arc::from("24/7 Shipment"), shipment),
+        ]);
+        let history = [HostName {
+            parsed: String::from("best rust"),
+            raw: String::from("^1Best ^7Rust"),
+        }];
+        let seen = SystemTime::now() - Duration::from_secs(60);
+        let mut book = ServerBook::from_legacy(legacy, &history, seen);
+        assert_eq!(book.history_entry(1), Some(rust));
+
+        assert!(book.record(rust, Arc::from("Rust Only"), SystemTime::now()));
+        assert!(!book.record(rust, Arc::from("Rust Only"), SystemTime::now()));
+        assert_eq!(book.candidates("best rust"), [rust]);
+        assert_eq!(book.candidates("rust only"), [rust]);
+        assert_eq!(book.search("203.0.113"), [rust, shipment]);
+        assert_eq!(book.candidates("24/7"), [shipment]);
+        assert!(book.candidates("missing").is_empty());
+
+        assert!(book.push_history(shipment));
+        assert!(!book.push_history(shipment));
+        assert_eq!(book.history_entry(2), Some(rust));
+        // history is saved with the cache and read back by the next session
+        let saved =
+            serde_json::from_str::<ServerBook>(&serde_json::to_string(&book).unwrap()).unwrap();
+        assert_eq!(saved.history(), [rust, shipment]);
+
+        let sample = |clients, ago| PlayerSample {
+            clients,
+            max_clients: 18,
+            at: SystemTime::now() - Duration::from_secs(ago),
+        };
+        book.record_players(rust, sample(12, 60));
+        book.record_players(rust, sample(3, 600));
+        book.record_players("203.0.113.9:27016".parse().unwrap(), sample(1, 0));
+        assert_eq!(
+            book.get(rust)
+                .and_then(|entry| entry.players)
+                .map(|players| players.clients),
+            Some(12)
+        );
+        assert!(book.get(shipment).unwrap().players.is_none());
+        assert_eq!(book.len(), 2);
+
+        book.forget_before(SystemTime::now());
+        assert_eq!(book.len(), 2);
+        let mut rebuilt = ServerBook::default();
+        rebuilt.adopt_history(&book);
+        assert_eq!(
+            rebuilt.host_name(rust).map(|name| &**name),
+            Some("Rust Only")
+        );
+    }
+
+    #[test]
+    fn fuzzy_host_names() {
+        let servers: [SocketAddr; 4] = [
+            "203.0.113.1:27016".parse().unwrap(),
+            "203.0.113.2:27016".parse().unwrap(),
+            "203.0.113.3:27016".parse().unwrap(),
+            "203.0.113.4:27016".parse().unwrap(),
+        ];
+        let names = [
+            "^3[EU] ^7Best ^1| ^7Shipment 24/7",
+            "[US] Best | Shipment 24/7",
+            "Rust Only ^2#1",
+            "Rust Only #2",
+        ];
+        let mut book = ServerBook::default();
+        for (i, (&server, name)) in servers.iter().zip(names).enumerate() {
+            let seen = SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64);
+            book.record(server, Arc::from(name), seen);
+        }
+
+        assert_eq!(book.candidates("eu best shipment 24/7"), [servers[0]]);
+        assert_eq!(book.candidates("US BEST|SHIPMENT"), [servers[1]]);
+        assert_eq!(book.candidates("shipment eu"), [servers[0]]);
+        assert_eq!(book.candidates("bst shpmnt"), [servers[1], servers[0]]);
+        assert_eq!(book.candidates("rust only"), [servers[3], servers[2]]);
+        assert_eq!(book.candidates("rust only #1"), [servers[2]]);
+        assert!(book.candidates("rst").is_empty());
+        assert!(book.candidates("^7 |").is_empty());
+    }
+
+    #[test]
+    fn import_lists() {
+        let a: SocketAddr = "203.0.113.1:27016".parse().unwrap();
+        let b: SocketAddr = "[2001:db8::1]:27017".parse().unwrap();
+
+        let plain = parse_import(r#"["203.0.113.1:27016", "not a server", "[2001:db8::1]:27017"]"#);
+        assert_eq!(plain.favorites, [a, b]);
+        assert!(plain.history.is_empty() && plain.names.is_empty());
+
+        let objects = parse_import(
+            r#"{
+                "favourites": [{"address": "203.0.113.1:27016", "name": "^1Red"}],
+                "history": [{"ip": "2001:db8::1", "port": 27017}, {"ip": "203.0.113.1", "port": "27016"}],
+                "version": 2
+            }"#,
+        );
+        assert_eq!(objects.favorites, [a]);
+        assert_eq!(objects.history, [b, a]);
+        assert_eq!(objects.names, HashMap::from([(a, String::from("^1Red"))]));
+
+        let text =
+            parse_import("# exported\n203.0.113.1:27016   Some Server\n\n[2001:db8::1]:27017\n");
+        assert_eq!(text.favorites, [a, b]);
+        assert_eq!(
+            text.names,
+            HashMap::from([(a, String::from("Some Server"))])
+        );
+
+        assert_eq!(parse_import_source("IW4x"), Ok(ImportSource::Iw4x));
+        assert_eq!(
+            parse_import_source("plutonium"),
+            Ok(ImportSource::Plutonium)
+        );
+        assert_eq!(
+            parse_import_source("lists/servers.txt"),
+            Ok(ImportSource::Path(PathBuf::from("lists/servers.txt")))
+        );
+        assert!(parse_import_source(" ").is_err());
+        assert!(UserCommand::try_parse_from(["", "import", "--from", "iw4x"]).is_ok());
+    }
+
+    #[test]
+    fn ping_monitor_health() {
+        let ms = Duration::from_millis;
+        let spike = ms(150);
+        assert_eq!(assess(&[None, None], spike, 20), Health::Steady);
+        assert_eq!(
+            assess(&[Some(ms(40)), Some(ms(200))], spike, 20),
+            Health::Steady
+        );
+        assert_eq!(
+            assess(&[Some(ms(40)), Some(ms(160)), Some(ms(210))], spike, 20),
+            Health::Spiking(ms(210))
+        );
+
+        let mut probes = vec![Some(ms(40)); RECENT_PROBES];
+        probes[1] = None;
+        assert_eq!(assess(&probes, spike, 20), Health::Losing(20));
+        assert_eq!(assess(&probes, spike, 40), Health::Steady);
+        // older loss is forgotten
+        probes.push(Some(ms(40)));
+        probes.push(Some(ms(40)));
+        assert_eq!(assess(&probes, spike, 20), Health::Steady);
+
+        let (msg_sender, _messages) = tokio::sync::mpsc::channel(1);
+        let mut log = SessionLog::new(None, &Arc::new(msg_sender));
+        for (sent, lost, average, worst, lag) in [
+            (12, 0, Some(40), Some(60), None),
+            (4, 4, None, None, Some(LagSide::Local)),
+            (4, 0, Some(100), Some(300), Some(LagSide::Server)),
+        ] {
+            log.record(MatchEvent::Connection {
+                server: String::from("Server"),
+                sent,
+                lost,
+                average_ms: average,
+                worst_ms: worst,
+                lag,
+            });
+        }
+        let connection = SessionStats::from(&log).connection;
+        assert_eq!((connection.sent, connection.lost), (20, 4));
+        assert_eq!(
+            (connection.average_ms, connection.worst_ms),
+            (Some(55), Some(300))
+        );
+        let sides = connection.lag.iter().map(|(_, _, side)| *side);
+        assert!(sides.eq([LagSide::Local, LagSide::Server]));
+    }
+
+    #[tokio::test]
+    async fn favorites_writes_in_order() {
+        let dir = std::env::temp_dir().join(format!("match_wire-writes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets = [dir.join("favourites.json")];
+        // the second write is still waiting when the third is queued, so only the third follows
+        let results = tokio::join!(
+            write_targets(&targets, b"[\"first\"]"),
+            write_targets(&targets, b"[\"second\"]"),
+            write_targets(&targets, b"[\"third\"]"),
+        );
+        assert_eq!(results.0.unwrap(), WriteOutcome::Written);
+        assert_eq!(results.1.unwrap(), WriteOutcome::Superseded);
+        assert_eq!(results.2.unwrap(), WriteOutcome::Written);
+        assert_eq!(std::fs::read(&targets[0]).unwrap(), b"[\"third\"]");
+
+        let outcome = write_targets(&targets, b"[]").await.unwrap();
+        assert_eq!(outcome, WriteOutcome::Written);
+        assert_eq!(std::fs::read(&targets[0]).unwrap(), b"[]");
+        // the contents are renamed into place, nothing is left beside the target
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn session_file_appends() {
+        let dir = std::env::temp_dir().join(format!("match_wire-session-{}", std::process::id()));
+        let (msg_sender, mut messages) = tokio::sync::mpsc::channel(1);
+        let mut log = SessionLog::new(Some(&dir), &Arc::new(msg_sender));
+        for map in ["mp_rust", "mp_crash"] {
+            log.record(MatchEvent::MapChange {
+                map: String::from(map),
+                game_type: String::from("war"),
+            });
+        }
+        // dropping the log closes the file once both records are written
+        drop(log);
+        assert!(messages.recv().await.is_none());
+
+        let sessions = std::fs::read_dir(dir.join("sessions")).unwrap();
+        let files = sessions
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let saved = std::fs::read_to_string(&files[0]).unwrap();
+        let maps = saved
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["map"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(maps, ["mp_rust", "mp_crash"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
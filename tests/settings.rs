@@ -0,0 +1,243 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+    use match_wire::{
+        cli::{AppArgs, Region, UserCommand},
+        location_api_key::FIND_IP_NET_PRIVATE_KEY,
+        utils::{
+            config::{env_key, Config},
+            geo_key::{redact, validate_key},
+            lang::{check_translation, tr, translation_path},
+            names::Names,
+            regions::RegionSettings,
+            window::window_title,
+        },
+    };
+
+    #[test]
+    fn config_with_value() {
+        let config = Config::default()
+            .with_value("filter.limit", "50")
+            .and_then(|config| config.with_value("filter.region", r#"["eu", "na"]"#))
+            .and_then(|config| config.with_value("display.prompt", "{server}"))
+            .unwrap();
+
+        assert_eq!(config.filter.limit, Some(50));
+        assert_eq!(config.filter.region, Some(vec![Region::EU, Region::NA]));
+        assert_eq!(config.display.prompt, "{server}");
+        assert_eq!(
+            config
+                .with_value("filter.limit", "default")
+                .unwrap()
+                .filter
+                .limit,
+            None
+        );
+
+        assert!(config.with_value("filter.limit", "many").is_err());
+        assert!(config.with_value("filter.limt", "50").is_err());
+        assert!(config.with_value("limit", "50").is_err());
+    }
+
+    #[test]
+    fn custom_regions() {
+        let config = Config::default()
+            .with_value("region.middle_east", r#"["AS:SA", "as:ae"]"#)
+            .and_then(|config| config.with_value("region.Asia", r#"["AS", "OC"]"#))
+            .unwrap();
+        let middle_east = Region::Custom(String::from("middle_east"));
+        assert_eq!(config.region.get(&middle_east).map(Vec::len), Some(2));
+        assert!(config.region.contains_key(&Region::Apac));
+        assert!(config.with_value("region.gulf", r#"["ASIA"]"#).is_err());
+        assert!(config.with_value("region.gulf", r#"["AS:"]"#).is_err());
+
+        assert_eq!("Middle_East".parse(), Ok(middle_east.clone()));
+        assert_eq!("asiapacific".parse(), Ok(Region::Apac));
+        assert!("middle east".parse::<Region>().is_err());
+        assert!(!middle_east.is_defined(&RegionSettings::new()));
+        assert!(Region::Apac.is_defined(&RegionSettings::new()));
+
+        let groups =
+            RegionSettings::from([(middle_east.clone(), config.region[&middle_east].clone())]);
+        assert!(middle_east.is_defined(&groups));
+        assert!(middle_east.matches(&groups, ['A', 'S'], Some(['S', 'A'])));
+        assert!(!middle_east.matches(&groups, ['A', 'S'], Some(['J', 'P'])));
+        assert!(!middle_east.matches(&groups, ['A', 'S'], None));
+        assert!(middle_east.needs_country(&groups, ['A', 'S']));
+        assert!(!middle_east.needs_country(&groups, ['E', 'U']));
+        assert!(Region::Apac.matches(&groups, ['A', 'F'], None));
+        assert!(!Region::Apac.needs_country(&groups, ['A', 'S']));
+        assert!(!Region::Apac.matches(&config.region, ['A', 'F'], None));
+    }
+
+    #[test]
+    fn window_settings() {
+        let config = Config::default()
+            .with_value("window.title-server", "false")
+            .and_then(|config| config.with_value("window.tray", "true"))
+            .unwrap();
+        assert!(config.window.tray && config.window.remember_placement);
+        assert!(config.with_value("window.tray", "maybe").is_err());
+
+        let defaults = Config::default().window;
+        assert_eq!(
+            window_title(Some("^1Best ^7Server"), &defaults),
+            "match_wire - Best Server"
+        );
+        assert_eq!(
+            window_title(Some("^1Best ^7Server"), &config.window),
+            "match_wire"
+        );
+        assert_eq!(window_title(None, &defaults), "match_wire");
+    }
+
+    #[test]
+    fn launch_refresh_presets() {
+        assert!(Config::default().launch_refresh(Some("-r eu")).is_none());
+
+        let config = Config::default()
+            .with_value("filter.presets", r#"{ eu_evening = "-r eu -p 4" }"#)
+            .and_then(|config| config.with_value("launch.refresh-on-launch", "preset:eu_evening"))
+            .unwrap();
+        assert_eq!(
+            config.launch_refresh(None),
+            Some(Ok(String::from("-r eu -p 4")))
+        );
+
+        let last = config
+            .with_value("launch.refresh-on-launch", "last")
+            .unwrap();
+        assert_eq!(
+            last.launch_refresh(Some("-r na")),
+            Some(Ok(String::from("-r na")))
+        );
+        assert!(matches!(last.launch_refresh(None), Some(Err(_))));
+
+        for value in ["preset:na_morning", "eu_evening"] {
+            let config = config
+                .with_value("launch.refresh-on-launch", value)
+                .unwrap();
+            assert!(matches!(config.launch_refresh(None), Some(Err(_))));
+        }
+    }
+
+    #[test]
+    fn network_launch_options() {
+        let args = AppArgs::try_parse_from([
+            "match_wire",
+            "--timeout",
+            "5",
+            "--proxy",
+            "socks5://127.0.0.1:1080",
+            "--favorites-path",
+            r"D:\HMW\players2\favourites.json",
+            "--favorites-path",
+            "players2/favourites.json",
+        ])
+        .unwrap();
+        let config = args
+            .setting_overrides()
+            .iter()
+            .try_fold(Config::default(), |config, (key, value)| {
+                config.with_value(key, value)
+            })
+            .unwrap();
+
+        assert_eq!(config.network.timeout, 5);
+        assert_eq!(config.network.retries, 2);
+        assert_eq!(
+            config.network.proxy.as_deref(),
+            Some("socks5://127.0.0.1:1080")
+        );
+        assert_eq!(
+            config.filter.favorites_path,
+            [
+                PathBuf::from(r"D:\HMW\players2\favourites.json"),
+                PathBuf::from("players2/favourites.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn setting_env_keys() {
+        assert_eq!(
+            env_key("H2M_FAVORITES_FILTER_LIMIT").as_deref(),
+            Some("filter.limit")
+        );
+        assert_eq!(
+            env_key("H2M_FAVORITES_LAUNCH_AUTO_RELAUNCH").as_deref(),
+            Some("launch.auto-relaunch")
+        );
+        assert_eq!(
+            env_key("H2M_FAVORITES_PING_MONITOR_SPIKE_MS").as_deref(),
+            Some("ping-monitor.spike-ms")
+        );
+        assert_eq!(env_key("H2M_FAVORITES_LIMIT"), None);
+        assert_eq!(env_key("FILTER_LIMIT"), None);
+    }
+
+    #[test]
+    fn translations() {
+        let path = translation_path(std::path::Path::new(env!("CARGO_MANIFEST_DIR")), "es");
+        let texts = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(check_translation(&texts), Vec::<String>::new());
+
+        let bad = std::collections::BTreeMap::from([
+            (String::from("error.wine"), String::from("Wine: {mensaje}")),
+            (String::from("error.nope"), String::from("")),
+        ]);
+        assert_eq!(
+            check_translation(&bad),
+            [
+                "unknown key 'error.nope'",
+                "'error.wine' must use {message}"
+            ]
+        );
+
+        assert_eq!(
+            tr("error.pty", &[("message", &"no handle"), ("unused", &1)]),
+            "Pseudoconsole error, no handle"
+        );
+        assert_eq!(tr("fetch.status", &[]), "server responded with {status}");
+        assert_eq!(tr("no.such.key", &[]), "no.such.key");
+    }
+
+    #[test]
+    fn map_and_mode_names() {
+        let mut display = Config::default().display;
+        let names = Names::from(&display);
+        assert_eq!(names.map("mp_shipment"), "Shipment");
+        assert_eq!(names.map("mp_cargoship"), "Wet Work");
+        assert_eq!(names.map("MP_BOG_SUMMER"), "Beach Bog");
+        assert_eq!(names.map("mp_dome_night"), "Dome Night");
+        assert_eq!(names.mode("dom"), "Domination");
+        assert_eq!(names.mode("sd"), "Search and Destroy");
+        assert_eq!(names.mode("gungame"), "gungame");
+
+        display.map_names = [(String::from("MP_Dome_Night"), String::from("Dome"))].into();
+        display.mode_names = [(String::from("dom"), String::from("Dom"))].into();
+        let names = Names::from(&display);
+        assert_eq!(names.map("mp_dome_night"), "Dome");
+        assert_eq!(names.mode("dom"), "Dom");
+    }
+
+    #[test]
+    fn geo_keys() {
+        assert_eq!(validate_key(" abc123-DEF\n"), Ok("abc123-DEF"));
+        for key in ["", "  ", "a&b", "a b", "abc/../x", "key?token=x"] {
+            assert!(validate_key(key).is_err(), "{key:?}");
+        }
+        assert!(
+            UserCommand::try_parse_from(["", "geo-key", "set", "abc123"]).is_ok()
+                && UserCommand::try_parse_from(["", "geo-key", "clear"]).is_ok()
+                && UserCommand::try_parse_from(["", "geo-key"]).is_ok()
+        );
+
+        let url = format!("https://api.findip.net/203.0.113.1{FIND_IP_NET_PRIVATE_KEY}");
+        let token = FIND_IP_NET_PRIVATE_KEY.trim_start_matches("/?token=");
+        assert!(!token.is_empty() && !redact(&url).contains(token));
+        assert_eq!(redact("no keys in here"), "no keys in here");
+    }
+}